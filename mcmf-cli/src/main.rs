@@ -0,0 +1,357 @@
+// Solves flow problems from files with the same solvers as the browser demo, e.g. to script
+// benchmarks or to check the demo against larger instances:
+//
+//   mcmf graph.json
+//   mcmf --format dimacs --output json --stats < problem.max
+//
+// See `USAGE` for the options.
+
+use std::{env, fs, io::{self, Read}, process::ExitCode};
+
+use mcmf_core::{
+    CsvOptions, FlowSolution, GraphBuilder, LogLevel, MaxFlowAlgorithm, McmfSolution, MinCostFlowAlgorithm,
+    PathDecomposition, SolverStats,
+};
+
+const USAGE: &str = "\
+usage: mcmf [options] [file]
+
+Reads a problem from `file`, or from standard input if it is missing or `-`, and solves it.
+
+options:
+  --format <dimacs|json|csv|graphml>
+      Input format. Guessed from the file extension by default, JSON for standard input.
+  --delimiter <char>
+      CSV field delimiter, ',' by default and a tab for .tsv files.
+  --no-header
+      The CSV input has no header row.
+  --problem <mcmf|max-flow|min-cost-flow|b-flow|circulation>
+      What to solve: mcmf by default if the source and sink are known, b-flow otherwise.
+  --source <node>, --sink <node>
+      Terminals, overriding those stored in the file.
+  --flow-value <amount>
+      Flow to send for min-cost-flow.
+  --algorithm <network-simplex|successive-shortest-paths|cost-scaling|cycle-canceling>
+  --max-flow-algorithm <dinic|push-relabel>
+  --decomposition <depth-first|fewest-paths|widest-first|shortest-first|lexicographic>
+  --output <summary|json|dimacs|dot>
+      What to write to standard output, a readable summary by default.
+  --stats
+      Print solver statistics to standard error.
+  --log-level <off|error|warn|info|trace>
+      Log what the solver does to standard error. Off by default, as errors are printed anyway.
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Dimacs,
+    Json,
+    Csv,
+    GraphMl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Problem {
+    Mcmf,
+    MaxFlow,
+    MinCostFlow,
+    BFlow,
+    Circulation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Output {
+    Summary,
+    Json,
+    Dimacs,
+    Dot,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    file: Option<String>,
+    format: Option<Format>,
+    delimiter: Option<char>,
+    no_header: bool,
+    problem: Option<Problem>,
+    source: Option<String>,
+    sink: Option<String>,
+    flow_value: Option<f64>,
+    algorithm: MinCostFlowAlgorithm,
+    max_flow_algorithm: MaxFlowAlgorithm,
+    decomposition: PathDecomposition,
+    output: Option<Output>,
+    stats: bool,
+    log_level: Option<LogLevel>,
+    help: bool,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if options.help {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    mcmf_core::set_log_level(options.log_level.unwrap_or(LogLevel::Off));
+    match read_input(&options).and_then(|text| run(&options, &text)) {
+        Ok((output, stats)) => {
+            print!("{}", output);
+            if let Some(stats) = stats.filter(|_| options.stats) {
+                eprint!("{}", format_stats(&stats));
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--format" => options.format = Some(choice("--format", &value()?, &[
+                ("dimacs", Format::Dimacs), ("json", Format::Json), ("csv", Format::Csv), ("graphml", Format::GraphMl),
+            ])?),
+            "--delimiter" => {
+                let delimiter = value()?;
+                let mut chars = delimiter.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => options.delimiter = Some(c),
+                    _ => return Err(format!("delimiter must be a single character, got '{}'", delimiter)),
+                }
+            }
+            "--no-header" => options.no_header = true,
+            "--problem" => options.problem = Some(choice("--problem", &value()?, &[
+                ("mcmf", Problem::Mcmf), ("max-flow", Problem::MaxFlow), ("min-cost-flow", Problem::MinCostFlow),
+                ("b-flow", Problem::BFlow), ("circulation", Problem::Circulation),
+            ])?),
+            "--source" => options.source = Some(value()?),
+            "--sink" => options.sink = Some(value()?),
+            "--flow-value" => {
+                let amount = value()?;
+                options.flow_value = Some(amount.parse().map_err(|_| format!("invalid flow value '{}'", amount))?);
+            }
+            "--algorithm" => options.algorithm = choice("--algorithm", &value()?, &[
+                ("network-simplex", MinCostFlowAlgorithm::NetworkSimplex),
+                ("successive-shortest-paths", MinCostFlowAlgorithm::SuccessiveShortestPaths),
+                ("cost-scaling", MinCostFlowAlgorithm::CostScaling),
+                ("cycle-canceling", MinCostFlowAlgorithm::CycleCanceling),
+            ])?,
+            "--max-flow-algorithm" => options.max_flow_algorithm = choice("--max-flow-algorithm", &value()?, &[
+                ("dinic", MaxFlowAlgorithm::Dinic), ("push-relabel", MaxFlowAlgorithm::PushRelabel),
+            ])?,
+            "--decomposition" => options.decomposition = choice("--decomposition", &value()?, &[
+                ("depth-first", PathDecomposition::DepthFirst), ("fewest-paths", PathDecomposition::FewestPaths),
+                ("widest-first", PathDecomposition::WidestFirst), ("shortest-first", PathDecomposition::ShortestFirst),
+                ("lexicographic", PathDecomposition::Lexicographic),
+            ])?,
+            "--output" => options.output = Some(choice("--output", &value()?, &[
+                ("summary", Output::Summary), ("json", Output::Json), ("dimacs", Output::Dimacs), ("dot", Output::Dot),
+            ])?),
+            "--stats" => options.stats = true,
+            "--log-level" => options.log_level = Some(choice("--log-level", &value()?, &[
+                ("off", LogLevel::Off), ("error", LogLevel::Error), ("warn", LogLevel::Warn), ("info", LogLevel::Info),
+                ("trace", LogLevel::Trace),
+            ])?),
+            "--help" | "-h" => options.help = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ if options.file.is_some() => return Err(format!("unexpected argument '{}'", arg)),
+            _ => options.file = Some(arg),
+        }
+    }
+    Ok(options)
+}
+
+fn choice<T: Copy>(option: &str, value: &str, choices: &[(&str, T)]) -> Result<T, String> {
+    choices.iter().find(|(name, _)| *name == value).map(|&(_, choice)| choice).ok_or_else(|| {
+        let names: Vec<_> = choices.iter().map(|(name, _)| *name).collect();
+        format!("{} must be one of {}, got '{}'", option, names.join(", "), value)
+    })
+}
+
+fn read_input(options: &Options) -> Result<String, String> {
+    match options.file.as_deref() {
+        None | Some("-") => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).map_err(|e| format!("can't read standard input: {}", e))?;
+            Ok(text)
+        }
+        Some(file) => fs::read_to_string(file).map_err(|e| format!("can't read '{}': {}", file, e)),
+    }
+}
+
+// Solves the problem in `text` and returns what to print, together with the solver statistics if
+// there are any.
+fn run(options: &Options, text: &str) -> Result<(String, Option<SolverStats>), String> {
+    let mut builder = load(options, text)?;
+    if options.source.is_some() {
+        builder.set_source(options.source.clone());
+    }
+    if options.sink.is_some() {
+        builder.set_sink(options.sink.clone());
+    }
+    let terminals = builder.source().zip(builder.sink()).map(|(source, sink)| (source.to_owned(), sink.to_owned()));
+    let problem = options.problem.unwrap_or(if terminals.is_some() { Problem::Mcmf } else { Problem::BFlow });
+    let needs_terminals = || terminals.clone().ok_or_else(|| "the problem needs a source and a sink".to_owned());
+    let output = options.output.unwrap_or(Output::Summary);
+    match problem {
+        Problem::Mcmf => {
+            let (source, sink) = needs_terminals()?;
+            let solution = builder.solve_mcmf(
+                source, sink, options.decomposition, options.algorithm, options.max_flow_algorithm,
+            )?;
+            Ok((paths_output(&solution, output), Some(solution.stats())))
+        }
+        Problem::MaxFlow => {
+            let (source, sink) = needs_terminals()?;
+            let solution = builder.solve_max_flow(source, sink, options.decomposition, options.max_flow_algorithm)?;
+            Ok((paths_output(&solution, output), Some(solution.stats())))
+        }
+        Problem::MinCostFlow => {
+            let (source, sink) = needs_terminals()?;
+            let flow_value = options.flow_value.ok_or("min-cost-flow needs --flow-value")?;
+            let solution =
+                builder.solve_min_cost_flow(source, sink, flow_value, options.decomposition, options.algorithm)?;
+            Ok((paths_output(&solution, output), Some(solution.stats())))
+        }
+        Problem::BFlow => Ok((flows_output(&builder.solve_b_flow(options.algorithm)?, output)?, None)),
+        Problem::Circulation => {
+            Ok((flows_output(&builder.solve_min_cost_circulation(options.algorithm)?, output)?, None))
+        }
+    }
+}
+
+fn paths_output(solution: &McmfSolution, output: Output) -> String {
+    match output {
+        Output::Summary => paths_summary(solution),
+        Output::Json => solution.to_json() + "\n",
+        Output::Dimacs => solution.to_dimacs_flow(),
+        Output::Dot => solution.to_dot(),
+    }
+}
+
+fn flows_output(solution: &FlowSolution, output: Output) -> Result<String, String> {
+    Ok(match output {
+        Output::Summary => flows_summary(solution),
+        Output::Json => solution.to_json() + "\n",
+        Output::Dimacs => solution.to_dimacs_flow(),
+        Output::Dot => return Err("dot output needs a problem with a source and a sink".to_owned()),
+    })
+}
+
+fn load(options: &Options, text: &str) -> Result<GraphBuilder, String> {
+    let extension = options.file.as_deref().and_then(|file| file.rsplit_once('.')).map(|(_, extension)| extension);
+    let format = match (options.format, extension) {
+        (Some(format), _) => format,
+        (None, None) => Format::Json,
+        (None, Some(extension)) => match extension.to_ascii_lowercase().as_str() {
+            "json" => Format::Json,
+            "csv" | "tsv" | "txt" => Format::Csv,
+            "graphml" | "xml" => Format::GraphMl,
+            "dimacs" | "min" | "max" | "inp" => Format::Dimacs,
+            _ => return Err(format!("can't tell the format of '.{}' files, use --format", extension)),
+        },
+    };
+    let builder = match format {
+        Format::Dimacs => GraphBuilder::from_dimacs(text),
+        Format::Json => GraphBuilder::from_json(text),
+        Format::Csv => {
+            let tab = extension.is_some_and(|extension| extension.eq_ignore_ascii_case("tsv"));
+            let delimiter = options.delimiter.unwrap_or(if tab { '\t' } else { ',' });
+            GraphBuilder::from_csv(text, CsvOptions { delimiter, has_header: !options.no_header })
+        }
+        Format::GraphMl => GraphBuilder::from_graphml(text, "capacity", "cost"),
+    };
+    Ok(builder?)
+}
+
+fn paths_summary(solution: &McmfSolution) -> String {
+    let mut out = format!("flow: {}\n", solution.max_flow());
+    if let Some(cost) = solution.total_cost() {
+        out += &format!("cost: {}\n", cost);
+    }
+    if solution.limit_reached() {
+        out += "limit reached, the flow may not be maximum or of minimum cost\n";
+    }
+    out += "paths:\n";
+    for path in solution.paths() {
+        out += &format!("  {}\t{}\n", path.flow(), path.nodes().join(" -> "));
+    }
+    if !solution.cycles().is_empty() {
+        out += "cycles:\n";
+        for cycle in solution.cycles() {
+            out += &format!("  {}\t{}\n", cycle.flow(), cycle.nodes().join(" -> "));
+        }
+    }
+    out
+}
+
+fn flows_summary(solution: &FlowSolution) -> String {
+    let mut out = format!("cost: {}\n", solution.total_cost());
+    if solution.limit_reached() {
+        out += "limit reached, the flow may not be of minimum cost\n";
+    }
+    out += "edge flows:\n";
+    for edge_flow in solution.edge_flows().iter().filter(|edge_flow| edge_flow.flow() != 0.) {
+        out += &format!("  {}\t{} -> {}\t{}\n", edge_flow.edge(), edge_flow.from(), edge_flow.to(), edge_flow.flow());
+    }
+    out
+}
+
+fn format_stats(stats: &SolverStats) -> String {
+    let count = |value: Option<u32>| value.map_or("-".to_owned(), |value| value.to_string());
+    format!(
+        "nodes: {}, edges: {}, augmentations: {}, pivots: {}\n\
+         network: {:.1} ms, max flow: {:.1} ms, min-cost flow: {:.1} ms, paths: {:.1} ms\n",
+        stats.num_nodes(), stats.num_edges(), count(stats.augmentations()), count(stats.pivots()),
+        stats.network_ms(), stats.max_flow_ms(), stats.min_cost_flow_ms(), stats.paths_ms(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Options {
+        parse_args(args.split_whitespace().map(str::to_owned)).unwrap()
+    }
+
+    #[test]
+    fn solve() {
+        let problem = r#"{"edges": [
+            {"from": "s", "to": "a", "capacity": 2, "cost": 1},
+            {"from": "a", "to": "t", "capacity": 3, "cost": 1},
+            {"from": "s", "to": "t", "capacity": 1, "cost": 5}
+        ], "source": "s", "sink": "t"}"#;
+        let (output, stats) = run(&args(""), problem).unwrap();
+        assert_eq!(output, "flow: 3\ncost: 9\npaths:\n  2\ts -> a -> t\n  1\ts -> t\n");
+        assert_eq!(stats.unwrap().num_edges(), 3);
+        let (output, _) = run(&args("--problem max-flow --sink a --output dimacs"), problem).unwrap();
+        assert_eq!(output, "s 2\nf s a 2\nf a t 0\nf s t 0\n");
+
+        let csv = "from,to,capacity,cost\na,b,4,2\nb,a,4,-3\n";
+        let (output, stats) = run(&args("--format csv --problem circulation"), csv).unwrap();
+        assert_eq!(output, "cost: -4\nedge flows:\n  0\ta -> b\t4\n  1\tb -> a\t4\n");
+        assert!(stats.is_none());
+        // Without terminals, the default is a b-flow, which without supplies is a circulation.
+        assert_eq!(run(&args("--format csv"), csv).unwrap().0, output);
+        assert_eq!(run(&args("--format csv --problem mcmf"), csv).unwrap_err(), "the problem needs a source and a sink");
+
+        assert_eq!(
+            parse_args(["--output".to_owned(), "svg".to_owned()]).unwrap_err(),
+            "--output must be one of summary, json, dimacs, dot, got 'svg'",
+        );
+        assert_eq!(run(&args("problem.lp"), "").unwrap_err(), "can't tell the format of '.lp' files, use --format");
+    }
+}
@@ -0,0 +1,270 @@
+// Classic problems that reduce to flows. The helpers build the network themselves, so callers
+// only deal with the problem's own terms.
+
+use std::collections::HashMap;
+
+use crate::{IndexedGraphBuilder, MaxFlowAlgorithm, McmfError, ID};
+
+// An optimal assignment of rows to columns, see `solve_assignment`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    pairs: Vec<(ID, ID)>,
+    total_cost: f64,
+}
+
+impl Assignment {
+    // Assigned `(row, column)` pairs, ordered by row.
+    pub fn pairs(&self) -> &[(ID, ID)] { &self.pairs }
+    pub fn column(&self, row: ID) -> Option<ID> {
+        self.pairs.iter().find(|&&(i, _)| i == row).map(|&(_, column)| column)
+    }
+    // The sum of the exact costs of the assigned pairs.
+    pub fn total_cost(&self) -> f64 { self.total_cost }
+}
+
+// Assigns each row of the cost matrix to a different column, or each column to a different row if
+// there are fewer columns, so that the total cost is minimal. Costs may be negative.
+pub fn solve_assignment(costs: &[Vec<f64>]) -> Result<Assignment, McmfError> {
+    let num_columns = costs.first().map_or(0, Vec::len);
+    check_costs(costs, num_columns, false)?;
+    let pairs: Vec<_> = costs.iter().enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, &cost)| (i, j, cost)))
+        .collect();
+    min_cost_matching(costs.len(), num_columns, &pairs)
+}
+
+// Optimal shipments from suppliers to consumers, see `solve_transportation`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transportation {
+    shipments: Vec<Vec<f64>>,
+    total_cost: f64,
+}
+
+impl Transportation {
+    // The amount shipped from each supplier (row) to each consumer (column).
+    pub fn shipments(&self) -> &[Vec<f64>] { &self.shipments }
+    pub fn total_cost(&self) -> f64 { self.total_cost }
+}
+
+// Ships the supplies to the demands at the minimum total cost, where `costs[i][j]` is the cost per
+// unit from supplier `i` to consumer `j`. If the totals differ, a dummy consumer takes the surplus
+// supply, or a dummy supplier covers the missing demand, at no cost; those amounts stay unshipped.
+// Amounts must be integers, like capacities.
+pub fn solve_transportation(
+    supplies: &[f64], demands: &[f64], costs: &[Vec<f64>]
+) -> Result<Transportation, McmfError> {
+    if costs.len() != supplies.len() {
+        return Err(format!("expected {} rows of costs, got {}", supplies.len(), costs.len()).into());
+    }
+    check_costs(costs, demands.len(), false)?;
+    if let Some(amount) = supplies.iter().chain(demands).find(|&&amount| amount < 0.) {
+        return Err(format!("supplies and demands must be non-negative, got {}", amount).into());
+    }
+    let (num_suppliers, num_consumers) = (supplies.len(), demands.len());
+    let mut builder = IndexedGraphBuilder::new();
+    builder.set_cost_precision(None);
+    builder.add_nodes((num_suppliers + num_consumers) as u32);
+    for (i, &supply) in supplies.iter().enumerate() {
+        builder.set_supply(i, supply)?;
+    }
+    for (j, &demand) in demands.iter().enumerate() {
+        builder.set_demand(num_suppliers + j, demand)?;
+    }
+    for (i, row) in costs.iter().enumerate() {
+        for (j, &cost) in row.iter().enumerate() {
+            builder.add_edge(i, num_suppliers + j, supplies[i].min(demands[j]), cost)?;
+        }
+    }
+    let surplus = supplies.iter().sum::<f64>() - demands.iter().sum::<f64>();
+    if surplus != 0. {
+        let dummy = builder.add_nodes(1) as usize;
+        builder.set_supply(dummy, -surplus)?;
+        if surplus > 0. {
+            for (i, &supply) in supplies.iter().enumerate() {
+                builder.add_edge(i, dummy, supply, 0.)?;
+            }
+        } else {
+            for (j, &demand) in demands.iter().enumerate() {
+                builder.add_edge(dummy, num_suppliers + j, demand, 0.)?;
+            }
+        }
+    }
+    let solution = builder.solve_b_flow(Default::default())?;
+    let flows: Vec<_> = solution.edge_flows().iter().map(|edge_flow| edge_flow.flow()).collect();
+    Ok(Transportation {
+        shipments: (0..num_suppliers).map(|i| flows[i * num_consumers..(i + 1) * num_consumers].to_vec()).collect(),
+        total_cost: solution.total_cost(),
+    })
+}
+
+// A maximum matching, see `solve_bipartite_matching`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matching {
+    pairs: Vec<(String, String)>,
+}
+
+impl Matching {
+    // Matched `(left, right)` pairs, in the order of the left nodes.
+    pub fn pairs(&self) -> &[(String, String)] { &self.pairs }
+    pub fn size(&self) -> usize { self.pairs.len() }
+}
+
+// Matches as many left nodes to right nodes as possible along the given `(left, right)` edges,
+// each node at most once. The two sides may share names: edges always go from left to right.
+pub fn solve_bipartite_matching(
+    left: &[String], right: &[String], edges: &[(String, String)]
+) -> Result<Matching, McmfError> {
+    let indices = |side: &str, names: &[String], offset: usize| {
+        let mut indices = HashMap::new();
+        for (i, name) in names.iter().enumerate() {
+            if indices.insert(name.clone(), offset + i).is_some() {
+                return Err(McmfError::Invalid(format!("duplicate {} node: {}", side, name)));
+            }
+        }
+        Ok(indices)
+    };
+    let left_indices = indices("left", left, 1)?;
+    let right_indices = indices("right", right, 1 + left.len())?;
+    let mut builder = IndexedGraphBuilder::new();
+    let (source, sink) = (0, 1 + left.len() + right.len());
+    builder.add_nodes(sink as u32 + 1);
+    for i in 0..left.len() {
+        builder.add_edge(source, 1 + i, 1., 0.)?;
+    }
+    for j in 0..right.len() {
+        builder.add_edge(1 + left.len() + j, sink, 1., 0.)?;
+    }
+    let first_edge = left.len() + right.len();
+    for (from, to) in edges {
+        let from = *left_indices.get(from).ok_or_else(|| format!("unknown left node: {}", from))?;
+        let to = *right_indices.get(to).ok_or_else(|| format!("unknown right node: {}", to))?;
+        builder.add_edge(from, to, 1., 0.)?;
+    }
+    let solution = builder.solve_max_flow(source, sink, Default::default(), MaxFlowAlgorithm::Dinic)?;
+    let mut pairs: Vec<_> = edges.iter().zip(&solution.edge_flows()[first_edge..])
+        .filter(|(_, edge_flow)| edge_flow.flow() > 0.)
+        .map(|(edge, _)| edge.clone())
+        .collect();
+    pairs.sort_by_key(|(from, _)| left_indices[from]);
+    Ok(Matching { pairs })
+}
+
+// An optimal set of projects, see `solve_project_selection`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectSelection {
+    projects: Vec<ID>,
+    profit: f64,
+}
+
+impl ProjectSelection {
+    // The selected projects, in increasing order.
+    pub fn projects(&self) -> &[ID] { &self.projects }
+    // The total profit of the selected projects.
+    pub fn profit(&self) -> f64 { self.profit }
+}
+
+// Selects projects so that their total profit is maximal, where a `(project, required)` dependency
+// means that `project` can only be selected along with `required`. Profits may be negative, e.g. for
+// tools that other projects need, and must be integers, like capacities. This is a maximum-weight
+// closure: the source gives each profitable project its profit, each costly project pays its cost to
+// the sink, and dependencies can't be cut. The projects left on the source side of a minimum cut
+// form the smallest optimal selection.
+pub fn solve_project_selection(profits: &[f64], dependencies: &[(ID, ID)]) -> Result<ProjectSelection, McmfError> {
+    if let Some(&(project, required)) = dependencies.iter()
+        .find(|&&(project, required)| project.max(required) as usize >= profits.len())
+    {
+        return Err(format!("dependency {} -> {} refers to an unknown project", project, required).into());
+    }
+    let mut builder = IndexedGraphBuilder::new();
+    let (source, sink) = (profits.len(), profits.len() + 1);
+    builder.add_nodes(sink as u32 + 1);
+    for (i, &profit) in profits.iter().enumerate() {
+        if !profit.is_finite() {
+            return Err(format!("profit of project {} is not finite", i).into());
+        }
+        if profit > 0. {
+            builder.add_edge(source, i, profit, 0.)?;
+        } else if profit < 0. {
+            builder.add_edge(i, sink, -profit, 0.)?;
+        }
+    }
+    for &(project, required) in dependencies.iter().filter(|(project, required)| project != required) {
+        builder.add_edge(project as usize, required as usize, f64::INFINITY, 0.)?;
+    }
+    let solution = builder.solve_max_flow(source, sink, Default::default(), MaxFlowAlgorithm::Dinic)?;
+    // Without node names, nodes are named by their indices.
+    let mut projects: Vec<ID> = solution.min_cut().unwrap().source_side().iter()
+        .map(|name| name.parse().unwrap())
+        .filter(|&v| v < profits.len() as ID)
+        .collect();
+    projects.sort_unstable();
+    let profit = projects.iter().map(|&i| profits[i as usize]).sum();
+    Ok(ProjectSelection { projects, profit })
+}
+
+// Like `solve_assignment`, but an infinite cost forbids the pair, so the cost matrix can be sparse.
+// If the forbidden pairs rule out assigning every row (or every column, if there are fewer), fails
+// unless `allow_partial` is set, in which case the matching is as large as possible and, among
+// those, the cheapest.
+pub fn solve_min_cost_matching(costs: &[Vec<f64>], allow_partial: bool) -> Result<Assignment, McmfError> {
+    let num_columns = costs.first().map_or(0, Vec::len);
+    check_costs(costs, num_columns, true)?;
+    let pairs: Vec<_> = costs.iter().enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, &cost)| (i, j, cost)))
+        .filter(|&(_, _, cost)| cost.is_finite())
+        .collect();
+    let matching = min_cost_matching(costs.len(), num_columns, &pairs)?;
+    if !allow_partial && matching.pairs.len() < costs.len().min(num_columns) {
+        return Err(format!(
+            "forbidden pairs leave at most {} of {} matched", matching.pairs.len(), costs.len().min(num_columns)
+        ).into());
+    }
+    Ok(matching)
+}
+
+// Checks that each row has `num_columns` finite costs, or infinite ones if `forbidden` pairs are
+// allowed.
+fn check_costs(costs: &[Vec<f64>], num_columns: usize, forbidden: bool) -> Result<(), McmfError> {
+    for (i, row) in costs.iter().enumerate() {
+        if row.len() != num_columns {
+            return Err(format!("row {} has {} costs, expected {}", i, row.len(), num_columns).into());
+        }
+        if let Some(j) = row.iter().position(|&cost| !(cost.is_finite() || forbidden && cost == f64::INFINITY)) {
+            return Err(format!("cost at row {}, column {} is not finite", i, j).into());
+        }
+    }
+    Ok(())
+}
+
+// Matches as many of the given `(row, column, cost)` pairs as possible, no row or column twice,
+// and among maximum matchings picks the cheapest: MCMF from a source through the rows and columns
+// to a sink, with unit capacities.
+fn min_cost_matching(
+    num_rows: usize, num_columns: usize, pairs: &[(usize, usize, f64)]
+) -> Result<Assignment, McmfError> {
+    let mut builder = IndexedGraphBuilder::new();
+    // Picks a precision that keeps the costs exact if possible.
+    builder.set_cost_precision(None);
+    let (source, sink) = (0, num_rows + num_columns + 1);
+    builder.add_nodes(sink as u32 + 1);
+    for i in 0..num_rows {
+        builder.add_edge(source, 1 + i, 1., 0.)?;
+    }
+    for j in 0..num_columns {
+        builder.add_edge(1 + num_rows + j, sink, 1., 0.)?;
+    }
+    let first_pair = num_rows + num_columns;
+    for &(i, j, cost) in pairs {
+        builder.add_edge(1 + i, 1 + num_rows + j, 1., cost)?;
+    }
+    let solution = builder.solve_mcmf(source, sink, Default::default(), Default::default(), Default::default())?;
+    let mut assignment = Assignment { pairs: vec![], total_cost: 0. };
+    for (&(i, j, cost), edge_flow) in pairs.iter().zip(&solution.edge_flows()[first_pair..]) {
+        if edge_flow.flow() > 0. {
+            assignment.pairs.push((i as ID, j as ID));
+            assignment.total_cost += cost;
+        }
+    }
+    assignment.pairs.sort_unstable();
+    Ok(assignment)
+}
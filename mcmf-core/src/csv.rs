@@ -0,0 +1,110 @@
+// CSV edge lists, one edge per row:
+//
+//   from,to,capacity,cost
+//   a,b,10,1.5
+//
+// With a header row, columns are found by name (case-insensitively) and may come in any order,
+// and other columns are ignored. Without one, they must come in the order above. The cost column
+// is optional. Fields may be quoted with `"`, with `""` standing for a quote inside a field, but
+// a row must fit on one line. Blank lines are skipped.
+
+use crate::{GraphBuilder, McmfError};
+
+#[derive(Clone, Copy, Debug)]
+pub struct CsvOptions {
+    // E.g. '\t' for text copied from a spreadsheet. ',' by default.
+    pub delimiter: char,
+    // Whether the first row names the columns. True by default.
+    pub has_header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsvOptions {
+    pub fn new() -> Self {
+        CsvOptions { delimiter: ',', has_header: true }
+    }
+}
+
+struct Columns {
+    from: usize,
+    to: usize,
+    capacity: usize,
+    cost: Option<usize>,
+}
+
+pub(crate) fn builder_from_csv(text: &str, options: CsvOptions) -> Result<GraphBuilder, McmfError> {
+    let mut builder = GraphBuilder::new();
+    let mut columns = (!options.has_header).then_some(Columns { from: 0, to: 1, capacity: 2, cost: Some(3) });
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |message: &str| McmfError::Invalid(format!("line {}: {}", i + 1, message));
+        let fields = split_row(line, options.delimiter).map_err(|e| error(&e.to_string()))?;
+        let columns = match &columns {
+            Some(columns) => columns,
+            None => {
+                let column = |name: &str| fields.iter().position(|field| field.eq_ignore_ascii_case(name));
+                let required = |name: &str| column(name).ok_or_else(|| error(&format!("missing '{}' column", name)));
+                columns = Some(Columns {
+                    from: required("from")?,
+                    to: required("to")?,
+                    capacity: required("capacity")?,
+                    cost: column("cost"),
+                });
+                continue;
+            }
+        };
+        let field = |column: usize| fields.get(column).ok_or_else(|| error("too few fields"));
+        let number = |column: usize| -> Result<f64, McmfError> {
+            let field = field(column)?;
+            field.parse().map_err(|_| error(&format!("invalid number '{}'", field)))
+        };
+        // A missing trailing cost field means zero, so that cost can be left out without a header.
+        let cost = match columns.cost {
+            Some(column) if fields.get(column).is_some_and(|field| !field.is_empty()) => number(column)?,
+            _ => 0.,
+        };
+        builder.add_edge(field(columns.from)?.clone(), field(columns.to)?.clone(), number(columns.capacity)?, cost)
+            .map_err(|e| error(&e.to_string()))?;
+    }
+    Ok(builder)
+}
+
+// Splits a row into trimmed fields.
+fn split_row(line: &str, delimiter: char) -> Result<Vec<String>, McmfError> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|&c| c != delimiter && c.is_whitespace()).is_some() {}
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("unterminated quoted field".into()),
+                }
+            }
+            while chars.next_if(|&c| c != delimiter && c.is_whitespace()).is_some() {}
+            if chars.peek().is_some_and(|&c| c != delimiter) {
+                return Err("unexpected text after a quoted field".into());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != delimiter) {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
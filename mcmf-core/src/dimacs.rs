@@ -0,0 +1,137 @@
+// The DIMACS formats used by network flow benchmarks. A min-cost flow problem looks like
+//
+//   c comment
+//   p min <nodes> <arcs>
+//   n <node> <supply>
+//   a <from> <to> <lower> <capacity> <cost>
+//
+// and a max flow problem like
+//
+//   p max <nodes> <arcs>
+//   n <node> s
+//   n <node> t
+//   a <from> <to> <capacity>
+//
+// Nodes are numbered from 1 and are named by their numbers in the builder. Solutions are written
+// as a `s <value>` line followed by `f <from> <to> <flow>` lines.
+
+use std::fmt::Write;
+
+use crate::{network::INFINITE_CAPACITY, EdgeFlow, GraphBuilder, McmfError};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Problem {
+    Min,
+    Max,
+}
+
+pub(crate) fn builder_from_dimacs(text: &str) -> Result<GraphBuilder, McmfError> {
+    let mut builder = GraphBuilder::new();
+    let mut problem = None;
+    let mut num_nodes = 0;
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: &str| McmfError::Invalid(format!("line {}: {}", i + 1, message));
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let number = |field: usize| -> Result<f64, McmfError> {
+            let field = fields.get(field).ok_or_else(|| error("too few fields"))?;
+            field.parse().map_err(|_| error(&format!("invalid number '{}'", field)))
+        };
+        let node = |field: usize| -> Result<String, McmfError> {
+            let v = number(field)?;
+            if v.fract() != 0. || v < 1. || v > num_nodes as f64 {
+                return Err(error(&format!("node {} out of range", v)));
+            }
+            Ok(v.to_string())
+        };
+        match (fields.first().copied(), problem) {
+            (None | Some("c"), _) => {}
+            (Some("p"), None) => {
+                problem = Some(match fields.get(1).copied() {
+                    Some("min") => Problem::Min,
+                    Some("max") => Problem::Max,
+                    _ => return Err(error("expected 'p min' or 'p max'")),
+                });
+                num_nodes = number(2)? as usize;
+                builder.add_nodes((1..=num_nodes).map(|v| v.to_string()).collect())?;
+            }
+            (Some("p"), Some(_)) => return Err(error("duplicate problem line")),
+            (Some(_), None) => return Err(error("expected the problem line first")),
+            (Some("n"), Some(Problem::Min)) => {
+                builder.set_supply(node(1)?, number(2)?).map_err(|e| error(&e.to_string()))?;
+            }
+            (Some("n"), Some(Problem::Max)) => {
+                match fields.get(2).copied() {
+                    Some("s") => builder.source = Some(node(1)?),
+                    Some("t") => builder.sink = Some(node(1)?),
+                    _ => return Err(error("expected 's' or 't'")),
+                }
+            }
+            (Some("a"), Some(Problem::Min)) => {
+                builder.add_edge_with_bounds(node(1)?, node(2)?, number(3)?, number(4)?, number(5)?)
+                    .map_err(|e| error(&e.to_string()))?;
+            }
+            (Some("a"), Some(Problem::Max)) => {
+                builder.add_edge(node(1)?, node(2)?, number(3)?, 0.).map_err(|e| error(&e.to_string()))?;
+            }
+            (Some(kind), _) => return Err(error(&format!("unknown line type '{}'", kind))),
+        }
+    }
+    if problem.is_none() {
+        return Err("missing problem line".into());
+    }
+    Ok(builder)
+}
+
+// Nodes are numbered in the order they were added. Undirected edges are written as a pair of
+// opposite arcs, which is equivalent since undirected edge costs are non-negative. For min-cost
+// problems, the pieces of a piecewise-linear cost are written as parallel arcs.
+pub(crate) fn builder_to_dimacs(builder: &GraphBuilder, max_flow: bool) -> Result<String, McmfError> {
+    let graph = &builder.graph;
+    if graph.nodes.iter().any(|node| node.capacity.is_some()) {
+        return Err("node capacities cannot be represented in DIMACS".into());
+    }
+    if graph.capacity_denominator != 1 {
+        return Err("fractional capacities cannot be represented in DIMACS".into());
+    }
+    if graph.edges.iter().any(|edge| edge.capacity == INFINITE_CAPACITY) {
+        return Err("infinite capacities cannot be represented in DIMACS".into());
+    }
+    let arcs: Vec<_> = graph.edges.iter().flat_map(|edge| {
+        let reverse = edge.undirected.then_some((edge.to, edge.from, edge.lower, edge.capacity, edge.cost));
+        let pieces: Vec<_> = match max_flow || edge.pieces.is_empty() {
+            true => vec![(edge.from, edge.to, edge.lower, edge.capacity, edge.cost)],
+            false => edge.pieces.iter().map(|&(capacity, cost)| (edge.from, edge.to, 0, capacity, cost)).collect(),
+        };
+        pieces.into_iter().chain(reverse)
+    }).collect();
+    let mut out = String::new();
+    let problem = if max_flow { "max" } else { "min" };
+    writeln!(out, "p {} {} {}", problem, graph.nodes.len(), arcs.len()).unwrap();
+    if max_flow {
+        for (terminal, kind) in [(&builder.source, "s"), (&builder.sink, "t")] {
+            let terminal = terminal.clone().ok_or_else(|| "source and sink must be set".to_owned())?;
+            writeln!(out, "n {} {}", builder.get_vertex(terminal)? + 1, kind).unwrap();
+        }
+    } else {
+        for (v, node) in graph.nodes.iter().enumerate().filter(|(_, node)| node.supply != 0) {
+            writeln!(out, "n {} {}", v + 1, node.supply).unwrap();
+        }
+    }
+    for (from, to, lower, capacity, cost) in arcs {
+        if max_flow {
+            writeln!(out, "a {} {} {}", from + 1, to + 1, capacity).unwrap();
+        } else {
+            writeln!(out, "a {} {} {} {} {}", from + 1, to + 1, lower, capacity, cost).unwrap();
+        }
+    }
+    Ok(out)
+}
+
+pub(crate) fn flow_to_dimacs(value: f64, edge_flows: &[EdgeFlow]) -> String {
+    let mut out = String::new();
+    writeln!(out, "s {}", value).unwrap();
+    for flow in edge_flows {
+        writeln!(out, "f {} {} {}", flow.from, flow.to, flow.flow).unwrap();
+    }
+    out
+}
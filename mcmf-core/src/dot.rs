@@ -0,0 +1,60 @@
+// Graphviz DOT export. Edges of a solution are labeled `flow/capacity`; saturated edges are
+// drawn bold, edges of the minimum cut red, and edges without flow gray.
+
+use std::fmt::Write;
+
+use crate::{GraphBuilder, McmfSolution};
+
+pub(crate) fn builder_to_dot(builder: &GraphBuilder) -> String {
+    let graph = &builder.graph;
+    let mut out = "digraph {\n".to_owned();
+    for (v, node) in graph.nodes.iter().enumerate() {
+        let mut label = graph.node_name(v);
+        if node.supply != 0 {
+            write!(label, "\nsupply {}", graph.unscale_amount(node.supply)).unwrap();
+        }
+        if let Some(capacity) = node.capacity {
+            write!(label, "\ncapacity {}", graph.unscale_amount(capacity)).unwrap();
+        }
+        writeln!(out, "  {} [label={}];", quote(&graph.node_name(v)), quote(&label)).unwrap();
+    }
+    for edge in &graph.edges {
+        let mut label = if edge.lower != 0 {
+            format!("[{}, {}]", graph.unscale_amount(edge.lower), graph.unscale_amount(edge.capacity))
+        } else {
+            graph.unscale_amount(edge.capacity).to_string()
+        };
+        if edge.cost != 0. {
+            write!(label, " @ {}", edge.cost).unwrap();
+        }
+        let direction = if edge.undirected { ", dir=none" } else { "" };
+        writeln!(
+            out, "  {} -> {} [label={}{}];",
+            quote(&graph.node_name(edge.from)), quote(&graph.node_name(edge.to)), quote(&label), direction
+        ).unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+pub(crate) fn solution_to_dot(solution: &McmfSolution) -> String {
+    let mut out = "digraph {\n".to_owned();
+    for flow in &solution.edge_flows {
+        let mut attributes = vec![format!("label={}", quote(&format!("{}/{}", flow.flow, flow.capacity)))];
+        if solution.min_cut.as_ref().is_some_and(|cut| cut.edges.contains(&flow.edge)) {
+            attributes.push("color=red".to_owned());
+        } else if flow.flow == 0. {
+            attributes.push("color=gray".to_owned());
+        }
+        if flow.flow == flow.capacity {
+            attributes.push("style=bold".to_owned());
+        }
+        writeln!(out, "  {} -> {} [{}];", quote(&flow.from), quote(&flow.to), attributes.join(", ")).unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
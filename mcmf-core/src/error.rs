@@ -0,0 +1,76 @@
+// Errors of the builders and solvers. Each displays as a message for users with the names and
+// values involved, e.g. "node 'x' not found". The variants tell apart the errors that callers
+// may want to handle, e.g. by retrying with a larger limit; the rest are `Invalid`.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum McmfError {
+    // A node name that is not in the graph.
+    UnknownNode(String),
+    // A node or edge index that is out of range.
+    NodeOutOfRange(usize),
+    EdgeOutOfRange(usize),
+    // A capacity below zero, as given.
+    NegativeCapacity(f64),
+    // Numbers that don't fit into the integer arithmetic of the solvers, with the reason.
+    Overflow(String),
+    // No flow satisfies the constraints, with the reason.
+    Infeasible(String),
+    // A cycle of negative cost with unbounded capacity, by node name. The first node is repeated
+    // at the end.
+    NegativeCycle(Vec<String>),
+    // See `GraphBuilder::set_cancellation_token`.
+    Cancelled,
+    // An iteration or time limit, as described.
+    LimitReached(String),
+    // Any other invalid input or request, as described.
+    Invalid(String),
+    // A solver ended up in a state that it should never reach, as described. This is a bug.
+    Internal(String),
+}
+
+impl fmt::Display for McmfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            McmfError::UnknownNode(name) => write!(f, "node '{}' not found", name),
+            McmfError::NodeOutOfRange(index) => write!(f, "node index {} out of range", index),
+            McmfError::EdgeOutOfRange(index) => write!(f, "edge {} out of range", index),
+            McmfError::NegativeCapacity(capacity) => write!(f, "capacity must be non-negative, got {}", capacity),
+            McmfError::Overflow(reason) => write!(f, "numeric overflow: {}", reason),
+            McmfError::NegativeCycle(nodes) => write!(f, "negative cost cycle: {}", nodes.join(" -> ")),
+            McmfError::Cancelled => write!(f, "solve cancelled"),
+            McmfError::Internal(message) => write!(f, "internal error: {}", message),
+            McmfError::Infeasible(message) | McmfError::LimitReached(message) | McmfError::Invalid(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for McmfError {}
+
+impl From<String> for McmfError {
+    fn from(message: String) -> Self {
+        McmfError::Invalid(message)
+    }
+}
+
+impl From<&str> for McmfError {
+    fn from(message: &str) -> Self {
+        McmfError::Invalid(message.to_owned())
+    }
+}
+
+impl From<McmfError> for String {
+    fn from(error: McmfError) -> Self {
+        error.to_string()
+    }
+}
+
+// Compares the message, e.g. `assert_eq!(error, "solve cancelled")`.
+impl PartialEq<&str> for McmfError {
+    fn eq(&self, other: &&str) -> bool {
+        self.to_string().as_str() == *other
+    }
+}
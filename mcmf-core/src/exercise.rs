@@ -0,0 +1,217 @@
+// Classroom exercises. In a max flow found by hand, the user proposes augmenting paths in the
+// residual graph one at a time, and each is checked against the current flow before it is sent.
+// Mistakes are explained with the edge and the amounts involved; costs play no part. Generated
+// exercises come with their answers, so that a frontend can grade them without solving.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    generate_random_graph, network::INFINITE_CAPACITY, AugmentingPath, GraphBuilder, IndexedGraphBuilder, McmfError,
+    MinCut, ResidualEdge, ID,
+};
+
+// How large the instances of `generate_exercise` are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    // 4 nodes, 5 edges, capacities and costs up to 5.
+    #[default]
+    Easy,
+    // 6 nodes, 10 edges, capacities and costs up to 10.
+    Medium,
+    // 8 nodes, 16 edges, capacities and costs up to 20.
+    Hard,
+}
+
+// The result of `generate_exercise`.
+#[derive(Clone)]
+pub struct Exercise {
+    builder: GraphBuilder,
+    max_flow: f64,
+    min_cost: f64,
+    min_cut: MinCut,
+}
+
+impl Exercise {
+    // The instance, with the source "s" and the sink "t" set.
+    pub fn builder(&self) -> &GraphBuilder { &self.builder }
+    pub fn max_flow(&self) -> f64 { self.max_flow }
+    // The total cost of a min-cost max flow.
+    pub fn min_cost(&self) -> f64 { self.min_cost }
+    // One of the minimum cuts. Others may exist, so an answer is right if its capacity equals
+    // `max_flow`.
+    pub fn min_cut(&self) -> &MinCut { &self.min_cut }
+}
+
+// A small random instance from `generate_random_graph`, which the same seed always reproduces,
+// solved for min-cost max flow.
+pub fn generate_exercise(difficulty: Difficulty, seed: u32) -> Result<Exercise, McmfError> {
+    let (nodes, edges, max_amount) = match difficulty {
+        Difficulty::Easy => (4, 5, 5),
+        Difficulty::Medium => (6, 10, 10),
+        Difficulty::Hard => (8, 16, 20),
+    };
+    let builder = generate_random_graph(nodes, edges, max_amount, max_amount, seed)?;
+    let solution = builder.solve_mcmf(
+        "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+    )?;
+    Ok(Exercise {
+        max_flow: solution.max_flow(),
+        min_cost: solution.total_cost().unwrap(),
+        min_cut: solution.min_cut().unwrap().clone(),
+        builder,
+    })
+}
+
+// See `GraphBuilder::manual_max_flow`.
+#[derive(Clone)]
+pub struct ManualMaxFlow {
+    graph: IndexedGraphBuilder,
+    node_indices: HashMap<String, usize>,
+    source: usize,
+    sink: usize,
+    // Net flow on each edge, negative against undirected ones.
+    flows: Vec<i64>,
+    value: i64,
+}
+
+impl ManualMaxFlow {
+    pub(crate) fn new(
+        graph: &IndexedGraphBuilder, node_indices: HashMap<String, usize>, source: usize, sink: usize
+    ) -> Result<ManualMaxFlow, McmfError> {
+        if source == sink {
+            return Err("source and sink must differ".into());
+        }
+        if graph.edges.iter().any(|edge| edge.lower > 0) {
+            return Err("manual max flows don't support lower bounds".into());
+        }
+        if graph.nodes.iter().any(|node| node.capacity.is_some()) {
+            return Err("manual max flows don't support node capacities".into());
+        }
+        let flows = vec![0; graph.edges.len()];
+        Ok(ManualMaxFlow { graph: graph.clone(), node_indices, source, sink, flows, value: 0 })
+    }
+
+    // Sends as much flow as fits along `nodes`, from the source to the sink, and returns the step
+    // like `McmfSolution::augmenting_paths` does. Between two nodes, the path takes the residual
+    // arc of an edge joining them that can take the most: forward along an edge that isn't full,
+    // or backward along one with flow to undo. Fails without changing the flow, explaining why, if
+    // the path doesn't go from the source to the sink without visiting a node twice, or if some
+    // step has no residual capacity left.
+    pub fn augment(&mut self, nodes: &[String]) -> Result<AugmentingPath, McmfError> {
+        let path = nodes.iter()
+            .map(|name| self.node_indices.get(name).copied().ok_or_else(|| McmfError::UnknownNode(name.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+        let name = |v| self.graph.node_name(v);
+        if path.first() != Some(&self.source) {
+            return Err(format!("an augmenting path must start at the source '{}'", name(self.source)).into());
+        }
+        if path.last() != Some(&self.sink) {
+            return Err(format!("an augmenting path must end at the sink '{}'", name(self.sink)).into());
+        }
+        let mut visited = HashSet::new();
+        if let Some(&v) = path.iter().find(|&&v| !visited.insert(v)) {
+            return Err(format!("an augmenting path can't visit '{}' twice", name(v)).into());
+        }
+        let mut steps = vec![];
+        for pair in path.windows(2) {
+            let (u, v) = (pair[0], pair[1]);
+            let joining = (0..self.graph.edges.len()).filter(|&e| {
+                let edge = &self.graph.edges[e];
+                (edge.from, edge.to) == (u, v) || (edge.from, edge.to) == (v, u)
+            });
+            // The first edge among those that can take the most.
+            let best = joining.map(|e| (e, self.residual(e, u))).reduce(|a, b| if b.1 > a.1 { b } else { a });
+            let Some((e, residual)) = best else {
+                return Err(format!("no edge joins '{}' and '{}'", name(u), name(v)).into());
+            };
+            if residual == 0 {
+                let edge = &self.graph.edges[e];
+                return Err(match edge.from == u || edge.undirected {
+                    true => format!(
+                        "edge {} from '{}' to '{}' is saturated: it carries its full capacity of {}",
+                        e, name(u), name(v), self.graph.unscale_amount(edge.capacity)
+                    ),
+                    false => format!(
+                        "edge {} goes from '{}' to '{}' and has no flow to send back", e, name(v), name(u)
+                    ),
+                }.into());
+            }
+            steps.push((e, u, v, residual));
+        }
+        let amount = steps.iter().map(|step| step.3).min().unwrap();
+        if amount == INFINITE_CAPACITY {
+            return Err("the path has infinite capacity, so the max flow is unbounded".into());
+        }
+        let arcs = steps.into_iter().map(|(e, u, v, residual)| {
+            let edge = &self.graph.edges[e];
+            self.flows[e] += if edge.from == u { amount } else { -amount };
+            let left = if residual == INFINITE_CAPACITY { residual } else { residual - amount };
+            self.residual_edge(e, u, v, left)
+        }).collect();
+        self.value += amount;
+        Ok(AugmentingPath {
+            flow: self.graph.unscale_amount(amount),
+            nodes: path.into_iter().map(|v| self.graph.node_name(v)).collect(),
+            arcs,
+        })
+    }
+
+    // The flow value so far.
+    pub fn flow(&self) -> f64 { self.graph.unscale_amount(self.value) }
+
+    // Net flow on each edge by ID, negative against undirected ones, as in `SolverState`.
+    pub fn edge_flows(&self) -> Vec<f64> {
+        self.flows.iter().map(|&flow| self.graph.unscale_amount(flow)).collect()
+    }
+
+    // The arcs of the residual graph that can take flow, forward and backward for each edge.
+    pub fn residual_edges(&self) -> Vec<ResidualEdge> {
+        let arcs = self.graph.edges.iter().enumerate()
+            .flat_map(|(e, edge)| [(e, edge.from, edge.to), (e, edge.to, edge.from)]);
+        arcs.filter_map(|(e, u, v)| {
+            let residual = self.residual(e, u);
+            (residual > 0 && u != v).then(|| self.residual_edge(e, u, v, residual))
+        }).collect()
+    }
+
+    // Whether no augmenting path is left, so that the flow is maximum.
+    pub fn is_maximum(&self) -> bool {
+        let mut reachable = vec![false; self.graph.nodes.len()];
+        reachable[self.source] = true;
+        let mut stack = vec![self.source];
+        while let Some(u) = stack.pop() {
+            for (e, edge) in self.graph.edges.iter().enumerate() {
+                let v = if edge.from == u { edge.to } else if edge.to == u { edge.from } else { continue };
+                if !reachable[v] && self.residual(e, u) > 0 {
+                    reachable[v] = true;
+                    stack.push(v);
+                }
+            }
+        }
+        !reachable[self.sink]
+    }
+
+    // How much more flow edge `e` can take from its end `u` to the other one.
+    fn residual(&self, e: usize, u: usize) -> i64 {
+        let edge = &self.graph.edges[e];
+        let flow = if edge.from == u { self.flows[e] } else { -self.flows[e] };
+        match edge.from == u || edge.undirected {
+            true if edge.capacity == INFINITE_CAPACITY => INFINITE_CAPACITY,
+            true => edge.capacity - flow,
+            false => -flow,
+        }
+    }
+
+    fn residual_edge(&self, e: usize, u: usize, v: usize, residual: i64) -> ResidualEdge {
+        let edge = &self.graph.edges[e];
+        let reverse = edge.from != u && !edge.undirected;
+        ResidualEdge {
+            edge: Some(e as ID),
+            from: self.graph.node_name(u),
+            to: self.graph.node_name(v),
+            capacity: self.graph.unscale_amount(residual),
+            cost: if reverse { -edge.cost } else { edge.cost },
+            reverse,
+        }
+    }
+}
@@ -0,0 +1,240 @@
+// GraphML import. Node IDs become node names. Edge capacities and costs are read from `<data>`
+// elements whose key has the given `attr.name` (or, failing that, the given key ID), falling back
+// to the key `<default>`. Edges are undirected if the graph says `edgedefault="undirected"`
+// unless they override it with `directed="true"`, and vice versa.
+
+use crate::{GraphBuilder, McmfError};
+
+pub(crate) fn builder_from_graphml(text: &str, capacity_key: &str, cost_key: &str) -> Result<GraphBuilder, McmfError> {
+    let root = Parser { text, pos: 0 }.parse_document()?;
+    if root.name != "graphml" {
+        return Err(format!("expected a 'graphml' root element, got '{}'", root.name).into());
+    }
+    let keys: Vec<_> = root.children("key").collect();
+    let capacity_key = find_key(&keys, capacity_key)
+        .ok_or_else(|| format!("no GraphML key for capacity '{}'", capacity_key))?;
+    let cost_key = find_key(&keys, cost_key);
+    let graph = root.child("graph").ok_or_else(|| "missing 'graph' element".to_owned())?;
+    let undirected_default = graph.attribute("edgedefault") == Some("undirected");
+
+    let mut builder = GraphBuilder::new();
+    for node in graph.children("node") {
+        let id = node.attribute("id").ok_or_else(|| "node without 'id'".to_owned())?;
+        builder.add_nodes(vec![id.to_owned()])?;
+    }
+    for (i, edge) in graph.children("edge").enumerate() {
+        let context = |e: String| McmfError::Invalid(format!("edge {}: {}", i, e));
+        let attribute = |name| edge.attribute(name).ok_or_else(|| context(format!("missing '{}'", name)));
+        let (from, to) = (attribute("source")?.to_owned(), attribute("target")?.to_owned());
+        let value = |(key, default): (&str, Option<&str>)| {
+            let data = edge.children("data").find(|data| data.attribute("key") == Some(key));
+            data.map(|data| data.text.trim()).or(default).map(|value| {
+                value.parse::<f64>().map_err(|_| context(format!("invalid number '{}'", value)))
+            }).transpose()
+        };
+        let capacity = value(capacity_key)?.ok_or_else(|| context("missing capacity".to_owned()))?;
+        let cost = cost_key.map(value).transpose()?.flatten().unwrap_or(0.);
+        let undirected = match edge.attribute("directed") {
+            Some("true") => false,
+            Some("false") => true,
+            _ => undirected_default,
+        };
+        let result = if undirected {
+            builder.add_undirected_edge(from, to, capacity, cost)
+        } else {
+            builder.add_edge(from, to, capacity, cost)
+        };
+        result.map_err(|e| context(e.to_string()))?;
+    }
+    Ok(builder)
+}
+
+// Returns the ID and the default value of the edge key with the given name or ID.
+fn find_key<'a>(keys: &[&'a Element], name: &'a str) -> Option<(&'a str, Option<&'a str>)> {
+    keys.iter().find(|key| key.attribute("attr.name") == Some(name) && key.attribute("for") != Some("node"))
+        .or_else(|| keys.iter().find(|key| key.attribute("id") == Some(name)))
+        .map(|key| (key.attribute("id").unwrap_or(name), key.child("default").map(|default| default.text.trim())))
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    elements: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements.iter().filter(move |element| element.name == name)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+}
+
+// A minimal XML parser: enough for GraphML, but without DTD support.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse_document(&mut self) -> Result<Element, McmfError> {
+        self.skip_misc()?;
+        let root = self.parse_element()?;
+        self.skip_misc()?;
+        if self.pos < self.text.len() {
+            return Err(self.error("unexpected content after the root element"));
+        }
+        Ok(root)
+    }
+
+    // Skips whitespace, comments, processing instructions and the doctype.
+    fn skip_misc(&mut self) -> Result<(), McmfError> {
+        loop {
+            self.pos += self.rest().len() - self.rest().trim_start().len();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_element(&mut self) -> Result<Element, McmfError> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        self.pos += 1;
+        let name = self.parse_name()?;
+        let mut element = Element { name: local_name(&name).to_owned(), attributes: vec![], elements: vec![], text: String::new() };
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let attribute = self.parse_name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = self.rest().chars().next().filter(|&c| c == '"' || c == '\'')
+                .ok_or_else(|| self.error("expected a quoted attribute value"))?;
+            self.pos += 1;
+            let end = self.rest().find(quote).ok_or_else(|| self.error("unterminated attribute value"))?;
+            let value = self.unescape(&self.rest()[..end])?;
+            self.pos += end + 1;
+            element.attributes.push((local_name(&attribute).to_owned(), value));
+        }
+        loop {
+            if self.rest().starts_with("</") {
+                self.pos += 2;
+                let closing = self.parse_name()?;
+                if closing != name {
+                    return Err(self.error(&format!("expected '</{}>'", name)));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(element);
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let end = self.rest().find("]]>").ok_or_else(|| self.error("unterminated CDATA section"))?;
+                element.text.push_str(&self.rest()[..end]);
+                self.pos += end + 3;
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with('<') {
+                element.elements.push(self.parse_element()?);
+            } else if self.rest().is_empty() {
+                return Err(self.error(&format!("unterminated element '{}'", name)));
+            } else {
+                let end = self.rest().find('<').unwrap_or(self.rest().len());
+                let text = self.unescape(&self.rest()[..end])?;
+                element.text.push_str(&text);
+                self.pos += end;
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, McmfError> {
+        let len = self.rest().find(|c: char| c.is_whitespace() || "=/>".contains(c)).unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = self.rest()[..len].to_owned();
+        self.pos += len;
+        Ok(name)
+    }
+
+    fn unescape(&self, s: &str) -> Result<String, McmfError> {
+        let mut out = String::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('&') {
+            out.push_str(&rest[..start]);
+            let end = rest[start..].find(';').ok_or_else(|| self.error("unterminated entity"))? + start;
+            let entity = &rest[start + 1..end];
+            let c = match entity {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity.strip_prefix("#x").map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                    .and_then(|code| code.ok()).and_then(char::from_u32),
+            };
+            out.push(c.ok_or_else(|| self.error(&format!("unknown entity '&{};'", entity)))?);
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> Result<(), McmfError> {
+        let end = self.rest().find(terminator).ok_or_else(|| self.error(&format!("expected '{}'", terminator)))?;
+        self.pos += end + terminator.len();
+        Ok(())
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), McmfError> {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", s)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos += self.rest().len() - self.rest().trim_start().len();
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> McmfError {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        McmfError::Invalid(format!("invalid XML at line {}: {}", line, message))
+    }
+}
+
+// Namespace prefixes are ignored.
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap()
+}
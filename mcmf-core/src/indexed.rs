@@ -0,0 +1,2417 @@
+use std::{cell::RefCell, collections::{HashMap, HashSet}, rc::Rc};
+
+use rs_graph::{traits::{GraphSize, IndexGraph}, EdgeVec};
+
+use crate::{
+    layout::{self, Layout},
+    network::{EdgeData, Network, NodeData, INFINITE_CAPACITY},
+    paths::{self, FlowPath},
+    sensitivity::Sensitivity, AugmentingPath, BalanceDiagnosis, Benchmark, CancellationToken, Circulation,
+    DinicPhase, DisjointPaths, DuplicateEdges, Edge, EdgeFlow, EdgeHandle, EdgeOverride, FlowDiagnosis, FlowSolution,
+    FlowVerification, GomoryHuTree, GraphFlows, GraphNode, MaxFlowAlgorithm, McmfError, McmfSolution,
+    MinCostFlowAlgorithm, MinCut, NodeFlow, NodeImbalance, Path, PathDecomposition, Pivot, ResidualEdge,
+    ScenarioResults, SecondaryObjective, SelfLoops, SolverStats, StorageStats, ViolatedCut, DEFAULT_COST_PRECISION, ID,
+    task::{SolveTask, Solver, SolverState},
+    logging::log,
+    utils::{block_on, map_bytes, Budget, Progress, Stopwatch, Trace, MAX_FLOW_PHASE, MIN_COST_FLOW_PHASE},
+};
+
+// A graph builder that refers to nodes by index instead of by name, for programmatically
+// generated graphs. Nodes are indexed from 0 in the order they were added. Names are optional:
+// unnamed nodes are reported by their index.
+#[derive(Clone)]
+pub struct IndexedGraphBuilder {
+    pub(crate) nodes: Vec<NodeData>,
+    pub(crate) edges: Vec<EdgeData>,
+    pub(crate) node_names: Vec<String>,
+    pub(crate) cost_precision: Option<u32>,
+    pub(crate) cost_denominator: Option<u32>,
+    pub(crate) capacity_denominator: u32,
+    pub(crate) time_limit_ms: Option<f64>,
+    pub(crate) iteration_limit: Option<u32>,
+    pub(crate) partial_results: bool,
+    pub(crate) pruning: bool,
+    pub(crate) chain_contraction: bool,
+    pub(crate) single_pass: bool,
+    pub(crate) max_paths: Option<u32>,
+    pub(crate) secondary_objective: SecondaryObjective,
+    pub(crate) trace: bool,
+    pub(crate) cross_validation: bool,
+    pub(crate) path_seed: Option<u32>,
+    pub(crate) path_node_names: bool,
+    pub(crate) duplicate_edges: DuplicateEdges,
+    pub(crate) self_loops: SelfLoops,
+    pub(crate) split_antiparallel_edges: bool,
+    // The first plain directed edge between each pair of nodes, only kept while duplicates are
+    // rejected or merged.
+    edge_ends: HashMap<(usize, usize), usize>,
+    // Shared with a `Solver`, which reads the trace while the solve is suspended.
+    pub(crate) trace_handle: Option<Rc<RefCell<Trace>>>,
+    pub(crate) cancellation: Option<CancellationToken>,
+    // Called with unscaled flows.
+    pub(crate) progress: Option<Progress<f64>>,
+    // Set for async solves only, see `SolveTask`.
+    pub(crate) yield_interval_ms: Option<f64>,
+    // The last `solve_mcmf`, see `GraphBuilder::resolve`.
+    last_mcmf: RefCell<Option<LastMcmf>>,
+    // The handle of the next edge added, see `GraphBuilder::edge_handle`.
+    next_edge_handle: u32,
+}
+
+// A smaller graph to solve instead and the builder edges that each of its edges stands for.
+type Reduction = (IndexedGraphBuilder, Vec<Vec<usize>>);
+
+#[derive(Clone)]
+struct LastMcmf {
+    source: usize,
+    sink: usize,
+    decomposition: PathDecomposition,
+    algorithm: MinCostFlowAlgorithm,
+    max_flow_algorithm: MaxFlowAlgorithm,
+    edge_flows: Vec<i64>,
+}
+
+impl Default for IndexedGraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IndexedGraphBuilder {
+    pub fn new() -> Self {
+        IndexedGraphBuilder {
+            nodes: vec![],
+            edges: vec![],
+            node_names: vec![],
+            cost_precision: Some(DEFAULT_COST_PRECISION),
+            cost_denominator: None,
+            capacity_denominator: 1,
+            time_limit_ms: None,
+            iteration_limit: None,
+            partial_results: false,
+            pruning: false,
+            chain_contraction: false,
+            single_pass: false,
+            max_paths: None,
+            secondary_objective: SecondaryObjective::None,
+            trace: false,
+            cross_validation: false,
+            path_seed: None,
+            path_node_names: true,
+            duplicate_edges: DuplicateEdges::Keep,
+            self_loops: SelfLoops::Keep,
+            split_antiparallel_edges: false,
+            edge_ends: HashMap::new(),
+            trace_handle: None,
+            cancellation: None,
+            progress: None,
+            yield_interval_ms: None,
+            last_mcmf: RefCell::new(None),
+            next_edge_handle: 0,
+        }
+    }
+
+    // See `GraphBuilder::clear`.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.edges.clear();
+        self.edge_ends.clear();
+        self.node_names.clear();
+        self.last_mcmf.get_mut().take();
+    }
+
+    // Adds `count` nodes and returns the index of the first one.
+    pub fn add_nodes(&mut self, count: u32) -> ID {
+        let first = self.nodes.len() as ID;
+        self.nodes.resize(self.nodes.len() + count as usize, NodeData::default());
+        first
+    }
+
+    pub fn num_nodes(&self) -> u32 { self.nodes.len() as u32 }
+
+    // See `GraphBuilder::storage_stats`.
+    pub fn storage_stats(&self) -> StorageStats {
+        let string = |s: &Option<String>| s.as_ref().map_or(0, String::capacity);
+        let node_bytes = self.nodes.capacity() * size_of::<NodeData>()
+            + self.nodes.iter().map(|node| string(&node.group) + string(&node.metadata)).sum::<usize>();
+        let edge_bytes = self.edges.capacity() * size_of::<EdgeData>()
+            + self.edges.iter()
+                .map(|edge| edge.pieces.capacity() * size_of::<(i64, f64)>() + string(&edge.metadata))
+                .sum::<usize>()
+            + map_bytes(&self.edge_ends);
+        let name_bytes = self.node_names.capacity() * size_of::<String>()
+            + self.node_names.iter().map(String::capacity).sum::<usize>();
+        StorageStats { node_bytes, edge_bytes, name_bytes }
+    }
+
+    // See `GraphBuilder::set_cost_precision`.
+    pub fn set_cost_precision(&mut self, decimals: Option<u32>) {
+        self.cost_precision = decimals;
+    }
+
+    // See `GraphBuilder::set_iteration_limit`.
+    pub fn set_iteration_limit(&mut self, limit: Option<u32>) {
+        self.iteration_limit = limit;
+    }
+
+    // See `GraphBuilder::set_partial_results`.
+    pub fn set_partial_results(&mut self, enabled: bool) {
+        self.partial_results = enabled;
+    }
+
+    // See `GraphBuilder::set_pruning`.
+    pub fn set_pruning(&mut self, enabled: bool) {
+        self.pruning = enabled;
+    }
+
+    // See `GraphBuilder::set_chain_contraction`.
+    pub fn set_chain_contraction(&mut self, enabled: bool) {
+        self.chain_contraction = enabled;
+    }
+
+    // See `GraphBuilder::set_single_pass`.
+    pub fn set_single_pass(&mut self, enabled: bool) {
+        self.single_pass = enabled;
+    }
+
+    // See `GraphBuilder::set_max_paths`.
+    pub fn set_max_paths(&mut self, max_paths: Option<u32>) {
+        self.max_paths = max_paths;
+    }
+
+    // See `GraphBuilder::set_secondary_objective`.
+    pub fn set_secondary_objective(&mut self, objective: SecondaryObjective) {
+        self.secondary_objective = objective;
+    }
+
+    // See `GraphBuilder::set_trace`.
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.trace = enabled;
+    }
+
+    // See `GraphBuilder::set_path_seed`.
+    pub fn set_path_seed(&mut self, seed: Option<u32>) {
+        self.path_seed = seed;
+    }
+
+    // See `GraphBuilder::set_path_node_names`.
+    pub fn set_path_node_names(&mut self, enabled: bool) {
+        self.path_node_names = enabled;
+    }
+
+    // See `GraphBuilder::set_split_antiparallel_edges`.
+    pub fn set_split_antiparallel_edges(&mut self, enabled: bool) {
+        self.split_antiparallel_edges = enabled;
+    }
+
+    // See `GraphBuilder::set_cross_validation`.
+    pub fn set_cross_validation(&mut self, enabled: bool) {
+        self.cross_validation = enabled;
+    }
+
+    // See `GraphBuilder::set_cancellation_token`.
+    pub fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.cancellation = Some(token.clone());
+    }
+
+    // See `GraphBuilder::set_progress_callback`. Flows are reported in the same units as capacities.
+    pub fn set_progress_callback(&mut self, callback: Option<Progress<f64>>) {
+        self.progress = callback;
+    }
+
+    // Attaches names to all nodes, replacing any previous ones. Names are only used for
+    // reporting, so they can be set at any point before solving.
+    pub fn set_node_names(&mut self, names: Vec<String>) -> Result<(), McmfError> {
+        if names.len() != self.nodes.len() {
+            return Err(format!("expected {} node names, got {}", self.nodes.len(), names.len()).into());
+        }
+        self.node_names = names;
+        Ok(())
+    }
+
+    pub fn set_capacity_denominator(&mut self, denominator: u32) -> Result<(), McmfError> {
+        if denominator == 0 {
+            return Err("capacity denominator must be positive".into());
+        }
+        let is_empty = self.edges.is_empty()
+            && self.nodes.iter().all(|node| node.supply == 0 && node.capacity.is_none());
+        if !is_empty && denominator != self.capacity_denominator {
+            return Err("capacity denominator must be set before adding edges, supplies or node capacities".into());
+        }
+        self.capacity_denominator = denominator;
+        Ok(())
+    }
+
+    pub fn set_time_limit_ms(&mut self, limit: Option<f64>) -> Result<(), McmfError> {
+        if limit.is_some_and(|limit| limit.is_nan() || limit < 0.) {
+            return Err("time limit must be non-negative".into());
+        }
+        self.time_limit_ms = limit;
+        Ok(())
+    }
+
+    pub fn set_cost_denominator(&mut self, denominator: Option<u32>) -> Result<(), McmfError> {
+        if denominator == Some(0) {
+            return Err("cost denominator must be positive".into());
+        }
+        self.cost_denominator = denominator;
+        Ok(())
+    }
+
+    // See `GraphBuilder::set_duplicate_edges`.
+    pub fn set_duplicate_edges(&mut self, policy: DuplicateEdges) {
+        self.duplicate_edges = policy;
+        self.index_edge_ends();
+    }
+
+    // See `GraphBuilder::set_self_loops`.
+    pub fn set_self_loops(&mut self, policy: SelfLoops) {
+        self.self_loops = policy;
+    }
+
+    // See `GraphBuilder::add_edge`.
+    pub fn add_edge(&mut self, from: usize, to: usize, capacity: f64, cost: f64) -> Result<ID, McmfError> {
+        self.add_edge_with_bounds(from, to, 0., capacity, cost)
+    }
+
+    // See `GraphBuilder::add_edge_with_bounds`.
+    pub fn add_edge_with_bounds(
+        &mut self, from: usize, to: usize, lower: f64, upper: f64, cost: f64
+    ) -> Result<ID, McmfError> {
+        let (lower, capacity) = self.edge_bounds(lower, upper)?;
+        self.add_scaled_edge(from, to, lower, capacity, check_cost(cost)?)
+    }
+
+    // See `GraphBuilder::add_edge_exact`.
+    pub fn add_edge_exact(&mut self, from: usize, to: usize, capacity: i64, cost: i64) -> Result<ID, McmfError> {
+        let (capacity, cost) = self.exact_edge_bounds(capacity, cost)?;
+        self.add_scaled_edge(from, to, 0, capacity, cost)
+    }
+
+    fn add_scaled_edge(
+        &mut self, from: usize, to: usize, lower: i64, capacity: i64, cost: f64
+    ) -> Result<ID, McmfError> {
+        let (from, to) = (self.check_node(from)?, self.check_node(to)?);
+        if from == to {
+            self.check_self_loop(&self.node_name(from))?;
+        }
+        if let Some(&e) = self.edge_ends.get(&(from, to)) {
+            return self.merge_edge(e, lower, capacity, cost);
+        }
+        self.push_edges([EdgeData { from, to, lower, capacity, cost, undirected: false, ..Default::default() }]);
+        if self.duplicate_edges != DuplicateEdges::Keep {
+            self.edge_ends.insert((from, to), self.edges.len() - 1);
+        }
+        Ok((self.edges.len() - 1) as ID)
+    }
+
+    // Adds the bounds of a duplicate of edge `e` to it, see `GraphBuilder::set_duplicate_edges`.
+    fn merge_edge(&mut self, e: usize, lower: i64, capacity: i64, cost: f64) -> Result<ID, McmfError> {
+        let edge = &self.edges[e];
+        let merged_capacity = match edge.capacity == INFINITE_CAPACITY || capacity == INFINITE_CAPACITY {
+            true => INFINITE_CAPACITY,
+            false => edge.capacity + capacity,
+        };
+        let merged_cost = match self.duplicate_edges {
+            DuplicateEdges::Keep => unreachable!(),
+            DuplicateEdges::Reject => return Err(self.duplicate_error(edge.from, edge.to)),
+            DuplicateEdges::MergeMinCost => edge.cost.min(cost),
+            // Zero or infinite capacities weigh the costs equally or only count the infinite one.
+            DuplicateEdges::MergeAverageCost if merged_capacity == 0 => (edge.cost + cost) / 2.,
+            DuplicateEdges::MergeAverageCost => {
+                let (weight, other_weight) = (edge.capacity as f64, capacity as f64);
+                (edge.cost * weight + cost * other_weight) / (weight + other_weight)
+            }
+        };
+        if merged_capacity != INFINITE_CAPACITY && merged_capacity as f64 > MAX_AMOUNT {
+            return Err(McmfError::Overflow("merged capacity is too large".to_owned()));
+        }
+        let edge = &mut self.edges[e];
+        edge.lower += lower;
+        edge.capacity = merged_capacity;
+        edge.cost = merged_cost;
+        Ok(e as ID)
+    }
+
+    fn duplicate_error(&self, from: usize, to: usize) -> McmfError {
+        let (from, to) = (self.node_name(from), self.node_name(to));
+        McmfError::Invalid(format!("an edge from '{}' to '{}' already exists", from, to))
+    }
+
+    // Errors if self-loops are rejected, see `GraphBuilder::set_self_loops`.
+    pub(crate) fn check_self_loop(&self, node: &str) -> Result<(), McmfError> {
+        match self.self_loops {
+            SelfLoops::Reject => Err(format!("self-loop at '{}' is not allowed", node).into()),
+            SelfLoops::Keep | SelfLoops::Ignore => Ok(()),
+        }
+    }
+
+    fn index_edge_ends(&mut self) {
+        self.edge_ends.clear();
+        if self.duplicate_edges == DuplicateEdges::Keep {
+            return;
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            if !edge.undirected && edge.pieces.is_empty() {
+                self.edge_ends.entry((edge.from, edge.to)).or_insert(i);
+            }
+        }
+    }
+
+    // Appends edges with new handles. Handles only grow, so they stay sorted by edge ID.
+    fn push_edges(&mut self, edges: impl IntoIterator<Item = EdgeData>) {
+        for edge in edges {
+            self.edges.push(EdgeData { handle: EdgeHandle(self.next_edge_handle), ..edge });
+            self.next_edge_handle += 1;
+        }
+    }
+
+    // See `GraphBuilder::edge_handle`.
+    pub fn edge_handle(&self, edge: usize) -> Result<EdgeHandle, McmfError> {
+        Ok(self.edges[self.check_edge(edge)?].handle)
+    }
+
+    // See `GraphBuilder::edge_id`.
+    pub fn edge_id(&self, handle: EdgeHandle) -> Result<ID, McmfError> {
+        match self.edges.binary_search_by_key(&handle, |edge| edge.handle) {
+            Ok(e) => Ok(e as ID),
+            Err(_) => Err(format!("edge handle {} refers to no edge", handle.0).into()),
+        }
+    }
+
+    // See `GraphBuilder::add_edge_piecewise`.
+    pub fn add_edge_piecewise(
+        &mut self, from: usize, to: usize, capacities: &[f64], costs: &[f64]
+    ) -> Result<ID, McmfError> {
+        let pieces = self.pieces(capacities, costs)?;
+        let (from, to) = (self.check_node(from)?, self.check_node(to)?);
+        if from == to {
+            self.check_self_loop(&self.node_name(from))?;
+        }
+        let capacity = pieces.iter().map(|&(capacity, _)| capacity).sum();
+        let pieces = if pieces.len() > 1 { pieces } else { vec![] };
+        self.push_edges([EdgeData { from, to, capacity, cost: costs[0], pieces, ..Default::default() }]);
+        Ok((self.edges.len() - 1) as ID)
+    }
+
+    pub(crate) fn pieces(&self, capacities: &[f64], costs: &[f64]) -> Result<Vec<(i64, f64)>, McmfError> {
+        if costs.len() != capacities.len() {
+            return Err(format!("expected {} costs, got {}", capacities.len(), costs.len()).into());
+        }
+        if costs.is_empty() {
+            return Err("piecewise cost needs at least one piece".into());
+        }
+        for &cost in costs {
+            check_cost(cost)?;
+        }
+        if costs.windows(2).any(|pair| pair[1] < pair[0]) {
+            return Err("piece costs must be non-decreasing".into());
+        }
+        if capacities.iter().any(|capacity| capacity.is_infinite()) {
+            return Err("piece capacities must be finite".into());
+        }
+        capacities.iter().zip(costs).map(|(&capacity, &cost)| Ok((self.edge_bounds(0., capacity)?.1, cost))).collect()
+    }
+
+    pub fn add_edges_bulk(
+        &mut self, froms: &[u32], tos: &[u32], capacities: &[f64], costs: &[f64]
+    ) -> Result<ID, McmfError> {
+        for (values, len) in [("destinations", tos.len()), ("capacities", capacities.len()), ("costs", costs.len())] {
+            if len != froms.len() {
+                return Err(format!("expected {} {}, got {}", froms.len(), values, len).into());
+            }
+        }
+        let edges = (0..froms.len()).map(|i| {
+            let (lower, capacity) = self.edge_bounds(0., capacities[i])?;
+            let cost = check_cost(costs[i])?;
+            let (from, to) = (self.check_node(froms[i] as usize)?, self.check_node(tos[i] as usize)?);
+            if from == to {
+                self.check_self_loop(&self.node_name(from))?;
+            }
+            Ok(EdgeData { from, to, lower, capacity, cost, undirected: false, ..Default::default() })
+        }).collect::<Result<Vec<_>, McmfError>>()?;
+        if self.duplicate_edges != DuplicateEdges::Keep {
+            if self.duplicate_edges != DuplicateEdges::Reject {
+                return Err("add_edges_bulk cannot merge duplicate edges".into());
+            }
+            let mut ends = HashSet::new();
+            if let Some(edge) = edges.iter().find(|edge| {
+                self.edge_ends.contains_key(&(edge.from, edge.to)) || !ends.insert((edge.from, edge.to))
+            }) {
+                return Err(self.duplicate_error(edge.from, edge.to));
+            }
+        }
+        let first = self.edges.len() as ID;
+        self.push_edges(edges);
+        self.index_edge_ends();
+        Ok(first)
+    }
+
+    pub fn add_undirected_edge(&mut self, a: usize, b: usize, capacity: f64, cost: f64) -> Result<ID, McmfError> {
+        let (_, capacity) = self.edge_bounds(0., capacity)?;
+        if check_cost(cost)? < 0. {
+            return Err("undirected edge cost must be non-negative".into());
+        }
+        let (from, to) = (self.check_node(a)?, self.check_node(b)?);
+        if from == to {
+            self.check_self_loop(&self.node_name(from))?;
+        }
+        self.push_edges([EdgeData { from, to, lower: 0, capacity, cost, undirected: true, ..Default::default() }]);
+        Ok((self.edges.len() - 1) as ID)
+    }
+
+    pub fn update_capacity(&mut self, edge: usize, capacity: f64) -> Result<(), McmfError> {
+        let edge = self.check_plain_edge(edge)?;
+        let lower = self.unscale_amount(self.edges[edge].lower);
+        (_, self.edges[edge].capacity) = self.edge_bounds(lower, capacity)?;
+        Ok(())
+    }
+
+    pub fn update_cost(&mut self, edge: usize, cost: f64) -> Result<(), McmfError> {
+        let edge = self.check_plain_edge(edge)?;
+        let cost = check_cost(cost)?;
+        if self.edges[edge].undirected && cost < 0. {
+            return Err("undirected edge cost must be non-negative".into());
+        }
+        self.edges[edge].cost = cost;
+        Ok(())
+    }
+
+    // See `GraphBuilder::set_edge_metadata`.
+    pub fn set_edge_metadata(&mut self, edge: usize, metadata: Option<String>) -> Result<(), McmfError> {
+        let edge = self.check_edge(edge)?;
+        self.edges[edge].metadata = metadata;
+        Ok(())
+    }
+
+    pub fn remove_edge(&mut self, edge: usize) -> Result<(), McmfError> {
+        let edge = self.check_edge(edge)?;
+        self.edges.remove(edge);
+        self.index_edge_ends();
+        if let Some(last) = self.last_mcmf.get_mut().as_mut().filter(|last| edge < last.edge_flows.len()) {
+            last.edge_flows.remove(edge);
+        }
+        Ok(())
+    }
+
+    pub fn set_supply(&mut self, node: usize, amount: f64) -> Result<(), McmfError> {
+        let node = self.check_node(node)?;
+        self.nodes[node].supply = self.scale_amount(amount, "supply")?;
+        Ok(())
+    }
+
+    pub fn set_demand(&mut self, node: usize, amount: f64) -> Result<(), McmfError> {
+        self.set_supply(node, -amount)
+    }
+
+    pub fn set_node_capacity(&mut self, node: usize, capacity: f64) -> Result<(), McmfError> {
+        let capacity = self.scale_amount(capacity, "capacity")?;
+        if capacity <= 0 {
+            return Err("capacity must be positive".into());
+        }
+        let node = self.check_node(node)?;
+        self.nodes[node].capacity = Some(capacity);
+        Ok(())
+    }
+
+    // See `GraphBuilder::set_node_group`.
+    pub fn set_node_group(&mut self, node: usize, group: Option<String>) -> Result<(), McmfError> {
+        let node = self.check_node(node)?;
+        self.nodes[node].group = group;
+        Ok(())
+    }
+
+    // See `GraphBuilder::set_node_metadata`.
+    pub fn set_node_metadata(&mut self, node: usize, metadata: Option<String>) -> Result<(), McmfError> {
+        let node = self.check_node(node)?;
+        self.nodes[node].metadata = metadata;
+        Ok(())
+    }
+
+    // See `GraphBuilder::find_negative_cycle`. Returns node indices.
+    pub fn find_negative_cycle(&self) -> Option<Vec<usize>> {
+        let network = self.build_network();
+        let cycle = network.negative_cycle()?;
+        Some(node_path(&network, &cycle))
+    }
+
+    pub fn solve_mcmf(
+        &self, source: usize, sink: usize, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let result = block_on(self.solve_mcmf_async(source, sink, decomposition, algorithm, max_flow_algorithm));
+        logged("solve_mcmf", result)
+    }
+
+    async fn solve_mcmf_async(
+        &self, source: usize, sink: usize, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        self.check_terminals(source, sink)?;
+        let solution = match self.reduced(source, sink)? {
+            Some((reduced, origins)) => {
+                log!(Trace, "reduced the graph to {} nodes and {} edges", reduced.nodes.len(), reduced.edges.len());
+                let solution =
+                    reduced.solve_mcmf_directly(source, sink, decomposition, algorithm, max_flow_algorithm).await?;
+                self.expanded(solution, &origins, (source, sink), decomposition)?
+            }
+            None => self.solve_mcmf_directly(source, sink, decomposition, algorithm, max_flow_algorithm).await?,
+        };
+        if self.cross_validation && !solution.limit_reached {
+            self.cross_validate(&solution, (source, sink), algorithm, max_flow_algorithm).await?;
+        }
+        let solution = self.limit_paths(solution, (source, sink))?;
+        let edge_flows = solution.scaled_edge_flows.clone();
+        *self.last_mcmf.borrow_mut() = Some(LastMcmf {
+            source, sink, decomposition, algorithm, max_flow_algorithm, edge_flows,
+        });
+        Ok(solution)
+    }
+
+    async fn solve_mcmf_directly(
+        &self, source: usize, sink: usize, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let mut stopwatch = Stopwatch::start();
+        let network = self.checked_network()?;
+        let source = network.entry(self.check_node(source)?);
+        let sink = network.exit(self.check_node(sink)?);
+        let network_ms = stopwatch.lap();
+        let mut solution = self.mcmf(&network, source, sink, decomposition, algorithm, max_flow_algorithm).await?;
+        solution.stats.network_ms = network_ms;
+        Ok(solution)
+    }
+
+    // Applies `set_cross_validation`: solves again from scratch with the other algorithms and
+    // fails if the flow value or cost differs. The other solve may reach a limit, which skips the
+    // comparison.
+    async fn cross_validate(
+        &self, solution: &McmfSolution, (source, sink): (usize, usize), algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<(), McmfError> {
+        let mut reference = self.clone();
+        (reference.pruning, reference.chain_contraction, reference.trace) = (false, false, false);
+        (reference.trace_handle, reference.progress) = (None, None);
+        reference.secondary_objective = SecondaryObjective::None;
+        let other_algorithm = match algorithm {
+            MinCostFlowAlgorithm::NetworkSimplex => MinCostFlowAlgorithm::SuccessiveShortestPaths,
+            _ => MinCostFlowAlgorithm::NetworkSimplex,
+        };
+        let other_max_flow_algorithm = match max_flow_algorithm {
+            MaxFlowAlgorithm::Dinic => MaxFlowAlgorithm::PushRelabel,
+            MaxFlowAlgorithm::PushRelabel => MaxFlowAlgorithm::Dinic,
+        };
+        let other = reference.solve_mcmf_directly(
+            source, sink, PathDecomposition::default(), other_algorithm, other_max_flow_algorithm
+        ).await?;
+        let result = |solution: &McmfSolution| {
+            (solution.max_flow, solution.exact_total_cost.clone().unwrap_or_default())
+        };
+        if other.limit_reached || result(solution) == result(&other) {
+            return Ok(());
+        }
+        let describe = |solution: &McmfSolution| {
+            let (flow, cost) = result(solution);
+            format!("a flow of {} costing {}", flow, cost)
+        };
+        Err(format!(
+            "cross-validation failed: {:?} with {:?} found {}, but {:?} with {:?} found {}",
+            algorithm, max_flow_algorithm, describe(solution),
+            other_algorithm, other_max_flow_algorithm, describe(&other),
+        ).into())
+    }
+
+    // Applies `set_pruning` and `set_chain_contraction`. Nodes are kept, so that their indices stay
+    // the same. `None` if both are off or wouldn't change anything.
+    fn reduced(&self, source: usize, sink: usize) -> Result<Option<Reduction>, McmfError> {
+        let (source, sink) = (self.check_node(source)?, self.check_node(sink)?);
+        if self.trace {
+            return Ok(None);
+        }
+        let mut edges: Vec<_> = self.edges.iter().cloned().zip((0..self.edges.len()).map(|i| vec![i])).collect();
+        // Lower bounds force flow through edges regardless of where they are.
+        if self.pruning && self.edges.iter().all(|edge| edge.lower == 0) {
+            let (forward, backward) = self.adjacency();
+            let (from_source, to_sink) = (reachable(&forward, source), reachable(&backward, sink));
+            let usable = |v: usize| from_source[v] && to_sink[v];
+            edges.retain(|(edge, _)| usable(edge.from) && usable(edge.to));
+        }
+        if self.chain_contraction {
+            edges = self.contract_chains(edges, (source, sink));
+        }
+        if edges.len() == self.edges.len() {
+            return Ok(None);
+        }
+        let mut reduced = self.clone();
+        reduced.pruning = false;
+        reduced.chain_contraction = false;
+        reduced.max_paths = None;
+        let origins;
+        (reduced.edges, origins) = edges.into_iter().unzip();
+        Ok(Some((reduced, origins)))
+    }
+
+    // Replaces each path through nodes with just one incoming and one outgoing edge by a single
+    // edge with the smallest capacity and the total cost. Terminals, nodes with a supply and
+    // undirected edges or edges with lower bounds are left alone.
+    fn contract_chains(
+        &self, mut edges: Vec<(EdgeData, Vec<usize>)>, (source, sink): (usize, usize)
+    ) -> Vec<(EdgeData, Vec<usize>)> {
+        let (mut incoming, mut outgoing) = (vec![vec![]; self.nodes.len()], vec![vec![]; self.nodes.len()]);
+        for (i, (edge, _)) in edges.iter().enumerate() {
+            outgoing[edge.from].push(i);
+            incoming[edge.to].push(i);
+            if edge.undirected {
+                outgoing[edge.to].push(i);
+                incoming[edge.from].push(i);
+            }
+        }
+        let mut removed = vec![false; edges.len()];
+        for (v, node) in self.nodes.iter().enumerate() {
+            let (&[first], &[second]) = (&incoming[v][..], &outgoing[v][..]) else {
+                continue;
+            };
+            let (from, to) = (edges[first].0.from, edges[second].0.to);
+            let simple = |edge: &EdgeData| !edge.undirected && edge.lower == 0 && edge.pieces.is_empty();
+            if v == source || v == sink || node.supply != 0 || from == to || first == second
+                || !simple(&edges[first].0) || !simple(&edges[second].0)
+            {
+                continue;
+            }
+            let (second_edge, second_origins) = std::mem::take(&mut edges[second]);
+            let (edge, origins) = &mut edges[first];
+            edge.to = to;
+            edge.capacity = edge.capacity.min(second_edge.capacity).min(node.capacity.unwrap_or(i64::MAX));
+            edge.cost += second_edge.cost;
+            origins.extend(second_origins);
+            removed[second] = true;
+            for i in incoming[to].iter_mut().filter(|i| **i == second) {
+                *i = first;
+            }
+        }
+        edges.into_iter().zip(removed).filter(|(_, removed)| !removed).map(|(edge, _)| edge).collect()
+    }
+
+    // Applies `set_max_paths` to a solution from `source` to `sink`: if its flow takes more paths,
+    // it is decomposed widest first and only the flow on the first `max_paths` paths is kept.
+    fn limit_paths(&self, solution: McmfSolution, (source, sink): (usize, usize)) -> Result<McmfSolution, McmfError> {
+        let Some(max_paths) = self.max_paths else {
+            return Ok(solution);
+        };
+        if solution.paths.len() <= max_paths as usize {
+            return Ok(solution);
+        }
+        if self.edges.iter().any(|edge| edge.lower > 0) {
+            return Err("path limits don't support lower bounds".into());
+        }
+        let network = self.checked_network()?;
+        let arc_flows = network.arc_flows(&solution.scaled_edge_flows);
+        let flows = EdgeVec::new_with(&network.graph, |e| arc_flows[network.graph.edge_id(e)]);
+        let terminals = (network.entry(source), network.exit(sink));
+        let (mut paths, _) = self.paths(&network, &flows, terminals.0, terminals.1, PathDecomposition::WidestFirst);
+        paths.truncate(max_paths as usize);
+        let mut edge_flows = vec![0; self.edges.len()];
+        let mut flow_value = 0;
+        for path in &paths {
+            let flow = self.scale_amount(path.flow, "flow")?;
+            for (&e, &v) in path.edges.iter().zip(&path.node_indices) {
+                let forward = self.edges[e as usize].from == v as usize;
+                edge_flows[e as usize] += if forward { flow } else { -flow };
+            }
+            flow_value += flow;
+        }
+        let arc_flows = network.arc_flows(&edge_flows);
+        let flows = EdgeVec::new_with(&network.graph, |e| arc_flows[network.graph.edge_id(e)]);
+        // The decomposition is the one chosen above, whatever the solve asked for.
+        let mut limited = self.solution(&network, &flows, terminals, flow_value, PathDecomposition::WidestFirst);
+        limited.paths = paths;
+        if solution.total_cost.is_some() {
+            self.set_total_cost(&mut limited, &network, network.total_scaled_cost(&flows));
+        }
+        limited.limit_reached = solution.limit_reached;
+        limited.stats = solution.stats;
+        Ok(limited)
+    }
+
+    // Maps a solution for the `reduced` copy back onto the whole graph: each builder edge takes
+    // the flow of the edge standing for it, if any. The stats are those of the smaller solve.
+    fn expanded(
+        &self, reduced: McmfSolution, origins: &[Vec<usize>], (source, sink): (usize, usize),
+        decomposition: PathDecomposition,
+    ) -> Result<McmfSolution, McmfError> {
+        let mut stopwatch = Stopwatch::start();
+        let mut edge_flows = vec![0; self.edges.len()];
+        for (edges, &flow) in origins.iter().zip(&reduced.scaled_edge_flows) {
+            for &i in edges {
+                edge_flows[i] = flow;
+            }
+        }
+        let network = self.checked_network()?;
+        let arc_flows = network.arc_flows(&edge_flows);
+        let flows = EdgeVec::new_with(&network.graph, |e| arc_flows[network.graph.edge_id(e)]);
+        let terminals = (network.entry(source), network.exit(sink));
+        let flow_value = reduced.exact_max_flow.unwrap();
+        let mut solution = self.solution(&network, &flows, terminals, flow_value, decomposition);
+        if reduced.total_cost.is_some() {
+            self.set_total_cost(&mut solution, &network, network.total_scaled_cost(&flows));
+        }
+        if reduced.potentials.is_some() {
+            self.set_potentials(&mut solution);
+        }
+        solution.limit_reached = reduced.limit_reached;
+        solution.stats = SolverStats { paths_ms: stopwatch.lap(), ..reduced.stats };
+        Ok(solution)
+    }
+
+    pub fn resolve(&self) -> Result<McmfSolution, McmfError> {
+        let last = self.last_mcmf.borrow().clone().ok_or("nothing to resolve, solve_mcmf has not been called")?;
+        let LastMcmf { source: source_index, sink: sink_index, decomposition, algorithm, max_flow_algorithm, .. } = last;
+        if self.edges.iter().any(|edge| edge.lower > 0) || self.secondary_objective != SecondaryObjective::None {
+            return self.solve_mcmf(source_index, sink_index, decomposition, algorithm, max_flow_algorithm);
+        }
+        let mut stopwatch = Stopwatch::start();
+        let network = self.checked_network()?;
+        let (source, sink) = (network.entry(source_index), network.exit(sink_index));
+        let network_ms = stopwatch.lap();
+        if let Some(cycle) = network.negative_cycle() {
+            return Err(self.negative_cycle_error(&network, &cycle));
+        }
+        self.check_costs(&network)?;
+        let result = block_on(network.reoptimize(source, sink, &last.edge_flows));
+        let limit_reached = self.limit_reached(&network, result.is_some())?;
+        // The clamped flow can be repaired by routing the excess back along the old flow, so
+        // this is only a safety net.
+        let Some((value, flows)) = result else {
+            return self.solve_mcmf(source_index, sink_index, decomposition, algorithm, max_flow_algorithm);
+        };
+        let min_cost_flow_ms = stopwatch.lap();
+        let mut solution = self.solution(&network, &flows, (source, sink), value, decomposition);
+        self.set_total_cost(&mut solution, &network, network.total_scaled_cost(&flows));
+        if !limit_reached {
+            self.set_potentials(&mut solution);
+        }
+        solution.limit_reached = limit_reached;
+        solution.stats = SolverStats { network_ms, min_cost_flow_ms, ..solution.stats };
+        let solution = self.limit_paths(solution, (source_index, sink_index))?;
+        *self.last_mcmf.borrow_mut() = Some(LastMcmf { edge_flows: solution.scaled_edge_flows.clone(), ..last });
+        Ok(solution)
+    }
+
+    // See `GraphBuilder::layout`.
+    pub fn layout(&self, iterations: u32, weight_by_cost: bool) -> Layout {
+        let edges: Vec<_> = self.edges.iter().map(|edge| (edge.from, edge.to, edge.cost)).collect();
+        layout::force_directed(self.nodes.len(), &edges, iterations, weight_by_cost)
+    }
+
+    // See `GraphBuilder::evaluate_scenarios`.
+    pub fn evaluate_scenarios(
+        &self, source: usize, sink: usize, scenarios: &[Vec<EdgeOverride>], algorithm: MinCostFlowAlgorithm,
+    ) -> Result<ScenarioResults, McmfError> {
+        let mut graph = self.clone();
+        graph.solve_mcmf(source, sink, PathDecomposition::default(), algorithm, MaxFlowAlgorithm::default())?;
+        let mut results = ScenarioResults::default();
+        // Edges changed by the previous scenario, to be reset to the base graph.
+        let mut changed: Vec<usize> = vec![];
+        for (i, scenario) in scenarios.iter().enumerate() {
+            for e in changed.drain(..) {
+                graph.edges[e] = self.edges[e].clone();
+            }
+            for change in scenario {
+                let e = change.edge as usize;
+                if let Some(capacity) = change.capacity {
+                    graph.update_capacity(e, capacity).map_err(|error| format!("scenario {}: {}", i, error))?;
+                }
+                if let Some(cost) = change.cost {
+                    graph.update_cost(e, cost).map_err(|error| format!("scenario {}: {}", i, error))?;
+                }
+                changed.push(e);
+            }
+            let solution = graph.resolve().map_err(|error| format!("scenario {}: {}", i, error))?;
+            results.max_flows.push(solution.max_flow);
+            results.total_costs.push(solution.total_cost.unwrap_or(0.));
+            results.limit_reached |= solution.limit_reached;
+        }
+        Ok(results)
+    }
+
+    // See `GraphBuilder::benchmark`.
+    pub fn benchmark(
+        &self, source: usize, sink: usize, algorithm: MinCostFlowAlgorithm, max_flow_algorithm: MaxFlowAlgorithm,
+        repetitions: u32,
+    ) -> Result<Benchmark, McmfError> {
+        if repetitions == 0 {
+            return Err("benchmark needs at least one repetition".into());
+        }
+        let runs = (0..repetitions).map(|_| {
+            let mut stopwatch = Stopwatch::start();
+            let solution = self.solve_mcmf(source, sink, PathDecomposition::default(), algorithm, max_flow_algorithm)?;
+            Ok((solution.stats, stopwatch.lap()))
+        }).collect::<Result<Vec<_>, McmfError>>()?;
+        Ok(Benchmark::new(&runs))
+    }
+
+    // See `GraphBuilder::solver`.
+    pub fn solver(
+        &self, source: usize, sink: usize, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<Solver, McmfError> {
+        let (source, sink) = self.check_terminals(source, sink)?;
+        let trace = Rc::new(RefCell::new(Trace::default()));
+        let mut graph = self.clone();
+        graph.yield_interval_ms = Some(0.);
+        graph.trace = true;
+        graph.trace_handle = Some(trace.clone());
+        let mut state_graph = graph.clone();
+        state_graph.trace_handle = None;
+        let task = SolveTask::new(async move {
+            graph.solve_mcmf_async(source, sink, decomposition, algorithm, max_flow_algorithm).await
+        });
+        Solver::new(task, state_graph, source, trace)
+    }
+
+    // The solve works on a copy of the builder, so the builder can be changed in the meantime.
+    pub fn solve_mcmf_task(
+        &self, source: usize, sink: usize, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm, slice_ms: f64,
+    ) -> Result<SolveTask, McmfError> {
+        if slice_ms.is_nan() || slice_ms < 0. {
+            return Err("slice length must be non-negative".into());
+        }
+        let mut graph = self.clone();
+        graph.yield_interval_ms = Some(slice_ms);
+        Ok(SolveTask::new(async move {
+            let result = graph.solve_mcmf_async(source, sink, decomposition, algorithm, max_flow_algorithm).await;
+            logged("solve_mcmf", result)
+        }))
+    }
+
+    pub fn solve_min_cost_flow(
+        &self, source: usize, sink: usize, flow_value: f64, decomposition: PathDecomposition,
+        algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let flow_value = self.scale_amount(flow_value, "flow value")?;
+        if flow_value < 0 {
+            return Err("flow value must be non-negative".into());
+        }
+        let (source, sink) = self.check_terminals(source, sink)?;
+        let mut stopwatch = Stopwatch::start();
+        let network = self.checked_network()?;
+        let (source, sink) = (network.entry(source), network.exit(sink));
+        let network_ms = stopwatch.lap();
+        let mut solution = block_on(self.min_cost_flow(&network, source, sink, flow_value, decomposition, algorithm))?;
+        solution.stats.network_ms = network_ms;
+        Ok(solution)
+    }
+
+    // See `GraphBuilder::max_flow_profile`.
+    pub fn max_flow_profile(
+        &self, source: usize, sink: usize, edge: usize, capacities: &[f64], algorithm: MaxFlowAlgorithm
+    ) -> Result<Vec<f64>, McmfError> {
+        let edge = self.check_plain_edge(edge)?;
+        if self.edges.iter().any(|edge| edge.lower > 0) {
+            return Err("max flow profiles don't support lower bounds".into());
+        }
+        let mut scaled = vec![];
+        for &capacity in capacities {
+            let amount = self.scale_amount(capacity, "capacity")?;
+            if amount < 0 {
+                return Err(McmfError::NegativeCapacity(capacity));
+            }
+            scaled.push(amount);
+        }
+        let mut order: Vec<_> = (0..scaled.len()).collect();
+        order.sort_by_key(|&i| scaled[i]);
+        // The totals are checked with the edge at its largest capacity, which bounds all others.
+        let mut widest = self.clone();
+        if let Some(&i) = order.last() {
+            widest.edges[edge].capacity = scaled[i];
+        }
+        let mut network = widest.checked_network()?;
+        let (source, sink) = self.check_terminals(source, sink)?;
+        let (source, sink) = (network.entry(source), network.exit(sink));
+        let sorted: Vec<_> = order.iter().map(|&i| scaled[i]).collect();
+        let values = block_on(network.max_flow_profile(source, sink, edge, &sorted, algorithm));
+        self.limit_reached(&network, false)?;
+        let mut profile = vec![0.; scaled.len()];
+        for (&i, value) in order.iter().zip(values) {
+            profile[i] = self.unscale_amount(value);
+        }
+        Ok(profile)
+    }
+
+    // See `GraphBuilder::solve_max_flow_with_budget`. The min cost is convex in the flow value
+    // and zero for no flow, so the affordable flow values form a range starting at zero, and its
+    // end is found by bisection.
+    pub fn solve_max_flow_with_budget(
+        &self, source: usize, sink: usize, budget: f64, decomposition: PathDecomposition,
+        algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        if budget.is_nan() || budget < 0. {
+            return Err("budget must be non-negative".into());
+        }
+        let solution = self.solve_mcmf(source, sink, decomposition, algorithm, Default::default())?;
+        if solution.total_cost.unwrap_or_default() <= budget {
+            return Ok(solution);
+        }
+        // The bisection starts from an empty flow, which lower bounds may rule out.
+        if self.edges.iter().any(|edge| edge.lower > 0) {
+            return Err("max flow with a budget doesn't support lower bounds".into());
+        }
+        let unit = 1. / self.capacity_denominator as f64;
+        let cost_within_budget = |units: i64| -> Result<bool, McmfError> {
+            let solution = self.solve_min_cost_flow(source, sink, units as f64 * unit, decomposition, algorithm)?;
+            Ok(solution.total_cost.unwrap_or_default() <= budget)
+        };
+        // `affordable` units fit in the budget, `too_expensive` don't.
+        let (mut affordable, mut too_expensive) = (0, self.scale_amount(solution.max_flow, "flow value")?);
+        while too_expensive - affordable > 1 {
+            let units = affordable + (too_expensive - affordable) / 2;
+            if cost_within_budget(units)? {
+                affordable = units;
+            } else {
+                too_expensive = units;
+            }
+        }
+        self.solve_min_cost_flow(source, sink, affordable as f64 * unit, decomposition, algorithm)
+    }
+
+    // See `GraphBuilder::solve_max_profit_flow`. A min-cost circulation through an extra edge from
+    // the sink back to the source, whose cost is the negated revenue, picks the most profitable
+    // flow value, and a min-cost flow of that value gives the paths.
+    pub fn solve_max_profit_flow(
+        &self, source: usize, sink: usize, revenue: f64, decomposition: PathDecomposition,
+        algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        if !revenue.is_finite() {
+            return Err("revenue must be finite".into());
+        }
+        let (source, sink) = self.check_terminals(source, sink)?;
+        let mut with_revenue = self.clone();
+        for node in &mut with_revenue.nodes {
+            node.supply = 0;
+        }
+        // No flow can exceed what leaves the source. Saturating to an infinite capacity.
+        let capacity = self.edges.iter()
+            .filter(|edge| edge.from == source || (edge.undirected && edge.to == source))
+            .fold(0, |total: i64, edge| total.saturating_add(edge.capacity));
+        let revenue_edge = EdgeData { from: sink, to: source, capacity, cost: -revenue, ..Default::default() };
+        with_revenue.edges.push(revenue_edge);
+        let circulation = with_revenue.solve_min_cost_circulation(algorithm)?;
+        let flow_value = circulation.edge_flows.last().map_or(0., |edge_flow| edge_flow.flow);
+        self.solve_min_cost_flow(source, sink, flow_value, decomposition, algorithm)
+    }
+
+    // Max flow, then a min-cost flow of that value, or both at once, see `set_single_pass`.
+    async fn mcmf(
+        &self, network: &Network, source: GraphNode, sink: GraphNode, decomposition: PathDecomposition,
+        algorithm: MinCostFlowAlgorithm, max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        if self.single_pass {
+            match self.return_arc(network, source) {
+                Some(return_arc) => {
+                    return self.single_pass_mcmf(network, source, sink, return_arc, decomposition, algorithm).await;
+                }
+                None => log!(Warn, "costs are too large for a single pass, solving the max flow first"),
+            }
+        }
+        let mut stopwatch = Stopwatch::start();
+        let (max_flow, flows, augmentations) = max_flow(network, source, sink, max_flow_algorithm).await?;
+        let max_flow_ms = stopwatch.lap();
+        log!(Trace, "found a max flow of {} in {:.1} ms", self.unscale_amount(max_flow), max_flow_ms);
+        let augmenting_paths = self.augmenting_paths(network, source);
+        let dinic_phases = self.dinic_phases(network);
+        let mut solution = if self.limit_reached(network, true)? {
+            // Nothing is left for the min-cost phase, so the max flow found so far is reported as is.
+            self.check_costs(network)?;
+            let mut solution = self.solution(network, &flows, (source, sink), max_flow, decomposition);
+            self.set_total_cost(&mut solution, network, network.total_scaled_cost(&flows));
+            solution.limit_reached = true;
+            solution
+        } else {
+            self.min_cost_flow(network, source, sink, max_flow, decomposition, algorithm).await?
+        };
+        solution.stats = SolverStats { max_flow_ms, augmentations, ..solution.stats };
+        solution.augmenting_paths = augmenting_paths;
+        solution.dinic_phases = dinic_phases;
+        Ok(solution)
+    }
+
+    async fn min_cost_flow(
+        &self, network: &Network, source: GraphNode, sink: GraphNode, flow_value: i64,
+        decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let mut stopwatch = Stopwatch::start();
+        if let Some(cycle) = network.negative_cycle() {
+            return Err(self.negative_cycle_error(network, &cycle));
+        }
+        self.check_costs(network)?;
+        let graph = &network.graph;
+        let mut balances = vec![0; graph.num_nodes()];
+        balances[graph.node_id(source)] += flow_value;
+        balances[graph.node_id(sink)] -= flow_value;
+        let result = min_cost_flow(network, balances, algorithm).await;
+        let limit_reached = self.limit_reached(network, result.is_some())?;
+        let (cost, flows, pivots) = result.ok_or_else(|| McmfError::Infeasible(format!(
+            "cannot route {} units from '{}' to '{}'",
+            self.unscale_amount(flow_value), self.terminal_name(network, source), self.terminal_name(network, sink)
+        )))?;
+        let terminals = (source, sink);
+        let mut solution = self.min_cost_solution(
+            network, terminals, flow_value, cost, &flows, limit_reached, decomposition, algorithm
+        ).await;
+        let min_cost_flow_ms = stopwatch.lap();
+        log!(Trace, "found a min-cost flow in {:.1} ms", min_cost_flow_ms);
+        solution.limit_reached = limit_reached;
+        solution.stats = SolverStats { pivots, min_cost_flow_ms, ..solution.stats };
+        solution.simplex_pivots = self.simplex_pivots(network);
+        Ok(solution)
+    }
+
+    // The capacity and cost of the return arc for `Network::min_cost_max_flow`: no flow exceeds
+    // the capacity leaving the source, and no path costs more than the sum of absolute costs.
+    // `None` if the cost of a flow around it may overflow, see `check_costs`.
+    fn return_arc(&self, network: &Network, source: GraphNode) -> Option<(i64, i64)> {
+        let source = network.graph.node_id(source);
+        let capacity: i128 = network.arcs.iter()
+            .filter(|arc| arc.from == source)
+            .map(|arc| arc.capacity as i128)
+            .sum();
+        let cost: i128 = network.arcs.iter().map(|arc| network.scaled_cost(arc.cost).unsigned_abs() as i128).sum();
+        let bound: i128 = network.arcs.iter()
+            .map(|arc| network.scaled_cost(arc.cost).unsigned_abs() as i128 * arc.capacity as i128)
+            .sum();
+        (bound + capacity * (cost + 1) <= MAX_TOTAL).then_some((capacity as i64, -(cost as i64 + 1)))
+    }
+
+    // Min-cost max flow in one solve, see `set_single_pass`.
+    async fn single_pass_mcmf(
+        &self, network: &Network, source: GraphNode, sink: GraphNode, return_arc: (i64, i64),
+        decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let mut stopwatch = Stopwatch::start();
+        if let Some(cycle) = network.negative_cycle() {
+            return Err(self.negative_cycle_error(network, &cycle));
+        }
+        self.check_costs(network)?;
+        network.budget.report_now(MIN_COST_FLOW_PHASE, 0., 0);
+        let result = network.min_cost_max_flow(source, sink, return_arc, algorithm).await;
+        let limit_reached = self.limit_reached(network, result.is_some())?;
+        let (flow_value, cost, flows, pivots) =
+            result.ok_or_else(|| McmfError::Infeasible("lower bounds cannot be satisfied".to_owned()))?;
+        network.check_bounded(flow_value)?;
+        if !limit_reached {
+            network.budget.report_now(MIN_COST_FLOW_PHASE, 100., flow_value);
+        }
+        let terminals = (source, sink);
+        let mut solution = self.min_cost_solution(
+            network, terminals, flow_value, cost, &flows, limit_reached, decomposition, algorithm
+        ).await;
+        let min_cost_flow_ms = stopwatch.lap();
+        log!(Trace, "found a min-cost flow in {:.1} ms", min_cost_flow_ms);
+        solution.limit_reached = limit_reached;
+        solution.stats = SolverStats { pivots, min_cost_flow_ms, ..solution.stats };
+        solution.simplex_pivots = self.simplex_pivots(network);
+        solution.augmenting_paths = self.augmenting_paths(network, source);
+        solution.dinic_phases = self.dinic_phases(network);
+        Ok(solution)
+    }
+
+    // The solution for min-cost flow `flows` of `flow_value` units between `terminals` that cost
+    // `cost`, along with potentials and the secondary objective unless a limit was reached.
+    #[allow(clippy::too_many_arguments)]
+    async fn min_cost_solution(
+        &self, network: &Network, terminals: (GraphNode, GraphNode), flow_value: i64, cost: i64, flows: &GraphFlows<'_>,
+        limit_reached: bool, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+    ) -> McmfSolution {
+        let graph = &network.graph;
+        let mut solution = self.solution(network, flows, terminals, flow_value, decomposition);
+        self.set_total_cost(&mut solution, network, cost);
+        if !limit_reached {
+            self.set_potentials(&mut solution);
+            let mut balances = vec![0; graph.num_nodes()];
+            balances[graph.node_id(terminals.0)] += flow_value;
+            balances[graph.node_id(terminals.1)] -= flow_value;
+            if let Some(flows) = self.secondary_flows(&solution, balances, algorithm).await {
+                let flows = EdgeVec::new_with(graph, |e| flows[graph.edge_id(e)]);
+                solution = self.solution(network, &flows, terminals, flow_value, decomposition);
+                self.set_total_cost(&mut solution, network, cost);
+                self.set_potentials(&mut solution);
+            }
+        }
+        solution
+    }
+
+    // Applies `set_secondary_objective` to a min-cost flow with potentials: returns the arc flows
+    // of a flow with the same balances and cost that does better on the secondary objective, if
+    // one is found. The flows are restricted to `Sensitivity::optimal_bounds`, so that they stay
+    // of minimum cost, and solved again with per-arc weights as costs.
+    async fn secondary_flows(
+        &self, solution: &McmfSolution, balances: Vec<i64>, algorithm: MinCostFlowAlgorithm,
+    ) -> Option<Vec<i64>> {
+        if self.secondary_objective == SecondaryObjective::None {
+            return None;
+        }
+        let bounds = solution.sensitivity.as_ref()?.optimal_bounds()?;
+        let mut face = self.build_network();
+        face.cost_multiplier = 1.;
+        // Only the pivots of the solve proper are traced.
+        face.budget.trace = None;
+        for (arc, (lower, capacity)) in face.arcs.iter_mut().zip(bounds) {
+            (arc.lower, arc.capacity) = (lower, capacity);
+        }
+        // Only arcs standing for builder edges count towards path lengths and edges used.
+        let counted: Vec<_> = face.graph.edges().map(|e| face.arc_origin(e).is_some()).collect();
+        // Fewer edges is a concave objective, so it is approximated by repeatedly making the arcs
+        // that carry a lot of flow cheaper, starting from the shortest paths. That is skipped if
+        // the weighted costs could overflow.
+        let total_capacity: i128 = face.arcs.iter().map(|arc| arc.capacity as i128).sum();
+        let rounds = match self.secondary_objective {
+            SecondaryObjective::FewestEdges if total_capacity * (FEWEST_EDGES_SCALE as i128) <= MAX_TOTAL => 8,
+            _ => 1,
+        };
+        let mut best = None;
+        let mut weights = vec![1; counted.len()];
+        for _ in 0..rounds {
+            for (i, arc) in face.arcs.iter_mut().enumerate() {
+                arc.cost = if counted[i] { weights[i] as f64 } else { 0. };
+            }
+            let (_, flows, _) = min_cost_flow(&face, balances.clone(), algorithm).await?;
+            let flows: Vec<_> = face.graph.edges().map(|e| flows[e]).collect();
+            let used = flows.iter().zip(&counted).filter(|&(&flow, &counted)| counted && flow > 0).count();
+            if best.as_ref().is_some_and(|&(best_used, _)| best_used <= used) {
+                break;
+            }
+            weights = flows.iter().map(|&flow| FEWEST_EDGES_SCALE / (flow + 1)).collect();
+            best = Some((used, flows));
+        }
+        best.map(|(_, flows)| flows)
+    }
+
+    fn negative_cycle_error(&self, network: &Network, cycle: &[GraphNode]) -> McmfError {
+        let mut cycle = self.node_names_of(&node_path(network, cycle));
+        cycle.push(cycle[0].clone());
+        McmfError::NegativeCycle(cycle)
+    }
+
+    // A solution without a cost for the given flows, with only the network size and the time
+    // taken to compute it in the stats.
+    fn solution(
+        &self, network: &Network, flows: &GraphFlows, (source, sink): (GraphNode, GraphNode), flow_value: i64,
+        decomposition: PathDecomposition,
+    ) -> McmfSolution {
+        let mut stopwatch = Stopwatch::start();
+        let min_cut = self.min_cut(network, flows, source, sink);
+        let edge_flows = self.edge_flows(network, flows);
+        let node_flows = self.node_flows(network, flows);
+        let residual_edges = self.residual_edges(network, flows);
+        let (paths, cycles) = self.paths(network, flows, source, sink, decomposition);
+        McmfSolution {
+            max_flow: self.unscale_amount(flow_value),
+            exact_max_flow: Some(flow_value),
+            total_cost: None,
+            exact_total_cost: None,
+            costs_rounded: false,
+            limit_reached: false,
+            paths,
+            cycles,
+            edge_flows,
+            node_flows,
+            residual_edges,
+            augmenting_paths: vec![],
+            dinic_phases: vec![],
+            simplex_pivots: vec![],
+            min_cut,
+            potentials: None,
+            sensitivity: Some(Sensitivity::new(network, flows, (source, sink), self.capacity_denominator)),
+            stats: SolverStats { paths_ms: stopwatch.lap(), ..network_stats(network) },
+            scaled_edge_flows: (0..self.edges.len()).map(|i| network.edge_flow(flows, i)).collect(),
+            edge_handles: self.edges.iter().map(|edge| edge.handle).collect(),
+        }
+    }
+
+    fn set_total_cost(&self, solution: &mut McmfSolution, network: &Network, cost: i64) {
+        solution.total_cost = Some((cost as f64) / self.cost_denominator(network));
+        solution.exact_total_cost = Some(fraction(cost, self.cost_denominator(network) as i64));
+        solution.costs_rounded = self.costs_rounded(network);
+    }
+
+    // Potentials of builder nodes in cost units, shifted so that the source has zero. A split
+    // node is reported by its entry.
+    // Only for flows of minimum cost.
+    fn set_potentials(&self, solution: &mut McmfSolution) {
+        let Some(sensitivity) = &mut solution.sensitivity else {
+            return;
+        };
+        if sensitivity.find_potentials() {
+            let potentials = (0..self.nodes.len()).map(|v| (self.node_name(v), sensitivity.potential(v).unwrap()));
+            solution.potentials = Some(potentials.collect());
+        }
+    }
+
+    // Whether a limit cut the solve short. That is an error unless partial results are enabled
+    // and there is a feasible flow to return. Cancellation is always an error.
+    fn limit_reached(&self, network: &Network, feasible: bool) -> Result<bool, McmfError> {
+        match network.budget.error() {
+            Some(error) if !self.partial_results || !feasible || network.budget.cancelled() => Err(error),
+            error => Ok(error.is_some()),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn solve_mcmf_multi(
+        &self, sources: Vec<usize>, sinks: Vec<usize>,
+        source_capacities: Option<Vec<f64>>, sink_capacities: Option<Vec<f64>>,
+        decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm, max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        if sources.is_empty() || sinks.is_empty() {
+            return Err("at least one source and one sink are required".into());
+        }
+        let mut stopwatch = Stopwatch::start();
+        let sources = self.terminals(sources, source_capacities, "source")?;
+        let sinks = self.terminals(sinks, sink_capacities, "sink")?;
+        // Flow from a node to itself through the super terminals would be unbounded.
+        if let Some(&(v, _)) = sinks.iter().find(|sink| sources.iter().any(|source| source.0 == sink.0)) {
+            return Err(format!("node '{}' is both a source and a sink", self.node_name(v)).into());
+        }
+        let mut network = Network::with_super_terminals(
+            &self.nodes, &self.edges, self.cost_multiplier(), &sources, &sinks, self.split_antiparallel_edges
+        );
+        network.budget = self.budget();
+        self.check_capacities(&network)?;
+        let (source, sink) = (network.super_source(), network.super_sink());
+        let network_ms = stopwatch.lap();
+        let mut solution = block_on(self.mcmf(&network, source, sink, decomposition, algorithm, max_flow_algorithm))?;
+        solution.stats.network_ms = network_ms;
+        Ok(solution)
+    }
+
+    fn terminals(
+        &self, nodes: Vec<usize>, capacities: Option<Vec<f64>>, kind: &str
+    ) -> Result<Vec<(usize, Option<i64>)>, McmfError> {
+        if let Some(capacities) = &capacities {
+            if capacities.len() != nodes.len() {
+                return Err(format!("expected {} {} capacities, got {}", nodes.len(), kind, capacities.len()).into());
+            }
+        }
+        let mut listed = HashSet::new();
+        nodes.into_iter().enumerate().map(|(i, node)| {
+            if !listed.insert(self.check_node(node)?) {
+                return Err(format!("node '{}' is listed twice as a {}", self.node_name(node), kind).into());
+            }
+            let capacity = capacities.as_ref()
+                .map(|capacities| self.scale_amount(capacities[i], "capacity"))
+                .transpose()?;
+            if capacity.is_some_and(|capacity| capacity <= 0) {
+                return Err("capacity must be positive".into());
+            }
+            Ok((self.check_node(node)?, capacity))
+        }).collect()
+    }
+
+    pub fn solve_max_flow(
+        &self, source: usize, sink: usize, decomposition: PathDecomposition, algorithm: MaxFlowAlgorithm
+    ) -> Result<McmfSolution, McmfError> {
+        self.check_terminals(source, sink)?;
+        if self.secondary_objective != SecondaryObjective::None {
+            return self.solve_max_flow_by_secondary_objective(source, sink, decomposition, algorithm);
+        }
+        if let Some((reduced, origins)) = self.reduced(source, sink)? {
+            let solution = reduced.solve_max_flow(source, sink, decomposition, algorithm)?;
+            return self.limit_paths(self.expanded(solution, &origins, (source, sink), decomposition)?, (source, sink));
+        }
+        let mut stopwatch = Stopwatch::start();
+        let network = self.checked_network()?;
+        let network_ms = stopwatch.lap();
+        let mut solution = self.max_flow_on(&network, (source, sink), decomposition, algorithm)?;
+        solution.stats.network_ms = network_ms;
+        Ok(solution)
+    }
+
+    fn max_flow_on(
+        &self, network: &Network, terminals: (usize, usize), decomposition: PathDecomposition,
+        algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let mut stopwatch = Stopwatch::start();
+        let source = network.entry(self.check_node(terminals.0)?);
+        let sink = network.exit(self.check_node(terminals.1)?);
+        let (max_flow, flows, augmentations) = block_on(max_flow(network, source, sink, algorithm))?;
+        let max_flow_ms = stopwatch.lap();
+        let limit_reached = self.limit_reached(network, true)?;
+        let mut solution = self.solution(network, &flows, (source, sink), max_flow, decomposition);
+        solution.limit_reached = limit_reached;
+        solution.stats = SolverStats { max_flow_ms, augmentations, ..solution.stats };
+        solution.augmenting_paths = self.augmenting_paths(network, source);
+        solution.dinic_phases = self.dinic_phases(network);
+        self.limit_paths(solution, terminals)
+    }
+
+    // With all costs zero, every maximum flow is of minimum cost, so the min-cost solve picks the one
+    // that is best for the secondary objective. Its flows are then reported with the actual costs.
+    fn solve_max_flow_by_secondary_objective(
+        &self, source: usize, sink: usize, decomposition: PathDecomposition, algorithm: MaxFlowAlgorithm
+    ) -> Result<McmfSolution, McmfError> {
+        let mut free = self.clone();
+        free.max_paths = None;
+        for edge in &mut free.edges {
+            edge.cost = 0.;
+            for piece in &mut edge.pieces {
+                piece.1 = 0.;
+            }
+        }
+        let mut solution = free.solve_mcmf(source, sink, decomposition, Default::default(), algorithm)?;
+        (solution.total_cost, solution.potentials) = (None, None);
+        let origins: Vec<_> = (0..self.edges.len()).map(|i| vec![i]).collect();
+        self.limit_paths(self.expanded(solution, &origins, (source, sink), decomposition)?, (source, sink))
+    }
+
+    // See `GraphBuilder::validate`. Without terminals the path check is skipped.
+    pub fn validate(&self, terminals: Option<(usize, usize)>) -> Result<Vec<String>, McmfError> {
+        let mut warnings = vec![];
+        let (forward, backward) = self.adjacency();
+        // Nodes that are reachable from the source and reach the sink.
+        let on_path = match terminals {
+            Some((source, sink)) => {
+                let from_source = reachable(&forward, self.check_node(source)?);
+                let to_sink = reachable(&backward, self.check_node(sink)?);
+                Some((0..self.nodes.len()).map(|v| from_source[v] && to_sink[v]).collect::<Vec<_>>())
+            }
+            None => None,
+        };
+        for v in 0..self.nodes.len() {
+            if forward[v].is_empty() && backward[v].is_empty() {
+                warnings.push(format!("node '{}' is isolated", self.node_name(v)));
+            } else if let (Some(on_path), Some((source, sink))) = (&on_path, terminals) {
+                if !on_path[v] {
+                    warnings.push(format!(
+                        "node '{}' is not on any path from '{}' to '{}'",
+                        self.node_name(v), self.node_name(source), self.node_name(sink)
+                    ));
+                }
+            }
+        }
+        let network = self.build_network();
+        let mut endpoints = HashMap::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            if edge.from == edge.to {
+                warnings.push(format!("edge {} is a self-loop at '{}'", i, self.node_name(edge.from)));
+            }
+            if edge.capacity == 0 {
+                warnings.push(format!("edge {} has zero capacity", i));
+            }
+            let key = match edge.undirected {
+                true => (edge.from.min(edge.to), edge.from.max(edge.to), true),
+                false => (edge.from, edge.to, false),
+            };
+            let first = *endpoints.entry(key).or_insert(i);
+            if first != i {
+                warnings.push(format!("edge {} is parallel to edge {}", i, first));
+            }
+            if let Some(cost) = edge.costs().find(|&cost| cost != 0. && network.scaled_cost(cost) == 0) {
+                warnings.push(format!("edge {}: cost {} is rounded to 0, increase cost precision", i, cost));
+            }
+        }
+        if let Err(error) = self.check_costs(&network) {
+            warnings.push(error.to_string());
+        }
+        Ok(warnings)
+    }
+
+    // Outgoing and incoming neighbours of each node. Undirected edges go both ways.
+    fn adjacency(&self) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let (mut forward, mut backward) = (vec![vec![]; self.nodes.len()], vec![vec![]; self.nodes.len()]);
+        for edge in &self.edges {
+            forward[edge.from].push(edge.to);
+            backward[edge.to].push(edge.from);
+            if edge.undirected {
+                forward[edge.to].push(edge.from);
+                backward[edge.from].push(edge.to);
+            }
+        }
+        (forward, backward)
+    }
+
+    pub fn diagnose_flow(
+        &self, source: usize, sink: usize, flow_value: f64
+    ) -> Result<FlowDiagnosis, McmfError> {
+        let flow_value = self.scale_amount(flow_value, "flow value")?;
+        let (source, sink) = self.check_terminals(source, sink)?;
+        let reachable = reachable(&self.adjacency().0, source);
+        let unreachable: Vec<_> = (0..self.nodes.len()).filter(|&v| !reachable[v]).collect();
+        let network = self.checked_network()?;
+        let (entry, exit) = (network.entry(source), network.exit(sink));
+        let (max_flow, flows, _) = block_on(max_flow(&network, entry, exit, Default::default()))?;
+        self.limit_reached(&network, false)?;
+        Ok(FlowDiagnosis {
+            max_flow: self.unscale_amount(max_flow),
+            shortfall: self.unscale_amount((flow_value - max_flow).max(0)),
+            unreachable: self.node_names_of(&unreachable),
+            cut: self.min_cut(&network, &flows, entry, exit).unwrap(),
+        })
+    }
+
+    pub fn verify_flow(
+        &self, source: usize, sink: usize, flows: &[f64]
+    ) -> Result<FlowVerification, McmfError> {
+        let (source, sink) = (self.check_node(source)?, self.check_node(sink)?);
+        if flows.len() != self.edges.len() {
+            return Err(format!("expected {} edge flows, got {}", self.edges.len(), flows.len()).into());
+        }
+        let flows = flows.iter().map(|&flow| self.scale_amount(flow, "flow")).collect::<Result<Vec<_>, _>>()?;
+        let mut violations = vec![];
+        let (mut inflows, mut outflows) = (vec![0; self.nodes.len()], vec![0; self.nodes.len()]);
+        for (i, (edge, &flow)) in self.edges.iter().zip(&flows).enumerate() {
+            let (from, to, amount) = if flow < 0 { (edge.to, edge.from, -flow) } else { (edge.from, edge.to, flow) };
+            if amount > edge.capacity {
+                violations.push(format!(
+                    "edge {}: flow {} exceeds capacity {}",
+                    i, self.unscale_amount(flow), self.unscale_amount(edge.capacity)
+                ));
+            } else if flow < edge.lower && !edge.undirected {
+                violations.push(format!(
+                    "edge {}: flow {} is below lower bound {}",
+                    i, self.unscale_amount(flow), self.unscale_amount(edge.lower)
+                ));
+            }
+            outflows[from] += amount;
+            inflows[to] += amount;
+        }
+        for (v, node) in self.nodes.iter().enumerate() {
+            let (inflow, outflow) = (self.unscale_amount(inflows[v]), self.unscale_amount(outflows[v]));
+            if v != source && v != sink && inflow != outflow {
+                violations.push(format!(
+                    "node '{}': inflow {} does not match outflow {}", self.node_name(v), inflow, outflow
+                ));
+            }
+            if let Some(capacity) = node.capacity.filter(|&capacity| inflows[v].max(outflows[v]) > capacity) {
+                violations.push(format!(
+                    "node '{}': throughput {} exceeds capacity {}",
+                    self.node_name(v), inflow.max(outflow), self.unscale_amount(capacity)
+                ));
+            }
+        }
+        let net_outflow = outflows[source] - inflows[source];
+        let total_cost = self.edges.iter().zip(&flows).map(|(edge, &flow)| {
+            if edge.pieces.is_empty() {
+                return edge.cost * self.unscale_amount(flow.abs());
+            }
+            let mut remaining = flow;
+            edge.pieces.iter().map(|&(capacity, cost)| {
+                let amount = remaining.min(capacity);
+                remaining -= amount;
+                cost * self.unscale_amount(amount)
+            }).sum()
+        }).sum();
+        let mut verification = FlowVerification {
+            violations,
+            flow_value: self.unscale_amount(net_outflow),
+            total_cost,
+            is_maximum: None,
+            is_optimal: None,
+        };
+        if verification.violations.is_empty() {
+            let network = self.build_network();
+            let arc_flows = network.arc_flows(&flows);
+            let graph = &network.graph;
+            let arc_flows = EdgeVec::new_with(graph, |e| arc_flows[graph.edge_id(e)]);
+            let terminals = (network.entry(source), network.exit(sink));
+            let mut sensitivity = Sensitivity::new(&network, &arc_flows, terminals, self.capacity_denominator);
+            verification.is_maximum = Some(sensitivity.is_maximum());
+            verification.is_optimal = Some(sensitivity.find_potentials());
+        }
+        Ok(verification)
+    }
+
+    pub fn solve_min_cost_circulation(&self, algorithm: MinCostFlowAlgorithm) -> Result<FlowSolution, McmfError> {
+        let network = self.checked_network()?;
+        // Without lower bounds a zero flow is always feasible. Capacities are finite, so the
+        // problem is never unbounded.
+        self.min_cost_b_flow(&network, &vec![0; self.nodes.len()], algorithm, "lower bounds cannot be satisfied")
+    }
+
+    // See `GraphBuilder::solve_feasible_circulation`.
+    pub fn solve_feasible_circulation(&self, algorithm: MaxFlowAlgorithm) -> Result<Circulation, McmfError> {
+        let network = self.checked_network()?;
+        let (flows, reachable) = block_on(network.feasible_circulation(algorithm))?;
+        if !reachable.contains(&true) {
+            return Ok(Circulation { edge_flows: self.edge_flows(&network, &flows), violated_cut: None });
+        }
+        let graph = &network.graph;
+        let (mut entering_edges, mut leaving_edges) = (vec![], vec![]);
+        let (mut required_inflow, mut outflow_capacity) = (0, 0);
+        for (i, arc) in network.arcs.iter().enumerate() {
+            let origin = network.arc_origin(graph.id2edge(i)).map(|e| e as ID);
+            match (reachable[arc.from], reachable[arc.to]) {
+                (false, true) if arc.lower > 0 => {
+                    required_inflow += arc.lower;
+                    entering_edges.extend(origin);
+                }
+                (true, false) => {
+                    outflow_capacity += arc.capacity;
+                    leaving_edges.extend(origin);
+                }
+                _ => {}
+            }
+        }
+        leaving_edges.sort_unstable();
+        leaving_edges.dedup();
+        let nodes: Vec<_> = (0..self.nodes.len()).filter(|&v| reachable[graph.node_id(network.entry(v))]).collect();
+        Ok(Circulation {
+            edge_flows: vec![],
+            violated_cut: Some(ViolatedCut {
+                nodes: self.node_names_of(&nodes),
+                entering_edges,
+                leaving_edges,
+                required_inflow: self.unscale_amount(required_inflow),
+                outflow_capacity: self.unscale_amount(outflow_capacity),
+            }),
+        })
+    }
+
+    pub fn solve_b_flow(&self, algorithm: MinCostFlowAlgorithm) -> Result<FlowSolution, McmfError> {
+        let supplies: Vec<_> = self.nodes.iter().map(|node| node.supply).collect();
+        let total_supply: i64 = supplies.iter().filter(|&&b| b > 0).sum();
+        let total_demand: i64 = -supplies.iter().filter(|&&b| b < 0).sum::<i64>();
+        if total_supply != total_demand {
+            return Err(format!(
+                "total supply {} does not match total demand {}",
+                self.unscale_amount(total_supply), self.unscale_amount(total_demand)
+            ).into());
+        }
+        let network = self.checked_network()?;
+        self.min_cost_b_flow(&network, &supplies, algorithm, "supplies cannot be routed to demands")
+    }
+
+    // See `GraphBuilder::diagnose_b_flow`. A max flow from a super source feeding each supply to a
+    // super sink fed by each demand.
+    pub fn diagnose_b_flow(&self) -> Result<BalanceDiagnosis, McmfError> {
+        let supplies: Vec<_> = self.nodes.iter().map(|node| node.supply).collect();
+        let terminals = |sign: i64| (0..self.nodes.len())
+            .filter(|&v| supplies[v].signum() == sign)
+            .map(|v| (v, Some(supplies[v].abs())))
+            .collect::<Vec<_>>();
+        let (sources, sinks) = (terminals(1), terminals(-1));
+        let total = |terminals: &[(usize, Option<i64>)]| {
+            self.unscale_amount(terminals.iter().filter_map(|terminal| terminal.1).sum())
+        };
+        let mut diagnosis = BalanceDiagnosis {
+            total_supply: total(&sources),
+            total_demand: total(&sinks),
+            imbalances: vec![],
+            cut: None,
+        };
+        if sources.is_empty() && sinks.is_empty() {
+            return Ok(diagnosis);
+        }
+        let mut network = Network::with_super_terminals(
+            &self.nodes, &self.edges, self.cost_multiplier(), &sources, &sinks, self.split_antiparallel_edges
+        );
+        network.budget = self.budget();
+        self.check_capacities(&network)?;
+        let (source, sink) = (network.super_source(), network.super_sink());
+        let (_, flows, _) = block_on(max_flow(&network, source, sink, Default::default()))?;
+        self.limit_reached(&network, false)?;
+        let graph = &network.graph;
+        let mut unmet: Vec<_> = supplies.iter().map(|supply| supply.abs()).collect();
+        for e in graph.edges() {
+            let arc = &network.arcs[graph.edge_id(e)];
+            let end = match (graph.id2node(arc.from) == source, graph.id2node(arc.to) == sink) {
+                (true, _) => arc.to,
+                (_, true) => arc.from,
+                _ => continue,
+            };
+            unmet[network.origin(graph.id2node(end)).unwrap()] -= flows[e];
+        }
+        diagnosis.imbalances = (0..self.nodes.len()).filter(|&v| unmet[v] > 0).map(|v| NodeImbalance {
+            node: self.node_name(v),
+            supply: self.unscale_amount(supplies[v]),
+            unmet: self.unscale_amount(unmet[v]),
+        }).collect();
+        diagnosis.cut = self.min_cut(&network, &flows, source, sink);
+        Ok(diagnosis)
+    }
+
+    // See `GraphBuilder::edge_disjoint_paths`.
+    pub fn edge_disjoint_paths(&self, source: usize, sink: usize, k: Option<u32>) -> Result<Vec<Path>, McmfError> {
+        let unit = self.with_unit_capacities();
+        let max_paths = unit.solve_max_flow(source, sink, Default::default(), Default::default())?;
+        let paths = match k {
+            Some(k) if (k as f64) < max_paths.max_flow() => {
+                unit.solve_min_cost_flow(source, sink, k as f64, Default::default(), Default::default())?.paths
+            }
+            _ => max_paths.paths,
+        };
+        Ok(paths)
+    }
+
+    // See `GraphBuilder::vertex_disjoint_paths`.
+    pub fn vertex_disjoint_paths(&self, source: usize, sink: usize) -> Result<DisjointPaths, McmfError> {
+        let mut unit = self.with_unit_capacities();
+        let (source, sink) = (self.check_node(source)?, self.check_node(sink)?);
+        for (v, node) in unit.nodes.iter_mut().enumerate() {
+            if v != source && v != sink {
+                node.capacity = Some(self.capacity_denominator as i64);
+            }
+        }
+        let solution = unit.solve_max_flow(source, sink, Default::default(), Default::default())?;
+        Ok(DisjointPaths { connectivity: solution.max_flow() as u32, paths: solution.paths })
+    }
+
+    // See `GraphBuilder::gomory_hu_tree`. Gusfield's algorithm: each node in turn is separated
+    // from its current tree neighbour by a minimum cut, and the nodes on its side of the cut that
+    // hung off the same neighbour move under it.
+    pub fn gomory_hu_tree(&self) -> Result<GomoryHuTree, McmfError> {
+        let mut undirected = self.clone();
+        for edge in &mut undirected.edges {
+            (edge.lower, edge.cost, edge.undirected, edge.pieces) = (0, 0., true, vec![]);
+        }
+        for node in &mut undirected.nodes {
+            (node.supply, node.capacity) = (0, None);
+        }
+        let mut network = undirected.checked_network()?;
+        let unbounded = network.infinite_capacity.take();
+        let mut parents = vec![0; self.nodes.len()];
+        let mut cut_values = vec![0; self.nodes.len()];
+        for s in 1..self.nodes.len() {
+            let t = parents[s];
+            let (source, sink) = (network.entry(s), network.exit(t));
+            let (cut_value, flows, _) = block_on(max_flow(&network, source, sink, Default::default()))?;
+            undirected.limit_reached(&network, false)?;
+            let reachable = network.residual_reachable(&flows, source);
+            let on_source_side = |v| reachable[network.graph.node_id(network.entry(v))];
+            cut_values[s] = cut_value;
+            for (v, parent) in parents.iter_mut().enumerate() {
+                if v != s && *parent == t && on_source_side(v) {
+                    *parent = s;
+                }
+            }
+            if on_source_side(parents[t]) {
+                (parents[s], parents[t]) = (parents[t], s);
+                (cut_values[s], cut_values[t]) = (cut_values[t], cut_value);
+            }
+        }
+        Ok(GomoryHuTree {
+            node_indices: (0..self.nodes.len()).map(|v| (self.node_name(v), v)).collect(),
+            nodes: self.node_names_of(&(0..self.nodes.len()).collect::<Vec<_>>()),
+            parents,
+            cut_values: cut_values.into_iter().map(|value| self.unscale_bounded(value, unbounded)).collect(),
+        })
+    }
+
+    // See `GraphBuilder::reversed`.
+    pub fn reversed(&self) -> IndexedGraphBuilder {
+        let mut reversed = self.clone();
+        for edge in &mut reversed.edges {
+            (edge.from, edge.to) = (edge.to, edge.from);
+        }
+        for node in &mut reversed.nodes {
+            node.supply = -node.supply;
+        }
+        reversed.index_edge_ends();
+        *reversed.last_mcmf.get_mut() = None;
+        reversed
+    }
+
+    // See `GraphBuilder::all_pairs_max_flow`.
+    pub fn all_pairs_max_flow(&self) -> Result<Vec<f64>, McmfError> {
+        let mut network = self.checked_network()?;
+        let unbounded = network.infinite_capacity.take();
+        let n = self.nodes.len();
+        let mut values = vec![0.; n * n];
+        for (s, t) in (0..n).flat_map(|s| (0..n).map(move |t| (s, t))).filter(|(s, t)| s != t) {
+            let (value, _, _) = block_on(max_flow(&network, network.entry(s), network.exit(t), Default::default()))?;
+            self.limit_reached(&network, false)?;
+            values[s * n + t] = self.unscale_bounded(value, unbounded);
+        }
+        Ok(values)
+    }
+
+    // See `GraphBuilder::reachable_from`.
+    pub fn reachable_from(&self, node: usize) -> Result<Vec<usize>, McmfError> {
+        let reachable = reachable(&self.adjacency().0, self.check_node(node)?);
+        Ok((0..self.nodes.len()).filter(|&v| reachable[v]).collect())
+    }
+
+    // See `GraphBuilder::can_reach`.
+    pub fn can_reach(&self, node: usize) -> Result<Vec<usize>, McmfError> {
+        let reaching = reachable(&self.adjacency().1, self.check_node(node)?);
+        Ok((0..self.nodes.len()).filter(|&v| reaching[v]).collect())
+    }
+
+    // See `GraphBuilder::strongly_connected_components`.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let mut components = tarjan(&self.adjacency().0);
+        for component in &mut components {
+            component.sort_unstable();
+        }
+        // Tarjan finds a component only after all the ones it leads to.
+        components.reverse();
+        components
+    }
+
+    // A copy where every edge can carry one unit and nodes can carry any amount, so that flows
+    // are sets of edge-disjoint paths.
+    fn with_unit_capacities(&self) -> IndexedGraphBuilder {
+        let mut unit = self.clone();
+        for edge in &mut unit.edges {
+            (edge.lower, edge.capacity, edge.pieces) = (0, self.capacity_denominator as i64, vec![]);
+        }
+        for node in &mut unit.nodes {
+            (node.supply, node.capacity) = (0, None);
+        }
+        unit
+    }
+
+    fn min_cost_b_flow(
+        &self, network: &Network, supplies: &[i64], algorithm: MinCostFlowAlgorithm, infeasible: &str
+    ) -> Result<FlowSolution, McmfError> {
+        // Other negative cycles just take as much flow as they can.
+        if let Some(cycle) = network.unbounded_cycle() {
+            return Err(self.negative_cycle_error(network, &cycle));
+        }
+        self.check_costs(network)?;
+        let result = block_on(min_cost_flow(network, network.balances(supplies), algorithm));
+        let limit_reached = self.limit_reached(network, result.is_some())?;
+        let (cost, flows, _) = result.ok_or_else(|| McmfError::Infeasible(infeasible.to_owned()))?;
+        Ok(FlowSolution {
+            total_cost: (cost as f64) / self.cost_denominator(network),
+            exact_total_cost: fraction(cost, self.cost_denominator(network) as i64),
+            costs_rounded: self.costs_rounded(network),
+            limit_reached,
+            edge_flows: self.edge_flows(network, &flows),
+        })
+    }
+
+    pub(crate) fn paths(
+        &self, network: &Network, flows: &GraphFlows, source: GraphNode, sink: GraphNode,
+        decomposition: PathDecomposition,
+    ) -> (Vec<Path>, Vec<Path>) {
+        let seed = self.path_seed;
+        let (paths, cycles) = paths::decompose(network, flows, &self.node_names, source, sink, decomposition, seed);
+        (self.merged_paths(network, paths), self.merged_paths(network, cycles))
+    }
+
+    // Pieces of a piecewise-linear cost are parallel arcs, so different paths in the network can
+    // run along the same edges. Those are merged, adding up the costs of their pieces.
+    fn merged_paths(&self, network: &Network, paths: Vec<FlowPath>) -> Vec<Path> {
+        let mut merged: Vec<(i64, f64, Vec<GraphNode>, Vec<ID>)> = vec![];
+        let mut indices: HashMap<Vec<ID>, usize> = HashMap::new();
+        for path in paths {
+            let edges: Vec<_> = path.edges.iter().filter_map(|&e| network.arc_origin(e)).map(|e| e as ID).collect();
+            let unit_cost: f64 = path.edges.iter().map(|&e| network.arcs[network.graph.edge_id(e)].cost).sum();
+            let cost = unit_cost * self.unscale_amount(path.flow);
+            match indices.get(&edges) {
+                Some(&i) => (merged[i].0, merged[i].1) = (merged[i].0 + path.flow, merged[i].1 + cost),
+                None => {
+                    indices.insert(edges.clone(), merged.len());
+                    merged.push((path.flow, cost, path.nodes, edges));
+                }
+            }
+        }
+        merged.into_iter().map(|(flow, cost, nodes, edges)| {
+            let nodes = node_path(network, &nodes);
+            Path {
+                flow: self.unscale_amount(flow),
+                cost,
+                nodes: if self.path_node_names { self.node_names_of(&nodes) } else { vec![] },
+                node_indices: nodes.into_iter().map(|v| v as ID).collect(),
+                edges,
+            }
+        }).collect()
+    }
+
+    // A node is on the source side if flow can still reach it. If its capacity is part of the cut,
+    // it is on the source side while its outgoing edges are not in the cut.
+    fn min_cut(&self, network: &Network, flows: &GraphFlows, source: GraphNode, sink: GraphNode) -> Option<MinCut> {
+        let reachable = network.residual_reachable(flows, source);
+        if reachable[network.graph.node_id(sink)] {
+            return None;
+        }
+        let is_reachable = |node| reachable[network.graph.node_id(node)];
+        let crosses = |from, to| is_reachable(network.exit(from)) && !is_reachable(network.entry(to));
+        let edges = self.edges.iter().enumerate()
+            .filter(|(_, edge)| crosses(edge.from, edge.to) || (edge.undirected && crosses(edge.to, edge.from)))
+            .map(|(i, _)| i as ID)
+            .collect();
+        let (source_side, sink_side): (Vec<_>, Vec<_>) =
+            (0..self.nodes.len()).partition(|&v| is_reachable(network.entry(v)));
+        Some(MinCut {
+            edges,
+            source_side: self.node_names_of(&source_side),
+            sink_side: self.node_names_of(&sink_side),
+        })
+    }
+
+    pub(crate) fn edges_info(&self, filter: impl Fn(&EdgeData) -> bool) -> Vec<Edge> {
+        self.edges.iter().enumerate().filter(|(_, edge)| filter(edge)).map(|(i, edge)| Edge {
+            id: i as ID,
+            from: self.node_name(edge.from),
+            to: self.node_name(edge.to),
+            lower: self.unscale_amount(edge.lower),
+            capacity: self.unscale_amount(edge.capacity),
+            cost: edge.cost,
+            undirected: edge.undirected,
+            metadata: edge.metadata.clone(),
+        }).collect()
+    }
+
+    fn node_flows(&self, network: &Network, flows: &GraphFlows) -> Vec<NodeFlow> {
+        let (mut inflows, mut outflows) = (vec![0; self.nodes.len()], vec![0; self.nodes.len()]);
+        for (i, edge) in self.edges.iter().enumerate() {
+            let flow = network.edge_flow(flows, i);
+            let (from, to) = if flow < 0 { (edge.to, edge.from) } else { (edge.from, edge.to) };
+            outflows[from] += flow.abs();
+            inflows[to] += flow.abs();
+        }
+        self.nodes.iter().enumerate().map(|(v, node)| NodeFlow {
+            node: self.node_name(v),
+            group: node.group.clone(),
+            metadata: node.metadata.clone(),
+            inflow: self.unscale_amount(inflows[v]),
+            outflow: self.unscale_amount(outflows[v]),
+        }).collect()
+    }
+
+    fn edge_flows(&self, network: &Network, flows: &GraphFlows) -> Vec<EdgeFlow> {
+        self.edges.iter().enumerate().map(|(i, edge)| {
+            let flow = network.edge_flow(flows, i);
+            let (from, to) = if flow < 0 { (edge.to, edge.from) } else { (edge.from, edge.to) };
+            EdgeFlow {
+                edge: i as ID,
+                from: self.node_name(from),
+                to: self.node_name(to),
+                from_index: from,
+                to_index: to,
+                flow: self.unscale_amount(flow.abs()),
+                capacity: self.unscale_amount(edge.capacity),
+                cost: edge.cost,
+                metadata: edge.metadata.clone(),
+            }
+        }).collect()
+    }
+
+    // The augmenting paths recorded by the max flow phase, see `GraphBuilder::set_trace`.
+    fn augmenting_paths(&self, network: &Network, source: GraphNode) -> Vec<AugmentingPath> {
+        let Some(trace) = &network.budget.trace else {
+            return vec![];
+        };
+        let trace = trace.borrow();
+        trace.augmentations.iter().map(|(arcs, amount)| self.augmenting_path(network, source, arcs, *amount)).collect()
+    }
+
+    // The phases recorded by Dinic, see `GraphBuilder::set_trace`.
+    fn dinic_phases(&self, network: &Network) -> Vec<DinicPhase> {
+        let Some(trace) = &network.budget.trace else {
+            return vec![];
+        };
+        let trace = trace.borrow();
+        let ends = trace.phases.iter().skip(1).map(|phase| phase.first_augmentation);
+        trace.phases.iter().zip(ends.chain([trace.augmentations.len()])).map(|(phase, end)| {
+            let mut named = HashSet::new();
+            DinicPhase {
+                levels: phase.levels.iter()
+                    .map(|&(v, level)| (self.terminal_name(network, network.graph.id2node(v)), level as u32))
+                    .filter(|(name, _)| named.insert(name.clone()))
+                    .collect(),
+                arcs: phase.arcs.iter().map(|&(a, capacity)| self.residual_arc(network, a, capacity)).collect(),
+                first_path: phase.first_augmentation,
+                path_count: end - phase.first_augmentation,
+            }
+        }).collect()
+    }
+
+    // The pivots recorded by network simplex, see `GraphBuilder::set_trace`.
+    fn simplex_pivots(&self, network: &Network) -> Vec<Pivot> {
+        let Some(trace) = &network.budget.trace else {
+            return vec![];
+        };
+        let edge = |a: usize| network.arc_origin(network.graph.id2edge(a)).map(|edge| edge as ID);
+        trace.borrow().pivots.iter().map(|&(entering, leaving, cost)| Pivot {
+            entering: edge(entering),
+            leaving: leaving.and_then(edge),
+            total_cost: cost as f64 / self.cost_denominator(network),
+        }).collect()
+    }
+
+    fn augmenting_path(
+        &self, network: &Network, source: GraphNode, residual_arcs: &[(usize, i64)], amount: i64
+    ) -> AugmentingPath {
+        let mut nodes = vec![self.terminal_name(network, source)];
+        let arcs = residual_arcs.iter().map(|&(a, capacity)| {
+            let arc = self.residual_arc(network, a, capacity);
+            nodes.push(arc.to.clone());
+            arc
+        }).collect();
+        // The entry and exit of a split node have the same name.
+        nodes.dedup();
+        AugmentingPath { flow: self.unscale_amount(amount), nodes, arcs }
+    }
+
+    // Residual arc `a` of `maxflow::Residual` with `capacity` left.
+    fn residual_arc(&self, network: &Network, a: usize, capacity: i64) -> ResidualEdge {
+        let graph = &network.graph;
+        let name = |v| self.terminal_name(network, graph.id2node(v));
+        let arc = &network.arcs[a / 2];
+        let reverse = a % 2 == 1;
+        let (from, to) = if reverse { (arc.to, arc.from) } else { (arc.from, arc.to) };
+        ResidualEdge {
+            edge: network.arc_origin(graph.id2edge(a / 2)).map(|edge| edge as ID),
+            from: name(from),
+            to: name(to),
+            capacity: self.unscale_amount(capacity),
+            cost: if reverse { -arc.cost } else { arc.cost },
+            reverse,
+        }
+    }
+
+    // The state of a `Solver` from `source` that has taken `steps` steps so far. Before the
+    // solution is ready, the flow is the sum of the traced augmenting paths.
+    pub(crate) fn solver_state(
+        &self, source: usize, trace: &Trace, steps: u32, solution: Option<&McmfSolution>
+    ) -> SolverState {
+        if let Some(solution) = solution {
+            return SolverState {
+                phase: "done".to_owned(),
+                steps,
+                flow: solution.max_flow(),
+                edge_flows: solution.scaled_edge_flows.iter().map(|&flow| self.unscale_amount(flow)).collect(),
+                last_path: None,
+            };
+        }
+        let network = self.build_network();
+        let mut arc_flows = vec![0; network.arcs.len()];
+        for (arcs, amount) in &trace.augmentations {
+            for &(a, _) in arcs {
+                arc_flows[a / 2] += if a % 2 == 0 { *amount } else { -*amount };
+            }
+        }
+        let flows = EdgeVec::new_with(&network.graph, |e| arc_flows[network.graph.edge_id(e)]);
+        let source = network.entry(source);
+        SolverState {
+            phase: trace.phase.clone(),
+            steps,
+            flow: self.unscale_amount(trace.augmentations.iter().map(|(_, amount)| amount).sum()),
+            edge_flows: (0..self.edges.len()).map(|i| self.unscale_amount(network.edge_flow(&flows, i))).collect(),
+            last_path: trace.augmentations.last().map(|(arcs, amount)| {
+                self.augmenting_path(&network, source, arcs, *amount)
+            }),
+        }
+    }
+
+    fn residual_edges(&self, network: &Network, flows: &GraphFlows) -> Vec<ResidualEdge> {
+        let graph = &network.graph;
+        graph.edges().flat_map(|e| {
+            let arc = &network.arcs[graph.edge_id(e)];
+            let edge = network.arc_origin(e).map(|edge| edge as ID);
+            let name = |v| self.terminal_name(network, graph.id2node(v));
+            let (from, to) = (name(arc.from), name(arc.to));
+            let forward = (flows[e] < arc.capacity).then(|| ResidualEdge {
+                edge, from: from.clone(), to: to.clone(), capacity: self.unscale_amount(arc.capacity - flows[e]),
+                cost: arc.cost, reverse: false,
+            });
+            let backward = (flows[e] > arc.lower).then(|| ResidualEdge {
+                edge, from: to, to: from, capacity: self.unscale_amount(flows[e] - arc.lower), cost: -arc.cost,
+                reverse: true,
+            });
+            forward.into_iter().chain(backward)
+        }).collect()
+    }
+
+    // Validates and converts edge bounds to the integer `(lower, capacity)` pair.
+    // An infinite capacity becomes `INFINITE_CAPACITY`. Zero is allowed, e.g. for edges that
+    // only get capacity later with `update_capacity`.
+    pub(crate) fn edge_bounds(&self, lower: f64, upper: f64) -> Result<(i64, i64), McmfError> {
+        let lower = self.scale_amount(lower, "lower bound")?;
+        let capacity = match upper {
+            f64::INFINITY => INFINITE_CAPACITY,
+            _ => self.scale_amount(upper, "capacity")?,
+        };
+        if capacity < 0 {
+            return Err(McmfError::NegativeCapacity(upper));
+        }
+        if lower < 0 {
+            return Err("lower bound must be non-negative".into());
+        }
+        if lower > capacity {
+            return Err("lower bound must not exceed capacity".into());
+        }
+        Ok((lower, capacity))
+    }
+
+    // Converts an exact capacity to solver units without going through `f64`, which can't represent
+    // every integer beyond 2^53. The cost can't be larger than that, since the solvers use `f64`
+    // costs, but products of costs and flows are kept in `i64`.
+    pub(crate) fn exact_edge_bounds(&self, capacity: i64, cost: i64) -> Result<(i64, f64), McmfError> {
+        if capacity < 0 {
+            return Err(McmfError::NegativeCapacity(capacity as f64));
+        }
+        let scaled = capacity.checked_mul(self.capacity_denominator as i64)
+            .filter(|&scaled| scaled as i128 <= MAX_TOTAL)
+            .ok_or_else(|| McmfError::Overflow(format!("capacity {} is too large", capacity)))?;
+        if cost.unsigned_abs() > MAX_AMOUNT as u64 {
+            return Err(McmfError::Overflow(format!("cost {} is too large", cost)));
+        }
+        Ok((scaled, cost as f64))
+    }
+
+    // Capacities, lower bounds, supplies and flows are integers in units of
+    // `1 / capacity_denominator`. Amounts that are not whole units are rejected.
+    pub(crate) fn scale_amount(&self, amount: f64, what: &str) -> Result<i64, McmfError> {
+        if amount.is_nan() {
+            return Err(format!("{} must be a number", what).into());
+        }
+        let scaled = amount * self.capacity_denominator as f64;
+        if scaled.abs() > MAX_AMOUNT {
+            return Err(McmfError::Overflow(format!("{} {} is too large", what, amount)));
+        }
+        if !is_integer(scaled) {
+            return Err(McmfError::Invalid(match self.capacity_denominator {
+                1 => format!("{} {} is not an integer", what, amount),
+                denominator => format!("{} {} is not a multiple of 1/{}", what, amount, denominator),
+            }));
+        }
+        Ok(scaled.round() as i64)
+    }
+
+    pub(crate) fn unscale_amount(&self, amount: i64) -> f64 {
+        if amount == INFINITE_CAPACITY {
+            return f64::INFINITY;
+        }
+        amount as f64 / self.capacity_denominator as f64
+    }
+
+    // A max flow or cut value on a network whose `infinite_capacity` was taken out to keep
+    // unbounded pairs from failing the solve: values reaching the bound are infinite.
+    fn unscale_bounded(&self, amount: i64, bound: Option<i64>) -> f64 {
+        match bound {
+            Some(bound) if amount >= bound => f64::INFINITY,
+            _ => self.unscale_amount(amount),
+        }
+    }
+
+    // The network is rebuilt for every solve, so that the builder stays usable afterwards.
+    pub(crate) fn build_network(&self) -> Network {
+        let ignored_loops;
+        let edges = if self.self_loops == SelfLoops::Ignore && self.edges.iter().any(|edge| edge.from == edge.to) {
+            ignored_loops = self.edges.iter().map(|edge| match edge.from == edge.to {
+                true => EdgeData { lower: 0, capacity: 0, pieces: vec![], ..edge.clone() },
+                false => edge.clone(),
+            }).collect::<Vec<_>>();
+            &ignored_loops
+        } else {
+            &self.edges
+        };
+        let mut network = Network::new(&self.nodes, edges, self.cost_multiplier(), self.split_antiparallel_edges);
+        network.budget = self.budget();
+        network
+    }
+
+    fn budget(&self) -> Budget {
+        let mut budget = Budget::new(self.time_limit_ms, self.iteration_limit, self.yield_interval_ms);
+        budget.cancelled = self.cancellation.as_ref().map(|token| token.cancelled.clone());
+        budget.trace = self.trace_handle.clone().or_else(|| self.trace.then(Default::default));
+        if let Some(progress) = self.progress.clone() {
+            let denominator = self.capacity_denominator as f64;
+            budget.progress = Some(Rc::new(move |phase, percent, flow| progress(phase, percent, flow as f64 / denominator)));
+        }
+        budget
+    }
+
+    // Without a fixed precision, picks the fewest decimals that represent all costs exactly,
+    // if there are at most `MAX_COST_PRECISION` of them. A cost denominator takes precedence.
+    fn cost_multiplier(&self) -> f64 {
+        if let Some(denominator) = self.cost_denominator {
+            return denominator as f64;
+        }
+        let decimals = self.cost_precision.unwrap_or_else(|| {
+            (0..MAX_COST_PRECISION)
+                .find(|&decimals| {
+                    let multiplier = 10f64.powi(decimals as i32);
+                    self.edges.iter().flat_map(EdgeData::costs).all(|cost| is_integer(cost * multiplier))
+                })
+                .unwrap_or(MAX_COST_PRECISION)
+        });
+        10f64.powi(decimals as i32)
+    }
+
+    // Scaled costs are multiplied by scaled flows, so the solver cost is in units of
+    // `1 / (cost multiplier * capacity denominator)`.
+    fn cost_denominator(&self, network: &Network) -> f64 {
+        network.cost_multiplier * self.capacity_denominator as f64
+    }
+
+    fn costs_rounded(&self, network: &Network) -> bool {
+        self.edges.iter().flat_map(EdgeData::costs).any(|cost| !is_integer(cost * network.cost_multiplier))
+    }
+
+    // With a cost denominator, costs must not be rounded. In any case the solvers sum up scaled
+    // costs times flows in `i64`, so the largest possible total must fit.
+    fn check_costs(&self, network: &Network) -> Result<(), McmfError> {
+        if let Some(denominator) = self.cost_denominator {
+            let costs = self.edges.iter().enumerate().flat_map(|(i, edge)| edge.costs().map(move |cost| (i, cost)));
+            if let Some((i, cost)) = costs.into_iter().find(|&(_, cost)| !is_integer(cost * network.cost_multiplier)) {
+                return Err(format!("edge {}: cost {} is not a multiple of 1/{}", i, cost, denominator).into());
+            }
+        }
+        let bound: i128 = network.arcs.iter()
+            .map(|arc| network.scaled_cost(arc.cost).unsigned_abs() as i128 * arc.capacity as i128)
+            .sum();
+        if bound > MAX_TOTAL {
+            return Err(McmfError::Overflow(format!(
+                "costs are too large, reduce cost precision (costs are scaled by {})",
+                network.cost_multiplier
+            )));
+        }
+        Ok(())
+    }
+
+    // The solvers add up capacities, e.g. to get an effectively unbounded capacity, so their
+    // total must fit into `i64` with room to spare.
+    fn check_capacities(&self, network: &Network) -> Result<(), McmfError> {
+        let total: i128 = network.arcs.iter().map(|arc| arc.capacity as i128).sum::<i128>()
+            + self.nodes.iter().map(|node| node.supply.unsigned_abs() as i128).sum::<i128>();
+        if total > MAX_TOTAL {
+            return Err(McmfError::Overflow("total capacity is too large".to_owned()));
+        }
+        Ok(())
+    }
+
+    // Builds the network for a solve, see `build_network`.
+    fn checked_network(&self) -> Result<Network, McmfError> {
+        let mut stopwatch = Stopwatch::start();
+        let network = self.build_network();
+        self.check_capacities(&network)?;
+        let (nodes, arcs) = (network.graph.num_nodes(), network.graph.num_edges());
+        log!(Trace, "built a network of {} nodes and {} arcs in {:.1} ms", nodes, arcs, stopwatch.lap());
+        Ok(network)
+    }
+
+    fn terminal_name(&self, network: &Network, node: GraphNode) -> String {
+        match (network.origin(node), network.middle_edge(node)) {
+            (Some(v), _) => self.node_name(v),
+            (None, Some(edge)) => format!("<middle of edge {}>", edge),
+            (None, None) => "<super terminal>".to_owned(),
+        }
+    }
+    pub(crate) fn node_name(&self, v: usize) -> String {
+        self.node_names.get(v).cloned().unwrap_or_else(|| v.to_string())
+    }
+    pub(crate) fn node_names_of(&self, nodes: &[usize]) -> Vec<String> {
+        nodes.iter().map(|&v| self.node_name(v)).collect()
+    }
+    fn check_node(&self, v: usize) -> Result<usize, McmfError> {
+        if v < self.nodes.len() { Ok(v) } else { Err(McmfError::NodeOutOfRange(v)) }
+    }
+    // A flow from a node to itself has no path to augment along.
+    fn check_terminals(&self, source: usize, sink: usize) -> Result<(usize, usize), McmfError> {
+        let (source, sink) = (self.check_node(source)?, self.check_node(sink)?);
+        if source == sink {
+            return Err(format!("source and sink must differ, both are '{}'", self.node_name(source)).into());
+        }
+        Ok((source, sink))
+    }
+    fn check_edge(&self, e: usize) -> Result<usize, McmfError> {
+        if e < self.edges.len() { Ok(e) } else { Err(McmfError::EdgeOutOfRange(e)) }
+    }
+    // Pieces of a piecewise-linear cost can only be replaced as a whole, by adding a new edge.
+    fn check_plain_edge(&self, e: usize) -> Result<usize, McmfError> {
+        let e = self.check_edge(e)?;
+        if self.edges[e].pieces.is_empty() { Ok(e) } else { Err(format!("edge {} has a piecewise cost", e).into()) }
+    }
+}
+
+// See `GraphBuilder::solved_network`.
+pub struct SolvedNetwork {
+    builder: IndexedGraphBuilder,
+    node_indices: HashMap<String, usize>,
+    network: Network,
+}
+
+impl SolvedNetwork {
+    pub(crate) fn new(builder: &IndexedGraphBuilder, node_indices: HashMap<String, usize>) -> Result<Self, McmfError> {
+        Ok(SolvedNetwork { network: builder.checked_network()?, builder: builder.clone(), node_indices })
+    }
+
+    // See `GraphBuilder::solve_max_flow`.
+    pub fn max_flow(
+        &mut self, source: &str, sink: &str, decomposition: PathDecomposition, algorithm: MaxFlowAlgorithm
+    ) -> Result<McmfSolution, McmfError> {
+        let terminals = self.terminals(source, sink)?;
+        self.network.budget = self.builder.budget();
+        self.builder.max_flow_on(&self.network, terminals, decomposition, algorithm)
+    }
+
+    // See `GraphBuilder::solve_mcmf`.
+    pub fn min_cost_max_flow(
+        &mut self, source: &str, sink: &str, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let terminals = self.terminals(source, sink)?;
+        self.network.budget = self.builder.budget();
+        let (source, sink) = (self.network.entry(terminals.0), self.network.exit(terminals.1));
+        let solution = block_on(
+            self.builder.mcmf(&self.network, source, sink, decomposition, algorithm, max_flow_algorithm)
+        )?;
+        self.builder.limit_paths(solution, terminals)
+    }
+
+    fn terminals(&self, source: &str, sink: &str) -> Result<(usize, usize), McmfError> {
+        let node = |name: &str| {
+            self.node_indices.get(name).copied().ok_or_else(|| McmfError::UnknownNode(name.to_owned()))
+        };
+        Ok((node(source)?, node(sink)?))
+    }
+}
+
+const MAX_COST_PRECISION: u32 = 9;
+// Arc weights when minimizing the edges used, see `secondary_flows`.
+const FEWEST_EDGES_SCALE: i64 = 1 << 20;
+// Scaled amounts beyond 2^53 are not exactly representable as `f64`.
+const MAX_AMOUNT: f64 = 9007199254740992.;
+const MAX_TOTAL: i128 = (i64::MAX / 4) as i128;
+
+// Formats `numerator / denominator` in lowest terms, e.g. "7/3", or "2" for "4/2".
+fn fraction(numerator: i64, denominator: i64) -> String {
+    let (mut a, mut b) = (numerator.abs(), denominator);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    let (numerator, denominator) = (numerator / a.max(1), denominator / a.max(1));
+    if denominator == 1 { numerator.to_string() } else { format!("{}/{}", numerator, denominator) }
+}
+
+// Costs are summed up and compared, which NaN and infinities would silently break.
+pub(crate) fn check_cost(cost: f64) -> Result<f64, McmfError> {
+    match cost.is_finite() {
+        true => Ok(cost),
+        false => Err(format!("cost must be a finite number, got {}", cost).into()),
+    }
+}
+
+fn is_integer(x: f64) -> bool {
+    (x - x.round()).abs() <= 1e-9 * x.abs().max(1.)
+}
+
+// Nodes reachable from `start` along `adjacent` lists.
+fn reachable(adjacent: &[Vec<usize>], start: usize) -> Vec<bool> {
+    let mut reachable = vec![false; adjacent.len()];
+    reachable[start] = true;
+    let mut stack = vec![start];
+    while let Some(u) = stack.pop() {
+        for &v in &adjacent[u] {
+            if !reachable[v] {
+                reachable[v] = true;
+                stack.push(v);
+            }
+        }
+    }
+    reachable
+}
+
+// Strongly connected components along `adjacent` lists with Tarjan's algorithm, iteratively so
+// that long chains don't overflow the stack. Components come in reverse topological order.
+fn tarjan(adjacent: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = adjacent.len();
+    let (mut order, mut low) = (vec![usize::MAX; n], vec![0; n]);
+    let (mut on_stack, mut stack) = (vec![false; n], vec![]);
+    let mut components = vec![];
+    let mut visited = 0;
+    for root in 0..n {
+        if order[root] != usize::MAX {
+            continue;
+        }
+        // Nodes being explored with the position of their next neighbour.
+        let mut calls = vec![(root, 0)];
+        while let Some((u, next)) = calls.last_mut() {
+            let u = *u;
+            if *next == 0 {
+                (order[u], low[u]) = (visited, visited);
+                visited += 1;
+                stack.push(u);
+                on_stack[u] = true;
+            }
+            if let Some(&v) = adjacent[u].get(*next) {
+                *next += 1;
+                if order[v] == usize::MAX {
+                    calls.push((v, 0));
+                } else if on_stack[v] {
+                    low[u] = low[u].min(order[v]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[u]);
+            }
+            if low[u] == order[u] {
+                let mut component = vec![];
+                loop {
+                    let v = stack.pop().unwrap();
+                    on_stack[v] = false;
+                    component.push(v);
+                    if v == u {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+// Builder nodes along a sequence of graph nodes. Copies of a split node are adjacent in the
+// sequence, so they are merged back here. Super terminals and middle nodes are dropped.
+fn node_path(network: &Network, nodes: &[GraphNode]) -> Vec<usize> {
+    let mut path: Vec<_> = nodes.iter().filter_map(|&n| network.origin(n)).collect();
+    path.dedup();
+    path
+}
+
+// `Network::max_flow` with progress reports at the start and end.
+async fn max_flow(
+    network: &Network, source: GraphNode, sink: GraphNode, algorithm: MaxFlowAlgorithm
+) -> Result<(i64, GraphFlows<'_>, Option<u32>), McmfError> {
+    network.budget.report_now(MAX_FLOW_PHASE, 0., 0);
+    let result = network.max_flow(source, sink, algorithm).await;
+    if let Ok((value, _, _)) = &result {
+        if !network.budget.reached() {
+            network.budget.report_now(MAX_FLOW_PHASE, 100., *value);
+        }
+    }
+    result
+}
+
+// `Network::min_cost_flow` with progress reports at the start and end. The flow is the total
+// supply routed.
+async fn min_cost_flow(
+    network: &Network, balances: Vec<i64>, algorithm: MinCostFlowAlgorithm
+) -> Option<(i64, GraphFlows<'_>, Option<u32>)> {
+    let total_supply = balances.iter().filter(|&&balance| balance > 0).sum();
+    network.budget.report_now(MIN_COST_FLOW_PHASE, 0., 0);
+    let result = network.min_cost_flow(balances, algorithm).await;
+    if result.is_some() && !network.budget.reached() {
+        network.budget.report_now(MIN_COST_FLOW_PHASE, 100., total_supply);
+    }
+    result
+}
+
+// Logs the outcome of a solve named `solve`, see `logging`.
+fn logged(solve: &str, result: Result<McmfSolution, McmfError>) -> Result<McmfSolution, McmfError> {
+    match &result {
+        Ok(solution) => {
+            if solution.limit_reached {
+                log!(Warn, "{}: limit reached, the result may not be optimal", solve);
+            }
+            let cost = solution.total_cost.map_or("unknown".to_owned(), |cost| cost.to_string());
+            log!(Info, "{}: a flow of {} costing {}", solve, solution.max_flow, cost);
+        }
+        Err(error) => log!(Error, "{} failed: {}", solve, error),
+    }
+    result
+}
+
+// Stats with just the network size filled in.
+fn network_stats(network: &Network) -> SolverStats {
+    let graph = &network.graph;
+    SolverStats { num_nodes: graph.num_nodes() as u32, num_edges: graph.num_edges() as u32, ..Default::default() }
+}
@@ -0,0 +1,642 @@
+// Import and export of whole problems as JSON. The schema is:
+//
+//   {
+//     "nodes": [{"name": "a", "supply": 5, "capacity": 10, "group": "plants"}, ...],
+//     "edges": [{"from": "a", "to": "b", "capacity": 10, "cost": 1, "lower": 2, "undirected": false}, ...],
+//     "source": "a",
+//     "sink": "b",
+//     "capacityDenominator": 2
+//   }
+//
+// Only "name", "from", "to" and "capacity" are required; other numbers default to 0,
+// "undirected" to false and "capacityDenominator" to 1. A node that only appears in edges doesn't
+// need to be listed, and a node can be given as just its name. Edges get IDs in the order they are
+// listed. An edge with a piecewise-linear cost has "pieces": [{"capacity": 10, "cost": 1}, ...]
+// instead of "capacity" and "cost". An infinite capacity is written as "Infinity". Nodes and
+// edges can also have a "metadata" string, see `GraphBuilder::set_edge_metadata`.
+//
+// Solutions are written as plain objects with camelCase keys mirroring the getters, e.g.
+// `{"maxFlow": 3, "totalCost": 7, "paths": [{"flow": 3, "nodes": ["a", "b"], ...}], ...}`.
+// Infinite capacities are null there, like other numbers that JSON can't represent, and are read
+// back as infinite.
+
+use std::collections::HashMap;
+
+use crate::{EdgeFlow, FlowSolution, GraphBuilder, McmfError, McmfSolution, MinCut, NodeFlow, Path, ID};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn builder_from_json(text: &str) -> Result<GraphBuilder, McmfError> {
+    let root = Parser { text: text.as_bytes(), pos: 0 }.parse_document()?;
+    if !matches!(root, Value::Object(_)) {
+        return Err("expected a JSON object".into());
+    }
+    let mut builder = GraphBuilder::new();
+    if let Some(denominator) = number_field(&root, "capacityDenominator", "problem")? {
+        builder.graph.set_capacity_denominator(denominator as u32)?;
+    }
+    for (i, node) in array_field(&root, "nodes", "problem")?.iter().enumerate() {
+        let context = format!("node {}", i);
+        let (name, node) = match node {
+            Value::String(name) => (name.clone(), None),
+            _ => (string_field(node, "name", &context)?, Some(node)),
+        };
+        builder.add_nodes(vec![name.clone()])?;
+        if let Some(node) = node {
+            if let Some(supply) = number_field(node, "supply", &context)? {
+                builder.set_supply(name.clone(), supply).map_err(|e| format!("{}: {}", context, e))?;
+            }
+            if let Some(capacity) = number_field(node, "capacity", &context)? {
+                builder.set_node_capacity(name.clone(), capacity).map_err(|e| format!("{}: {}", context, e))?;
+            }
+            builder.set_node_group(name.clone(), optional_string_field(node, "group", &context)?)?;
+            builder.set_node_metadata(name, optional_string_field(node, "metadata", &context)?)?;
+        }
+    }
+    for (i, edge) in array_field(&root, "edges", "problem")?.iter().enumerate() {
+        let context = format!("edge {}", i);
+        let from = string_field(edge, "from", &context)?;
+        let to = string_field(edge, "to", &context)?;
+        let metadata = optional_string_field(edge, "metadata", &context)?;
+        let result = if edge.get("pieces").is_some() {
+            let (mut capacities, mut costs) = (vec![], vec![]);
+            for (j, piece) in array_field(edge, "pieces", &context)?.iter().enumerate() {
+                let piece_context = format!("{}, piece {}", context, j);
+                capacities.push(number_field(piece, "capacity", &piece_context)?
+                    .ok_or_else(|| format!("{}: missing 'capacity'", piece_context))?);
+                costs.push(number_field(piece, "cost", &piece_context)?.unwrap_or(0.));
+            }
+            builder.add_edge_piecewise(from, to, &capacities, &costs)
+        } else {
+            let capacity = match edge.get("capacity") {
+                Some(Value::String(capacity)) if capacity == "Infinity" => f64::INFINITY,
+                _ => number_field(edge, "capacity", &context)?
+                    .ok_or_else(|| format!("{}: missing 'capacity'", context))?,
+            };
+            let cost = number_field(edge, "cost", &context)?.unwrap_or(0.);
+            let lower = number_field(edge, "lower", &context)?.unwrap_or(0.);
+            let undirected = match edge.get("undirected") {
+                None | Some(Value::Null) => false,
+                Some(Value::Bool(undirected)) => *undirected,
+                Some(_) => return Err(format!("{}: 'undirected' must be a boolean", context).into()),
+            };
+            if undirected {
+                if lower != 0. {
+                    return Err(format!("{}: undirected edges cannot have lower bounds", context).into());
+                }
+                builder.add_undirected_edge(from, to, capacity, cost)
+            } else {
+                builder.add_edge_with_bounds(from, to, lower, capacity, cost)
+            }
+        };
+        let id = result.map_err(|e| format!("{}: {}", context, e))?;
+        builder.set_edge_metadata(id, metadata)?;
+    }
+    for (key, terminal) in [("source", &mut builder.source), ("sink", &mut builder.sink)] {
+        *terminal = match root.get(key) {
+            None | Some(Value::Null) => None,
+            Some(Value::String(name)) => Some(name.clone()),
+            Some(_) => return Err(format!("'{}' must be a string", key).into()),
+        };
+    }
+    Ok(builder)
+}
+
+pub(crate) fn builder_to_json(builder: &GraphBuilder) -> String {
+    let graph = &builder.graph;
+    let nodes = graph.nodes.iter().enumerate().map(|(v, node)| {
+        let mut fields = vec![("name".to_owned(), Value::String(graph.node_name(v)))];
+        if node.supply != 0 {
+            fields.push(("supply".to_owned(), Value::Number(graph.unscale_amount(node.supply))));
+        }
+        if let Some(capacity) = node.capacity {
+            fields.push(("capacity".to_owned(), Value::Number(graph.unscale_amount(capacity))));
+        }
+        for (key, value) in [("group", &node.group), ("metadata", &node.metadata)] {
+            if let Some(value) = value {
+                fields.push((key.to_owned(), Value::String(value.clone())));
+            }
+        }
+        Value::Object(fields)
+    }).collect();
+    let edges = graph.edges.iter().map(|edge| {
+        let mut fields = vec![
+            ("from".to_owned(), Value::String(graph.node_name(edge.from))),
+            ("to".to_owned(), Value::String(graph.node_name(edge.to))),
+        ];
+        if edge.pieces.is_empty() {
+            let capacity = match graph.unscale_amount(edge.capacity) {
+                f64::INFINITY => Value::String("Infinity".to_owned()),
+                capacity => Value::Number(capacity),
+            };
+            fields.push(("capacity".to_owned(), capacity));
+            fields.push(("cost".to_owned(), Value::Number(edge.cost)));
+        } else {
+            let pieces = edge.pieces.iter().map(|&(capacity, cost)| Value::Object(vec![
+                ("capacity".to_owned(), Value::Number(graph.unscale_amount(capacity))),
+                ("cost".to_owned(), Value::Number(cost)),
+            ])).collect();
+            fields.push(("pieces".to_owned(), Value::Array(pieces)));
+        }
+        if edge.lower != 0 {
+            fields.push(("lower".to_owned(), Value::Number(graph.unscale_amount(edge.lower))));
+        }
+        if let Some(metadata) = &edge.metadata {
+            fields.push(("metadata".to_owned(), Value::String(metadata.clone())));
+        }
+        if edge.undirected {
+            fields.push(("undirected".to_owned(), Value::Bool(true)));
+        }
+        Value::Object(fields)
+    }).collect();
+    let mut fields = vec![("nodes".to_owned(), Value::Array(nodes)), ("edges".to_owned(), Value::Array(edges))];
+    for (key, terminal) in [("source", &builder.source), ("sink", &builder.sink)] {
+        if let Some(name) = terminal {
+            fields.push((key.to_owned(), Value::String(name.clone())));
+        }
+    }
+    if graph.capacity_denominator != 1 {
+        fields.push(("capacityDenominator".to_owned(), Value::Number(graph.capacity_denominator as f64)));
+    }
+    let mut out = String::new();
+    write_value(&mut out, &Value::Object(fields));
+    out
+}
+
+pub(crate) fn solution_to_json(solution: &McmfSolution) -> String {
+    let paths = |paths: &[Path]| Value::Array(paths.iter().map(|path| Value::Object(vec![
+        ("flow".to_owned(), Value::Number(path.flow)),
+        ("cost".to_owned(), Value::Number(path.cost)),
+        ("nodes".to_owned(), strings(&path.nodes)),
+        ("nodeIndices".to_owned(), numbers(&path.node_indices)),
+        ("edges".to_owned(), numbers(&path.edges)),
+    ])).collect());
+    let min_cut = solution.min_cut.as_ref().map_or(Value::Null, |cut| Value::Object(vec![
+        ("edges".to_owned(), numbers(&cut.edges)),
+        ("sourceSide".to_owned(), strings(&cut.source_side)),
+        ("sinkSide".to_owned(), strings(&cut.sink_side)),
+    ]));
+    let mut out = String::new();
+    write_value(&mut out, &Value::Object(vec![
+        ("maxFlow".to_owned(), Value::Number(solution.max_flow)),
+        ("totalCost".to_owned(), solution.total_cost.map_or(Value::Null, Value::Number)),
+        ("exactTotalCost".to_owned(), solution.exact_total_cost.clone().map_or(Value::Null, Value::String)),
+        ("costsRounded".to_owned(), Value::Bool(solution.costs_rounded)),
+        ("limitReached".to_owned(), Value::Bool(solution.limit_reached)),
+        ("paths".to_owned(), paths(&solution.paths)),
+        ("cycles".to_owned(), paths(&solution.cycles)),
+        ("edgeFlows".to_owned(), edge_flows(&solution.edge_flows)),
+        ("nodeFlows".to_owned(), node_flows(&solution.node_flows)),
+        ("minCut".to_owned(), min_cut),
+        ("potentials".to_owned(), solution.potentials.as_deref().map_or(Value::Null, potentials)),
+    ]));
+    out
+}
+
+pub(crate) fn solution_from_json(text: &str) -> Result<McmfSolution, McmfError> {
+    let root = Parser { text: text.as_bytes(), pos: 0 }.parse_document()?;
+    if !matches!(root, Value::Object(_)) {
+        return Err("expected a JSON object".into());
+    }
+    let context = "solution";
+    let paths = |key, name| array_field(&root, key, context)?.iter().enumerate().map(|(i, path)| {
+        let context = format!("{} {}", name, i);
+        Ok(Path {
+            flow: required_number_field(path, "flow", &context)?,
+            cost: required_number_field(path, "cost", &context)?,
+            nodes: strings_field(path, "nodes", &context)?,
+            node_indices: ids_field(path, "nodeIndices", &context)?,
+            edges: ids_field(path, "edges", &context)?,
+        })
+    }).collect::<Result<Vec<_>, McmfError>>();
+    let node_flows: Vec<_> = array_field(&root, "nodeFlows", context)?.iter().enumerate().map(|(i, flow)| {
+        let context = format!("node flow {}", i);
+        Ok(NodeFlow {
+            node: string_field(flow, "node", &context)?,
+            group: optional_string_field(flow, "group", &context)?,
+            metadata: optional_string_field(flow, "metadata", &context)?,
+            inflow: required_number_field(flow, "inflow", &context)?,
+            outflow: required_number_field(flow, "outflow", &context)?,
+        })
+    }).collect::<Result<_, McmfError>>()?;
+    let node_indices: HashMap<_, _> = node_flows.iter().enumerate().map(|(v, flow)| (flow.node.as_str(), v)).collect();
+    let node_index = |node: &str, context: &str| match node_indices.get(node) {
+        Some(&v) => Ok(v),
+        None => Err(McmfError::from(format!("{}: no node flow for '{}'", context, node))),
+    };
+    let edge_flows = array_field(&root, "edgeFlows", context)?.iter().enumerate().map(|(i, flow)| {
+        let context = format!("edge flow {}", i);
+        let (from, to) = (string_field(flow, "from", &context)?, string_field(flow, "to", &context)?);
+        Ok(EdgeFlow {
+            edge: required_number_field(flow, "edge", &context)? as ID,
+            from_index: node_index(&from, &context)?,
+            to_index: node_index(&to, &context)?,
+            from,
+            to,
+            flow: required_number_field(flow, "flow", &context)?,
+            capacity: number_field(flow, "capacity", &context)?.unwrap_or(f64::INFINITY),
+            cost: required_number_field(flow, "cost", &context)?,
+            metadata: optional_string_field(flow, "metadata", &context)?,
+        })
+    }).collect::<Result<_, McmfError>>()?;
+    let min_cut = match root.get("minCut") {
+        None | Some(Value::Null) => None,
+        Some(cut) => Some(MinCut {
+            edges: ids_field(cut, "edges", "min cut")?,
+            source_side: strings_field(cut, "sourceSide", "min cut")?,
+            sink_side: strings_field(cut, "sinkSide", "min cut")?,
+        }),
+    };
+    // Potentials are kept in the order of the node flows, see `McmfSolution::reduced_cost`.
+    let potentials = match root.get("potentials") {
+        None | Some(Value::Null) => None,
+        Some(Value::Object(fields)) => {
+            let mut potentials = vec![None; node_flows.len()];
+            for (node, potential) in fields {
+                let Value::Number(potential) = potential else {
+                    return Err(format!("potentials: '{}' must be a number", node).into());
+                };
+                potentials[node_index(node, "potentials")?] = Some((node.clone(), *potential));
+            }
+            let missing = node_flows.iter().zip(&potentials).find(|(_, potential)| potential.is_none());
+            if let Some((flow, _)) = missing {
+                return Err(format!("potentials: missing '{}'", flow.node).into());
+            }
+            Some(potentials.into_iter().flatten().collect())
+        }
+        Some(_) => return Err("'potentials' must be an object".into()),
+    };
+    Ok(McmfSolution {
+        max_flow: required_number_field(&root, "maxFlow", context)?,
+        exact_max_flow: None,
+        total_cost: number_field(&root, "totalCost", context)?,
+        exact_total_cost: optional_string_field(&root, "exactTotalCost", context)?,
+        costs_rounded: bool_field(&root, "costsRounded", context)?,
+        limit_reached: bool_field(&root, "limitReached", context)?,
+        paths: paths("paths", "path")?,
+        cycles: paths("cycles", "cycle")?,
+        edge_flows,
+        node_flows,
+        residual_edges: vec![],
+        augmenting_paths: vec![],
+        dinic_phases: vec![],
+        simplex_pivots: vec![],
+        min_cut,
+        potentials,
+        sensitivity: None,
+        stats: Default::default(),
+        scaled_edge_flows: vec![],
+        edge_handles: vec![],
+    })
+}
+
+fn potentials(node_potentials: &[(String, f64)]) -> Value {
+    Value::Object(node_potentials.iter().map(|(node, potential)| (node.clone(), Value::Number(*potential))).collect())
+}
+
+pub(crate) fn flow_solution_to_json(solution: &FlowSolution) -> String {
+    let mut out = String::new();
+    write_value(&mut out, &Value::Object(vec![
+        ("totalCost".to_owned(), Value::Number(solution.total_cost)),
+        ("exactTotalCost".to_owned(), Value::String(solution.exact_total_cost.clone())),
+        ("costsRounded".to_owned(), Value::Bool(solution.costs_rounded)),
+        ("limitReached".to_owned(), Value::Bool(solution.limit_reached)),
+        ("edgeFlows".to_owned(), edge_flows(&solution.edge_flows)),
+    ]));
+    out
+}
+
+fn edge_flows(flows: &[EdgeFlow]) -> Value {
+    Value::Array(flows.iter().map(|flow| {
+        let mut fields = vec![
+            ("edge".to_owned(), Value::Number(flow.edge as f64)),
+            ("from".to_owned(), Value::String(flow.from.clone())),
+            ("to".to_owned(), Value::String(flow.to.clone())),
+            ("flow".to_owned(), Value::Number(flow.flow)),
+            ("capacity".to_owned(), Value::Number(flow.capacity)),
+            ("cost".to_owned(), Value::Number(flow.cost)),
+        ];
+        // Like in problems, only edges that have metadata get the key.
+        if let Some(metadata) = &flow.metadata {
+            fields.push(("metadata".to_owned(), Value::String(metadata.clone())));
+        }
+        Value::Object(fields)
+    }).collect())
+}
+
+fn node_flows(flows: &[NodeFlow]) -> Value {
+    Value::Array(flows.iter().map(|flow| {
+        let mut fields = vec![("node".to_owned(), Value::String(flow.node.clone()))];
+        for (key, value) in [("group", &flow.group), ("metadata", &flow.metadata)] {
+            if let Some(value) = value {
+                fields.push((key.to_owned(), Value::String(value.clone())));
+            }
+        }
+        fields.push(("inflow".to_owned(), Value::Number(flow.inflow)));
+        fields.push(("outflow".to_owned(), Value::Number(flow.outflow)));
+        Value::Object(fields)
+    }).collect())
+}
+
+fn strings(items: &[String]) -> Value {
+    Value::Array(items.iter().map(|item| Value::String(item.clone())).collect())
+}
+
+fn numbers(items: &[u32]) -> Value {
+    Value::Array(items.iter().map(|&item| Value::Number(item as f64)).collect())
+}
+
+fn array_field<'a>(value: &'a Value, key: &str, context: &str) -> Result<&'a [Value], McmfError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(Value::Array(items)) => Ok(items),
+        Some(_) => Err(format!("{}: '{}' must be an array", context, key).into()),
+    }
+}
+
+fn string_field(value: &Value, key: &str, context: &str) -> Result<String, McmfError> {
+    match value.get(key) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        None | Some(Value::Null) => Err(format!("{}: missing '{}'", context, key).into()),
+        Some(_) => Err(format!("{}: '{}' must be a string", context, key).into()),
+    }
+}
+
+fn optional_string_field(value: &Value, key: &str, context: &str) -> Result<Option<String>, McmfError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        _ => string_field(value, key, context).map(Some),
+    }
+}
+
+fn number_field(value: &Value, key: &str, context: &str) -> Result<Option<f64>, McmfError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(x)) => Ok(Some(*x)),
+        Some(_) => Err(format!("{}: '{}' must be a number", context, key).into()),
+    }
+}
+
+fn required_number_field(value: &Value, key: &str, context: &str) -> Result<f64, McmfError> {
+    number_field(value, key, context)?.ok_or_else(|| format!("{}: missing '{}'", context, key).into())
+}
+
+fn bool_field(value: &Value, key: &str, context: &str) -> Result<bool, McmfError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(_) => Err(format!("{}: '{}' must be a boolean", context, key).into()),
+    }
+}
+
+fn strings_field(value: &Value, key: &str, context: &str) -> Result<Vec<String>, McmfError> {
+    array_field(value, key, context)?.iter().map(|item| match item {
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(format!("{}: '{}' must be an array of strings", context, key).into()),
+    }).collect()
+}
+
+fn ids_field(value: &Value, key: &str, context: &str) -> Result<Vec<ID>, McmfError> {
+    array_field(value, key, context)?.iter().map(|item| match item {
+        Value::Number(x) if *x >= 0. && x.fract() == 0. => Ok(*x as ID),
+        _ => Err(format!("{}: '{}' must be an array of indices", context, key).into()),
+    }).collect()
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(x) if x.is_finite() => out.push_str(&x.to_string()),
+        Value::Number(_) => out.push_str("null"),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(fields) => {
+            out.push('{');
+            for (i, (key, item)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, item);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse_document(&mut self) -> Result<Value, McmfError> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.pos < self.text.len() {
+            return Err(self.error("unexpected trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Value, McmfError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => self.parse_string().map(Value::String),
+            Some(b't') => self.parse_literal("true", Value::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Value::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Value::Null),
+            Some(c) if c == b'-' || c.is_ascii_digit() => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, McmfError> {
+        self.pos += 1;
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            fields.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b'}') => return Ok(Value::Object(fields)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, McmfError> {
+        self.pos += 1;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b']') => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, McmfError> {
+        self.pos += 1;
+        let mut bytes = vec![];
+        loop {
+            match self.next() {
+                Some(b'"') => break,
+                Some(b'\\') => match self.next() {
+                    Some(b'"') => bytes.push(b'"'),
+                    Some(b'\\') => bytes.push(b'\\'),
+                    Some(b'/') => bytes.push(b'/'),
+                    Some(b'b') => bytes.push(0x08),
+                    Some(b'f') => bytes.push(0x0c),
+                    Some(b'n') => bytes.push(b'\n'),
+                    Some(b'r') => bytes.push(b'\r'),
+                    Some(b't') => bytes.push(b'\t'),
+                    Some(b'u') => {
+                        let mut code = self.parse_hex4()?;
+                        if (0xd800..0xdc00).contains(&code) {
+                            // A surrogate pair encodes a character outside the BMP.
+                            if self.next() != Some(b'\\') || self.next() != Some(b'u') {
+                                return Err(self.error("expected a low surrogate"));
+                            }
+                            let low = self.parse_hex4()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return Err(self.error("expected a low surrogate"));
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        let c = char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))?;
+                        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(c) if c < 0x20 => return Err(self.error("control character in string")),
+                Some(c) => bytes.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        // The input is a `&str` and escapes produce valid UTF-8, so this cannot fail.
+        Ok(String::from_utf8(bytes).unwrap())
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, McmfError> {
+        let digits = self.text.get(self.pos..self.pos + 4).ok_or_else(|| self.error("invalid unicode escape"))?;
+        let code = std::str::from_utf8(digits).ok()
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, McmfError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || b"+-.eE".contains(&c)) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos]).unwrap().parse().map(Value::Number)
+            .map_err(|_| Parser { text: self.text, pos: start }.error("invalid number"))
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value, McmfError> {
+        if self.text[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected character"))
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), McmfError> {
+        if self.next() == Some(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c as char)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn error(&self, message: &str) -> McmfError {
+        McmfError::Invalid(format!("invalid JSON at position {}: {}", self.pos, message))
+    }
+}
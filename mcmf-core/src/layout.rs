@@ -0,0 +1,157 @@
+// Force-directed layout after Fruchterman and Reingold: edges pull their ends together, all nodes
+// push each other apart, and the moves are capped by a temperature that cools down linearly. The
+// repulsion is approximated with a Barnes-Hut quadtree, where a far away cell of nodes pushes like
+// a single node at its center of mass, so that an iteration takes O(n log n) time instead of
+// quadratic. Positions start on a sunflower spiral, which makes layouts deterministic.
+
+// Node positions of `GraphBuilder::layout`, by node index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    x: Vec<f64>,
+    y: Vec<f64>,
+}
+
+impl Layout {
+    pub fn x(&self) -> &[f64] { &self.x }
+    pub fn y(&self) -> &[f64] { &self.y }
+}
+
+// How far a cell must be, relative to its size, to be approximated by its center of mass.
+const THETA: f64 = 0.8;
+// Cells smaller than this are not split further, so that nodes at the same spot end up in one.
+const MIN_CELL: f64 = 1e-6;
+
+// `edges` are `(from, to, cost)`. With `weight_by_cost`, edges are shorter the cheaper they are
+// compared to the average edge.
+pub(crate) fn force_directed(
+    num_nodes: usize, edges: &[(usize, usize, f64)], iterations: u32, weight_by_cost: bool,
+) -> Layout {
+    // The ideal distance between nodes. The spiral starts with about one node per unit of area.
+    let k = 1.;
+    let golden_angle = std::f64::consts::PI * (3. - 5f64.sqrt());
+    let (mut x, mut y): (Vec<_>, Vec<_>) = (0..num_nodes).map(|v| {
+        let (r, angle) = (((v as f64 + 0.5) / std::f64::consts::PI).sqrt(), v as f64 * golden_angle);
+        (r * angle.cos(), r * angle.sin())
+    }).unzip();
+    let mean_cost = edges.iter().map(|e| e.2.abs()).sum::<f64>() / edges.len().max(1) as f64;
+    let lengths: Vec<_> = edges.iter().map(|&(_, _, cost)| match weight_by_cost && mean_cost > 0. {
+        true => k * (cost.abs() / mean_cost).clamp(0.1, 10.),
+        false => k,
+    }).collect();
+    let initial_temperature = (num_nodes as f64).sqrt() / 10. + k;
+    for iteration in 0..iterations {
+        let temperature = initial_temperature * (1. - iteration as f64 / iterations as f64);
+        let tree = QuadTree::new(&x, &y);
+        let (mut dx, mut dy): (Vec<_>, Vec<_>) = (0..num_nodes).map(|u| tree.repulsion(&x, &y, u, k)).unzip();
+        for (&(from, to, _), length) in edges.iter().zip(&lengths) {
+            if from == to {
+                continue;
+            }
+            let (ddx, ddy) = separation(x[from] - x[to], y[from] - y[to], from, to);
+            let d = ddx.hypot(ddy);
+            let force = d * d / length;
+            dx[from] -= ddx / d * force;
+            dy[from] -= ddy / d * force;
+            dx[to] += ddx / d * force;
+            dy[to] += ddy / d * force;
+        }
+        for v in 0..num_nodes {
+            let d = dx[v].hypot(dy[v]);
+            if d > 0. {
+                x[v] += dx[v] / d * d.min(temperature);
+                y[v] += dy[v] / d * d.min(temperature);
+            }
+        }
+    }
+    Layout { x, y }
+}
+
+// The offset from `v` to `u`, or a tiny one depending on the nodes if they are at the same spot,
+// so that they get pushed apart.
+fn separation(dx: f64, dy: f64, u: usize, v: usize) -> (f64, f64) {
+    if dx == 0. && dy == 0. {
+        let sign = if u < v { 1. } else { -1. };
+        (sign * 1e-6, sign * 1e-6)
+    } else {
+        (dx, dy)
+    }
+}
+
+struct Cell {
+    size: f64,
+    mass: f64,
+    center: (f64, f64),
+    // Child cells, or the nodes in a leaf.
+    children: Vec<usize>,
+    nodes: Vec<usize>,
+}
+
+struct QuadTree {
+    cells: Vec<Cell>,
+}
+
+impl QuadTree {
+    fn new(x: &[f64], y: &[f64]) -> QuadTree {
+        let mut tree = QuadTree { cells: vec![] };
+        if x.is_empty() {
+            return tree;
+        }
+        let (min_x, max_x) = x.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), &x| (a.min(x), b.max(x)));
+        let (min_y, max_y) = y.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), &y| (a.min(y), b.max(y)));
+        let size = (max_x - min_x).max(max_y - min_y).max(MIN_CELL);
+        tree.build(x, y, (0..x.len()).collect(), (min_x, min_y), size);
+        tree
+    }
+
+    // Adds the cell with corner `origin` holding `nodes` and its descendants. Returns its index.
+    fn build(&mut self, x: &[f64], y: &[f64], nodes: Vec<usize>, origin: (f64, f64), size: f64) -> usize {
+        let mass = nodes.len() as f64;
+        let mean = |coordinates: &[f64]| nodes.iter().map(|&v| coordinates[v]).sum::<f64>() / mass;
+        let center = (mean(x), mean(y));
+        let index = self.cells.len();
+        self.cells.push(Cell { size, mass, center, children: vec![], nodes: vec![] });
+        if nodes.len() == 1 || size <= MIN_CELL {
+            self.cells[index].nodes = nodes;
+            return index;
+        }
+        let half = size / 2.;
+        let mut quadrants = [vec![], vec![], vec![], vec![]];
+        for v in nodes {
+            let right = x[v] >= origin.0 + half;
+            let top = y[v] >= origin.1 + half;
+            quadrants[right as usize + 2 * top as usize].push(v);
+        }
+        for (i, quadrant) in quadrants.into_iter().enumerate().filter(|(_, quadrant)| !quadrant.is_empty()) {
+            let corner = (origin.0 + half * (i % 2) as f64, origin.1 + half * (i / 2) as f64);
+            let child = self.build(x, y, quadrant, corner, half);
+            self.cells[index].children.push(child);
+        }
+        index
+    }
+
+    // The total repulsion `k^2 / d` of the other nodes on node `u`.
+    fn repulsion(&self, x: &[f64], y: &[f64], u: usize, k: f64) -> (f64, f64) {
+        let mut force = (0., 0.);
+        let mut push = |ddx: f64, ddy: f64, mass: f64| {
+            let d2 = ddx * ddx + ddy * ddy;
+            force.0 += ddx * k * k * mass / d2;
+            force.1 += ddy * k * k * mass / d2;
+        };
+        let mut stack = if self.cells.is_empty() { vec![] } else { vec![0] };
+        while let Some(cell) = stack.pop() {
+            let cell = &self.cells[cell];
+            let (ddx, ddy) = (x[u] - cell.center.0, y[u] - cell.center.1);
+            if !cell.children.is_empty() && cell.size * cell.size < THETA * THETA * (ddx * ddx + ddy * ddy) {
+                push(ddx, ddy, cell.mass);
+            } else if cell.children.is_empty() {
+                for &v in cell.nodes.iter().filter(|&&v| v != u) {
+                    let (ddx, ddy) = separation(x[u] - x[v], y[u] - y[v], u, v);
+                    push(ddx, ddy, 1.);
+                }
+            } else {
+                stack.extend(&cell.children);
+            }
+        }
+        force
+    }
+}
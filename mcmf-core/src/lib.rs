@@ -0,0 +1,4489 @@
+// Min-cost max-flow solvers with named or indexed nodes, path decomposition and analysis of the
+// results. This crate is plain Rust; `mcmf-wasm` wraps its types for JS.
+
+mod applications;
+mod csv;
+mod dimacs;
+mod dot;
+mod error;
+mod exercise;
+mod graphml;
+mod indexed;
+mod json;
+mod layout;
+mod logging;
+mod lp;
+mod maxflow;
+mod mcf;
+mod multicommodity;
+mod network;
+mod paths;
+mod random;
+mod sensitivity;
+mod task;
+mod time_expanded;
+mod utils;
+
+use std::{cell::Cell, collections::{HashMap, HashSet}, rc::Rc};
+
+pub use applications::{
+    solve_assignment, solve_bipartite_matching, solve_min_cost_matching, solve_project_selection, solve_transportation,
+    Assignment, Matching, ProjectSelection, Transportation,
+};
+pub use csv::CsvOptions;
+pub use error::McmfError;
+pub use exercise::{generate_exercise, Difficulty, Exercise, ManualMaxFlow};
+pub use indexed::{IndexedGraphBuilder, SolvedNetwork};
+pub use layout::Layout;
+pub use logging::{log_level, set_log_level, LogLevel};
+pub use multicommodity::{CommodityFlow, MultiCommodityBuilder, MultiCommodityFlow};
+pub use random::generate_random_graph;
+pub use task::{SolveTask, Solver, SolverState};
+pub use time_expanded::{Departure, FlowOverTime, TimeExpandedBuilder};
+pub use utils::Progress;
+use indexed::check_cost;
+use logging::log;
+use utils::map_bytes;
+use rs_graph::{VecGraph, EdgeVec, vecgraph};
+use sensitivity::Sensitivity;
+
+pub type ID = u32;
+
+// Identifies an edge for as long as the builder has it, unlike its ID, which `remove_edge` shifts.
+// See `GraphBuilder::edge_handle`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EdgeHandle(pub(crate) u32);
+type Graph = VecGraph<ID>;
+type GraphNode = vecgraph::Node<ID>;
+type GraphEdge = vecgraph::Edge<ID>;
+type GraphFlows<'g> = EdgeVec<'g, &'g Graph, i64>;
+
+// Costs are rounded to this many decimal places unless `set_cost_precision` says otherwise.
+const DEFAULT_COST_PRECISION: u32 = 3;
+
+// How solution flow is split into paths. A decomposition is not unique and the default depth-first
+// one is arbitrary, so the others are there to produce more readable routing plans.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathDecomposition {
+    #[default]
+    DepthFirst,
+    // Heuristic: tries the other strategies and picks the one with the fewest paths.
+    FewestPaths,
+    // Repeatedly takes the path that can carry the most flow.
+    WidestFirst,
+    // Repeatedly takes the path with the fewest edges.
+    ShortestFirst,
+    // Repeatedly takes the path with the lexicographically smallest sequence of node names.
+    Lexicographic,
+}
+
+// Algorithm used for the min-cost part of a solve. They all find an optimal flow, but may pick
+// different ones when there are ties, and differ in performance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MinCostFlowAlgorithm {
+    // Primal network simplex from rs-graph. Usually the fastest.
+    #[default]
+    NetworkSimplex,
+    // Augments along shortest paths, one path at a time. Fast when the flow value is small.
+    SuccessiveShortestPaths,
+    // Goldberg-Tarjan cost scaling with push-relabel. Polynomial in the size of the graph.
+    CostScaling,
+    // Cancels negative cycles in a feasible flow. Slow, but simple; mostly useful as a reference.
+    CycleCanceling,
+}
+
+// Algorithm used for max flow, which is also the first phase of MCMF.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaxFlowAlgorithm {
+    // Reports the number of augmenting paths in `SolverStats`.
+    #[default]
+    Dinic,
+    // Push-relabel with the gap and global relabeling heuristics. Often faster on dense graphs.
+    PushRelabel,
+}
+
+// What min-cost solves optimize next among the flows of minimum cost, and max-flow solves among the
+// maximum flows, see `GraphBuilder::set_secondary_objective`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecondaryObjective {
+    #[default]
+    None,
+    // Minimizes the total number of edges that units of flow pass through, which makes paths
+    // shorter, although the longest one is not necessarily as short as possible.
+    ShortestPaths,
+    // Heuristic: tries to reduce the number of edges carrying any flow, e.g. links to keep open.
+    FewestEdges,
+}
+
+// What happens to a directed edge with the same ends as an earlier one, see
+// `GraphBuilder::set_duplicate_edges`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateEdges {
+    // Both are kept as parallel edges.
+    #[default]
+    Keep,
+    // Adding it fails.
+    Reject,
+    // The earlier edge takes the sum of both bounds and the lower of the two costs.
+    MergeMinCost,
+    // The earlier edge takes the sum of both bounds and the average of the costs, weighted by
+    // capacity.
+    MergeAverageCost,
+}
+
+// What happens to an edge from a node to itself, see `GraphBuilder::set_self_loops`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelfLoops {
+    #[default]
+    Keep,
+    // Adding it fails.
+    Reject,
+    // It is added, but solves treat it as having no capacity, so it never carries flow.
+    Ignore,
+}
+
+// How `McmfSolution::paths_sorted` orders paths. Ties keep the order of the decomposition.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathOrder {
+    // Largest flow first.
+    #[default]
+    Flow,
+    // Lowest cost per unit first, see `Path::cost`.
+    Cost,
+}
+
+#[derive(Clone, Debug)]
+pub struct Path {
+    flow: f64,
+    cost: f64,
+    nodes: Vec<String>,
+    node_indices: Vec<ID>,
+    edges: Vec<ID>,
+}
+
+impl Path {
+    pub fn flow(&self) -> f64 { self.flow }
+    // The cost of the flow along the path, i.e. the flow times the sum of the edge costs. Pieces of
+    // a piecewise cost count as far as the path uses them.
+    pub fn cost(&self) -> f64 { self.cost }
+    // The number of edges.
+    pub fn length(&self) -> u32 { self.edges.len() as u32 }
+    pub fn nodes(&self) -> &[String] { &self.nodes }
+    // Node indices as used by `IndexedGraphBuilder` and `GraphBuilder::add_edges_bulk`.
+    pub fn node_indices(&self) -> &[ID] { &self.node_indices }
+    // IDs of the edges along the path, as returned by `GraphBuilder::add_edge`.
+    pub fn edges(&self) -> &[ID] { &self.edges }
+}
+
+// The timing of a path in `McmfSolution::animation_plan`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathAnimation {
+    path: u32,
+    node_times: Vec<f64>,
+}
+
+impl PathAnimation {
+    // The index in `McmfSolution::paths`.
+    pub fn path(&self) -> u32 { self.path }
+    // When the particle is at each node of the path, in milliseconds from the start. It moves
+    // along each edge at a constant speed.
+    pub fn node_times(&self) -> &[f64] { &self.node_times }
+    pub fn start_ms(&self) -> f64 { self.node_times[0] }
+    pub fn end_ms(&self) -> f64 { *self.node_times.last().unwrap() }
+}
+
+// The first paths of a solution in some order, see `McmfSolution::paths_sorted`.
+#[derive(Clone, Debug)]
+pub struct SortedPaths {
+    paths: Vec<Path>,
+    other_paths: u32,
+    other_flow: f64,
+    other_cost: f64,
+}
+
+impl SortedPaths {
+    pub fn paths(&self) -> &[Path] { &self.paths }
+    // The paths beyond the limit: how many there are, and their flow and cost in total.
+    pub fn other_paths(&self) -> u32 { self.other_paths }
+    pub fn other_flow(&self) -> f64 { self.other_flow }
+    pub fn other_cost(&self) -> f64 { self.other_cost }
+}
+
+// All paths of a solution packed into flat arrays, which are much cheaper to pass around than
+// a `Path` per path when there are many: path `i` has flow `flows[i]`, visits the nodes
+// `node_indices[node_offsets[i]..node_offsets[i + 1]]` and uses the edges
+// `edges[edge_offsets[i]..edge_offsets[i + 1]]`. Node names are `GraphBuilder::nodes` by index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathArrays {
+    flows: Vec<f64>,
+    node_offsets: Vec<u32>,
+    node_indices: Vec<ID>,
+    edge_offsets: Vec<u32>,
+    edges: Vec<ID>,
+}
+
+impl PathArrays {
+    fn new(paths: &[Path]) -> PathArrays {
+        let offsets = |len: fn(&Path) -> usize| {
+            std::iter::once(0).chain(paths.iter().scan(0, |offset, path| {
+                *offset += len(path) as u32;
+                Some(*offset)
+            })).collect()
+        };
+        PathArrays {
+            flows: paths.iter().map(|path| path.flow).collect(),
+            node_offsets: offsets(|path| path.node_indices.len()),
+            node_indices: paths.iter().flat_map(|path| path.node_indices.iter().copied()).collect(),
+            edge_offsets: offsets(|path| path.edges.len()),
+            edges: paths.iter().flat_map(|path| path.edges.iter().copied()).collect(),
+        }
+    }
+
+    pub fn flows(&self) -> &[f64] { &self.flows }
+    // One more than there are paths.
+    pub fn node_offsets(&self) -> &[u32] { &self.node_offsets }
+    pub fn node_indices(&self) -> &[ID] { &self.node_indices }
+    pub fn edge_offsets(&self) -> &[u32] { &self.edge_offsets }
+    pub fn edges(&self) -> &[ID] { &self.edges }
+}
+
+#[derive(Clone, Debug)]
+pub struct MinCut {
+    edges: Vec<ID>,
+    source_side: Vec<String>,
+    sink_side: Vec<String>,
+}
+
+impl MinCut {
+    // IDs of the saturated edges going from the source side to the sink side.
+    pub fn edges(&self) -> &[ID] { &self.edges }
+    pub fn source_side(&self) -> &[String] { &self.source_side }
+    pub fn sink_side(&self) -> &[String] { &self.sink_side }
+}
+
+#[derive(Clone, Debug)]
+pub struct McmfSolution {
+    max_flow: f64,
+    // In solver units, `None` for solutions loaded with `from_json`.
+    exact_max_flow: Option<i64>,
+    total_cost: Option<f64>,
+    exact_total_cost: Option<String>,
+    costs_rounded: bool,
+    limit_reached: bool,
+    paths: Vec<Path>,
+    cycles: Vec<Path>,
+    edge_flows: Vec<EdgeFlow>,
+    node_flows: Vec<NodeFlow>,
+    residual_edges: Vec<ResidualEdge>,
+    augmenting_paths: Vec<AugmentingPath>,
+    dinic_phases: Vec<DinicPhase>,
+    simplex_pivots: Vec<Pivot>,
+    min_cut: Option<MinCut>,
+    potentials: Option<Vec<(String, f64)>>,
+    // `None` for solutions loaded with `from_json`.
+    sensitivity: Option<Sensitivity>,
+    stats: SolverStats,
+    // Net flow on each edge in solver units, for `GraphBuilder::resolve`.
+    scaled_edge_flows: Vec<i64>,
+    // By edge ID, empty for solutions loaded with `from_json`.
+    edge_handles: Vec<EdgeHandle>,
+}
+
+impl McmfSolution {
+    pub fn max_flow(&self) -> f64 { self.max_flow }
+    // The flow value and the net flow on each edge by edge ID, in units of `1 / denominator` with
+    // `GraphBuilder::set_capacity_denominator` and whole units otherwise. Unlike `max_flow` and
+    // `edge_flows`, they are exact beyond 2^53, e.g. for edges from `GraphBuilder::add_edge_exact`.
+    // Solutions loaded with `from_json` have neither.
+    pub fn exact_max_flow(&self) -> Option<i64> { self.exact_max_flow }
+    pub fn exact_edge_flows(&self) -> &[i64] { &self.scaled_edge_flows }
+    pub fn total_cost(&self) -> Option<f64> { self.total_cost }
+    // The total cost as a fraction in lowest terms, e.g. "7/3". Exact for the (possibly rounded)
+    // costs used by the solver, unlike `total_cost`.
+    pub fn exact_total_cost(&self) -> Option<&str> { self.exact_total_cost.as_deref() }
+    // Whether some edge costs had more decimals than the cost precision, so that the solution
+    // was computed for rounded costs and may not be optimal for the exact ones.
+    pub fn costs_rounded(&self) -> bool { self.costs_rounded }
+    // Whether the solve was cut short by a limit, so that the flow is feasible but not
+    // necessarily maximum or of minimum cost. See `GraphBuilder::set_partial_results`.
+    pub fn limit_reached(&self) -> bool { self.limit_reached }
+    // In the order the decomposition took them. The paths and their order only depend on the
+    // graph, including the order its nodes and edges were added in, and the settings, so the same
+    // input always gives the same output. See `GraphBuilder::set_path_seed` to vary it.
+    pub fn paths(&self) -> &[Path] { &self.paths }
+    pub fn path_arrays(&self) -> PathArrays { PathArrays::new(&self.paths) }
+    // Flow that goes around cycles instead of from the source to the sink, e.g. because lower
+    // bounds force it, in the same form as paths whose first and last nodes are the same. Edge
+    // flows are the sum of the paths and the cycles.
+    pub fn cycles(&self) -> &[Path] { &self.cycles }
+    // The paths in the given order, cut off after `limit` of them if set, e.g. to show the main
+    // routes of a solution with thousands. The rest are summarized.
+    pub fn paths_sorted(&self, by: PathOrder, limit: Option<u32>) -> SortedPaths {
+        let mut paths: Vec<_> = self.paths.iter().collect();
+        match by {
+            PathOrder::Flow => paths.sort_by(|a, b| b.flow.total_cmp(&a.flow)),
+            PathOrder::Cost => paths.sort_by(|a, b| (a.cost / a.flow).total_cmp(&(b.cost / b.flow))),
+        }
+        let rest = paths.split_off(limit.map_or(paths.len(), |limit| paths.len().min(limit as usize)));
+        SortedPaths {
+            paths: paths.into_iter().cloned().collect(),
+            other_paths: rest.len() as u32,
+            other_flow: rest.iter().map(|path| path.flow).sum(),
+            other_cost: rest.iter().map(|path| path.cost).sum(),
+        }
+    }
+    // Timings for animating a particle along each path within `duration_ms`, e.g. with positions
+    // from `GraphBuilder::layout`. Paths start one after another in the first half, and each moves
+    // at a speed in edges per millisecond proportional to its flow, as fast as possible while all
+    // still arrive in time. The duration must be positive.
+    pub fn animation_plan(&self, duration_ms: f64) -> Result<Vec<PathAnimation>, McmfError> {
+        if !(duration_ms > 0. && duration_ms.is_finite()) {
+            return Err(format!("duration must be a positive number, got {}", duration_ms).into());
+        }
+        let max_flow = self.paths.iter().map(|path| path.flow).fold(0., f64::max);
+        let stagger = duration_ms / 2. / self.paths.len().max(1) as f64;
+        let start = |i: usize| i as f64 * stagger;
+        // In edges per millisecond for a path with the largest flow.
+        let speed = self.paths.iter().enumerate()
+            .map(|(i, path)| path.edges.len() as f64 * max_flow / path.flow / (duration_ms - start(i)))
+            .fold(0., f64::max);
+        Ok(self.paths.iter().enumerate().map(|(i, path)| {
+            let path_speed = speed * path.flow / max_flow;
+            let node_times: Vec<_> = (0..=path.edges.len()).map(|j| start(i) + j as f64 / path_speed).collect();
+            PathAnimation { path: i as u32, node_times }
+        }).collect())
+    }
+    pub fn edge_flows(&self) -> &[EdgeFlow] { &self.edge_flows }
+    // The flow on the edge with the given handle, see `GraphBuilder::edge_handle`. Fails if the
+    // edge was added after the solve or the solution was loaded with `from_json`.
+    pub fn flow_on(&self, handle: EdgeHandle) -> Result<f64, McmfError> {
+        match self.edge_handles.binary_search(&handle) {
+            Ok(e) => Ok(self.edge_flows[e].flow),
+            Err(_) => Err(format!("edge handle {} refers to no edge of the solved graph", handle.0).into()),
+        }
+    }
+    // The metadata of edge `edge`, see `GraphBuilder::set_edge_metadata`. Handy for the edges of
+    // `paths`, which are only IDs.
+    pub fn edge_metadata(&self, edge: ID) -> Result<Option<&str>, McmfError> {
+        let flow = self.edge_flows.get(edge as usize).ok_or(McmfError::EdgeOutOfRange(edge as usize))?;
+        Ok(flow.metadata())
+    }
+    // The flow into and out of each node, in the order the nodes were added, with its group and
+    // metadata, see `GraphBuilder::set_node_group`.
+    pub fn node_flows(&self) -> &[NodeFlow] { &self.node_flows }
+    // The flow through each node, in the order of `node_flows`: the larger of its inflow and
+    // outflow, which are equal except where flow starts or ends.
+    pub fn node_throughput(&self) -> Vec<f64> {
+        self.node_flows.iter().map(|node| node.inflow.max(node.outflow)).collect()
+    }
+    // Totals per node group, in the order the groups first appear among the nodes. Nodes without
+    // a group are left out.
+    pub fn group_flows(&self) -> Vec<GroupFlow> {
+        let mut groups: Vec<GroupFlow> = vec![];
+        // Index in `groups` by node name.
+        let mut node_groups = HashMap::new();
+        for node in &self.node_flows {
+            let Some(group) = &node.group else {
+                continue;
+            };
+            let i = groups.iter().position(|g| &g.group == group).unwrap_or_else(|| {
+                groups.push(GroupFlow { group: group.clone(), ..Default::default() });
+                groups.len() - 1
+            });
+            groups[i].nodes += 1;
+            node_groups.insert(node.node.as_str(), i);
+        }
+        for flow in &self.edge_flows {
+            match (node_groups.get(flow.from.as_str()), node_groups.get(flow.to.as_str())) {
+                (Some(&from), Some(&to)) if from == to => {
+                    groups[from].internal_flow += flow.flow;
+                    groups[from].internal_cost += flow.flow * flow.cost;
+                }
+                (from, to) => {
+                    if let Some(&from) = from {
+                        groups[from].outflow += flow.flow;
+                    }
+                    if let Some(&to) = to {
+                        groups[to].inflow += flow.flow;
+                    }
+                }
+            }
+        }
+        groups
+    }
+    // Every edge with the fraction of its capacity in use, most used first and then by ID, e.g.
+    // for a heat map. Edges with an infinite or zero capacity count as unused.
+    pub fn utilization_report(&self) -> Vec<EdgeUtilization> {
+        let mut report: Vec<_> = self.edge_flows.iter().map(|flow| {
+            let bounded = flow.capacity > 0. && flow.capacity.is_finite();
+            EdgeUtilization {
+                edge: flow.edge,
+                from: flow.from.clone(),
+                to: flow.to.clone(),
+                flow: flow.flow,
+                capacity: flow.capacity,
+                utilization: if bounded { flow.flow / flow.capacity } else { 0. },
+                saturated: bounded && flow.flow >= flow.capacity,
+            }
+        }).collect();
+        report.sort_by(|a, b| b.utilization.total_cmp(&a.utilization).then(a.edge.cmp(&b.edge)));
+        report
+    }
+    // The arcs with spare capacity in the residual network of the flow: the flow can grow along
+    // an edge or shrink against it. The flow is maximum once these don't connect the source to
+    // the sink; see `min_cut` for where they are cut off. Edges with a piecewise cost have an
+    // arc per piece, and undirected edges can have arcs both ways.
+    pub fn residual_edges(&self) -> &[ResidualEdge] { &self.residual_edges }
+    // The augmenting paths of the max flow phase in the order they were used, if tracing is on,
+    // see `GraphBuilder::set_trace`.
+    pub fn augmenting_paths(&self) -> &[AugmentingPath] { &self.augmenting_paths }
+    // The phases of Dinic in the max flow phase in order, if tracing is on, see
+    // `GraphBuilder::set_trace`. Their number is how often the level graph was rebuilt.
+    pub fn dinic_phases(&self) -> &[DinicPhase] { &self.dinic_phases }
+    // The network simplex pivots of the min-cost phase in order, if tracing is on, see
+    // `GraphBuilder::set_trace`.
+    pub fn simplex_pivots(&self) -> &[Pivot] { &self.simplex_pivots }
+    // `None` if the flow is not maximum, e.g. for `solve_min_cost_flow` with a smaller value.
+    pub fn min_cut(&self) -> Option<&MinCut> { self.min_cut.as_ref() }
+    // Optimal dual values ("shadow prices") by node name in the order of `node_flows`, relative to
+    // the source: the reduced cost `cost + potential[from] - potential[to]` is non-negative on
+    // every edge with spare capacity and non-positive on every edge carrying flow. `None` unless
+    // the min-cost phase ran to the end.
+    pub fn potentials(&self) -> Option<&[(String, f64)]> { self.potentials.as_deref() }
+    // `cost + potential[from] - potential[to]` for edge `edge`, oriented along its flow. How much
+    // cheaper an unused edge would have to be to get flow, or `None` without potentials.
+    pub fn reduced_cost(&self, edge: ID) -> Result<Option<f64>, McmfError> {
+        let flow = self.edge_flows.get(edge as usize).ok_or(McmfError::EdgeOutOfRange(edge as usize))?;
+        let Some(potentials) = &self.potentials else {
+            return Ok(None);
+        };
+        Ok(Some(flow.cost + potentials[flow.from_index].1 - potentials[flow.to_index].1))
+    }
+    // The costs of edge `edge` for which this flow stays optimal, or `None` without potentials.
+    // Not supported for undirected edges.
+    pub fn cost_range(&self, edge: ID) -> Result<Option<SensitivityRange>, McmfError> {
+        let Some(sensitivity) = self.sensitivity(edge)? else {
+            return Ok(None);
+        };
+        let range = sensitivity.cost_range(edge as usize)?;
+        Ok(range.map(|(lower, upper)| SensitivityRange { lower, upper }))
+    }
+    // The capacities of edge `edge` for which this flow stays feasible and optimal for its value,
+    // or `None` without potentials. Whether more capacity would allow more flow is a different
+    // question.
+    pub fn capacity_range(&self, edge: ID) -> Result<Option<SensitivityRange>, McmfError> {
+        let Some(sensitivity) = self.sensitivity(edge)? else {
+            return Ok(None);
+        };
+        let range = sensitivity.capacity_range(edge as usize)?;
+        Ok(range.map(|(lower, upper)| SensitivityRange { lower, upper }))
+    }
+    // The cost per unit of sending a little more flow from the source to the sink along the
+    // cheapest remaining path, infinite if the flow is maximum. `None` without potentials.
+    // Sending a whole unit may cost more if the path can't carry it.
+    pub fn marginal_cost(&self) -> Option<f64> { self.sensitivity.as_ref()?.marginal_cost() }
+    // IDs of the saturated edges where more capacity would increase the max flow, or `None` if
+    // the flow is not maximum. Other saturated edges would just move the bottleneck elsewhere.
+    pub fn bottleneck_edges(&self) -> Option<Vec<ID>> {
+        let edges = self.sensitivity.as_ref()?.bottleneck_edges()?;
+        Some(edges.into_iter().map(|edge| edge as ID).collect())
+    }
+    // Whether no other flow of the same value has the same cost, or `None` without potentials.
+    pub fn is_unique(&self) -> Option<bool> {
+        self.potentials.as_ref()?;
+        Some(self.sensitivity.as_ref()?.alternative_edge_flows(self.edge_flows.len()).is_none())
+    }
+    // Another flow of the same value and cost, or `None` if `is_unique` isn't false.
+    pub fn alternative_solution(&self) -> Option<FlowSolution> {
+        let flows = self.sensitivity.as_ref()?.alternative_edge_flows(self.edge_flows.len())?;
+        let old_flows = self.edge_flows.iter().zip(&self.scaled_edge_flows);
+        let edge_flows = old_flows.zip(flows).map(|((edge_flow, &old), new)| {
+            // `from` and `to` follow the flow, see `EdgeFlow`.
+            let ends = ((edge_flow.from.clone(), edge_flow.from_index), (edge_flow.to.clone(), edge_flow.to_index));
+            let ((from, from_index), (to, to_index)) = if (old < 0) != (new < 0.) { (ends.1, ends.0) } else { ends };
+            EdgeFlow { from, to, from_index, to_index, flow: new.abs(), ..edge_flow.clone() }
+        }).collect();
+        Some(FlowSolution {
+            total_cost: self.total_cost.unwrap(),
+            exact_total_cost: self.exact_total_cost.clone().unwrap(),
+            costs_rounded: self.costs_rounded,
+            limit_reached: false,
+            edge_flows,
+        })
+    }
+    // Not included in `to_json`, since timings differ from run to run.
+    pub fn stats(&self) -> SolverStats { self.stats }
+    // The sensitivity analysis, after checking that `edge` exists.
+    fn sensitivity(&self, edge: ID) -> Result<Option<&Sensitivity>, McmfError> {
+        if edge as usize >= self.edge_flows.len() {
+            return Err(McmfError::EdgeOutOfRange(edge as usize));
+        }
+        Ok(self.sensitivity.as_ref())
+    }
+    // The solution value is the total cost if it was computed, and the flow value otherwise.
+    pub fn to_dimacs_flow(&self) -> String {
+        dimacs::flow_to_dimacs(self.total_cost.unwrap_or(self.max_flow), &self.edge_flows)
+    }
+    pub fn to_dot(&self) -> String { dot::solution_to_dot(self) }
+    // The whole solution, e.g. `{"maxFlow": 3, "totalCost": 5, "paths": [{"flow": 3, "nodes": [...]}]}`.
+    pub fn to_json(&self) -> String { json::solution_to_json(self) }
+    // Loads a solution saved with `to_json`, e.g. cached or shared, without solving again. Only
+    // what `to_json` writes is restored: there are no residual edges, traces or stats, and the
+    // sensitivity queries other than `reduced_cost` give `None`.
+    pub fn from_json(json: &str) -> Result<McmfSolution, McmfError> { json::solution_from_json(json) }
+    // What changed from this solution to `other`, e.g. a solve of the same graph after a capacity
+    // change. Edges are matched by ID and paths by their edges, so the graphs should differ at
+    // most by edges added at the end.
+    pub fn diff(&self, other: &McmfSolution) -> SolutionDiff {
+        fn by_edge(flows: &[EdgeFlow]) -> HashMap<ID, &EdgeFlow> {
+            flows.iter().map(|flow| (flow.edge, flow)).collect()
+        }
+        let (old, new) = (by_edge(&self.edge_flows), by_edge(&other.edge_flows));
+        let mut edges: Vec<_> = old.keys().chain(new.keys()).copied().collect();
+        edges.sort_unstable();
+        edges.dedup();
+        let edge_changes = edges.into_iter().filter_map(|edge| {
+            let current = new.get(&edge).or(old.get(&edge)).unwrap();
+            // Undirected edges can have flow both ways, see `EdgeFlow`.
+            let along = |flow: Option<&&EdgeFlow>| flow.map_or(0., |flow| {
+                if (&flow.from, &flow.to) == (&current.from, &current.to) { flow.flow } else { -flow.flow }
+            });
+            let (old_flow, new_flow) = (along(old.get(&edge)), along(new.get(&edge)));
+            (old_flow != new_flow).then(|| EdgeFlowChange {
+                edge, from: current.from.clone(), to: current.to.clone(), old_flow, new_flow,
+            })
+        }).collect();
+        let path_edges = |paths: &[Path]| paths.iter().map(|path| path.edges.clone()).collect::<HashSet<_>>();
+        let (old_paths, new_paths) = (path_edges(&self.paths), path_edges(&other.paths));
+        let unmatched = |paths: &[Path], others: &HashSet<Vec<ID>>| {
+            paths.iter().filter(|path| !others.contains(&path.edges)).cloned().collect()
+        };
+        SolutionDiff {
+            edge_changes,
+            added_paths: unmatched(&other.paths, &old_paths),
+            removed_paths: unmatched(&self.paths, &new_paths),
+            flow_delta: other.max_flow - self.max_flow,
+            cost_delta: other.total_cost.zip(self.total_cost).map(|(new, old)| new - old),
+        }
+    }
+}
+
+// The changes between two solutions, see `McmfSolution::diff`.
+#[derive(Clone, Debug)]
+pub struct SolutionDiff {
+    edge_changes: Vec<EdgeFlowChange>,
+    added_paths: Vec<Path>,
+    removed_paths: Vec<Path>,
+    flow_delta: f64,
+    cost_delta: Option<f64>,
+}
+
+impl SolutionDiff {
+    // The edges whose flow changed, by ID.
+    pub fn edge_changes(&self) -> &[EdgeFlowChange] { &self.edge_changes }
+    // Paths of the new solution whose edges no path of the old one has, and the other way round.
+    // A path of both with a different flow is in neither, but its edges are in `edge_changes`.
+    pub fn added_paths(&self) -> &[Path] { &self.added_paths }
+    pub fn removed_paths(&self) -> &[Path] { &self.removed_paths }
+    // New minus old.
+    pub fn flow_delta(&self) -> f64 { self.flow_delta }
+    // `None` unless both solutions have a total cost.
+    pub fn cost_delta(&self) -> Option<f64> { self.cost_delta }
+}
+
+// An edge in `SolutionDiff::edge_changes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeFlowChange {
+    edge: ID,
+    from: String,
+    to: String,
+    old_flow: f64,
+    new_flow: f64,
+}
+
+impl EdgeFlowChange {
+    pub fn edge(&self) -> ID { self.edge }
+    // The direction of the new flow, or of the old one if the edge is gone.
+    pub fn from(&self) -> &str { &self.from }
+    pub fn to(&self) -> &str { &self.to }
+    // Negative if the flow went the other way along an undirected edge. Zero if the edge wasn't
+    // in that solution.
+    pub fn old_flow(&self) -> f64 { self.old_flow }
+    pub fn new_flow(&self) -> f64 { self.new_flow }
+}
+
+// A range of values for an edge parameter, see `McmfSolution::cost_range`. Either end can be
+// infinite.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SensitivityRange {
+    lower: f64,
+    upper: f64,
+}
+
+impl SensitivityRange {
+    pub fn lower(&self) -> f64 { self.lower }
+    pub fn upper(&self) -> f64 { self.upper }
+}
+
+// Sizes, work counters and timings of a solve, for comparing algorithms and finding out why an
+// instance is slow.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolverStats {
+    num_nodes: u32,
+    num_edges: u32,
+    augmentations: Option<u32>,
+    pivots: Option<u32>,
+    network_ms: f64,
+    max_flow_ms: f64,
+    min_cost_flow_ms: f64,
+    paths_ms: f64,
+}
+
+impl SolverStats {
+    // Size of the network the solvers ran on, including nodes and arcs added for node capacities,
+    // undirected edges and multiple terminals.
+    pub fn num_nodes(&self) -> u32 { self.num_nodes }
+    pub fn num_edges(&self) -> u32 { self.num_edges }
+    // Augmenting paths found by Dinic in the max flow phase. `None` for push-relabel, which has no
+    // paths, and if there was no such phase.
+    pub fn augmentations(&self) -> Option<u32> { self.augmentations }
+    // Pivots made by the network simplex. `None` for other min-cost flow algorithms and if there
+    // was no min-cost flow phase.
+    pub fn pivots(&self) -> Option<u32> { self.pivots }
+    // Wall-clock time of each phase: building and checking the network, max flow, min-cost flow,
+    // and computing paths, edge flows and the min cut from the result.
+    pub fn network_ms(&self) -> f64 { self.network_ms }
+    pub fn max_flow_ms(&self) -> f64 { self.max_flow_ms }
+    pub fn min_cost_flow_ms(&self) -> f64 { self.min_cost_flow_ms }
+    pub fn paths_ms(&self) -> f64 { self.paths_ms }
+}
+
+// Approximate memory held by a builder, see `GraphBuilder::storage_stats`. Vectors and maps count
+// their allocated capacity, not just what is in use.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StorageStats {
+    pub(crate) node_bytes: usize,
+    pub(crate) edge_bytes: usize,
+    pub(crate) name_bytes: usize,
+}
+
+impl StorageStats {
+    // Supplies, capacities, groups and metadata of nodes.
+    pub fn node_bytes(&self) -> usize { self.node_bytes }
+    // Edges with their cost pieces and metadata, and the index used to find duplicate edges.
+    pub fn edge_bytes(&self) -> usize { self.edge_bytes }
+    // Node names and the map from names to nodes.
+    pub fn name_bytes(&self) -> usize { self.name_bytes }
+    pub fn total_bytes(&self) -> usize { self.node_bytes + self.edge_bytes + self.name_bytes }
+}
+
+// A change to an edge in a scenario for `GraphBuilder::evaluate_scenarios`. Unset values are left
+// as in the graph.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EdgeOverride {
+    pub edge: ID,
+    pub capacity: Option<f64>,
+    pub cost: Option<f64>,
+}
+
+// The outcome of each scenario of `GraphBuilder::evaluate_scenarios`, in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioResults {
+    max_flows: Vec<f64>,
+    total_costs: Vec<f64>,
+    limit_reached: bool,
+}
+
+impl ScenarioResults {
+    pub fn max_flows(&self) -> &[f64] { &self.max_flows }
+    pub fn total_costs(&self) -> &[f64] { &self.total_costs }
+    // Whether a limit cut any of the solves short, see `McmfSolution::limit_reached`.
+    pub fn limit_reached(&self) -> bool { self.limit_reached }
+}
+
+// Timings of repeated solves, see `GraphBuilder::benchmark`. Each is taken over all repetitions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Benchmark {
+    repetitions: u32,
+    network: Timing,
+    max_flow: Timing,
+    min_cost_flow: Timing,
+    paths: Timing,
+    total: Timing,
+}
+
+impl Benchmark {
+    // Builds the statistics from the stats and the total time of each run.
+    pub(crate) fn new(runs: &[(SolverStats, f64)]) -> Benchmark {
+        let timing = |time: fn(&(SolverStats, f64)) -> f64| Timing::new(runs.iter().map(time).collect());
+        Benchmark {
+            repetitions: runs.len() as u32,
+            network: timing(|(stats, _)| stats.network_ms),
+            max_flow: timing(|(stats, _)| stats.max_flow_ms),
+            min_cost_flow: timing(|(stats, _)| stats.min_cost_flow_ms),
+            paths: timing(|(stats, _)| stats.paths_ms),
+            total: timing(|&(_, total)| total),
+        }
+    }
+
+    pub fn repetitions(&self) -> u32 { self.repetitions }
+    // The phases as in `SolverStats`.
+    pub fn network(&self) -> Timing { self.network }
+    pub fn max_flow(&self) -> Timing { self.max_flow }
+    pub fn min_cost_flow(&self) -> Timing { self.min_cost_flow }
+    pub fn paths(&self) -> Timing { self.paths }
+    // Whole solves, including the checks and conversions between the phases.
+    pub fn total(&self) -> Timing { self.total }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    min_ms: f64,
+    median_ms: f64,
+    max_ms: f64,
+}
+
+impl Timing {
+    fn new(mut times: Vec<f64>) -> Timing {
+        times.sort_by(f64::total_cmp);
+        let middle = times.len() / 2;
+        let median_ms =
+            if times.len().is_multiple_of(2) { (times[middle - 1] + times[middle]) / 2. } else { times[middle] };
+        Timing { min_ms: times[0], median_ms, max_ms: times[times.len() - 1] }
+    }
+
+    pub fn min_ms(&self) -> f64 { self.min_ms }
+    // The mean of the middle two for an even number of repetitions.
+    pub fn median_ms(&self) -> f64 { self.median_ms }
+    pub fn max_ms(&self) -> f64 { self.max_ms }
+}
+
+// An edge as it was added to the builder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edge {
+    id: ID,
+    from: String,
+    to: String,
+    lower: f64,
+    capacity: f64,
+    cost: f64,
+    undirected: bool,
+    metadata: Option<String>,
+}
+
+impl Edge {
+    pub fn id(&self) -> ID { self.id }
+    pub fn from(&self) -> &str { &self.from }
+    pub fn to(&self) -> &str { &self.to }
+    pub fn lower(&self) -> f64 { self.lower }
+    pub fn capacity(&self) -> f64 { self.capacity }
+    pub fn cost(&self) -> f64 { self.cost }
+    pub fn undirected(&self) -> bool { self.undirected }
+    // See `GraphBuilder::set_edge_metadata`.
+    pub fn metadata(&self) -> Option<&str> { self.metadata.as_deref() }
+}
+
+#[derive(Clone, Debug)]
+pub struct EdgeFlow {
+    edge: ID,
+    from: String,
+    to: String,
+    // Indices of `from` and `to` in `McmfSolution::node_flows`.
+    from_index: usize,
+    to_index: usize,
+    flow: f64,
+    capacity: f64,
+    cost: f64,
+    metadata: Option<String>,
+}
+
+impl EdgeFlow {
+    pub fn edge(&self) -> ID { self.edge }
+    pub fn from(&self) -> &str { &self.from }
+    pub fn to(&self) -> &str { &self.to }
+    pub fn flow(&self) -> f64 { self.flow }
+    pub fn capacity(&self) -> f64 { self.capacity }
+    pub fn cost(&self) -> f64 { self.cost }
+    // See `GraphBuilder::set_edge_metadata`.
+    pub fn metadata(&self) -> Option<&str> { self.metadata.as_deref() }
+}
+
+// A builder node in a solution, see `McmfSolution::node_flows`.
+#[derive(Clone, Debug)]
+pub struct NodeFlow {
+    node: String,
+    group: Option<String>,
+    metadata: Option<String>,
+    inflow: f64,
+    outflow: f64,
+}
+
+impl NodeFlow {
+    pub fn node(&self) -> &str { &self.node }
+    pub fn group(&self) -> Option<&str> { self.group.as_deref() }
+    pub fn metadata(&self) -> Option<&str> { self.metadata.as_deref() }
+    // The flow on edges into the node; undirected edges count in the direction of their flow.
+    pub fn inflow(&self) -> f64 { self.inflow }
+    pub fn outflow(&self) -> f64 { self.outflow }
+    // What the node supplies to the rest of the graph, e.g. positive for the source and negative
+    // for the sink.
+    pub fn net_outflow(&self) -> f64 { self.outflow - self.inflow }
+}
+
+// The flow of a node group, see `McmfSolution::group_flows`.
+#[derive(Clone, Debug, Default)]
+pub struct GroupFlow {
+    group: String,
+    nodes: u32,
+    inflow: f64,
+    outflow: f64,
+    internal_flow: f64,
+    internal_cost: f64,
+}
+
+impl GroupFlow {
+    pub fn group(&self) -> &str { &self.group }
+    // The number of nodes in the group.
+    pub fn nodes(&self) -> u32 { self.nodes }
+    // The flow on edges entering the group from other nodes.
+    pub fn inflow(&self) -> f64 { self.inflow }
+    // The flow on edges leaving the group for other nodes.
+    pub fn outflow(&self) -> f64 { self.outflow }
+    // The flow on edges between nodes of the group, and its cost.
+    pub fn internal_flow(&self) -> f64 { self.internal_flow }
+    pub fn internal_cost(&self) -> f64 { self.internal_cost }
+}
+
+// An edge of `McmfSolution::utilization_report`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeUtilization {
+    edge: ID,
+    from: String,
+    to: String,
+    flow: f64,
+    capacity: f64,
+    utilization: f64,
+    saturated: bool,
+}
+
+impl EdgeUtilization {
+    pub fn edge(&self) -> ID { self.edge }
+    // As in `EdgeFlow`.
+    pub fn from(&self) -> &str { &self.from }
+    pub fn to(&self) -> &str { &self.to }
+    pub fn flow(&self) -> f64 { self.flow }
+    pub fn capacity(&self) -> f64 { self.capacity }
+    // The flow divided by the capacity, from 0 to 1.
+    pub fn utilization(&self) -> f64 { self.utilization }
+    // Whether the flow uses all of the capacity.
+    pub fn saturated(&self) -> bool { self.saturated }
+}
+
+// An arc of the residual network of a solution, see `McmfSolution::residual_edges`.
+#[derive(Clone, Debug)]
+pub struct ResidualEdge {
+    edge: Option<ID>,
+    from: String,
+    to: String,
+    capacity: f64,
+    cost: f64,
+    reverse: bool,
+}
+
+impl ResidualEdge {
+    // The edge the arc belongs to, or `None` for arcs limiting a node capacity or connecting
+    // super terminals, see `solve_mcmf_multi`.
+    pub fn edge(&self) -> Option<ID> { self.edge }
+    pub fn from(&self) -> &str { &self.from }
+    pub fn to(&self) -> &str { &self.to }
+    // How much more flow the arc can take.
+    pub fn capacity(&self) -> f64 { self.capacity }
+    pub fn cost(&self) -> f64 { self.cost }
+    // Whether the arc undoes existing flow, against the direction of its edge, at the negated cost.
+    pub fn reverse(&self) -> bool { self.reverse }
+}
+
+// A step of the max flow phase, see `McmfSolution::augmenting_paths`: `flow` units, the
+// bottleneck, are sent from the source to the sink along `arcs` of the residual network.
+#[derive(Clone, Debug)]
+pub struct AugmentingPath {
+    flow: f64,
+    nodes: Vec<String>,
+    arcs: Vec<ResidualEdge>,
+}
+
+impl AugmentingPath {
+    pub fn flow(&self) -> f64 { self.flow }
+    pub fn nodes(&self) -> &[String] { &self.nodes }
+    // The residual arcs along the path with their capacity after the step. The bottleneck ones
+    // are left with none. Each opposite arc gains `flow`.
+    pub fn arcs(&self) -> &[ResidualEdge] { &self.arcs }
+}
+
+// A phase of Dinic, see `McmfSolution::dinic_phases`: a breadth-first search from the source
+// assigns each node its distance in the residual network, and the blocking flow of the phase is
+// sent along the arcs that lead one level further, until none of their paths reach the sink.
+#[derive(Clone, Debug)]
+pub struct DinicPhase {
+    levels: Vec<(String, u32)>,
+    arcs: Vec<ResidualEdge>,
+    first_path: usize,
+    path_count: usize,
+}
+
+impl DinicPhase {
+    // The level of each node reached from the source, in level order, with the source at 0. The
+    // entry and exit of a split node share its name, and the entry's level is given.
+    pub fn levels(&self) -> &[(String, u32)] { &self.levels }
+    // The residual arcs of the level graph with their capacity when the phase started.
+    pub fn arcs(&self) -> &[ResidualEdge] { &self.arcs }
+    // The augmenting paths of the blocking flow are `path_count` of `McmfSolution::augmenting_paths`
+    // from index `first_path` on.
+    pub fn first_path(&self) -> usize { self.first_path }
+    pub fn path_count(&self) -> usize { self.path_count }
+}
+
+// A step of network simplex, see `McmfSolution::simplex_pivots`: flow is sent around the cycle
+// that the entering arc closes in the spanning tree of the basis, until an arc on it is empty or
+// saturated and leaves the tree.
+#[derive(Clone, Debug)]
+pub struct Pivot {
+    entering: Option<ID>,
+    leaving: Option<ID>,
+    total_cost: f64,
+}
+
+impl Pivot {
+    // The edges of the arcs, or `None` for arcs limiting a node capacity, as in `ResidualEdge`.
+    // The leaving arc is also `None` if it is one of the artificial arcs that the first basis is
+    // made of. It is the entering one if that becomes saturated or empty right away.
+    pub fn entering(&self) -> Option<ID> { self.entering }
+    pub fn leaving(&self) -> Option<ID> { self.leaving }
+    // The cost of the flow after the pivot. Until the artificial arcs are empty, the flow doesn't
+    // have the final value yet.
+    pub fn total_cost(&self) -> f64 { self.total_cost }
+}
+
+#[derive(Clone, Debug)]
+pub struct FlowSolution {
+    total_cost: f64,
+    exact_total_cost: String,
+    costs_rounded: bool,
+    limit_reached: bool,
+    edge_flows: Vec<EdgeFlow>,
+}
+
+impl FlowSolution {
+    pub fn total_cost(&self) -> f64 { self.total_cost }
+    pub fn exact_total_cost(&self) -> &str { &self.exact_total_cost }
+    // See `McmfSolution::costs_rounded`.
+    pub fn costs_rounded(&self) -> bool { self.costs_rounded }
+    // See `McmfSolution::limit_reached`.
+    pub fn limit_reached(&self) -> bool { self.limit_reached }
+    pub fn edge_flows(&self) -> &[EdgeFlow] { &self.edge_flows }
+    pub fn to_dimacs_flow(&self) -> String { dimacs::flow_to_dimacs(self.total_cost, &self.edge_flows) }
+    // `{"totalCost", "edgeFlows": [{"edge", "from", "to", "flow", "capacity", "cost"}]}`.
+    pub fn to_json(&self) -> String { json::flow_solution_to_json(self) }
+}
+
+// The result of `GraphBuilder::diagnose_flow`.
+#[derive(Clone, Debug)]
+pub struct FlowDiagnosis {
+    max_flow: f64,
+    shortfall: f64,
+    unreachable: Vec<String>,
+    cut: MinCut,
+}
+
+impl FlowDiagnosis {
+    pub fn feasible(&self) -> bool { self.shortfall == 0. }
+    pub fn max_flow(&self) -> f64 { self.max_flow }
+    // How much of the requested flow doesn't fit.
+    pub fn shortfall(&self) -> f64 { self.shortfall }
+    // Nodes that no edge path from the source leads to. The sink is among them if it's disconnected.
+    pub fn unreachable(&self) -> &[String] { &self.unreachable }
+    // A minimum cut, whose capacity limits the flow.
+    pub fn cut(&self) -> &MinCut { &self.cut }
+}
+
+// The result of `GraphBuilder::diagnose_b_flow`.
+#[derive(Clone, Debug)]
+pub struct BalanceDiagnosis {
+    total_supply: f64,
+    total_demand: f64,
+    imbalances: Vec<NodeImbalance>,
+    cut: Option<MinCut>,
+}
+
+impl BalanceDiagnosis {
+    pub fn feasible(&self) -> bool { self.imbalances.is_empty() }
+    pub fn total_supply(&self) -> f64 { self.total_supply }
+    pub fn total_demand(&self) -> f64 { self.total_demand }
+    // The nodes whose supply or demand is not fully met, in node order.
+    pub fn imbalances(&self) -> &[NodeImbalance] { &self.imbalances }
+    // A minimum cut between the supplies and the demands: the source side has more supply than its
+    // `edges` can take out of it, or than the demands on the other side need. `None` if there are
+    // no supplies or demands.
+    pub fn cut(&self) -> Option<&MinCut> { self.cut.as_ref() }
+}
+
+// A node of `BalanceDiagnosis::imbalances`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeImbalance {
+    node: String,
+    supply: f64,
+    unmet: f64,
+}
+
+impl NodeImbalance {
+    pub fn node(&self) -> &str { &self.node }
+    // As set with `GraphBuilder::set_supply`, negative for a demand.
+    pub fn supply(&self) -> f64 { self.supply }
+    // How much of the supply can't be sent, or of the demand can't be received. Positive.
+    pub fn unmet(&self) -> f64 { self.unmet }
+}
+
+// The result of `GraphBuilder::solve_feasible_circulation`.
+#[derive(Clone, Debug)]
+pub struct Circulation {
+    edge_flows: Vec<EdgeFlow>,
+    violated_cut: Option<ViolatedCut>,
+}
+
+impl Circulation {
+    pub fn feasible(&self) -> bool { self.violated_cut.is_none() }
+    // Flows that meet every lower bound and capacity, or none if that's impossible.
+    pub fn edge_flows(&self) -> &[EdgeFlow] { &self.edge_flows }
+    // Why there is no feasible circulation.
+    pub fn violated_cut(&self) -> Option<&ViolatedCut> { self.violated_cut.as_ref() }
+}
+
+// A set of nodes whose incoming lower bounds exceed the capacity for sending flow out of it again,
+// which by Hoffman's circulation theorem proves that no circulation meets the lower bounds.
+#[derive(Clone, Debug)]
+pub struct ViolatedCut {
+    nodes: Vec<String>,
+    entering_edges: Vec<ID>,
+    leaving_edges: Vec<ID>,
+    required_inflow: f64,
+    outflow_capacity: f64,
+}
+
+impl ViolatedCut {
+    pub fn nodes(&self) -> &[String] { &self.nodes }
+    // IDs of the edges with a lower bound that go into the set.
+    pub fn entering_edges(&self) -> &[ID] { &self.entering_edges }
+    // IDs of the edges that go out of the set, including undirected edges with one end in it.
+    pub fn leaving_edges(&self) -> &[ID] { &self.leaving_edges }
+    // The total lower bound of `entering_edges`.
+    pub fn required_inflow(&self) -> f64 { self.required_inflow }
+    // The total capacity of `leaving_edges`, plus that of nodes in the set whose capacity is too
+    // small for the flow through them. Less than `required_inflow`.
+    pub fn outflow_capacity(&self) -> f64 { self.outflow_capacity }
+}
+
+// The result of `GraphBuilder::vertex_disjoint_paths`.
+#[derive(Clone, Debug)]
+pub struct DisjointPaths {
+    paths: Vec<Path>,
+    connectivity: u32,
+}
+
+impl DisjointPaths {
+    pub fn paths(&self) -> &[Path] { &self.paths }
+    // The number of paths, which by Menger's theorem is the number of nodes that must be removed
+    // to disconnect the sink from the source, unless an edge joins them directly.
+    pub fn connectivity(&self) -> u32 { self.connectivity }
+}
+
+// The result of `GraphBuilder::gomory_hu_tree`. Node 0 is the root.
+#[derive(Clone, Debug)]
+pub struct GomoryHuTree {
+    nodes: Vec<String>,
+    node_indices: HashMap<String, usize>,
+    parents: Vec<usize>,
+    // The value of the cut between each node and its parent.
+    cut_values: Vec<f64>,
+}
+
+impl GomoryHuTree {
+    // Tree edges as `(node, parent, cut value)`. Removing an edge splits the nodes into the two
+    // sides of a minimum cut between its ends.
+    pub fn edges(&self) -> Vec<(&str, &str, f64)> {
+        (1..self.nodes.len())
+            .map(|v| (self.nodes[v].as_str(), self.nodes[self.parents[v]].as_str(), self.cut_values[v]))
+            .collect()
+    }
+
+    // The value of a minimum cut between two nodes, which is the smallest cut value on the tree
+    // path between them.
+    pub fn min_cut_between(&self, a: &str, b: &str) -> Result<f64, McmfError> {
+        let node = |name: &str| {
+            self.node_indices.get(name).copied().ok_or_else(|| McmfError::UnknownNode(name.to_owned()))
+        };
+        let (a, b) = (node(a)?, node(b)?);
+        if a == b {
+            return Err("a min cut needs two different nodes".into());
+        }
+        // The smallest cut value on the way from `a` up to each of its ancestors.
+        let mut up_from_a = HashMap::from([(a, f64::INFINITY)]);
+        let (mut v, mut value) = (a, f64::INFINITY);
+        while v != 0 {
+            (v, value) = (self.parents[v], value.min(self.cut_values[v]));
+            up_from_a.insert(v, value);
+        }
+        let (mut v, mut value) = (b, f64::INFINITY);
+        while !up_from_a.contains_key(&v) {
+            (v, value) = (self.parents[v], value.min(self.cut_values[v]));
+        }
+        Ok(value.min(up_from_a[&v]))
+    }
+}
+
+// The result of `GraphBuilder::verify_flow`.
+#[derive(Clone, Debug)]
+pub struct FlowVerification {
+    violations: Vec<String>,
+    flow_value: f64,
+    total_cost: f64,
+    is_maximum: Option<bool>,
+    is_optimal: Option<bool>,
+}
+
+impl FlowVerification {
+    pub fn feasible(&self) -> bool { self.violations.is_empty() }
+    // Violated capacities, lower bounds and conservation constraints, e.g.
+    // "edge 3: flow 5 exceeds capacity 4".
+    pub fn violations(&self) -> &[String] { &self.violations }
+    // The net flow out of the source.
+    pub fn flow_value(&self) -> f64 { self.flow_value }
+    pub fn total_cost(&self) -> f64 { self.total_cost }
+    // Whether no more flow can be sent, or `None` if the flow is not feasible.
+    pub fn is_maximum(&self) -> Option<bool> { self.is_maximum }
+    // Whether no flow of the same value is cheaper, or `None` if the flow is not feasible.
+    pub fn is_optimal(&self) -> Option<bool> { self.is_optimal }
+}
+
+// Stops a running solve from elsewhere, e.g. from a progress callback, see
+// `GraphBuilder::set_cancellation_token`. Like an `AbortSignal`, a token can't be reset once
+// cancelled. Clones share the cancellation.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    pub(crate) cancelled: Rc<Cell<bool>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) { self.cancelled.set(true); }
+    pub fn is_cancelled(&self) -> bool { self.cancelled.get() }
+}
+
+#[derive(Clone)]
+pub struct GraphBuilder {
+    node_indices: HashMap<String, usize>,
+    graph: IndexedGraphBuilder,
+    source: Option<String>,
+    sink: Option<String>,
+}
+
+impl Default for GraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        GraphBuilder {
+            node_indices: HashMap::new(),
+            graph: IndexedGraphBuilder::new(),
+            source: None,
+            sink: None,
+        }
+    }
+
+    // Removes all nodes and edges but keeps the settings and the allocated memory, which is cheaper
+    // than a new builder when generating many graphs in a row.
+    pub fn clear(&mut self) {
+        self.node_indices.clear();
+        self.graph.clear();
+        self.source = None;
+        self.sink = None;
+    }
+
+    // Memory held by the graph, e.g. to warn before a graph gets too large. Solves need several
+    // times more for the network and the results, see `SolverStats` for the network size.
+    pub fn storage_stats(&self) -> StorageStats {
+        let mut stats = self.graph.storage_stats();
+        let names = &self.node_indices;
+        stats.name_bytes += map_bytes(names) + names.keys().map(String::capacity).sum::<usize>();
+        stats
+    }
+
+    fn log_loaded(&self, format: &str) {
+        log!(Info, "loaded {} nodes and {} edges from {}", self.graph.nodes.len(), self.graph.edges.len(), format);
+    }
+
+    // Loads a whole problem; see `json.rs` for the schema.
+    pub fn from_json(json: &str) -> Result<GraphBuilder, McmfError> {
+        json::builder_from_json(json).inspect(|builder| builder.log_loaded("JSON"))
+    }
+
+    pub fn to_json(&self) -> String {
+        json::builder_to_json(self)
+    }
+
+    // Loads a GraphML graph, e.g. exported from yEd, Gephi or NetworkX. Capacities and costs are
+    // read from the edge attributes named `capacity_key` and `cost_key`. Costs are optional.
+    pub fn from_graphml(text: &str, capacity_key: &str, cost_key: &str) -> Result<GraphBuilder, McmfError> {
+        graphml::builder_from_graphml(text, capacity_key, cost_key).inspect(|builder| builder.log_loaded("GraphML"))
+    }
+
+    // Loads an edge list with `from`, `to`, `capacity` and optional `cost` columns, e.g. pasted
+    // from a spreadsheet; see `csv.rs`.
+    pub fn from_csv(text: &str, options: CsvOptions) -> Result<GraphBuilder, McmfError> {
+        csv::builder_from_csv(text, options).inspect(|builder| builder.log_loaded("CSV"))
+    }
+
+    // Edges are labeled with their capacity and cost, nodes with their supply and capacity.
+    pub fn to_dot(&self) -> String {
+        dot::builder_to_dot(self)
+    }
+
+    // Loads a DIMACS min-cost flow (`p min`) or max flow (`p max`) problem; see `dimacs.rs`.
+    // Nodes are named by their numbers. Max flow terminals become the `source` and `sink`.
+    pub fn from_dimacs(text: &str) -> Result<GraphBuilder, McmfError> {
+        dimacs::builder_from_dimacs(text).inspect(|builder| builder.log_loaded("DIMACS"))
+    }
+
+    // Writes the graph with supplies as a DIMACS min-cost flow problem.
+    pub fn to_dimacs_min(&self) -> Result<String, McmfError> {
+        dimacs::builder_to_dimacs(self, false)
+    }
+
+    // Writes the graph as a DIMACS max flow problem between `source` and `sink`.
+    pub fn to_dimacs_max(&self) -> Result<String, McmfError> {
+        dimacs::builder_to_dimacs(self, true)
+    }
+
+    // Positions for drawing the graph, computed with `iterations` rounds of a force-directed
+    // layout; a few hundred are usually enough. With `weight_by_cost`, cheap edges are drawn
+    // shorter than expensive ones. The coordinates are centered around 0 with about one unit
+    // between nodes, to be scaled to the view. The same graph always gets the same layout.
+    pub fn layout(&self, iterations: u32, weight_by_cost: bool) -> Layout {
+        self.graph.layout(iterations, weight_by_cost)
+    }
+
+    // Writes the min-cost flow problem with supplies as a linear program in the CPLEX LP and MPS
+    // formats, e.g. for checking `solve_b_flow` with another solver; see `lp.rs`.
+    pub fn to_lp(&self) -> Result<String, McmfError> {
+        lp::builder_to_lp(self)
+    }
+
+    pub fn to_mps(&self) -> Result<String, McmfError> {
+        lp::builder_to_mps(self)
+    }
+
+    // The source and sink are not used by the builder itself. They are only stored so that a
+    // problem saved with `to_json` can be solved again after loading.
+    pub fn source(&self) -> Option<&str> { self.source.as_deref() }
+    pub fn sink(&self) -> Option<&str> { self.sink.as_deref() }
+    pub fn set_source(&mut self, source: Option<String>) { self.source = source; }
+    pub fn set_sink(&mut self, sink: Option<String>) { self.sink = sink; }
+
+    // Returns the edge ID, which identifies the edge in solutions even among parallel edges.
+    // Costs may be negative. Solves that report paths refuse graphs with negative cost cycles,
+    // since flow around such a cycle is not part of any path; see `find_negative_cycle`.
+    // The capacity may be infinite, for uncapacitated edges, or zero, e.g. for an edge that is
+    // only opened later with `update_capacity`. Max flow solves fail if a path of infinite
+    // edges connects the source to the sink.
+    pub fn add_edge(&mut self, from: String, to: String, capacity: f64, cost: f64) -> Result<ID, McmfError> {
+        self.add_edge_with_bounds(from, to, 0., capacity, cost)
+    }
+
+    // Like `add_edge`, but with integers that are kept exact even beyond 2^53, e.g. from JS
+    // `BigInt`s. The capacity can be as large as the solvers' total capacity allows, while the
+    // cost must fit into 2^53, since the solvers compare costs as `f64`. Large costs times large
+    // capacities overflow once scaled by the cost precision, so `set_cost_denominator(Some(1))`
+    // is best to use them as they are. See `McmfSolution::exact_max_flow` for the exact results.
+    pub fn add_edge_exact(&mut self, from: String, to: String, capacity: i64, cost: i64) -> Result<ID, McmfError> {
+        self.graph.exact_edge_bounds(capacity, cost)?;
+        if from == to {
+            self.graph.check_self_loop(&from)?;
+        }
+        let from = self.get_or_insert_vertex(from);
+        let to = self.get_or_insert_vertex(to);
+        self.graph.add_edge_exact(from, to, capacity, cost)
+    }
+
+    // Every feasible flow must send at least `lower` units along the edge. Lower bounds are
+    // honored by all solve methods; `solve_mcmf` and `solve_max_flow` fail if they cannot be met.
+    pub fn add_edge_with_bounds(
+        &mut self, from: String, to: String, lower: f64, upper: f64, cost: f64
+    ) -> Result<ID, McmfError> {
+        self.graph.edge_bounds(lower, upper)?;
+        check_cost(cost)?;
+        if from == to {
+            self.graph.check_self_loop(&from)?;
+        }
+        let from = self.get_or_insert_vertex(from);
+        let to = self.get_or_insert_vertex(to);
+        self.graph.add_edge_with_bounds(from, to, lower, upper, cost)
+    }
+
+    // An edge whose cost per unit grows with the flow: the first `capacities[0]` units cost
+    // `costs[0]` each, the next `capacities[1]` units `costs[1]` and so on. Costs must be
+    // non-decreasing, i.e. the total cost convex. The edge is reported as a whole, with the total
+    // capacity and the cost of the first piece, and can't be updated in place.
+    pub fn add_edge_piecewise(
+        &mut self, from: String, to: String, capacities: &[f64], costs: &[f64]
+    ) -> Result<ID, McmfError> {
+        self.graph.pieces(capacities, costs)?;
+        if from == to {
+            self.graph.check_self_loop(&from)?;
+        }
+        let from = self.get_or_insert_vertex(from);
+        let to = self.get_or_insert_vertex(to);
+        self.graph.add_edge_piecewise(from, to, capacities, costs)
+    }
+
+    // Registers nodes so that `add_edges_bulk` can refer to them by index. Nodes are indexed in
+    // the order they were first mentioned, counting nodes created by other calls. Returns the
+    // index of the first added node.
+    pub fn add_nodes(&mut self, names: Vec<String>) -> Result<ID, McmfError> {
+        if let Some(name) = names.iter().find(|name| self.node_indices.contains_key(*name)) {
+            return Err(format!("node '{}' already exists", name).into());
+        }
+        let first = self.graph.nodes.len() as ID;
+        for name in names {
+            self.get_or_insert_vertex(name);
+        }
+        Ok(first)
+    }
+
+    // Adds many edges in one call: edge `i` goes from node `froms[i]` to node `tos[i]`, see
+    // `add_nodes`. Edges get consecutive IDs; the first one is returned. Nothing is added if any
+    // of the edges is invalid.
+    pub fn add_edges_bulk(
+        &mut self, froms: &[u32], tos: &[u32], capacities: &[f64], costs: &[f64]
+    ) -> Result<ID, McmfError> {
+        self.graph.add_edges_bulk(froms, tos, capacities, costs)
+    }
+
+    // Flow may go either way, sharing the capacity. In edge flow reports an undirected edge is
+    // oriented in the direction of the flow. Negative costs are not supported, because they
+    // would make sending flow back and forth profitable.
+    pub fn add_undirected_edge(&mut self, a: String, b: String, capacity: f64, cost: f64) -> Result<ID, McmfError> {
+        self.graph.edge_bounds(0., capacity)?;
+        if check_cost(cost)? < 0. {
+            return Err("undirected edge cost must be non-negative".into());
+        }
+        if a == b {
+            self.graph.check_self_loop(&a)?;
+        }
+        let a = self.get_or_insert_vertex(a);
+        let b = self.get_or_insert_vertex(b);
+        self.graph.add_undirected_edge(a, b, capacity, cost)
+    }
+
+    pub fn node_count(&self) -> u32 { self.graph.num_nodes() }
+    pub fn edge_count(&self) -> u32 { self.graph.edges.len() as u32 }
+    pub fn has_node(&self, name: &str) -> bool { self.node_indices.contains_key(name) }
+
+    // Node names in the order the nodes were added.
+    pub fn nodes(&self) -> &[String] {
+        &self.graph.node_names
+    }
+
+    // Edges in ID order.
+    pub fn edges(&self) -> Vec<Edge> {
+        self.graph.edges_info(|_| true)
+    }
+
+    // Edges leaving `node`, including undirected edges at either end.
+    pub fn out_edges(&self, node: String) -> Result<Vec<Edge>, McmfError> {
+        let v = self.get_vertex(node)?;
+        Ok(self.graph.edges_info(|edge| edge.from == v || (edge.undirected && edge.to == v)))
+    }
+
+    // Changes the capacity of edge `edge`, keeping its lower bound. Together with `update_cost`,
+    // this is cheaper than rebuilding the graph, and `resolve` can reuse the previous solution.
+    pub fn update_capacity(&mut self, edge: ID, capacity: f64) -> Result<(), McmfError> {
+        self.graph.update_capacity(edge as usize, capacity)
+    }
+
+    pub fn update_cost(&mut self, edge: ID, cost: f64) -> Result<(), McmfError> {
+        self.graph.update_cost(edge as usize, cost)
+    }
+
+    // Attaches an arbitrary string to edge `edge`, e.g. an ID or a JSON payload from the caller's
+    // own data, replacing any previous one. The solvers ignore it; it is returned with the edge in
+    // `edges`, `McmfSolution::edge_flows` and `McmfSolution::edge_metadata`, so results can be
+    // matched back to the caller's data even with parallel edges. `None` removes it.
+    pub fn set_edge_metadata(&mut self, edge: ID, metadata: Option<String>) -> Result<(), McmfError> {
+        self.graph.set_edge_metadata(edge as usize, metadata)
+    }
+
+    // Removes edge `edge`. Like `Vec::remove`, this shifts the IDs of all later edges down by one.
+    pub fn remove_edge(&mut self, edge: ID) -> Result<(), McmfError> {
+        self.graph.remove_edge(edge as usize)
+    }
+
+    // A handle for edge `edge` that keeps referring to it while other edges are removed, so that
+    // callers can track edges across changes and solves; see `edge_id` and
+    // `McmfSolution::flow_on`. Handles are never reused, not even after `clear`, and a merged
+    // duplicate edge has the handle of the edge it was merged into.
+    pub fn edge_handle(&self, edge: ID) -> Result<EdgeHandle, McmfError> {
+        self.graph.edge_handle(edge as usize)
+    }
+
+    // The current ID of the edge with the given handle, e.g. for `update_capacity`. Fails if the
+    // edge was removed.
+    pub fn edge_id(&self, handle: EdgeHandle) -> Result<ID, McmfError> {
+        self.graph.edge_id(handle)
+    }
+
+    // Supplies and demands are only used by `solve_b_flow`. Setting either one overwrites the
+    // previous value for the node: a demand is simply a negative supply.
+    pub fn set_supply(&mut self, node: String, amount: f64) -> Result<(), McmfError> {
+        self.graph.scale_amount(amount, "supply")?;
+        let node = self.get_or_insert_vertex(node);
+        self.graph.set_supply(node, amount)
+    }
+
+    pub fn set_demand(&mut self, node: String, amount: f64) -> Result<(), McmfError> {
+        self.set_supply(node, -amount)
+    }
+
+    // Limits the total flow passing through the node, including flow starting or ending there.
+    pub fn set_node_capacity(&mut self, node: String, capacity: f64) -> Result<(), McmfError> {
+        if self.graph.scale_amount(capacity, "capacity")? <= 0 {
+            return Err("capacity must be positive".into());
+        }
+        let node = self.get_or_insert_vertex(node);
+        self.graph.set_node_capacity(node, capacity)
+    }
+
+    // Puts the node in a group, e.g. a layer of a supply chain, replacing any previous one. The
+    // solvers ignore groups; solutions report them with `McmfSolution::node_flows` and total the
+    // flow per group with `McmfSolution::group_flows`. `None` removes the node from its group.
+    pub fn set_node_group(&mut self, node: String, group: Option<String>) -> Result<(), McmfError> {
+        let node = self.get_or_insert_vertex(node);
+        self.graph.set_node_group(node, group)
+    }
+
+    // Attaches an arbitrary string to the node, e.g. a display label or a JSON payload, which
+    // `McmfSolution::node_flows` returns. See `set_edge_metadata`.
+    pub fn set_node_metadata(&mut self, node: String, metadata: Option<String>) -> Result<(), McmfError> {
+        let node = self.get_or_insert_vertex(node);
+        self.graph.set_node_metadata(node, metadata)
+    }
+
+    // Parallel edges are kept by default. Otherwise a directed edge added with `add_edge` or
+    // `add_edge_with_bounds` between the same nodes, in the same direction, as an earlier such
+    // edge is rejected or merged into it, and the ID of the earlier edge is returned. Edges with
+    // a piecewise cost and undirected edges are never merged and don't count as duplicates.
+    // `add_edges_bulk` can reject duplicates but not merge them. Only affects edges added later.
+    pub fn set_duplicate_edges(&mut self, policy: DuplicateEdges) {
+        self.graph.set_duplicate_edges(policy);
+    }
+
+    // Self-loops are kept by default, which matters only for negative costs: a self-loop with a
+    // negative cost is a negative cycle. Rejecting them only affects edges added later, while
+    // ignoring applies to all self-loops at solve time.
+    pub fn set_self_loops(&mut self, policy: SelfLoops) {
+        self.graph.set_self_loops(policy);
+    }
+
+    // Capacities, lower bounds, supplies and flow values must be whole numbers by default.
+    // Setting a denominator allows multiples of `1 / denominator` instead, e.g. halves for 2;
+    // flows in solutions are then multiples of it as well. It must be set before any of these
+    // amounts are added.
+    pub fn set_capacity_denominator(&mut self, denominator: u32) -> Result<(), McmfError> {
+        self.graph.set_capacity_denominator(denominator)
+    }
+
+    // Solvers work with integer costs, so costs are rounded to `decimals` decimal places (3 by
+    // default). Without a value, the precision is chosen to represent all costs exactly if
+    // possible. Solutions report whether rounding occurred; too high a precision for large
+    // costs makes solves fail instead of overflowing.
+    pub fn set_cost_precision(&mut self, decimals: Option<u32>) {
+        self.graph.set_cost_precision(decimals);
+    }
+
+    // Exact cost mode: all costs must be multiples of `1 / denominator`, e.g. thirds for 3, and
+    // are then used without rounding, so that `exact_total_cost` is the exact optimum. Solves
+    // fail if some cost is not such a multiple. Overrides `set_cost_precision` while set.
+    pub fn set_cost_denominator(&mut self, denominator: Option<u32>) -> Result<(), McmfError> {
+        self.graph.set_cost_denominator(denominator)
+    }
+
+    // Stops solves that take longer than `limit` milliseconds, or none if not given. A solve that
+    // reaches the limit fails with "time limit of ... reached", or see `set_partial_results`.
+    // The limit is only checked by the algorithms implemented in this crate: Dinic, successive
+    // shortest paths, cost scaling and cycle canceling. Network simplex and push-relabel always
+    // run to completion.
+    pub fn set_time_limit_ms(&mut self, limit: Option<f64>) -> Result<(), McmfError> {
+        self.graph.set_time_limit_ms(limit)
+    }
+
+    // Like `set_time_limit_ms`, but limits iterations: augmenting paths for Dinic and successive
+    // shortest paths, relabels for cost scaling and canceled cycles for cycle canceling, summed
+    // over the phases of a solve.
+    pub fn set_iteration_limit(&mut self, limit: Option<u32>) {
+        self.graph.set_iteration_limit(limit);
+    }
+
+    // If enabled, a solve that reaches a limit returns the best feasible flow found so far
+    // instead of failing, with `limit_reached` set in the solution. It still fails if there is
+    // no such flow yet, e.g. for successive shortest paths, which only has one at the end.
+    pub fn set_partial_results(&mut self, enabled: bool) {
+        self.graph.set_partial_results(enabled);
+    }
+
+    // If enabled, `solve_mcmf` and `solve_max_flow` first drop the edges that no flow from the
+    // source to the sink can use, e.g. in parts of the graph the source doesn't reach, and solve
+    // the rest. Results are the same, but negative cycles in the dropped part are not reported.
+    // Graphs with lower bounds are solved as a whole.
+    pub fn set_pruning(&mut self, enabled: bool) {
+        self.graph.set_pruning(enabled);
+    }
+
+    // If enabled, `solve_mcmf` and `solve_max_flow` replace chains of nodes with one edge in and
+    // one edge out, as in road networks, by single edges with the smallest capacity and the total
+    // cost of the chain. Results are reported for the original edges and include the chain nodes.
+    pub fn set_chain_contraction(&mut self, enabled: bool) {
+        self.graph.set_chain_contraction(enabled);
+    }
+
+    // If enabled, `solve_mcmf` finds the max flow and its min cost in a single min-cost flow
+    // solve, with a return arc from the sink to the source whose cost is so negative that any
+    // flow around it pays off, instead of solving a max flow first. The max flow algorithm is then
+    // unused and `SolverStats` has no max flow time or augmentations. Falls back to two solves if
+    // the cost of the return flow could overflow.
+    pub fn set_single_pass(&mut self, enabled: bool) {
+        self.graph.set_single_pass(enabled);
+    }
+
+    // Limits the flow found by `solve_mcmf`, `resolve` and `solve_max_flow` to at most
+    // `max_paths` paths, e.g. for routing hardware that can only be configured with a few. The
+    // flow is decomposed widest first and only the widest paths are kept, so this is greedy: it
+    // doesn't necessarily find the most flow that fits on that many paths. Paths are always
+    // reported widest first then. Graphs with lower bounds are not supported.
+    pub fn set_max_paths(&mut self, max_paths: Option<u32>) {
+        self.graph.set_max_paths(max_paths);
+    }
+
+    // Breaks ties between flows of minimum cost in `solve_mcmf`, `solve_min_cost_flow` and the
+    // other min-cost solves: among them, the one that is best for `objective` is returned. The
+    // cost and the flow value are unchanged. Solves that reach a limit skip this. `solve_max_flow`
+    // likewise picks among all maximum flows regardless of their cost, e.g. with `FewestEdges` for
+    // a flow plan that is simple to set up.
+    pub fn set_secondary_objective(&mut self, objective: SecondaryObjective) {
+        self.graph.set_secondary_objective(objective);
+    }
+
+    // If enabled, `solve_mcmf` and `solve_max_flow` record each augmenting path of the max flow
+    // phase, see `McmfSolution::augmenting_paths`, and the level graph of each Dinic phase, see
+    // `McmfSolution::dinic_phases`, e.g. to animate the algorithm. Only Dinic has augmenting
+    // paths, and graphs with lower bounds are not traced. `solve_mcmf` also records each network
+    // simplex pivot, see `McmfSolution::simplex_pivots`, on a slower implementation than the
+    // untraced one. Tracing turns off pruning and chain contraction, so that the steps refer to
+    // the whole graph.
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.graph.set_trace(enabled);
+    }
+
+    // Whether solutions name the nodes of each path in `Path::nodes`, which is the default. For
+    // solutions with many paths, disabling it saves cloning every name for every path; the nodes
+    // are still given by `Path::node_indices`, and `McmfSolution::path_arrays` packs those.
+    pub fn set_path_node_names(&mut self, enabled: bool) {
+        self.graph.set_path_node_names(enabled);
+    }
+
+    // Randomizes the depth-first path decomposition, which otherwise follows the edges in the order
+    // they were added: with a seed, each node tries its edges in a shuffled order. Different seeds
+    // give different, equally valid decompositions of the same flow, and the same seed the same
+    // one. The other decompositions and the flow itself are not affected.
+    pub fn set_path_seed(&mut self, seed: Option<u32>) {
+        self.graph.set_path_seed(seed);
+    }
+
+    // If enabled, a directed edge from `b` to `a` next to one from `a` to `b` is routed through a
+    // middle node in the solver network, where `b` is the node added later, so that no two arcs
+    // go between the same nodes in opposite directions. Some textbook algorithms assume that, and
+    // residual graphs, see `McmfSolution::residual_edges`, are easier to draw: the two residual
+    // arcs of each edge can't be confused with those of another. Paths, edge flows and the other
+    // results don't show middle nodes; residual edges and augmenting paths name them after their
+    // edge, "<middle of edge 3>". Undirected edges are not split.
+    pub fn set_split_antiparallel_edges(&mut self, enabled: bool) {
+        self.graph.set_split_antiparallel_edges(enabled);
+    }
+
+    // A debugging aid: if enabled, `solve_mcmf` also solves the graph again from scratch with the
+    // other algorithms, network simplex or else successive shortest paths, and Dinic or else
+    // push-relabel, and fails if the flow value or total cost differ. That catches scaling and
+    // overflow bugs on graphs from users, at the cost of about twice the solve time.
+    pub fn set_cross_validation(&mut self, enabled: bool) {
+        self.graph.set_cross_validation(enabled);
+    }
+
+    // Makes later solves check `token` along the way and fail with "solve cancelled" once it is
+    // cancelled, even with partial results enabled. Solves check the token where they check
+    // limits, so network simplex and push-relabel can't be cancelled midway. A cancelled token has
+    // to be replaced with a new one for the next solve.
+    pub fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.graph.set_cancellation_token(token);
+    }
+
+    // Registers `callback(phase, percent, current_flow)` to be called during solves, e.g. to show
+    // a progress bar, or unregisters it. `phase` is "max_flow" or "min_cost_flow", and each phase
+    // reports 0 and 100 percent. Dinic and successive shortest paths also report about every
+    // 100 ms in between with the flow found so far; the other algorithms can't tell how far along
+    // they are.
+    pub fn set_progress_callback(&mut self, callback: Option<Progress<f64>>) {
+        self.graph.set_progress_callback(callback);
+    }
+
+    // Repeats the last `solve_mcmf` after the graph changed, starting from its solution, which is
+    // much faster if only a few capacities or costs changed. The flow is clamped to the new
+    // capacities, repaired and maximized, and then negative cycles are canceled, so the result
+    // is optimal but may route the flow differently than a fresh solve. Edges added since start
+    // empty. Solves from scratch if there are lower bounds. Limits apply as usual, counting
+    // augmenting paths and canceled cycles.
+    pub fn resolve(&self) -> Result<McmfSolution, McmfError> {
+        self.graph.resolve()
+    }
+
+    // Returns the nodes of some cycle with negative total cost, in order, or `None` if there is
+    // no such cycle. Edges that cannot carry flow beyond their lower bound are ignored.
+    pub fn find_negative_cycle(&self) -> Option<Vec<String>> {
+        let cycle = self.graph.find_negative_cycle()?;
+        Some(cycle.into_iter().map(|v| self.graph.node_name(v)).collect())
+    }
+
+    pub fn solve_mcmf(
+        &self, source: String, sink: String, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.solve_mcmf(source, sink, decomposition, algorithm, max_flow_algorithm)
+    }
+
+    // Runs `solve_mcmf` `repetitions` times and reports the min, median and max time of each
+    // phase, e.g. to compare algorithms. Timing inside the solver leaves out the cost of calling
+    // it, such as converting the solution for JS, which would otherwise dominate small graphs.
+    pub fn benchmark(
+        &self, source: String, sink: String, algorithm: MinCostFlowAlgorithm, max_flow_algorithm: MaxFlowAlgorithm,
+        repetitions: u32,
+    ) -> Result<Benchmark, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.benchmark(source, sink, algorithm, max_flow_algorithm, repetitions)
+    }
+
+    // Solves the graph with each scenario's overrides applied on top of it, e.g. for a slider that
+    // explores a range of capacities. The base graph is solved first and each scenario is then
+    // solved with `resolve` from the flow of the one before, so scenarios that change little from
+    // one to the next are fast. Only the flow value and cost of each are kept.
+    pub fn evaluate_scenarios(
+        &self, source: String, sink: String, scenarios: &[Vec<EdgeOverride>], algorithm: MinCostFlowAlgorithm,
+    ) -> Result<ScenarioResults, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.evaluate_scenarios(source, sink, scenarios, algorithm)
+    }
+
+    // Like `solve_mcmf`, but run by the caller one step at a time, e.g. to let a user click through
+    // the algorithm. See `Solver` for what a step is. The state after each step includes the
+    // augmenting path it took, as with `set_trace`.
+    pub fn solver(
+        &self, source: String, sink: String, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<Solver, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.solver(source, sink, decomposition, algorithm, max_flow_algorithm)
+    }
+
+    // Starts a max flow from `source` to `sink` at zero that the user builds by proposing
+    // augmenting paths, see `ManualMaxFlow::augment`, e.g. as a hands-on exercise next to `solver`.
+    // Later changes to the builder don't affect it. Lower bounds and node capacities are not
+    // supported.
+    pub fn manual_max_flow(&self, source: String, sink: String) -> Result<ManualMaxFlow, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        ManualMaxFlow::new(&self.graph, self.node_indices.clone(), source, sink)
+    }
+
+    // Like `solve_mcmf`, but runs in slices of about `slice_ms` milliseconds, one per
+    // `SolveTask::step`, so that the caller can do other work in between. The solve uses the graph
+    // as it was when it started. Network simplex and push-relabel can't be interrupted, so use
+    // other algorithms for large graphs.
+    pub fn solve_mcmf_task(
+        &self, source: String, sink: String, decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm,
+        max_flow_algorithm: MaxFlowAlgorithm, slice_ms: f64,
+    ) -> Result<SolveTask, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.solve_mcmf_task(source, sink, decomposition, algorithm, max_flow_algorithm, slice_ms)
+    }
+
+    // Solves MCMF from all `sources` to all `sinks`, which must be different nodes, each listed
+    // once. Per-terminal capacities limit how much flow may start or end at each of them;
+    // terminals are unbounded if capacities are omitted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn solve_mcmf_multi(
+        &self, sources: Vec<String>, sinks: Vec<String>,
+        source_capacities: Option<Vec<f64>>, sink_capacities: Option<Vec<f64>>,
+        decomposition: PathDecomposition, algorithm: MinCostFlowAlgorithm, max_flow_algorithm: MaxFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let sources = sources.into_iter().map(|v| self.get_vertex(v)).collect::<Result<_, _>>()?;
+        let sinks = sinks.into_iter().map(|v| self.get_vertex(v)).collect::<Result<_, _>>()?;
+        self.graph.solve_mcmf_multi(
+            sources, sinks, source_capacities, sink_capacities, decomposition, algorithm, max_flow_algorithm,
+        )
+    }
+
+    pub fn solve_max_flow(
+        &self, source: String, sink: String, decomposition: PathDecomposition, algorithm: MaxFlowAlgorithm
+    ) -> Result<McmfSolution, McmfError> {
+        self.graph.solve_max_flow(self.get_vertex(source)?, self.get_vertex(sink)?, decomposition, algorithm)
+    }
+
+    pub fn solve_min_cost_flow(
+        &self, source: String, sink: String, flow_value: f64, decomposition: PathDecomposition,
+        algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.solve_min_cost_flow(source, sink, flow_value, decomposition, algorithm)
+    }
+
+    // The largest flow from `source` to `sink` whose min cost doesn't exceed `budget`, at that
+    // min cost. Flow values are multiples of the capacity unit, see `set_capacity_denominator`.
+    // Lower bounds are only supported if the max flow is within the budget.
+    pub fn solve_max_flow_with_budget(
+        &self, source: String, sink: String, budget: f64, decomposition: PathDecomposition,
+        algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.solve_max_flow_with_budget(source, sink, budget, decomposition, algorithm)
+    }
+
+    // Max flow values from `source` to `sink` as the capacity of `edge` sweeps `capacities`, in
+    // the same order, e.g. for a capacity slider. Values are computed in increasing capacity
+    // order, each augmenting the previous flow instead of solving from scratch. The edge may not
+    // have a piecewise cost, and lower bounds are not supported.
+    pub fn max_flow_profile(
+        &self, source: String, sink: String, edge: ID, capacities: &[f64], algorithm: MaxFlowAlgorithm
+    ) -> Result<Vec<f64>, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.max_flow_profile(source, sink, edge as usize, capacities, algorithm)
+    }
+
+    // The flow from `source` to `sink` with the largest profit when each unit that arrives earns
+    // `revenue`: only paths that cost less than the revenue are used, so this may send less than
+    // the max flow. The profit is `revenue * max_flow() - total_cost()`.
+    pub fn solve_max_profit_flow(
+        &self, source: String, sink: String, revenue: f64, decomposition: PathDecomposition,
+        algorithm: MinCostFlowAlgorithm,
+    ) -> Result<McmfSolution, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.solve_max_profit_flow(source, sink, revenue, decomposition, algorithm)
+    }
+
+    pub fn solve_min_cost_circulation(&self, algorithm: MinCostFlowAlgorithm) -> Result<FlowSolution, McmfError> {
+        self.graph.solve_min_cost_circulation(algorithm)
+    }
+
+    // Some flow around cycles that meets every lower bound, found with Hoffman's reduction to a
+    // max flow, e.g. to check that mandatory flows fit together before optimizing anything. Costs
+    // and supplies are ignored; `solve_min_cost_circulation` finds the cheapest such flow. If there
+    // is none, the result has a set of nodes that must take in more flow than can leave it.
+    pub fn solve_feasible_circulation(&self, algorithm: MaxFlowAlgorithm) -> Result<Circulation, McmfError> {
+        self.graph.solve_feasible_circulation(algorithm)
+    }
+
+    pub fn solve_b_flow(&self, algorithm: MinCostFlowAlgorithm) -> Result<FlowSolution, McmfError> {
+        self.graph.solve_b_flow(algorithm)
+    }
+
+    // Explains why `solve_b_flow` fails: routes as much of the supplies to the demands as the
+    // capacities allow, regardless of costs, and reports what is left over at each node and a cut
+    // that is in the way. Doesn't fail itself when the total supply and demand differ.
+    pub fn diagnose_b_flow(&self) -> Result<BalanceDiagnosis, McmfError> {
+        self.graph.diagnose_b_flow()
+    }
+
+    // The largest set of paths from `source` to `sink` that share no edge, regardless of
+    // capacities. With `k`, at most `k` paths with the lowest total cost instead.
+    pub fn edge_disjoint_paths(&self, source: String, sink: String, k: Option<u32>) -> Result<Vec<Path>, McmfError> {
+        self.graph.edge_disjoint_paths(self.get_vertex(source)?, self.get_vertex(sink)?, k)
+    }
+
+    // The largest set of paths from `source` to `sink` that share no node other than the two
+    // terminals, regardless of capacities.
+    pub fn vertex_disjoint_paths(&self, source: String, sink: String) -> Result<DisjointPaths, McmfError> {
+        self.graph.vertex_disjoint_paths(self.get_vertex(source)?, self.get_vertex(sink)?)
+    }
+
+    // A copy with every edge reversed, keeping its ID, capacity and cost, e.g. to see which nodes
+    // can reach the sink or how flow would go back. Supplies become demands and the source and the
+    // sink swap places, so that every flow of the graph is one of the copy along reversed edges.
+    pub fn reversed(&self) -> GraphBuilder {
+        GraphBuilder {
+            node_indices: self.node_indices.clone(),
+            graph: self.graph.reversed(),
+            source: self.sink.clone(),
+            sink: self.source.clone(),
+        }
+    }
+
+    // The max flow from each node to each other node, as a matrix in row-major order with a row per
+    // source and a column per sink, in the order of `nodes`, and zeros on the diagonal. Flows in a
+    // directed graph are not symmetric, so this takes `n * (n - 1)` max-flow solves on one network
+    // and is meant for moderately sized graphs; see `gomory_hu_tree` for undirected cuts. A time
+    // limit applies to all of them together. Pairs joined by a path of infinite capacity edges
+    // get an infinite value.
+    pub fn all_pairs_max_flow(&self) -> Result<Vec<f64>, McmfError> {
+        self.graph.all_pairs_max_flow()
+    }
+
+    // The nodes that some path of edges leads to from `node`, including itself, in the order of
+    // `nodes`. Undirected edges go both ways, and capacities, lower bounds and costs are ignored,
+    // so this shows the parts of the graph that flow from `node` could possibly reach.
+    pub fn reachable_from(&self, node: String) -> Result<Vec<String>, McmfError> {
+        Ok(self.graph.node_names_of(&self.graph.reachable_from(self.get_vertex(node)?)?))
+    }
+
+    // The nodes with some path of edges to `node`, including itself, like `reachable_from`.
+    pub fn can_reach(&self, node: String) -> Result<Vec<String>, McmfError> {
+        Ok(self.graph.node_names_of(&self.graph.can_reach(self.get_vertex(node)?)?))
+    }
+
+    // Groups of nodes where each node has a path to every other one, e.g. to explain why a
+    // circulation can't exist. Components come in topological order, so edges only lead from a
+    // component to itself or later ones. Nodes within a component are in the order of `nodes`.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<String>> {
+        self.graph.strongly_connected_components().iter().map(|component| self.graph.node_names_of(component)).collect()
+    }
+
+    // The graph with its solver network built once, for many solves between different terminals,
+    // e.g. as the user picks them. Later changes to the builder don't affect it. Pruning, chain
+    // contraction and the secondary objective of max flows are skipped, since they depend on the
+    // terminals, and the solves don't count for `resolve`.
+    pub fn solved_network(&self) -> Result<SolvedNetwork, McmfError> {
+        SolvedNetwork::new(&self.graph, self.node_indices.clone())
+    }
+
+    // A Gomory-Hu tree of the graph, which gives the minimum cut between any two nodes after
+    // `n - 1` max-flow solves. Cut trees need cuts to be symmetric, so edges are treated as
+    // undirected; costs, lower bounds, supplies and node capacities are ignored. Cuts that only
+    // infinite capacity edges cross have an infinite value.
+    pub fn gomory_hu_tree(&self) -> Result<GomoryHuTree, McmfError> {
+        self.graph.gomory_hu_tree()
+    }
+
+    // Warnings about likely modelling mistakes, to show before solving: isolated nodes, nodes off
+    // every path from the source to the sink if both are set, self-loops, parallel edges, edges
+    // with zero capacity, which can never carry flow, and costs that are rounded away or too large.
+    pub fn validate(&self) -> Result<Vec<String>, McmfError> {
+        let terminals = match (&self.source, &self.sink) {
+            (Some(source), Some(sink)) => Some((self.get_vertex(source.clone())?, self.get_vertex(sink.clone())?)),
+            _ => None,
+        };
+        self.graph.validate(terminals)
+    }
+
+    // Explains why `flow_value` units can't be sent from `source` to `sink`, e.g. after
+    // `solve_min_cost_flow` failed. Doesn't fail itself when the sink is unreachable.
+    pub fn diagnose_flow(&self, source: String, sink: String, flow_value: f64) -> Result<FlowDiagnosis, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.diagnose_flow(source, sink, flow_value)
+    }
+
+    // Checks a flow from `source` to `sink` given as net flows on the edges in ID order, e.g. one
+    // computed by hand. Negative flows use undirected edges backwards.
+    pub fn verify_flow(&self, source: String, sink: String, flows: &[f64]) -> Result<FlowVerification, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        self.graph.verify_flow(source, sink, flows)
+    }
+
+    fn get_vertex(&self, v: String) -> Result<usize, McmfError> {
+        self.node_indices.get(&v).copied().ok_or(McmfError::UnknownNode(v))
+    }
+    fn get_or_insert_vertex(&mut self, v: String) -> usize {
+        if let Some(&id) = self.node_indices.get(&v) {
+            id
+        } else {
+            let id = self.graph.add_nodes(1) as usize;
+            self.node_indices.insert(v.clone(), id);
+            self.graph.node_names.push(v);
+            id
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_graph() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 10., 200.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 20., 0.).unwrap();
+        builder.add_edge("c".to_owned(), "e".to_owned(), 15., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "d".to_owned(), 2., 100.).unwrap();
+        builder.add_edge("d".to_owned(), "e".to_owned(), 3., 0.).unwrap();
+        let solution = builder.solve_mcmf("a".to_owned(), "e".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 12.0);
+        assert_eq!(solution.total_cost(), Some(2200.0));
+        let edge_flows: Vec<_> = solution.edge_flows.iter().map(|f| (f.flow, f.capacity, f.cost)).collect();
+        assert_eq!(edge_flows, vec![
+            (10.0, 10.0, 200.0), (10.0, 20.0, 0.0), (10.0, 15.0, 0.0), (2.0, 2.0, 100.0), (2.0, 3.0, 0.0),
+        ]);
+    }
+
+    #[test]
+    fn max_flow_only() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 10., 200.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 20., 0.).unwrap();
+        builder.add_edge("c".to_owned(), "e".to_owned(), 15., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "d".to_owned(), 2., 100.).unwrap();
+        builder.add_edge("d".to_owned(), "e".to_owned(), 3., 0.).unwrap();
+        let solution = builder.solve_max_flow("a".to_owned(), "e".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 12.0);
+        assert_eq!(solution.total_cost(), None);
+        assert_eq!(solution.paths.iter().map(|p| p.flow).sum::<f64>(), 12.0);
+    }
+
+    #[test]
+    fn fixed_flow_value() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 10., 200.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 20., 0.).unwrap();
+        builder.add_edge("c".to_owned(), "e".to_owned(), 15., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "d".to_owned(), 2., 100.).unwrap();
+        builder.add_edge("d".to_owned(), "e".to_owned(), 3., 0.).unwrap();
+        let solution = builder.solve_min_cost_flow("a".to_owned(), "e".to_owned(), 3., Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 3.0);
+        assert_eq!(solution.total_cost(), Some(400.0));
+        assert_eq!(
+            builder.solve_min_cost_flow("a".to_owned(), "e".to_owned(), 13., Default::default(), Default::default()).unwrap_err(),
+            "cannot route 13 units from 'a' to 'e'"
+        );
+    }
+
+    #[test]
+    fn circulation() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("c".to_owned(), "a".to_owned(), 4., -4.).unwrap();
+        builder.add_edge("a".to_owned(), "c".to_owned(), 1., 1.).unwrap();
+        let solution = builder.solve_min_cost_circulation(Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), -9.0);
+        let flows: Vec<_> = solution.edge_flows.iter().map(|f| f.flow).collect();
+        assert_eq!(flows, vec![3.0, 3.0, 4.0, 1.0]);
+    }
+
+    #[test]
+    fn feasible_circulation() {
+        let circulation = |capacity| {
+            let mut builder = GraphBuilder::new();
+            builder.add_edge_with_bounds("a".to_owned(), "b".to_owned(), 2., 5., 0.).unwrap();
+            builder.add_edge("b".to_owned(), "c".to_owned(), capacity, 0.).unwrap();
+            builder.add_edge("c".to_owned(), "a".to_owned(), 4., 0.).unwrap();
+            builder.solve_feasible_circulation(Default::default()).unwrap()
+        };
+        let feasible = circulation(3.);
+        assert!(feasible.feasible());
+        let flows: Vec<_> = feasible.edge_flows().iter().map(|f| f.flow).collect();
+        assert_eq!(flows, [2., 2., 2.]);
+        let infeasible = circulation(1.);
+        assert!(!infeasible.feasible() && infeasible.edge_flows().is_empty());
+        let cut = infeasible.violated_cut().unwrap();
+        assert_eq!(cut.nodes(), ["b"]);
+        assert_eq!((cut.entering_edges(), cut.leaving_edges()), (&[0][..], &[1][..]));
+        assert_eq!((cut.required_inflow(), cut.outflow_capacity()), (2., 1.));
+    }
+
+    #[test]
+    fn diagnose_b_flow() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s1".to_owned(), "t1".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("s1".to_owned(), "t2".to_owned(), 10., 5.).unwrap();
+        builder.add_edge("s2".to_owned(), "t2".to_owned(), 2., 1.).unwrap();
+        assert!(builder.diagnose_b_flow().unwrap().feasible());
+        builder.set_supply("s1".to_owned(), 5.).unwrap();
+        builder.set_supply("s2".to_owned(), 2.).unwrap();
+        builder.set_supply("t1".to_owned(), -3.).unwrap();
+        builder.set_supply("t2".to_owned(), -4.).unwrap();
+        let diagnosis = builder.diagnose_b_flow().unwrap();
+        assert!(diagnosis.feasible());
+        assert_eq!(diagnosis.cut().unwrap().sink_side().len(), 4);
+        let unmet = |diagnosis: &BalanceDiagnosis| diagnosis.imbalances().iter()
+            .map(|imbalance| (imbalance.node().to_owned(), imbalance.supply(), imbalance.unmet()))
+            .collect::<Vec<_>>();
+
+        builder.set_supply("t2".to_owned(), -5.).unwrap();
+        let diagnosis = builder.diagnose_b_flow().unwrap();
+        assert_eq!((diagnosis.total_supply(), diagnosis.total_demand()), (7., 8.));
+        assert_eq!(unmet(&diagnosis), [("t2".to_owned(), -5., 1.)]);
+        assert!(diagnosis.cut().unwrap().source_side().is_empty());
+
+        builder.set_supply("s2".to_owned(), 3.).unwrap();
+        let diagnosis = builder.diagnose_b_flow().unwrap();
+        assert_eq!(unmet(&diagnosis), [("t2".to_owned(), -5., 1.), ("s2".to_owned(), 3., 1.)]);
+        let cut = diagnosis.cut().unwrap();
+        assert_eq!((cut.source_side(), cut.edges()), (&["s2".to_owned()][..], &[2][..]));
+    }
+
+    #[test]
+    fn b_flow() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s1".to_owned(), "t1".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("s1".to_owned(), "t2".to_owned(), 10., 5.).unwrap();
+        builder.add_edge("s2".to_owned(), "t2".to_owned(), 2., 1.).unwrap();
+        builder.set_supply("s1".to_owned(), 5.).unwrap();
+        builder.set_supply("s2".to_owned(), 2.).unwrap();
+        builder.set_supply("t1".to_owned(), -3.).unwrap();
+        builder.set_supply("t2".to_owned(), -4.).unwrap();
+        let solution = builder.solve_b_flow(Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), 15.0);
+        let flows: Vec<_> = solution.edge_flows.iter().map(|f| f.flow).collect();
+        assert_eq!(flows, vec![3.0, 2.0, 2.0]);
+
+        builder.set_supply("t2".to_owned(), -5.).unwrap();
+        assert_eq!(
+            builder.solve_b_flow(Default::default()).unwrap_err(),
+            "total supply 7 does not match total demand 8"
+        );
+        builder.set_supply("s2".to_owned(), 3.).unwrap();
+        assert_eq!(builder.solve_b_flow(Default::default()).unwrap_err(), "supplies cannot be routed to demands");
+    }
+
+    #[test]
+    fn lower_bounds() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 10., 1.).unwrap();
+        builder.add_edge_with_bounds("a".to_owned(), "t".to_owned(), 0., 10., 1.).unwrap();
+        builder.add_edge_with_bounds("s".to_owned(), "b".to_owned(), 3., 10., 5.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 4., 5.).unwrap();
+        let solution = builder.solve_min_cost_flow("s".to_owned(), "t".to_owned(), 5., Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), Some(34.0));
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 14.0);
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 14.0);
+        assert_eq!(solution.total_cost(), Some(60.0));
+
+        builder.add_edge_with_bounds("b".to_owned(), "c".to_owned(), 1., 1., 0.).unwrap();
+        assert_eq!(
+            builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap_err(),
+            "lower bounds cannot be satisfied"
+        );
+        builder.add_edge_with_bounds("c".to_owned(), "b".to_owned(), 0., 1., 0.).unwrap();
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 14.0);
+        let cycles = |solution: &McmfSolution| {
+            solution.cycles().iter().map(|cycle| (cycle.flow(), cycle.nodes().join(" "))).collect::<Vec<_>>()
+        };
+        assert_eq!(cycles(&solution), [(1.0, "b c b".to_owned())]);
+        assert_eq!(cycles(&McmfSolution::from_json(&solution.to_json()).unwrap()), cycles(&solution));
+        assert_eq!(
+            builder.solve_min_cost_circulation(Default::default()).unwrap_err(),
+            "lower bounds cannot be satisfied"
+        );
+
+        // Flow around a cycle through the source and the sink is not part of the paths.
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 4., 0.).unwrap();
+        builder.add_edge_with_bounds("t".to_owned(), "a".to_owned(), 2., 2., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "s".to_owned(), 2., 0.).unwrap();
+        for decomposition in [
+            PathDecomposition::DepthFirst,
+            PathDecomposition::WidestFirst,
+            PathDecomposition::ShortestFirst,
+            PathDecomposition::Lexicographic,
+            PathDecomposition::FewestPaths,
+        ] {
+            let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), decomposition, Default::default()).unwrap();
+            assert_eq!(solution.max_flow(), 2.0);
+            let paths: Vec<_> = solution.paths().iter().map(|path| (path.flow(), path.nodes().join(" "))).collect();
+            assert_eq!(paths, [(2.0, "s t".to_owned())]);
+            assert_eq!(cycles(&solution), [(2.0, "s t a s".to_owned())]);
+        }
+    }
+
+    #[test]
+    fn node_capacities() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 10., 2.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 10., 2.).unwrap();
+        builder.set_node_capacity("a".to_owned(), 3.).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 13.0);
+        assert_eq!(solution.total_cost(), Some(46.0));
+        let mut paths: Vec<_> = solution.paths.iter().map(|p| (p.flow, p.nodes.join(" "))).collect();
+        paths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(paths, vec![(3.0, "s a t".to_owned()), (10.0, "s b t".to_owned())]);
+
+        builder.set_node_capacity("t".to_owned(), 5.).unwrap();
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 5.0);
+    }
+
+    #[test]
+    fn multiple_terminals() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s1".to_owned(), "a".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("s2".to_owned(), "a".to_owned(), 10., 2.).unwrap();
+        builder.add_edge("a".to_owned(), "t1".to_owned(), 4., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t2".to_owned(), 10., 1.).unwrap();
+        let sources = vec!["s1".to_owned(), "s2".to_owned()];
+        let sinks = vec!["t1".to_owned(), "t2".to_owned()];
+        let solution = builder.solve_mcmf_multi(
+            sources.clone(), sinks.clone(), None, None, Default::default(), Default::default(), Default::default(),
+        ).unwrap();
+        assert_eq!(solution.max_flow(), 14.0);
+        assert_eq!(solution.total_cost(), Some(32.0));
+        assert!(solution.paths.iter().all(|p| p.nodes.len() == 3));
+
+        let solution = builder.solve_mcmf_multi(
+            sources.clone(), sinks.clone(), Some(vec![3., 3.]), Some(vec![1., 10.]),
+            Default::default(), Default::default(), Default::default(),
+        ).unwrap();
+        assert_eq!(solution.max_flow(), 6.0);
+        assert_eq!(solution.total_cost(), Some(15.0));
+        assert_eq!(
+            builder.solve_mcmf_multi(
+                sources, sinks, Some(vec![1.]), None, Default::default(), Default::default(), Default::default(),
+            ).unwrap_err(),
+            "expected 2 source capacities, got 1"
+        );
+        let solve = |sources: &[&str], sinks: &[&str]| builder.solve_mcmf_multi(
+            sources.iter().map(|&v| v.to_owned()).collect(), sinks.iter().map(|&v| v.to_owned()).collect(),
+            None, None, Default::default(), Default::default(), Default::default(),
+        ).unwrap_err();
+        assert_eq!(solve(&["s1", "a"], &["t1", "a"]), "node 'a' is both a source and a sink");
+        assert_eq!(solve(&["s1", "s1"], &["t1"]), "node 's1' is listed twice as a source");
+        assert_eq!(solve(&["s1"], &["t2", "t1", "t2"]), "node 't2' is listed twice as a sink");
+    }
+
+    #[test]
+    fn undirected_edges() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 5., 3.).unwrap();
+        builder.add_undirected_edge("a".to_owned(), "b".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 10., 1.).unwrap();
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 10.0);
+
+        builder.set_supply("b".to_owned(), 3.).unwrap();
+        builder.set_supply("a".to_owned(), -3.).unwrap();
+        let solution = builder.solve_b_flow(Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), 3.0);
+        let flow = &solution.edge_flows[2];
+        assert_eq!((flow.from.as_str(), flow.to.as_str(), flow.flow), ("b", "a", 3.0));
+
+        assert_eq!(
+            builder.add_undirected_edge("a".to_owned(), "t".to_owned(), 1., -1.).unwrap_err(),
+            "undirected edge cost must be non-negative"
+        );
+
+        // With a node capacity, the two arcs of an undirected edge at that node aren't
+        // antiparallel, but flow in both directions still cancels.
+        let mut builder = GraphBuilder::new();
+        let edges = [("n0", "n4", 5., 9.), ("n1", "n2", 3., 0.), ("n4", "n3", 2., 4.), ("n2", "n0", 6., 5.)];
+        for (a, b, capacity, cost) in edges {
+            builder.add_undirected_edge(a.to_owned(), b.to_owned(), capacity, cost).unwrap();
+        }
+        builder.set_node_capacity("n2".to_owned(), 2.).unwrap();
+        for algorithm in [MinCostFlowAlgorithm::NetworkSimplex, MinCostFlowAlgorithm::CostScaling] {
+            let solution = builder.solve_mcmf("n0".to_owned(), "n3".to_owned(), Default::default(), algorithm, Default::default()).unwrap();
+            assert_eq!((solution.max_flow(), solution.total_cost()), (2.0, Some(26.0)));
+            assert!(solution.cycles().is_empty());
+            assert_eq!(builder.resolve().unwrap().total_cost(), Some(26.0));
+        }
+    }
+
+    #[test]
+    fn min_cut() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 4., 1.).unwrap();
+        for solution in [
+            builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap(),
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap(),
+        ] {
+            assert_eq!(solution.max_flow(), 7.0);
+            let cut = solution.min_cut().unwrap();
+            assert_eq!(cut.edges, vec![3, 4]);
+            assert_eq!(cut.source_side, vec!["s", "a", "b"]);
+            assert_eq!(cut.sink_side, vec!["t"]);
+        }
+        let solution = builder.solve_min_cost_flow("s".to_owned(), "t".to_owned(), 5., Default::default(), Default::default()).unwrap();
+        assert!(solution.min_cut().is_none());
+
+        builder.set_node_capacity("a".to_owned(), 1.).unwrap();
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 3.0);
+        let cut = solution.min_cut().unwrap();
+        assert_eq!(cut.edges, vec![1]);
+        assert_eq!(cut.source_side, vec!["s", "a"]);
+    }
+
+    #[test]
+    fn negative_costs() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 5., -2.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 5., 0.).unwrap();
+        assert_eq!(builder.find_negative_cycle(), None);
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), Some(-5.0));
+
+        // A negative cost edge that leads nowhere doesn't make the flow infeasible.
+        let mut dead_end = GraphBuilder::new();
+        dead_end.add_edge("s".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        dead_end.add_edge("s".to_owned(), "a".to_owned(), 5., -1.).unwrap();
+        let solution = dead_end.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (1.0, Some(0.0)));
+
+        builder.add_edge("t".to_owned(), "a".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 1., -1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        assert_eq!(builder.find_negative_cycle(), Some(vec!["t".to_owned(), "a".to_owned(), "b".to_owned()]));
+        assert_eq!(
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap_err(),
+            "negative cost cycle: t -> a -> b -> t"
+        );
+        assert!(builder.solve_min_cost_circulation(Default::default()).is_ok());
+    }
+
+    #[test]
+    fn path_decompositions() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "x".to_owned(), 4., 0.).unwrap();
+        builder.add_edge("x".to_owned(), "t".to_owned(), 4., 0.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("c".to_owned(), "t".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        let paths = |decomposition| -> Vec<_> {
+            let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), decomposition, Default::default()).unwrap();
+            solution.paths.iter().map(|p| (p.flow, p.nodes.join(" "))).collect()
+        };
+        let path = |flow, nodes: &str| (flow, nodes.to_owned());
+        assert_eq!(paths(PathDecomposition::WidestFirst), vec![path(4., "s x t"), path(2., "s b c t"), path(1., "s t")]);
+        assert_eq!(paths(PathDecomposition::ShortestFirst), vec![path(1., "s t"), path(4., "s x t"), path(2., "s b c t")]);
+        assert_eq!(paths(PathDecomposition::Lexicographic), vec![path(2., "s b c t"), path(1., "s t"), path(4., "s x t")]);
+        assert_eq!(paths(PathDecomposition::FewestPaths).len(), 3);
+        assert_eq!(paths(PathDecomposition::DepthFirst).len(), 3);
+    }
+
+    #[test]
+    fn long_chain() {
+        // Solvers from rs-graph are too slow or recurse too deep for such a chain themselves, so
+        // only the path decomposition is checked here.
+        let mut builder = GraphBuilder::new();
+        let n = 100_000;
+        for i in 0..n {
+            builder.add_edge(i.to_string(), (i + 1).to_string(), 1., 1.).unwrap();
+        }
+        let network = builder.graph.build_network();
+        let flows = EdgeVec::new(&network.graph, 1);
+        let (source, sink) = (network.entry(0), network.exit(n));
+        for decomposition in [PathDecomposition::DepthFirst, PathDecomposition::FewestPaths] {
+            let (paths, _) = builder.graph.paths(&network, &flows, source, sink, decomposition);
+            assert_eq!(paths.len(), 1);
+            assert_eq!(paths[0].nodes.len(), n + 1);
+        }
+    }
+
+    #[test]
+    fn bulk_edges() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 1., 0.).unwrap();
+        assert_eq!(builder.add_nodes(vec!["c".to_owned(), "d".to_owned()]), Ok(2));
+        assert_eq!(builder.graph.add_edges_bulk(&[0, 0, 2, 1], &[2, 3, 1, 3], &[5., 1., 5., 5.], &[1., 10., 1., 1.]), Ok(1));
+        let solution = builder.solve_mcmf("a".to_owned(), "d".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 6.0);
+        assert_eq!(solution.total_cost(), Some(23.0));
+
+        assert_eq!(builder.add_nodes(vec!["e".to_owned(), "a".to_owned()]).unwrap_err(), "node 'a' already exists");
+        assert_eq!(builder.graph.add_edges_bulk(&[0], &[1, 2], &[1.], &[1.]).unwrap_err(), "expected 1 destinations, got 2");
+        assert_eq!(builder.graph.add_edges_bulk(&[0, 0], &[1, 4], &[1., 1.], &[1., 1.]).unwrap_err(), "node index 4 out of range");
+        assert_eq!(builder.graph.edges.len(), 5);
+    }
+
+    #[test]
+    fn indexed_builder() {
+        let mut builder = IndexedGraphBuilder::new();
+        assert_eq!(builder.add_nodes(4), 0);
+        builder.add_edge_with_bounds(0, 1, 0., 5., 1.).unwrap();
+        builder.add_edge_with_bounds(1, 3, 0., 3., 1.).unwrap();
+        builder.add_edge_with_bounds(0, 2, 0., 5., 2.).unwrap();
+        builder.add_edge_with_bounds(2, 3, 0., 5., 2.).unwrap();
+        let solution = builder.solve_mcmf(0, 3, Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 8.0);
+        assert_eq!(solution.total_cost(), Some(26.0));
+        let mut paths: Vec<_> = solution.paths.iter().map(|p| (p.node_indices.clone(), p.nodes.join(" "))).collect();
+        paths.sort();
+        assert_eq!(paths, vec![(vec![0, 1, 3], "0 1 3".to_owned()), (vec![0, 2, 3], "0 2 3".to_owned())]);
+
+        builder.set_node_names(["s", "a", "b", "t"].map(|name| name.to_owned()).to_vec()).unwrap();
+        let solution = builder.solve_max_flow(0, 3, PathDecomposition::Lexicographic, Default::default()).unwrap();
+        assert_eq!(solution.paths[0].nodes, vec!["s", "a", "t"]);
+        assert_eq!(builder.add_edge_with_bounds(0, 4, 0., 1., 1.).unwrap_err(), "node index 4 out of range");
+        assert_eq!(builder.set_node_names(vec![]).unwrap_err(), "expected 4 node names, got 0");
+    }
+
+    #[test]
+    fn json() {
+        let text = r#"{
+            "nodes": ["s", {"name": "a", "capacity": 4}, {"name": "t", "supply": -1}],
+            "edges": [
+                {"from": "s", "to": "a", "capacity": 5, "cost": 1.5},
+                {"from": "a", "to": "t", "capacity": 5, "lower": 1},
+                {"from": "s", "to": "t\u00e9", "capacity": 2, "undirected": true}
+            ],
+            "source": "s",
+            "sink": "t"
+        }"#;
+        let builder = json::builder_from_json(text).unwrap();
+        assert_eq!((builder.source(), builder.sink()), (Some("s"), Some("t")));
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 4.0);
+        assert_eq!(solution.total_cost(), Some(6.0));
+        let exported = builder.to_json();
+        assert_eq!(
+            exported,
+            concat!(
+                r#"{"nodes":[{"name":"s"},{"name":"a","capacity":4},{"name":"t","supply":-1},{"name":"té"}],"#,
+                r#""edges":[{"from":"s","to":"a","capacity":5,"cost":1.5},{"from":"a","to":"t","capacity":5,"cost":0,"lower":1},"#,
+                r#"{"from":"s","to":"té","capacity":2,"cost":0,"undirected":true}],"source":"s","sink":"t"}"#,
+            )
+        );
+        assert_eq!(json::builder_from_json(&exported).unwrap().to_json(), exported);
+
+        assert_eq!(
+            json::builder_from_json(r#"{"edges": [{"from": "a", "to": "b"}]}"#).err().unwrap(),
+            "edge 0: missing 'capacity'"
+        );
+        assert_eq!(
+            json::builder_from_json(r#"{"nodes": ["a", "a"]}"#).err().unwrap(),
+            "node 'a' already exists"
+        );
+        assert_eq!(
+            json::builder_from_json(r#"{"nodes": [}"#).err().unwrap(),
+            "invalid JSON at position 11: unexpected character"
+        );
+    }
+
+    #[test]
+    fn solver_failures() {
+        let budget = utils::Budget::default();
+        let result = utils::block_on(maxflow::dinic(2, &[(0, 1, 3)], 0, 0, &budget, false));
+        assert_eq!(result, (0, vec![0], 0));
+        assert!(budget.error().is_none());
+        budget.fail("network simplex ended in state Unbounded".to_owned());
+        assert!(!budget.tick());
+        let error = budget.error().unwrap();
+        assert!(matches!(error, McmfError::Internal(_)));
+        assert_eq!(error, "internal error: network simplex ended in state Unbounded");
+    }
+
+    #[test]
+    fn residual_arcs() {
+        let residual = maxflow::Residual::new(4, &[(0, 1, 5, 1), (1, 2, 3, 2), (0, 2, 1, 0)]);
+        assert_eq!(residual.num_nodes(), 4);
+        let arcs: Vec<_> = (0..4).map(|v| residual.arcs_from(v).to_vec()).collect();
+        assert_eq!(arcs, [vec![0, 4], vec![1, 2], vec![3, 5], vec![]]);
+        assert_eq!((residual.to[4], residual.to[5], residual.cost[5]), (2, 0, 0));
+    }
+
+    #[test]
+    fn infinite_and_zero_capacities() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), f64::INFINITY, 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 3., 1.).unwrap();
+        let closed = builder.add_edge("s".to_owned(), "t".to_owned(), 0., 0.).unwrap();
+        let solve = |builder: &GraphBuilder| {
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+        };
+        let solution = solve(&builder).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (3., Some(6.)));
+        assert_eq!(solution.edge_flows()[0].capacity(), f64::INFINITY);
+        assert_eq!(solution.edge_flows()[closed as usize].flow(), 0.);
+        assert!(builder.to_json().contains(r#"{"from":"s","to":"a","capacity":"Infinity","cost":1}"#));
+        assert_eq!(json::builder_from_json(&builder.to_json()).unwrap().to_json(), builder.to_json());
+        assert_eq!(builder.to_dimacs_max().err().unwrap(), "infinite capacities cannot be represented in DIMACS");
+
+        builder.update_capacity(closed, 2.).unwrap();
+        assert_eq!(solve(&builder).unwrap().max_flow(), 5.);
+        builder.update_capacity(1, f64::INFINITY).unwrap();
+        assert_eq!(
+            solve(&builder).err().unwrap(),
+            "max flow is unbounded: a path of infinite capacity edges connects the source to the sink"
+        );
+        assert_eq!(
+            builder.add_edge_piecewise("a".to_owned(), "t".to_owned(), &[1., f64::INFINITY], &[1., 2.]).err().unwrap(),
+            "piece capacities must be finite"
+        );
+    }
+
+    #[test]
+    fn unbounded_negative_cycles() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), f64::INFINITY, -2.).unwrap();
+        builder.add_edge("b".to_owned(), "a".to_owned(), 4., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 3., 1.).unwrap();
+        // The cycle is limited by its finite edge.
+        assert_eq!(builder.solve_min_cost_circulation(Default::default()).unwrap().total_cost(), -4.);
+        builder.set_supply("a".to_owned(), 1.).unwrap();
+        builder.set_demand("t".to_owned(), 1.).unwrap();
+        assert_eq!(builder.solve_b_flow(Default::default()).unwrap().total_cost(), -3.);
+
+        builder.update_capacity(1, f64::INFINITY).unwrap();
+        let cycle = McmfError::NegativeCycle(vec!["a".to_owned(), "b".to_owned(), "a".to_owned()]);
+        assert_eq!(builder.solve_b_flow(Default::default()).unwrap_err(), cycle);
+        builder.set_supply("a".to_owned(), 0.).unwrap();
+        builder.set_demand("t".to_owned(), 0.).unwrap();
+        assert_eq!(builder.solve_min_cost_circulation(Default::default()).unwrap_err(), cycle);
+    }
+
+    #[test]
+    fn duplicate_edges_and_self_loops() {
+        let mut builder = GraphBuilder::new();
+        builder.set_duplicate_edges(DuplicateEdges::MergeAverageCost);
+        let first = builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        assert_eq!(builder.add_edge("s".to_owned(), "t".to_owned(), 3., 5.), Ok(first));
+        assert_eq!(builder.add_undirected_edge("s".to_owned(), "t".to_owned(), 1., 1.), Ok(1));
+        let edge = &builder.edges()[0];
+        assert_eq!((builder.edge_count(), edge.capacity(), edge.cost()), (2, 4., 4.));
+        assert_eq!(
+            builder.add_edges_bulk(&[0], &[1], &[1.], &[1.]).err().unwrap(),
+            "add_edges_bulk cannot merge duplicate edges"
+        );
+        builder.set_duplicate_edges(DuplicateEdges::MergeMinCost);
+        builder.add_edge_with_bounds("s".to_owned(), "t".to_owned(), 1., 2., 2.).unwrap();
+        let edge = &builder.edges()[0];
+        assert_eq!((edge.lower(), edge.capacity(), edge.cost()), (1., 6., 2.));
+        builder.set_duplicate_edges(DuplicateEdges::Reject);
+        assert_eq!(
+            builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).err().unwrap(),
+            "an edge from 's' to 't' already exists"
+        );
+        assert_eq!(
+            builder.add_edges_bulk(&[1, 1], &[0, 0], &[1., 1.], &[1., 1.]).err().unwrap(),
+            "an edge from 't' to 's' already exists"
+        );
+        builder.remove_edge(0).unwrap();
+        assert_eq!(builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.), Ok(1));
+
+        builder.set_self_loops(SelfLoops::Reject);
+        assert_eq!(
+            builder.add_edge("a".to_owned(), "a".to_owned(), 1., 1.).err().unwrap(),
+            "self-loop at 'a' is not allowed"
+        );
+        assert!(!builder.has_node("a"));
+        builder.set_self_loops(SelfLoops::Keep);
+        let a_loop = builder.add_edge("s".to_owned(), "s".to_owned(), 2., -1.).unwrap();
+        let solve = |builder: &GraphBuilder| {
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+        };
+        assert!(matches!(solve(&builder), Err(McmfError::NegativeCycle(_))));
+        builder.set_self_loops(SelfLoops::Ignore);
+        let solution = solve(&builder).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (2., Some(2.)));
+        assert_eq!(solution.edge_flows()[a_loop as usize].flow(), 0.);
+    }
+
+    #[test]
+    fn node_groups() {
+        let mut builder = GraphBuilder::new();
+        for (from, to, capacity, cost) in [
+            ("s", "p1", 3., 0.), ("s", "p2", 2., 0.), ("p1", "w1", 3., 1.), ("p2", "w1", 1., 2.), ("p2", "w2", 1., 1.),
+            ("w1", "w2", 1., 1.), ("w1", "t", 3., 0.), ("w2", "t", 5., 0.),
+        ] {
+            builder.add_edge(from.to_owned(), to.to_owned(), capacity, cost).unwrap();
+        }
+        for (node, group) in [("p1", "plants"), ("p2", "plants"), ("w1", "warehouses"), ("w2", "warehouses")] {
+            builder.set_node_group(node.to_owned(), Some(group.to_owned())).unwrap();
+        }
+        builder.set_node_metadata("w1".to_owned(), Some("Main warehouse".to_owned())).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (5., Some(7.)));
+        let w1 = solution.node_flows().iter().find(|node| node.node() == "w1").unwrap();
+        assert_eq!((w1.group(), w1.metadata()), (Some("warehouses"), Some("Main warehouse")));
+        assert_eq!((w1.inflow(), w1.outflow()), (4., 4.));
+        assert_eq!(solution.node_flows()[0].net_outflow(), 5.);
+        let groups: Vec<_> = solution.group_flows().iter()
+            .map(|g| (g.group().to_owned(), g.nodes(), g.inflow(), g.outflow(), g.internal_flow(), g.internal_cost()))
+            .collect();
+        assert_eq!(groups, [("plants".to_owned(), 2, 5., 5., 0., 0.), ("warehouses".to_owned(), 2, 5., 5., 1., 1.)]);
+
+        let exported = builder.to_json();
+        assert!(exported.contains(r#"{"name":"w1","group":"warehouses","metadata":"Main warehouse"}"#));
+        assert_eq!(json::builder_from_json(&exported).unwrap().to_json(), exported);
+    }
+
+    #[test]
+    fn edge_metadata() {
+        let mut builder = GraphBuilder::new();
+        let first = builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        let second = builder.add_edge("s".to_owned(), "t".to_owned(), 1., 2.).unwrap();
+        builder.set_edge_metadata(second, Some(r#"{"line": 7}"#.to_owned())).unwrap();
+        assert_eq!(builder.set_edge_metadata(2, None).err().unwrap(), "edge 2 out of range");
+        assert_eq!(builder.edges()[second as usize].metadata(), Some(r#"{"line": 7}"#));
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.edge_flows()[first as usize].metadata(), None);
+        let cheap = solution.paths().iter().find(|path| path.cost() == 2.).unwrap();
+        assert_eq!(solution.edge_metadata(cheap.edges()[0]).unwrap(), Some(r#"{"line": 7}"#));
+        assert!(solution.to_json().contains(r#""cost":2,"metadata":"{\"line\": 7}"}"#));
+
+        let exported = builder.to_json();
+        assert!(exported.contains(r#""cost":2,"metadata":"{\"line\": 7}"}"#));
+        assert_eq!(json::builder_from_json(&exported).unwrap().to_json(), exported);
+        builder.remove_edge(first).unwrap();
+        assert_eq!(builder.edges()[0].metadata(), Some(r#"{"line": 7}"#));
+    }
+
+    #[test]
+    fn solution_json() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 2.).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.to_json(), concat!(
+            r#"{"maxFlow":2,"totalCost":6,"exactTotalCost":"6","costsRounded":false,"limitReached":false,"paths":[{"flow":2,"cost":6,"nodes":["s","a","t"],"nodeIndices":[0,1,2],"edges":[0,1]}],"cycles":[],"#,
+            r#""edgeFlows":[{"edge":0,"from":"s","to":"a","flow":2,"capacity":2,"cost":1},"#,
+            r#"{"edge":1,"from":"a","to":"t","flow":2,"capacity":2,"cost":2}],"#,
+            r#""nodeFlows":[{"node":"s","inflow":0,"outflow":2},{"node":"a","inflow":2,"outflow":2},{"node":"t","inflow":2,"outflow":0}],"#,
+            r#""minCut":{"edges":[0],"sourceSide":["s"],"sinkSide":["a","t"]},"potentials":{"s":0,"a":1,"t":3}}"#,
+        ));
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert!(solution.to_json().starts_with(r#"{"maxFlow":2,"totalCost":null,"exactTotalCost":null,"costsRounded":false,"limitReached":false,"#));
+
+        builder.set_supply("s".to_owned(), 1.).unwrap();
+        builder.set_supply("t".to_owned(), -1.).unwrap();
+        assert_eq!(builder.solve_b_flow(Default::default()).unwrap().to_json(), concat!(
+            r#"{"totalCost":3,"exactTotalCost":"3","costsRounded":false,"limitReached":false,"edgeFlows":[{"edge":0,"from":"s","to":"a","flow":1,"capacity":2,"cost":1},"#,
+            r#"{"edge":1,"from":"a","to":"t","flow":1,"capacity":2,"cost":2}]}"#,
+        ));
+    }
+
+    #[test]
+    fn dimacs() {
+        let text = "\
+c Example min-cost flow problem
+p min 4 5
+n 1 4
+n 4 -4
+a 1 2 0 4 2
+a 1 3 0 2 2
+a 2 3 0 2 1
+a 2 4 1 3 3
+a 3 4 0 5 1
+";
+        let builder = dimacs::builder_from_dimacs(text).unwrap();
+        let solution = builder.solve_b_flow(Default::default()).unwrap();
+        assert_eq!(solution.to_dimacs_flow(), "s 15\nf 1 2 2\nf 1 3 2\nf 2 3 1\nf 2 4 1\nf 3 4 3\n");
+        assert_eq!(dimacs::builder_to_dimacs(&builder, false).unwrap(), text.lines().skip(1).map(|l| format!("{}\n", l)).collect::<String>());
+
+        let builder = dimacs::builder_from_dimacs("p max 3 2\nn 1 s\nn 3 t\na 1 2 5\na 2 3 3\n").unwrap();
+        let (source, sink) = (builder.source().unwrap().to_owned(), builder.sink().unwrap().to_owned());
+        let solution = builder.solve_max_flow(source, sink, Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.to_dimacs_flow(), "s 3\nf 1 2 3\nf 2 3 3\n");
+        assert_eq!(dimacs::builder_to_dimacs(&builder, true).unwrap(), "p max 3 2\nn 1 s\nn 3 t\na 1 2 5\na 2 3 3\n");
+
+        assert_eq!(dimacs::builder_from_dimacs("p max 2 1\na 1 3 5\n").err().unwrap(), "line 2: node 3 out of range");
+        assert_eq!(dimacs::builder_from_dimacs("a 1 2 5\n").err().unwrap(), "line 1: expected the problem line first");
+    }
+
+    #[test]
+    fn dot() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 5., 1.).unwrap();
+        builder.add_edge_with_bounds("a".to_owned(), "t".to_owned(), 1., 3., 0.).unwrap();
+        builder.add_undirected_edge("s".to_owned(), "\"b\"".to_owned(), 2., 0.).unwrap();
+        builder.set_node_capacity("a".to_owned(), 4.).unwrap();
+        assert_eq!(builder.to_dot(), concat!(
+            "digraph {\n",
+            "  \"s\" [label=\"s\"];\n",
+            "  \"a\" [label=\"a\\ncapacity 4\"];\n",
+            "  \"t\" [label=\"t\"];\n",
+            "  \"\\\"b\\\"\" [label=\"\\\"b\\\"\"];\n",
+            "  \"s\" -> \"a\" [label=\"5 @ 1\"];\n",
+            "  \"a\" -> \"t\" [label=\"[1, 3]\"];\n",
+            "  \"s\" -> \"\\\"b\\\"\" [label=\"2\", dir=none];\n",
+            "}\n",
+        ));
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.to_dot(), concat!(
+            "digraph {\n",
+            "  \"s\" -> \"a\" [label=\"3/5\"];\n",
+            "  \"a\" -> \"t\" [label=\"3/3\", color=red, style=bold];\n",
+            "  \"s\" -> \"\\\"b\\\"\" [label=\"0/2\", color=gray];\n",
+            "}\n",
+        ));
+    }
+
+    #[test]
+    fn graphml() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="edge" attr.name="weight" attr.type="double"/>
+              <key id="d1" for="edge" attr.name="price" attr.type="double">
+                <default>1</default>
+              </key>
+              <graph id="G" edgedefault="directed">
+                <node id="s"/>
+                <node id="a &amp; b"/>
+                <node id="t"/>
+                <!-- edges -->
+                <edge source="s" target="a &amp; b"><data key="d0">5</data></edge>
+                <edge source="a &amp; b" target="t"><data key="d0">3</data><data key="d1">2.5</data></edge>
+                <edge source="t" target="s" directed="false"><data key="d0">1</data></edge>
+              </graph>
+            </graphml>"#;
+        let builder = graphml::builder_from_graphml(text, "weight", "price").unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 4.0);
+        assert_eq!(solution.total_cost(), Some(11.5));
+        assert_eq!(solution.paths.iter().map(|p| p.nodes.len()).max(), Some(3));
+
+        assert_eq!(
+            graphml::builder_from_graphml(text, "capacity", "cost").err().unwrap(),
+            "no GraphML key for capacity 'capacity'"
+        );
+        assert_eq!(
+            graphml::builder_from_graphml("<graphml><graph></graphml>", "capacity", "cost").err().unwrap(),
+            "invalid XML at line 1: expected '</graph>'"
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let text = "Cost,From,To,Capacity,Comment\n1,s,\"a, b\",5,\"the \"\"first\"\" edge\"\n\n2.5,\"a, b\",t,3,\n";
+        let builder = csv::builder_from_csv(text, CsvOptions::new()).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 3.0);
+        assert_eq!(solution.total_cost(), Some(10.5));
+        assert_eq!(solution.paths[0].nodes, vec!["s", "a, b", "t"]);
+
+        let options = CsvOptions { delimiter: '\t', has_header: false };
+        let builder = csv::builder_from_csv("s\ta\t5\na\tt\t3\t2\n", options).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), Some(6.0));
+
+        assert_eq!(
+            csv::builder_from_csv("from,to\n", CsvOptions::new()).err().unwrap(),
+            "line 1: missing 'capacity' column"
+        );
+        assert_eq!(
+            csv::builder_from_csv("s,t,x\n", CsvOptions { has_header: false, ..CsvOptions::new() }).err().unwrap(),
+            "line 1: invalid number 'x'"
+        );
+        assert_eq!(
+            csv::builder_from_csv("from,to,capacity\n\"s,t,1\n", CsvOptions::new()).err().unwrap(),
+            "line 2: unterminated quoted field"
+        );
+    }
+
+    #[test]
+    fn cost_precision() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 0.0001).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.5).unwrap();
+        let solve = |builder: &GraphBuilder| builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default(),
+        );
+        let solution = solve(&builder).unwrap();
+        assert_eq!((solution.total_cost(), solution.costs_rounded()), (Some(3.0), true));
+
+        builder.set_cost_precision(None);
+        let solution = solve(&builder).unwrap();
+        assert_eq!((solution.total_cost(), solution.costs_rounded()), (Some(3.0002), false));
+
+        builder.set_cost_precision(Some(0));
+        assert_eq!(solve(&builder).unwrap().total_cost(), Some(4.0));
+
+        builder.set_cost_precision(Some(3));
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1e18).unwrap();
+        assert_eq!(
+            solve(&builder).err().unwrap(),
+            "numeric overflow: costs are too large, reduce cost precision (costs are scaled by 1000)"
+        );
+    }
+
+    #[test]
+    fn exact_costs() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1. / 3.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 0.5).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 2.).unwrap();
+        let solve = |builder: &GraphBuilder| builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default(),
+        );
+        assert_eq!(solve(&builder).unwrap().exact_total_cost(), Some("1833/500"));
+
+        builder.graph.set_cost_denominator(Some(6)).unwrap();
+        let solution = solve(&builder).unwrap();
+        assert_eq!(solution.exact_total_cost(), Some("11/3"));
+        assert!(!solution.costs_rounded());
+
+        builder.graph.set_cost_denominator(Some(4)).unwrap();
+        assert_eq!(solve(&builder).err().unwrap(), "edge 0: cost 0.3333333333333333 is not a multiple of 1/4");
+        assert_eq!(
+            builder.graph.set_cost_denominator(Some(0)).err().unwrap(),
+            "cost denominator must be positive"
+        );
+    }
+
+    #[test]
+    fn fractional_capacities() {
+        let mut builder = GraphBuilder::new();
+        assert_eq!(
+            builder.add_edge("s".to_owned(), "a".to_owned(), 2.5, 1.).unwrap_err(),
+            "capacity 2.5 is not an integer"
+        );
+        builder.graph.set_capacity_denominator(4).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2.5, 1.).unwrap();
+        builder.add_edge_with_bounds("a".to_owned(), "t".to_owned(), 0.25, 1.75, 2.).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (1.75, Some(5.25)));
+        assert_eq!(solution.exact_total_cost(), Some("21/4"));
+        assert_eq!(solution.edge_flows[0].capacity, 2.5);
+        assert_eq!(solution.paths[0].flow, 1.75);
+
+        let exported = builder.to_json();
+        assert!(exported.ends_with(r#""capacityDenominator":4}"#));
+        assert_eq!(json::builder_from_json(&exported).unwrap().to_json(), exported);
+        assert_eq!(
+            builder.add_edge("s".to_owned(), "t".to_owned(), 0.1, 1.).unwrap_err(),
+            "capacity 0.1 is not a multiple of 1/4"
+        );
+        assert_eq!(
+            builder.graph.set_capacity_denominator(2).unwrap_err(),
+            "capacity denominator must be set before adding edges, supplies or node capacities"
+        );
+    }
+
+    #[test]
+    fn non_finite_numbers() {
+        let mut builder = GraphBuilder::new();
+        assert_eq!(
+            builder.add_edge("s".to_owned(), "t".to_owned(), 1., f64::NAN).unwrap_err(),
+            "cost must be a finite number, got NaN"
+        );
+        assert_eq!(
+            builder.add_undirected_edge("s".to_owned(), "t".to_owned(), 1., f64::INFINITY).unwrap_err(),
+            "cost must be a finite number, got inf"
+        );
+        assert_eq!(
+            builder.add_edge("s".to_owned(), "t".to_owned(), f64::NAN, 1.).unwrap_err(),
+            "capacity must be a number"
+        );
+        assert_eq!(builder.node_count(), 0);
+        let edge = builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        assert_eq!(builder.update_cost(edge, f64::NEG_INFINITY).unwrap_err(), "cost must be a finite number, got -inf");
+        assert_eq!(
+            builder.solve_min_cost_flow("s".to_owned(), "t".to_owned(), f64::NAN, Default::default(), Default::default()).unwrap_err(),
+            "flow value must be a number"
+        );
+    }
+
+    #[test]
+    fn numeric_overflow() {
+        let mut builder = GraphBuilder::new();
+        assert_eq!(
+            builder.add_edge("s".to_owned(), "t".to_owned(), 1e20, 1.).unwrap_err(),
+            "numeric overflow: capacity 100000000000000000000 is too large"
+        );
+        for _ in 0..1100 {
+            builder.add_edge("s".to_owned(), "t".to_owned(), 2f64.powi(53), 0.).unwrap();
+        }
+        assert_eq!(
+            builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap_err(),
+            "numeric overflow: total capacity is too large"
+        );
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2f64.powi(40), 1e6).unwrap();
+        builder.set_cost_precision(Some(9));
+        assert!(builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).is_err());
+        builder.set_cost_precision(Some(0));
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), Some(2f64.powi(40) * 1e6));
+    }
+
+    #[test]
+    fn parallel_edges() {
+        let mut builder = GraphBuilder::new();
+        let cheap = builder.add_edge("a".to_owned(), "b".to_owned(), 2., 1.).unwrap();
+        let expensive = builder.add_edge("a".to_owned(), "b".to_owned(), 10., 5.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 5., 0.).unwrap();
+        assert_ne!(cheap, expensive);
+        let solution = builder.solve_min_cost_flow("a".to_owned(), "c".to_owned(), 5., Default::default(), Default::default()).unwrap();
+        let mut path_edges: Vec<_> = solution.paths.iter().map(|p| (p.flow, p.edges.clone())).collect();
+        path_edges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(path_edges, vec![(2.0, vec![cheap, 2]), (3.0, vec![expensive, 2])]);
+
+        builder.set_supply("a".to_owned(), 5.).unwrap();
+        builder.set_supply("c".to_owned(), -5.).unwrap();
+        let solution = builder.solve_b_flow(Default::default()).unwrap();
+        let edge_flows: Vec<_> = solution.edge_flows.iter().map(|f| (f.edge, f.flow)).collect();
+        assert_eq!(edge_flows, vec![(cheap, 2.0), (expensive, 3.0), (2, 5.0)]);
+    }
+
+    #[test]
+    fn algorithms() {
+        let algorithms = [
+            MinCostFlowAlgorithm::NetworkSimplex,
+            MinCostFlowAlgorithm::SuccessiveShortestPaths,
+            MinCostFlowAlgorithm::CostScaling,
+            MinCostFlowAlgorithm::CycleCanceling,
+        ];
+        let mut seed = 1u64;
+        let mut random = |n: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        for _ in 0..50 {
+            let mut builder = IndexedGraphBuilder::new();
+            let n = 2 + random(6) as u32;
+            builder.add_nodes(n);
+            for _ in 0..n as u64 + random(15) {
+                let (from, to) = (random(n as u64) as usize, random(n as u64) as usize);
+                let lower = if random(4) == 0 { random(3) as f64 } else { 0. };
+                let cost = random(21) as f64 - 5.;
+                builder.add_edge_with_bounds(from, to, lower, lower + 1. + random(10) as f64, cost).unwrap();
+            }
+            let amount = random(8) as f64;
+            builder.set_supply(0, amount).unwrap();
+            builder.set_supply(n as usize - 1, -amount).unwrap();
+            let results: Vec<_> = algorithms.iter().map(|&algorithm| {
+                builder.solve_b_flow(algorithm).map(|solution| solution.exact_total_cost)
+            }).collect();
+            assert!(results.iter().all(|result| *result == results[0]), "{:?}", results);
+            let results: Vec<_> = algorithms.iter().map(|&algorithm| {
+                let solution = builder.solve_mcmf(0, n as usize - 1, Default::default(), algorithm, Default::default());
+                solution.map(|solution| (solution.max_flow, solution.exact_total_cost))
+            }).collect();
+            assert!(results.iter().all(|result| *result == results[0]), "{:?}", results);
+            let results: Vec<_> = [MaxFlowAlgorithm::Dinic, MaxFlowAlgorithm::PushRelabel].iter().map(|&algorithm| {
+                let solution = builder.solve_max_flow(0, n as usize - 1, Default::default(), algorithm);
+                solution.map(|solution| solution.max_flow)
+            }).collect();
+            assert_eq!(results[0], results[1]);
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let mut seed = 7u64;
+        let mut random = |n: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        let builder = IndexedGraphBuilder::new();
+        assert_eq!(builder.resolve().unwrap_err(), "nothing to resolve, solve_mcmf has not been called");
+        for _ in 0..50 {
+            let mut builder = IndexedGraphBuilder::new();
+            let n = 2 + random(6) as u32;
+            builder.add_nodes(n);
+            for _ in 0..n as u64 + random(15) {
+                let (from, to) = (random(n as u64) as usize, random(n as u64) as usize);
+                let (capacity, cost) = (1. + random(10) as f64, random(10) as f64);
+                if random(5) == 0 {
+                    builder.add_undirected_edge(from, to, capacity, cost).unwrap();
+                } else {
+                    builder.add_edge_with_bounds(from, to, 0., capacity, cost).unwrap();
+                }
+            }
+            let (source, sink) = (0, n as usize - 1);
+            builder.solve_mcmf(source, sink, Default::default(), Default::default(), Default::default()).unwrap();
+            for _ in 0..3 {
+                let edge = random(builder.edges.len() as u64) as usize;
+                match random(4) {
+                    0 => builder.edges[edge].capacity = 1 + random(10) as i64,
+                    1 => builder.edges[edge].cost = random(10) as f64,
+                    2 => builder.set_node_capacity(random(n as u64) as usize, 1. + random(10) as f64).unwrap(),
+                    _ => { builder.add_edge_with_bounds(source, sink, 0., 1. + random(5) as f64, random(10) as f64).unwrap(); }
+                }
+                let expected = builder.clone()
+                    .solve_mcmf(source, sink, Default::default(), Default::default(), Default::default())
+                    .unwrap();
+                let solution = builder.resolve().unwrap();
+                assert_eq!(
+                    (solution.max_flow, solution.exact_total_cost),
+                    (expected.max_flow, expected.exact_total_cost)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn edge_updates() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        builder.add_undirected_edge("s".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default(),
+        ).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (4.0, Some(9.0)));
+        builder.graph.update_capacity(0, 1.).unwrap();
+        builder.graph.update_cost(2, 2.).unwrap();
+        let solution = builder.graph.resolve().unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (3.0, Some(4.0)));
+        builder.graph.remove_edge(3).unwrap();
+        builder.graph.remove_edge(0).unwrap();
+        let solution = builder.graph.resolve().unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (1.0, Some(2.0)));
+        assert_eq!(solution.edge_flows.len(), 2);
+
+        assert_eq!(builder.graph.update_capacity(2, 1.).unwrap_err(), "edge 2 out of range");
+        assert_eq!(builder.graph.update_capacity(0, -1.).unwrap_err(), McmfError::NegativeCapacity(-1.));
+        builder.add_undirected_edge("a".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        assert_eq!(builder.graph.update_cost(2, -1.).unwrap_err(), "undirected edge cost must be non-negative");
+    }
+
+    #[test]
+    fn inspection() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.5).unwrap();
+        builder.add_undirected_edge("t".to_owned(), "s".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 3., 0.).unwrap();
+        assert_eq!((builder.node_count(), builder.edge_count()), (3, 3));
+        assert!(builder.has_node("a") && !builder.has_node("b"));
+        assert_eq!(builder.graph.node_names, ["s", "a", "t"]);
+        let edges: Vec<_> = builder.out_edges("s".to_owned()).unwrap().iter().map(|edge| edge.id()).collect();
+        assert_eq!(edges, [0, 1]);
+        let edge = &builder.out_edges("a".to_owned()).unwrap()[0];
+        assert_eq!((edge.id(), edge.from(), edge.to(), edge.capacity(), edge.undirected()), (2, "a", "t", 3., false));
+        assert_eq!(builder.out_edges("b".to_owned()).unwrap_err(), "node 'b' not found");
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        let snapshot = builder.clone();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 0.).unwrap();
+        builder.graph.update_capacity(0, 5.).unwrap();
+        assert_eq!(snapshot.graph.node_names, ["s", "t"]);
+        assert_eq!(snapshot.graph.edges_info(|_| true)[0].capacity(), 2.);
+        builder.clone_from(&snapshot);
+        assert!(!builder.has_node("a"));
+        let solution = builder
+            .solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+            .unwrap();
+        assert_eq!((solution.max_flow, solution.total_cost), (2., Some(2.)));
+    }
+
+    #[test]
+    fn clear() {
+        let mut builder = GraphBuilder::new();
+        builder.set_iteration_limit(Some(100));
+        for i in 0..100 {
+            builder.add_edge(i.to_string(), (i + 1).to_string(), 1., 0.).unwrap();
+        }
+        builder
+            .solve_mcmf("0".to_owned(), "100".to_owned(), Default::default(), Default::default(), Default::default())
+            .unwrap();
+        let capacity = (builder.node_indices.capacity(), builder.graph.edges.capacity());
+        builder.clear();
+        assert_eq!((builder.node_count(), builder.edge_count()), (0, 0));
+        assert_eq!((builder.node_indices.capacity(), builder.graph.edges.capacity()), capacity);
+        assert_eq!(builder.graph.iteration_limit, Some(100));
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        assert_eq!(builder.graph.node_names, ["s", "t"]);
+        assert!(builder.graph.resolve().is_err());
+    }
+
+    #[test]
+    fn potentials() {
+        let mut builder = GraphBuilder::new();
+        for (from, to, capacity, cost) in [("s", "a", 2., 1.5), ("s", "b", 2., 0.), ("a", "t", 3., 1.), ("b", "a", 1., 0.5), ("b", "t", 1., 4.)] {
+            builder.add_edge(from.to_owned(), to.to_owned(), capacity, cost).unwrap();
+        }
+        for algorithm in [
+            MinCostFlowAlgorithm::NetworkSimplex,
+            MinCostFlowAlgorithm::SuccessiveShortestPaths,
+            MinCostFlowAlgorithm::CostScaling,
+            MinCostFlowAlgorithm::CycleCanceling,
+        ] {
+            let solution = builder
+                .solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), algorithm, Default::default())
+                .unwrap();
+            let potentials: HashMap<_, _> = solution.potentials.unwrap().into_iter().collect();
+            assert_eq!(potentials["s"], 0.);
+            for flow in &solution.edge_flows {
+                let reduced_cost = flow.cost + potentials[&flow.from] - potentials[&flow.to];
+                assert!(flow.flow == flow.capacity || reduced_cost >= 0., "{:?}", algorithm);
+                assert!(flow.flow == 0. || reduced_cost <= 0., "{:?}", algorithm);
+            }
+        }
+        builder.set_iteration_limit(Some(1));
+        builder.set_partial_results(true);
+        let solution = builder
+            .solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+            .unwrap();
+        assert!(solution.limit_reached && solution.potentials.is_none());
+        assert_eq!(solution.reduced_cost(0).unwrap(), None);
+    }
+
+    #[test]
+    fn reduced_costs() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 2.).unwrap();
+        let solution = builder
+            .solve_min_cost_flow("s".to_owned(), "t".to_owned(), 1., Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(solution.reduced_cost(0).unwrap(), Some(0.));
+        // The unused route costs 2 more per unit than the direct edge.
+        let detour = solution.reduced_cost(1).unwrap().unwrap() + solution.reduced_cost(2).unwrap().unwrap();
+        assert_eq!(detour, 2.);
+        assert_eq!(solution.reduced_cost(3).unwrap_err(), "edge 3 out of range");
+    }
+
+    #[test]
+    fn sensitivity_ranges() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 2.).unwrap();
+        builder.add_undirected_edge("a".to_owned(), "b".to_owned(), 1., 0.).unwrap();
+        let solution = builder
+            .solve_min_cost_flow("s".to_owned(), "t".to_owned(), 1., Default::default(), Default::default())
+            .unwrap();
+        let sensitivity = solution.sensitivity.as_ref().unwrap();
+        let cost_range = |edge| sensitivity.cost_range(edge).unwrap().unwrap();
+        // The direct edge is used until it gets more expensive than the detour.
+        assert_eq!(cost_range(0), (f64::NEG_INFINITY, 3.));
+        assert_eq!(cost_range(1), (-1., f64::INFINITY));
+        assert_eq!(sensitivity.capacity_range(0).unwrap(), Some((1., f64::INFINITY)));
+        assert_eq!(sensitivity.cost_range(3).unwrap_err(), "cost ranges are not supported for undirected edges");
+        assert_eq!(sensitivity.cost_range(4).unwrap_err(), "edge 4 out of range");
+        // The direct edge still has room for a second unit.
+        assert_eq!(solution.marginal_cost(), Some(1.));
+
+        builder.graph.update_capacity(0, 1.).unwrap();
+        let solution = builder
+            .solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+            .unwrap();
+        // More capacity on the direct edge would take flow off the detour.
+        let sensitivity = solution.sensitivity.as_ref().unwrap();
+        assert_eq!(sensitivity.capacity_range(0).unwrap(), Some((1., 1.)));
+        assert_eq!(sensitivity.capacity_range(2).unwrap(), Some((1., f64::INFINITY)));
+        assert_eq!(solution.marginal_cost(), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn bottleneck_edges() {
+        let mut builder = GraphBuilder::new();
+        // Widening `s -> a` needs `a -> t` widened too, `b -> t` alone is enough.
+        for (from, to, capacity) in [("s", "a", 1.), ("a", "t", 1.), ("s", "b", 2.), ("b", "t", 1.)] {
+            builder.add_edge(from.to_owned(), to.to_owned(), capacity, 0.).unwrap();
+        }
+        let solution = builder
+            .solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(solution.min_cut.as_ref().unwrap().edges, [0, 3]);
+        assert_eq!(solution.bottleneck_edges(), Some(vec![3]));
+        let solution = builder
+            .solve_min_cost_flow("s".to_owned(), "t".to_owned(), 1., Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(solution.bottleneck_edges(), None);
+    }
+
+    #[test]
+    fn alternative_solutions() {
+        let mut builder = GraphBuilder::new();
+        for (from, to) in [("s", "a"), ("a", "t"), ("s", "b"), ("b", "t")] {
+            builder.add_edge(from.to_owned(), to.to_owned(), 1., 1.).unwrap();
+        }
+        let solution = builder
+            .solve_min_cost_flow("s".to_owned(), "t".to_owned(), 1., Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(solution.is_unique(), Some(false));
+        let flows = |edge_flows: &[EdgeFlow]| edge_flows.iter().map(|flow| flow.flow).collect::<Vec<_>>();
+        let alternative = solution.alternative_solution().unwrap();
+        assert_eq!(alternative.total_cost, 2.);
+        let (old, new) = (flows(&solution.edge_flows), flows(&alternative.edge_flows));
+        assert_ne!(old, new);
+        assert_eq!(old.iter().zip(&new).map(|(a, b)| a + b).collect::<Vec<_>>(), [1., 1., 1., 1.]);
+
+        let solution = builder
+            .solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+            .unwrap();
+        assert_eq!(solution.is_unique(), Some(true));
+        assert!(solution.alternative_solution().is_none());
+    }
+
+    #[test]
+    fn verify_flow() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let verify = |flows: &[f64]| builder.verify_flow("s".to_owned(), "t".to_owned(), flows).unwrap();
+        let verification = verify(&[2., 2., 1.]);
+        assert!(verification.feasible());
+        assert_eq!((verification.flow_value, verification.total_cost), (3., 9.));
+        assert_eq!((verification.is_maximum, verification.is_optimal), (Some(true), Some(true)));
+        let verification = verify(&[1., 1., 1.]);
+        assert_eq!((verification.is_maximum, verification.is_optimal), (Some(false), Some(false)));
+        let verification = verify(&[3., 2., 0.]);
+        assert_eq!(verification.violations, [
+            "edge 0: flow 3 exceeds capacity 2",
+            "node 'a': inflow 3 does not match outflow 2",
+        ]);
+        assert_eq!(verification.is_optimal, None);
+        assert_eq!(
+            builder.verify_flow("s".to_owned(), "t".to_owned(), &[1.]).unwrap_err(),
+            "expected 3 edge flows, got 1"
+        );
+    }
+
+    #[test]
+    fn same_source_and_sink() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.set_node_capacity("s".to_owned(), 1.).unwrap();
+        let error = "source and sink must differ, both are 's'";
+        for trace in [false, true] {
+            builder.set_trace(trace);
+            let mcmf = builder.solve_mcmf(
+                "s".to_owned(), "s".to_owned(), Default::default(), Default::default(), Default::default()
+            );
+            assert_eq!(mcmf.unwrap_err(), error);
+            for algorithm in [MaxFlowAlgorithm::Dinic, MaxFlowAlgorithm::PushRelabel] {
+                let max_flow = builder.solve_max_flow("s".to_owned(), "s".to_owned(), Default::default(), algorithm);
+                assert_eq!(max_flow.unwrap_err(), error);
+            }
+        }
+        let min_cost =
+            builder.solve_min_cost_flow("s".to_owned(), "s".to_owned(), 1., Default::default(), Default::default());
+        assert_eq!(min_cost.unwrap_err(), error);
+        assert_eq!(builder.diagnose_flow("s".to_owned(), "s".to_owned(), 1.).unwrap_err(), error);
+    }
+
+    #[test]
+    fn diagnose_flow() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 5., 1.).unwrap();
+        let diagnosis = builder.diagnose_flow("s".to_owned(), "t".to_owned(), 3.).unwrap();
+        assert!(!diagnosis.feasible());
+        assert_eq!((diagnosis.max_flow, diagnosis.shortfall, diagnosis.unreachable), (2., 1., vec!["b".to_owned()]));
+        assert_eq!((diagnosis.cut.edges, diagnosis.cut.source_side), (vec![0], vec!["s".to_owned()]));
+        let diagnosis = builder.diagnose_flow("s".to_owned(), "b".to_owned(), 1.).unwrap();
+        assert_eq!((diagnosis.max_flow, diagnosis.shortfall), (0., 1.));
+        assert!(diagnosis.unreachable.contains(&"b".to_owned()));
+        assert!(builder.diagnose_flow("s".to_owned(), "t".to_owned(), 2.).unwrap().feasible());
+    }
+
+    #[test]
+    fn validate() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 0.0001).unwrap();
+        builder.add_edge("a".to_owned(), "a".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("t".to_owned(), "b".to_owned(), 1., 0.).unwrap();
+        builder.add_nodes(vec!["c".to_owned()]).unwrap();
+        builder.add_edge("t".to_owned(), "a".to_owned(), 0., 1.).unwrap();
+        assert_eq!(builder.validate().unwrap(), [
+            "node 'c' is isolated",
+            "edge 1 is parallel to edge 0",
+            "edge 1: cost 0.0001 is rounded to 0, increase cost precision",
+            "edge 2 is a self-loop at 'a'",
+            "edge 4 has zero capacity",
+        ]);
+        builder.set_source(Some("s".to_owned()));
+        builder.set_sink(Some("t".to_owned()));
+        let warnings = builder.validate().unwrap();
+        assert_eq!(warnings[..3], [
+            "node 'a' is not on any path from 's' to 't'",
+            "node 'b' is not on any path from 's' to 't'",
+            "node 'c' is isolated",
+        ]);
+    }
+
+    #[test]
+    fn pruning() {
+        let mut builder = GraphBuilder::new();
+        for (from, to, capacity, cost) in [
+            ("x", "s", 5., 1.), ("s", "a", 2., 1.), ("a", "t", 2., 1.),
+            ("a", "y", 3., -1.), ("s", "t", 1., 3.), ("t", "z", 1., 0.),
+        ] {
+            builder.add_edge(from.to_owned(), to.to_owned(), capacity, cost).unwrap();
+        }
+        let solve = |builder: &GraphBuilder| builder
+            .solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+            .unwrap();
+        let expected = solve(&builder);
+        builder.set_pruning(true);
+        let solution = solve(&builder);
+        assert_eq!((solution.stats.num_nodes, solution.stats.num_edges), (6, 3));
+        assert_eq!(solution.to_json(), expected.to_json());
+        assert_eq!(solution.scaled_edge_flows, [0, 2, 2, 0, 1, 0]);
+        let max_flow = builder
+            .solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default())
+            .unwrap();
+        assert_eq!((max_flow.max_flow, max_flow.edge_flows.len()), (3., 6));
+    }
+
+    #[test]
+    fn chain_contraction() {
+        let mut builder = GraphBuilder::new();
+        for (from, to, capacity, cost) in [
+            ("s", "a", 3., 1.), ("a", "b", 2., 1.), ("b", "t", 4., 1.),
+            ("s", "c", 2., 1.), ("c", "t", 2., 5.), ("c", "b", 1., 0.),
+        ] {
+            builder.add_edge(from.to_owned(), to.to_owned(), capacity, cost).unwrap();
+        }
+        let solve = |builder: &GraphBuilder| builder
+            .solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+            .unwrap();
+        let expected = solve(&builder);
+        builder.set_chain_contraction(true);
+        let solution = solve(&builder);
+        // Only `a` qualifies: `b` and `c` have two edges on one side.
+        assert_eq!(solution.stats.num_edges, 5);
+        assert_eq!(solution.to_json(), expected.to_json());
+        assert_eq!(solution.paths[0].nodes, ["s", "a", "b", "t"]);
+    }
+
+    #[test]
+    fn assignment() {
+        let costs = [vec![4., 1., 3.], vec![2., 0., 5.], vec![3., 2., 2.]];
+        let assignment = solve_assignment(&costs).unwrap();
+        assert_eq!(assignment.pairs(), [(0, 1), (1, 0), (2, 2)]);
+        assert_eq!(assignment.total_cost(), 5.);
+        // More rows than columns: the cheapest rows get the columns.
+        let assignment = solve_assignment(&[vec![1.5, 9.], vec![-1., 7.], vec![3., 0.25]]).unwrap();
+        assert_eq!(assignment.pairs(), [(1, 0), (2, 1)]);
+        assert_eq!((assignment.column(0), assignment.total_cost()), (None, -0.75));
+        assert_eq!(solve_assignment(&[]).unwrap().pairs(), []);
+        assert_eq!(solve_assignment(&[vec![1., 2.], vec![3.]]).unwrap_err(), "row 1 has 1 costs, expected 2");
+        assert_eq!(solve_assignment(&[vec![f64::NAN]]).unwrap_err(), "cost at row 0, column 0 is not finite");
+    }
+
+    #[test]
+    fn transportation() {
+        let costs = [vec![2., 3., 1.], vec![5., 4., 8.]];
+        let transportation = solve_transportation(&[20., 30.], &[10., 25., 15.], &costs).unwrap();
+        assert_eq!(transportation.shipments(), [vec![5., 0., 15.], vec![5., 25., 0.]]);
+        assert_eq!(transportation.total_cost(), 150.);
+        // Surplus supply stays with the supplier that is most expensive to ship from.
+        let transportation = solve_transportation(&[20., 30.], &[10., 20., 5.], &costs).unwrap();
+        assert_eq!(transportation.shipments(), [vec![10., 5., 5.], vec![0., 15., 0.]]);
+        assert_eq!(transportation.total_cost(), 100.);
+        // Missing demand is left where it's costliest to cover.
+        let transportation = solve_transportation(&[10., 10.], &[10., 25., 15.], &costs).unwrap();
+        assert_eq!(transportation.shipments(), [vec![0., 0., 10.], vec![0., 10., 0.]]);
+        assert_eq!(
+            solve_transportation(&[1.], &[1.], &[vec![1.], vec![2.]]).unwrap_err(),
+            "expected 1 rows of costs, got 2"
+        );
+        assert_eq!(
+            solve_transportation(&[1.], &[-1.], &[vec![1.]]).unwrap_err(),
+            "supplies and demands must be non-negative, got -1"
+        );
+    }
+
+    #[test]
+    fn bipartite_matching() {
+        let names = |names: &[&str]| names.iter().map(|&name| name.to_owned()).collect::<Vec<_>>();
+        let edges = |edges: &[(&str, &str)]| {
+            edges.iter().map(|&(a, b)| (a.to_owned(), b.to_owned())).collect::<Vec<_>>()
+        };
+        let (left, right) = (names(&["ann", "bob", "cid"]), names(&["x", "y", "ann"]));
+        // A greedy matching of ann with x would leave bob unmatched.
+        let matching = solve_bipartite_matching(
+            &left, &right, &edges(&[("ann", "x"), ("ann", "y"), ("bob", "x"), ("cid", "x")])
+        ).unwrap();
+        assert_eq!(matching.pairs(), edges(&[("ann", "y"), ("bob", "x")]));
+        // Names are per side, so "ann" on the right is a different node.
+        let matching = solve_bipartite_matching(&left, &right, &edges(&[("ann", "ann")])).unwrap();
+        assert_eq!(matching.size(), 1);
+        assert_eq!(
+            solve_bipartite_matching(&left, &right, &edges(&[("x", "y")])).unwrap_err(),
+            "unknown left node: x"
+        );
+        assert_eq!(
+            solve_bipartite_matching(&names(&["a", "a"]), &[], &[]).unwrap_err(),
+            "duplicate left node: a"
+        );
+    }
+
+    #[test]
+    fn min_cost_matching() {
+        let inf = f64::INFINITY;
+        let costs = [vec![1., 2., inf], vec![inf, 4., inf], vec![3., inf, 0.]];
+        let matching = solve_min_cost_matching(&costs, false).unwrap();
+        assert_eq!(matching.pairs(), [(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(matching.total_cost(), 5.);
+        // Rows 0 and 1 can only take column 1.
+        let costs = [vec![inf, 2., inf], vec![inf, 1., inf], vec![3., inf, 0.]];
+        assert_eq!(solve_min_cost_matching(&costs, false).unwrap_err(), "forbidden pairs leave at most 2 of 3 matched");
+        let matching = solve_min_cost_matching(&costs, true).unwrap();
+        assert_eq!(matching.pairs(), [(1, 1), (2, 2)]);
+        assert_eq!(matching.total_cost(), 1.);
+        assert_eq!(
+            solve_min_cost_matching(&[vec![-inf]], true).unwrap_err(),
+            "cost at row 0, column 0 is not finite"
+        );
+    }
+
+    #[test]
+    fn edge_disjoint_paths() {
+        let mut builder = GraphBuilder::new();
+        let edges = [("s", "a", 1.), ("a", "t", 1.), ("s", "b", 5.), ("b", "t", 5.), ("s", "t", 3.), ("a", "b", 0.)];
+        for (from, to, cost) in edges {
+            builder.add_edge(from.to_owned(), to.to_owned(), 10., cost).unwrap();
+        }
+        let nodes = |paths: &[Path]| paths.iter().map(|path| path.nodes().join("")).collect::<Vec<_>>();
+        let paths = builder.edge_disjoint_paths("s".to_owned(), "t".to_owned(), None).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert!(paths.iter().all(|path| path.flow() == 1.));
+        let paths = builder.edge_disjoint_paths("s".to_owned(), "t".to_owned(), Some(2)).unwrap();
+        assert_eq!(nodes(&paths), ["sat", "st"]);
+        assert_eq!(builder.edge_disjoint_paths("s".to_owned(), "t".to_owned(), Some(5)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn vertex_disjoint_paths() {
+        let mut builder = GraphBuilder::new();
+        for (from, to) in [("s", "a"), ("s", "b"), ("a", "c"), ("b", "c"), ("c", "t"), ("c", "t")] {
+            builder.add_edge(from.to_owned(), to.to_owned(), 5., 0.).unwrap();
+        }
+        builder.set_node_capacity("c".to_owned(), 10.).unwrap();
+        // Both edge-disjoint paths go through c.
+        assert_eq!(builder.edge_disjoint_paths("s".to_owned(), "t".to_owned(), None).unwrap().len(), 2);
+        assert_eq!(builder.vertex_disjoint_paths("s".to_owned(), "t".to_owned()).unwrap().connectivity(), 1);
+        builder.add_edge("a".to_owned(), "t".to_owned(), 5., 0.).unwrap();
+        let disjoint = builder.vertex_disjoint_paths("s".to_owned(), "t".to_owned()).unwrap();
+        assert_eq!(disjoint.connectivity(), 2);
+        let mut nodes: Vec<_> = disjoint.paths().iter().map(|path| path.nodes().join("")).collect();
+        nodes.sort();
+        assert_eq!(nodes, ["sat", "sbct"]);
+    }
+
+    #[test]
+    fn gomory_hu_tree() {
+        let mut builder = GraphBuilder::new();
+        let edges = [
+            ("a", "b", 1.), ("a", "c", 7.), ("b", "c", 1.), ("b", "d", 3.), ("b", "e", 2.), ("c", "e", 4.),
+            ("d", "e", 1.), ("d", "f", 6.), ("e", "f", 2.),
+        ];
+        for (from, to, capacity) in edges {
+            builder.add_edge(from.to_owned(), to.to_owned(), capacity, 0.).unwrap();
+        }
+        let tree = builder.gomory_hu_tree().unwrap();
+        assert_eq!(tree.edges().len(), 5);
+        let mut undirected = GraphBuilder::new();
+        for (from, to, capacity) in edges {
+            undirected.add_undirected_edge(from.to_owned(), to.to_owned(), capacity, 0.).unwrap();
+        }
+        let nodes = ["a", "b", "c", "d", "e", "f"];
+        for (a, b) in nodes.iter().flat_map(|&a| nodes.iter().map(move |&b| (a, b))).filter(|(a, b)| a != b) {
+            let flow = undirected.solve_max_flow(a.to_owned(), b.to_owned(), Default::default(), Default::default());
+            assert_eq!(tree.min_cut_between(a, b).unwrap(), flow.unwrap().max_flow());
+        }
+        assert_eq!(tree.min_cut_between("a", "a").unwrap_err(), "a min cut needs two different nodes");
+        assert_eq!(tree.min_cut_between("a", "z").unwrap_err(), "node 'z' not found");
+
+        let mut unbounded = GraphBuilder::new();
+        unbounded.add_edge("a".to_owned(), "b".to_owned(), f64::INFINITY, 0.).unwrap();
+        unbounded.add_edge("b".to_owned(), "c".to_owned(), 2., 0.).unwrap();
+        let tree = unbounded.gomory_hu_tree().unwrap();
+        assert_eq!(tree.min_cut_between("a", "b").unwrap(), f64::INFINITY);
+        assert_eq!(tree.min_cut_between("a", "c").unwrap(), 2.);
+    }
+
+    #[test]
+    fn max_flow_with_budget() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 3., 2.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 5., 4.).unwrap();
+        let solve = |budget| builder.solve_max_flow_with_budget(
+            "s".to_owned(), "t".to_owned(), budget, Default::default(), Default::default()
+        );
+        let solution = solve(100.).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (8., Some(31.)));
+        // 3 units at 2, then 2 units at 5.
+        let solution = solve(17.).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (5., Some(16.)));
+        assert_eq!(solve(1.).unwrap().max_flow(), 0.);
+        assert_eq!(solve(-1.).unwrap_err(), "budget must be non-negative");
+        builder.add_edge_with_bounds("a".to_owned(), "t".to_owned(), 1., 2., 1.).unwrap();
+        let solve = |budget| builder.solve_max_flow_with_budget(
+            "s".to_owned(), "t".to_owned(), budget, Default::default(), Default::default()
+        );
+        assert_eq!(solve(1.).unwrap_err(), "max flow with a budget doesn't support lower bounds");
+        assert_eq!(solve(100.).unwrap().max_flow(), 8.);
+    }
+
+    #[test]
+    fn max_paths() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 3., 1.).unwrap();
+        builder.set_max_paths(Some(2));
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (8., Some(16.)));
+        let paths: Vec<_> = solution.paths().iter().map(|path| (path.flow(), path.nodes().join(" "))).collect();
+        assert_eq!(paths, vec![(5., "s a t".to_owned()), (3., "s b t".to_owned())]);
+        assert_eq!(solution.edge_flows().iter().map(|edge| edge.flow()).collect::<Vec<_>>(), vec![0., 5., 5., 3., 3.]);
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default());
+        assert_eq!(solution.unwrap().max_flow(), 8.);
+        builder.set_max_paths(Some(3));
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default());
+        assert_eq!(solution.unwrap().max_flow(), 9.);
+    }
+
+    #[test]
+    fn residual_edges() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 2.).unwrap();
+        builder.set_node_capacity("a".to_owned(), 5.).unwrap();
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        let residual: Vec<_> = solution.residual_edges().iter()
+            .map(|arc| (arc.edge(), arc.from(), arc.to(), arc.capacity(), arc.cost(), arc.reverse()))
+            .collect();
+        assert_eq!(residual, vec![
+            (Some(0), "s", "a", 1., 1., false),
+            (Some(0), "a", "s", 2., -1., true),
+            (Some(1), "t", "a", 2., -2., true),
+            (None, "a", "a", 3., 0., false),
+            (None, "a", "a", 2., 0., true),
+        ]);
+    }
+
+    #[test]
+    fn split_antiparallel_edges() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "a".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        builder.set_split_antiparallel_edges(true);
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (1., Some(1.)));
+        assert_eq!(solution.paths()[0].nodes(), ["s", "a", "b", "t"]);
+        let flows: Vec<_> = solution.edge_flows().iter().map(|flow| flow.flow()).collect();
+        assert_eq!(flows, [1., 0., 1., 1.]);
+        let residual: Vec<_> = solution.residual_edges().iter().map(|arc| (arc.edge(), arc.from(), arc.to())).collect();
+        assert_eq!(residual, [
+            (Some(0), "a", "s"),
+            (Some(1), "b", "<middle of edge 1>"),
+            (Some(2), "b", "a"),
+            (Some(3), "t", "b"),
+            (None, "<middle of edge 1>", "a"),
+        ]);
+    }
+
+    #[test]
+    fn manual_max_flow() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        let mut exercise = builder.manual_max_flow("s".to_owned(), "t".to_owned()).unwrap();
+        let path = |nodes: &[&str]| nodes.iter().map(|&v| v.to_owned()).collect::<Vec<_>>();
+        let saturated = |e, from, to| {
+            format!("edge {} from '{}' to '{}' is saturated: it carries its full capacity of 1", e, from, to)
+        };
+        let step = exercise.augment(&path(&["s", "a", "b", "t"])).unwrap();
+        assert_eq!((step.flow(), step.arcs().len()), (1., 3));
+        assert_eq!(exercise.augment(&path(&["s", "a", "t"])).unwrap_err().to_string(), saturated(0, "s", "a"));
+        assert_eq!(
+            exercise.augment(&path(&["s", "b", "a", "s", "t"])).unwrap_err(), "an augmenting path can't visit 's' twice"
+        );
+        assert_eq!(exercise.augment(&path(&["s", "b", "t"])).unwrap_err().to_string(), saturated(4, "b", "t"));
+        assert!(!exercise.is_maximum());
+        let step = exercise.augment(&path(&["s", "b", "a", "t"])).unwrap();
+        assert!(step.arcs()[1].reverse());
+        assert_eq!(exercise.augment(&path(&["s", "b", "a", "t"])).unwrap_err().to_string(), saturated(1, "s", "b"));
+        assert_eq!((exercise.flow(), exercise.edge_flows()), (2., vec![1., 1., 0., 1., 1.]));
+        assert!(exercise.is_maximum());
+        assert_eq!(exercise.residual_edges().len(), 5);
+        assert_eq!(exercise.augment(&path(&["s", "t"])).unwrap_err(), "no edge joins 's' and 't'");
+    }
+
+    #[test]
+    fn exercise() {
+        for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+            let exercise = generate_exercise(difficulty, 7).unwrap();
+            let edges = exercise.builder().edges();
+            let cut_capacity: f64 = exercise.min_cut().edges().iter().map(|&e| edges[e as usize].capacity()).sum();
+            assert!(exercise.max_flow() > 0.);
+            assert_eq!(cut_capacity, exercise.max_flow());
+            let again = generate_exercise(difficulty, 7).unwrap();
+            assert_eq!((again.max_flow(), again.min_cost()), (exercise.max_flow(), exercise.min_cost()));
+        }
+        assert_eq!(generate_exercise(Difficulty::Easy, 1).unwrap().builder().edges().len(), 5);
+    }
+
+    #[test]
+    fn dinic_phases() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        builder.set_trace(true);
+        let solution = builder.solve_max_flow(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default()
+        ).unwrap();
+        let phases = solution.dinic_phases();
+        assert_eq!(phases.len(), 2);
+        let levels = |phase: &DinicPhase| phase.levels().iter()
+            .map(|(node, level)| format!("{}{}", node, level))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let arcs = |phase: &DinicPhase| phase.arcs().iter().map(|arc| arc.edge().unwrap()).collect::<Vec<_>>();
+        assert_eq!((levels(&phases[0]), arcs(&phases[0])), ("s0 a1 t1".to_owned(), vec![0, 2]));
+        assert_eq!((levels(&phases[1]), arcs(&phases[1])), ("s0 a1 t2".to_owned(), vec![0, 1]));
+        let paths: Vec<_> = phases.iter().map(|phase| (phase.first_path(), phase.path_count())).collect();
+        assert_eq!(paths, [(0, 1), (1, 1)]);
+        assert_eq!(solution.augmenting_paths()[1].nodes(), ["s", "a", "t"]);
+    }
+
+    #[test]
+    fn augmenting_paths() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let solve = |builder: &GraphBuilder| builder.solve_max_flow(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default()
+        ).unwrap();
+        assert!(solve(&builder).augmenting_paths().is_empty());
+        builder.set_trace(true);
+        let solution = solve(&builder);
+        let paths: Vec<_> = solution.augmenting_paths().iter()
+            .map(|path| (path.flow(), path.nodes().join(" ")))
+            .collect();
+        assert_eq!(paths, vec![(1., "s t".to_owned()), (2., "s a t".to_owned())]);
+        let arcs: Vec<_> = solution.augmenting_paths()[1].arcs().iter()
+            .map(|arc| (arc.edge(), arc.capacity(), arc.reverse()))
+            .collect();
+        assert_eq!(arcs, vec![(Some(0), 1., false), (Some(1), 0., false)]);
+    }
+
+    #[test]
+    fn benchmark() {
+        let builder = generate_random_graph(20, 60, 10, 10, 1).unwrap();
+        let benchmark = builder.benchmark(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), 5
+        ).unwrap();
+        assert_eq!(benchmark.repetitions(), 5);
+        for timing in [benchmark.network(), benchmark.max_flow(), benchmark.min_cost_flow(), benchmark.total()] {
+            assert!(0. <= timing.min_ms() && timing.min_ms() <= timing.median_ms());
+            assert!(timing.median_ms() <= timing.max_ms());
+        }
+        assert!(benchmark.max_flow().median_ms() <= benchmark.total().max_ms());
+        assert!(builder.benchmark("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), 0).is_err());
+    }
+
+    #[test]
+    fn path_costs() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1.).unwrap();
+        builder.add_edge_piecewise("a".to_owned(), "t".to_owned(), &[1., 2.], &[1., 3.]).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        let paths: Vec<_> = solution.paths().iter().map(|path| (path.flow(), path.cost(), path.length())).collect();
+        assert_eq!(paths, [(3., 10., 2), (1., 5., 1)]);
+        assert_eq!(solution.total_cost(), Some(15.));
+    }
+
+    #[test]
+    fn paths_sorted() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 4.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 3., 2.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 2., 2.).unwrap();
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        let nodes = |sorted: &SortedPaths| sorted.paths().iter().map(|path| path.nodes().join(" ")).collect::<Vec<_>>();
+        let by_flow = solution.paths_sorted(PathOrder::Flow, Some(2));
+        assert_eq!(nodes(&by_flow), ["s t", "s b t"]);
+        assert_eq!((by_flow.other_paths(), by_flow.other_flow(), by_flow.other_cost()), (1, 1., 5.));
+        let by_cost = solution.paths_sorted(PathOrder::Cost, None);
+        assert_eq!(nodes(&by_cost), ["s t", "s b t", "s a t"]);
+        assert_eq!((by_cost.other_paths(), by_cost.other_flow()), (0, 0.));
+        assert!(solution.paths_sorted(PathOrder::Cost, Some(0)).paths().is_empty());
+    }
+
+    #[test]
+    fn path_arrays() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        builder.set_path_node_names(false);
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        assert!(solution.paths().iter().all(|path| path.nodes().is_empty()));
+        let arrays = solution.path_arrays();
+        assert_eq!(arrays.flows(), [2., 1.]);
+        assert_eq!((arrays.node_offsets(), arrays.node_indices()), (&[0, 3, 5][..], &[0, 1, 2, 0, 2][..]));
+        assert_eq!((arrays.edge_offsets(), arrays.edges()), (&[0, 2, 3][..], &[0, 1, 2][..]));
+        assert_eq!(builder.nodes(), ["s", "a", "t"]);
+    }
+
+    #[test]
+    fn path_seed() {
+        let build = || {
+            let mut builder = GraphBuilder::new();
+            for v in ["a", "b", "c", "d"] {
+                builder.add_edge("s".to_owned(), v.to_owned(), 1., 0.).unwrap();
+                builder.add_edge(v.to_owned(), "t".to_owned(), 1., 0.).unwrap();
+            }
+            builder
+        };
+        let paths = |builder: &GraphBuilder| {
+            let solution = builder.solve_mcmf(
+                "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+            ).unwrap();
+            solution.paths().iter().map(|path| path.nodes().join(" ")).collect::<Vec<_>>()
+        };
+        let mut builder = build();
+        let unseeded = paths(&builder);
+        assert_eq!(unseeded, ["s a t", "s b t", "s c t", "s d t"]);
+        assert_eq!(paths(&build()), unseeded);
+        let seeded: Vec<_> = (0..10).map(|seed| {
+            builder.set_path_seed(Some(seed));
+            let seeded = paths(&builder);
+            assert_eq!(paths(&builder), seeded);
+            let mut sorted = seeded.clone();
+            sorted.sort();
+            assert_eq!(sorted, unseeded);
+            seeded
+        }).collect();
+        assert!(seeded.iter().any(|paths| *paths != unseeded));
+    }
+
+    #[test]
+    fn single_pass() {
+        let solve = |builder: &GraphBuilder, algorithm| {
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), algorithm, Default::default()).unwrap()
+        };
+        for seed in 0..5 {
+            let mut builder = generate_random_graph(12, 40, 10, 10, seed).unwrap();
+            builder.add_edge_with_bounds("s".to_owned(), "v1".to_owned(), 1., 2., 10.).unwrap();
+            let expected = solve(&builder, MinCostFlowAlgorithm::NetworkSimplex);
+            builder.set_single_pass(true);
+            for algorithm in [
+                MinCostFlowAlgorithm::NetworkSimplex, MinCostFlowAlgorithm::SuccessiveShortestPaths,
+                MinCostFlowAlgorithm::CostScaling, MinCostFlowAlgorithm::CycleCanceling,
+            ] {
+                let solution = solve(&builder, algorithm);
+                assert_eq!((solution.max_flow, solution.total_cost), (expected.max_flow, expected.total_cost));
+                assert_eq!(solution.stats.augmentations, None);
+            }
+        }
+    }
+
+    #[test]
+    fn storage_stats() {
+        let mut builder = GraphBuilder::new();
+        let empty = builder.storage_stats();
+        builder.add_edge("source".to_owned(), "sink".to_owned(), 1., 1.).unwrap();
+        let stats = builder.storage_stats();
+        assert!(stats.node_bytes() > empty.node_bytes());
+        assert!(stats.edge_bytes() > empty.edge_bytes());
+        assert!(stats.name_bytes() >= 2 * ("source".len() + "sink".len()));
+        assert_eq!(stats.total_bytes(), stats.node_bytes() + stats.edge_bytes() + stats.name_bytes());
+    }
+
+    #[test]
+    fn log_levels() {
+        assert_eq!(log_level(), LogLevel::Warn);
+        assert!(logging::enabled(LogLevel::Error) && !logging::enabled(LogLevel::Info));
+        set_log_level(LogLevel::Off);
+        assert!(!logging::enabled(LogLevel::Error) && !logging::enabled(LogLevel::Off));
+        set_log_level(LogLevel::Warn);
+    }
+
+    #[test]
+    fn error_variants() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 3.).unwrap();
+        let solve = |builder: &GraphBuilder, sink: &str| {
+            builder.solve_mcmf("s".to_owned(), sink.to_owned(), Default::default(), Default::default(), Default::default())
+        };
+        assert_eq!(solve(&builder, "x").unwrap_err(), McmfError::UnknownNode("x".to_owned()));
+        assert_eq!(builder.set_edge_metadata(1, None).unwrap_err(), McmfError::EdgeOutOfRange(1));
+        let token = CancellationToken::new();
+        token.cancel();
+        builder.set_cancellation_token(&token);
+        assert_eq!(solve(&builder, "t").unwrap_err(), McmfError::Cancelled);
+        builder.set_cancellation_token(&CancellationToken::new());
+        builder.set_iteration_limit(Some(0));
+        let error = solve(&builder, "t").unwrap_err();
+        assert!(matches!(error, McmfError::LimitReached(_)));
+        assert_eq!(String::from(error), "iteration limit of 0 reached");
+    }
+
+    #[test]
+    fn solution_from_json() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), f64::INFINITY, 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 2.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        builder.set_node_group("a".to_owned(), Some("hubs".to_owned())).unwrap();
+        builder.set_edge_metadata(1, Some("{\"line\": 7}".to_owned())).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        let loaded = McmfSolution::from_json(&solution.to_json()).unwrap();
+        assert_eq!(loaded.to_json(), solution.to_json());
+        assert_eq!(loaded.edge_flows()[0].capacity(), f64::INFINITY);
+        assert_eq!(loaded.group_flows()[0].inflow(), 2.);
+        assert_eq!(loaded.reduced_cost(2).unwrap(), Some(0.));
+        assert_eq!((loaded.marginal_cost(), loaded.cost_range(0).unwrap()), (None, None));
+        assert_eq!(loaded.cost_range(3).unwrap_err(), McmfError::EdgeOutOfRange(3));
+
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(McmfSolution::from_json(&solution.to_json()).unwrap().to_json(), solution.to_json());
+        assert_eq!(McmfSolution::from_json("[]").unwrap_err(), "expected a JSON object");
+        assert_eq!(McmfSolution::from_json(r#"{"maxFlow":1,"paths":[{"flow":1}]}"#).unwrap_err(), "path 0: missing 'cost'");
+
+        let load = |edge_to: &str, potentials: &str| McmfSolution::from_json(&format!(
+            r#"{{"maxFlow":1,"edgeFlows":[{{"edge":0,"from":"s","to":"{}","flow":1,"cost":3}}],"nodeFlows":[{{"node":"s","inflow":0,"outflow":1}},{{"node":"t","inflow":1,"outflow":0}}],"potentials":{}}}"#,
+            edge_to, potentials
+        ));
+        let loaded = load("t", r#"{"t":3,"s":0}"#).unwrap();
+        assert_eq!(loaded.potentials().unwrap(), [("s".to_owned(), 0.), ("t".to_owned(), 3.)]);
+        assert_eq!(loaded.reduced_cost(0).unwrap(), Some(0.));
+        assert_eq!(load("t", r#"{"s":0}"#).unwrap_err(), "potentials: missing 't'");
+        assert_eq!(load("t", r#"{"s":0,"x":1}"#).unwrap_err(), "potentials: no node flow for 'x'");
+        assert_eq!(load("x", "null").unwrap_err(), "edge flow 0: no node flow for 'x'");
+    }
+
+    #[test]
+    fn solution_diff() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let solve = |builder: &GraphBuilder| {
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default())
+        };
+        let before = solve(&builder).unwrap();
+        builder.graph.update_capacity(2, 0.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        builder.graph.update_capacity(1, 3.).unwrap();
+        let after = solve(&builder).unwrap();
+        let diff = before.diff(&after);
+        let changes: Vec<_> = diff.edge_changes().iter().map(|c| (c.edge(), c.old_flow(), c.new_flow())).collect();
+        assert_eq!(changes, [(2, 1., 0.), (3, 0., 1.)]);
+        let edges = |paths: &[Path]| paths.iter().map(|path| path.edges().to_vec()).collect::<Vec<_>>();
+        assert_eq!((edges(diff.added_paths()), edges(diff.removed_paths())), (vec![vec![3]], vec![vec![2]]));
+        assert_eq!((diff.flow_delta(), diff.cost_delta()), (0., Some(-4.)));
+        assert!(after.diff(&after).edge_changes().is_empty());
+    }
+
+    #[test]
+    fn scenarios() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let capacity = |edge, capacity| EdgeOverride { edge, capacity: Some(capacity), cost: None };
+        let scenarios = [
+            vec![],
+            vec![capacity(0, 1.)],
+            vec![capacity(2, 3.), EdgeOverride { edge: 2, capacity: None, cost: Some(1.) }],
+            vec![capacity(1, 0.)],
+        ];
+        let evaluate = |scenarios: &[Vec<EdgeOverride>]| {
+            builder.evaluate_scenarios("s".to_owned(), "t".to_owned(), scenarios, Default::default())
+        };
+        let results = evaluate(&scenarios).unwrap();
+        assert_eq!(results.max_flows(), [3., 2., 5., 1.]);
+        assert_eq!(results.total_costs(), [9., 7., 7., 5.]);
+        assert!(!results.limit_reached());
+        assert_eq!(evaluate(&[vec![capacity(3, 1.)]]).unwrap_err(), "scenario 0: edge 3 out of range");
+    }
+
+    #[test]
+    fn lp_export() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), f64::INFINITY, 2.).unwrap();
+        builder.set_supply("s".to_owned(), 2.).unwrap();
+        builder.set_demand("t".to_owned(), 2.).unwrap();
+        builder.set_node_capacity("a".to_owned(), 3.).unwrap();
+        assert_eq!(builder.to_lp().unwrap(), concat!(
+            "\\ x0: s -> a\n\\ x1: a -> t\n\\ y1: through a\n\\ n0: s\n\\ n1: a\n\\ n1_out: a\n\\ n2: t\n",
+            "Minimize\n obj: 1 x0 + 2 x1 + 0 y1\n",
+            "Subject To\n n0: 1 x0 = 2\n n1: -1 x0 + 1 y1 = 0\n n1_out: 1 x1 - 1 y1 = 0\n n2: -1 x1 = -2\n",
+            "Bounds\n 0 <= x0 <= 2\n x1 >= 0\n 0 <= y1 <= 3\nEnd\n",
+        ));
+        let mps = builder.to_mps().unwrap();
+        assert!(mps.contains("ROWS\n N obj\n E n0\n E n1\n E n1_out\n E n2\nCOLUMNS\n    x0 obj 1\n    x0 n0 1\n    x0 n1 -1\n"));
+        assert!(mps.ends_with("RHS\n    rhs n0 2\n    rhs n2 -2\nBOUNDS\n UP bnd x0 2\n UP bnd y1 3\nENDATA\n"));
+        assert_eq!(GraphBuilder::new().to_lp().unwrap_err(), "the graph has no edges");
+    }
+
+    #[test]
+    fn utilization_report() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 4., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), f64::INFINITY, 3.).unwrap();
+        let solution = builder.solve_min_cost_flow("s".to_owned(), "t".to_owned(), 5., Default::default(), Default::default()).unwrap();
+        let report: Vec<_> = solution.utilization_report().iter()
+            .map(|edge| (edge.edge(), edge.flow(), edge.utilization(), edge.saturated()))
+            .collect();
+        // Parallel edges are told apart, unlike when summing up paths by node pairs.
+        assert_eq!(report, [(1, 2., 1., true), (0, 3., 0.75, false), (2, 0., 0., false)]);
+    }
+
+    #[test]
+    fn node_throughput() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_nodes(vec!["c".to_owned()]).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.node_throughput(), [3., 2., 1., 3., 0.]);
+    }
+
+    #[test]
+    fn layout() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 10.).unwrap();
+        builder.add_edge("s".to_owned(), "s".to_owned(), 1., 1.).unwrap();
+        builder.add_nodes(vec!["b".to_owned(), "c".to_owned()]).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 1., 1.).unwrap();
+        let layout = builder.layout(200, true);
+        assert_eq!(layout, builder.layout(200, true));
+        assert!(layout.x().iter().chain(layout.y()).all(|p| p.is_finite()));
+        let distance = |u: usize, v: usize| (layout.x()[u] - layout.x()[v]).hypot(layout.y()[u] - layout.y()[v]);
+        // Expensive edges are longer, and nodes without an edge between them are pushed apart.
+        assert!(distance(0, 1) < distance(1, 2));
+        assert!(distance(3, 4) < distance(0, 3) && distance(3, 4) < distance(2, 4));
+        assert_eq!(builder.layout(0, false).x().len(), 5);
+    }
+
+    #[test]
+    fn animation_plan() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        let plan: Vec<_> = solution.animation_plan(1000.).unwrap().iter()
+            .map(|path| (solution.paths()[path.path() as usize].length(), path.node_times().to_vec()))
+            .collect();
+        // The longer path carries twice the flow, so it moves twice as fast and still starts last.
+        assert_eq!(plan, [(1, vec![0., 750.]), (2, vec![250., 625., 1000.])]);
+        for duration_ms in [0., -5., f64::NAN, f64::INFINITY] {
+            assert!(solution.animation_plan(duration_ms).is_err(), "{}", duration_ms);
+        }
+        assert_eq!(solution.animation_plan(-5.).unwrap_err(), "duration must be a positive number, got -5");
+    }
+
+    #[test]
+    fn project_selection() {
+        // Projects 0 and 1 both need the costly tool 2, which only pays off for both of them.
+        let selection = solve_project_selection(&[3., 4., -5., -1., 2.], &[(0, 2), (1, 2), (4, 3), (3, 3)]).unwrap();
+        assert_eq!((selection.projects(), selection.profit()), (&[0, 1, 2, 3, 4][..], 3.));
+        let selection = solve_project_selection(&[3., 1., -5.], &[(0, 2), (1, 2)]).unwrap();
+        assert_eq!((selection.projects(), selection.profit()), (&[][..], 0.));
+        assert_eq!(
+            solve_project_selection(&[1.], &[(0, 1)]).unwrap_err(),
+            "dependency 0 -> 1 refers to an unknown project"
+        );
+    }
+
+    #[test]
+    fn exact_edges() {
+        let big = (1 << 53) + 1;
+        let mut builder = GraphBuilder::new();
+        builder.set_cost_denominator(Some(1)).unwrap();
+        builder.add_edge_exact("s".to_owned(), "a".to_owned(), big, 0).unwrap();
+        builder.add_edge_exact("a".to_owned(), "t".to_owned(), big + 2, 3).unwrap();
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!((solution.exact_max_flow(), solution.exact_edge_flows()), (Some(big), &[big, big][..]));
+        assert_eq!(solution.exact_total_cost(), Some("27021597764222979"));
+        assert!(builder.add_edge("s".to_owned(), "t".to_owned(), (2 * big) as f64, 0.).is_err());
+        assert_eq!(
+            builder.add_edge_exact("s".to_owned(), "t".to_owned(), 1, big).unwrap_err(),
+            "numeric overflow: cost 9007199254740993 is too large"
+        );
+        assert_eq!(
+            builder.add_edge_exact("s".to_owned(), "t".to_owned(), i64::MAX, 0).unwrap_err(),
+            "numeric overflow: capacity 9223372036854775807 is too large"
+        );
+    }
+
+    #[test]
+    fn edge_handles() {
+        let mut builder = GraphBuilder::new();
+        let removed = builder.add_edge("s".to_owned(), "a".to_owned(), 1., 0.).unwrap();
+        let removed = builder.edge_handle(removed).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        let tracked = builder.add_edge("s".to_owned(), "t".to_owned(), 2., 0.).unwrap();
+        let tracked = builder.edge_handle(tracked).unwrap();
+        builder.remove_edge(0).unwrap();
+        assert_eq!(builder.edge_id(tracked).unwrap(), 1);
+        assert_eq!(builder.edge_id(removed).unwrap_err(), "edge handle 0 refers to no edge");
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.flow_on(tracked).unwrap(), 2.);
+        let added = builder.add_edge("s".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        assert_eq!(
+            solution.flow_on(builder.edge_handle(added).unwrap()).unwrap_err(),
+            "edge handle 3 refers to no edge of the solved graph"
+        );
+    }
+
+    #[test]
+    fn solved_network() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "c".to_owned(), 1., 5.).unwrap();
+        builder.set_iteration_limit(Some(10));
+        let mut network = builder.solved_network().unwrap();
+        // Each query gets the whole iteration limit.
+        for _ in 0..20 {
+            for (source, sink) in [("a", "c"), ("a", "b"), ("b", "c")] {
+                let solution = network.min_cost_max_flow(
+                    source, sink, Default::default(), Default::default(), Default::default()
+                ).unwrap();
+                let expected = builder.solve_mcmf(
+                    source.to_owned(), sink.to_owned(), Default::default(), Default::default(), Default::default()
+                ).unwrap();
+                assert_eq!((solution.max_flow(), solution.total_cost()), (expected.max_flow(), expected.total_cost()));
+                let solution = network.max_flow(source, sink, Default::default(), Default::default()).unwrap();
+                assert_eq!(solution.max_flow(), expected.max_flow());
+            }
+        }
+        assert_eq!(network.max_flow("a", "x", Default::default(), Default::default()).unwrap_err(), "node 'x' not found");
+    }
+
+    #[test]
+    fn all_pairs_max_flow() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("c".to_owned(), "a".to_owned(), 3., 0.).unwrap();
+        assert_eq!(builder.all_pairs_max_flow().unwrap(), [0., 2., 1., 1., 0., 1., 3., 2., 0.]);
+        assert_eq!(GraphBuilder::new().all_pairs_max_flow().unwrap(), []);
+
+        let mut unbounded = GraphBuilder::new();
+        unbounded.add_edge("a".to_owned(), "b".to_owned(), f64::INFINITY, 0.).unwrap();
+        unbounded.add_edge("b".to_owned(), "c".to_owned(), 2., 0.).unwrap();
+        assert_eq!(unbounded.all_pairs_max_flow().unwrap(), [0., f64::INFINITY, 2., 0., 0., 2., 0., 0., 0.]);
+    }
+
+    #[test]
+    fn reversed() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 3.).unwrap();
+        builder.set_source(Some("s".to_owned()));
+        builder.set_sink(Some("t".to_owned()));
+        builder.set_supply("s".to_owned(), 1.).unwrap();
+        builder.set_demand("t".to_owned(), 1.).unwrap();
+        let reversed = builder.reversed();
+        let edges = reversed.edges();
+        let edges: Vec<_> = edges.iter().map(|edge| (edge.from(), edge.to())).collect();
+        assert_eq!(edges, [("a", "s"), ("t", "a")]);
+        assert_eq!((reversed.source(), reversed.sink()), (Some("t"), Some("s")));
+        let solution = reversed.solve_b_flow(Default::default()).unwrap();
+        assert_eq!(solution.total_cost(), 4.);
+    }
+
+    #[test]
+    fn reachability() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "a".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 1., 0.).unwrap();
+        builder.add_undirected_edge("t".to_owned(), "u".to_owned(), 1., 0.).unwrap();
+        builder.add_nodes(vec!["x".to_owned()]).unwrap();
+        assert_eq!(builder.reachable_from("a".to_owned()).unwrap(), ["a", "b", "t", "u"]);
+        assert_eq!(builder.can_reach("a".to_owned()).unwrap(), ["s", "a", "b"]);
+        assert_eq!(builder.strongly_connected_components(), [vec!["x"], vec!["s"], vec!["a", "b"], vec!["t", "u"]]);
+        assert_eq!(builder.can_reach("y".to_owned()).unwrap_err(), "node 'y' not found");
+    }
+
+    #[test]
+    fn cross_validation() {
+        for seed in 0..10 {
+            let mut builder = generate_random_graph(12, 40, 10, 10, seed).unwrap();
+            builder.set_cross_validation(true);
+            builder.set_pruning(true);
+            for algorithm in [MinCostFlowAlgorithm::NetworkSimplex, MinCostFlowAlgorithm::CostScaling] {
+                for max_flow_algorithm in [MaxFlowAlgorithm::Dinic, MaxFlowAlgorithm::PushRelabel] {
+                    builder.solve_mcmf(
+                        "s".to_owned(), "t".to_owned(), Default::default(), algorithm, max_flow_algorithm
+                    ).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_graph() {
+        let builder = generate_random_graph(6, 12, 5, 3, 42).unwrap();
+        assert_eq!((builder.node_count(), builder.edge_count()), (6, 12));
+        assert_eq!((builder.source(), builder.sink()), (Some("s"), Some("t")));
+        assert!(builder.edges().iter().all(|edge| (1. ..=5.).contains(&edge.capacity()) && edge.cost() <= 3.));
+        assert_eq!(generate_random_graph(6, 12, 5, 3, 42).unwrap().to_json(), builder.to_json());
+        assert_ne!(generate_random_graph(6, 12, 5, 3, 43).unwrap().to_json(), builder.to_json());
+        let solution = builder.solve_max_flow(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default()
+        ).unwrap();
+        assert!(solution.max_flow() >= 1.);
+        assert!(generate_random_graph(6, 31, 5, 3, 42).is_err());
+        assert!(generate_random_graph(6, 4, 5, 3, 42).is_err());
+    }
+
+    #[test]
+    fn simplex_pivots() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 2., 2.).unwrap();
+        let solve = |builder: &GraphBuilder| builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        let untraced = solve(&builder);
+        assert!(untraced.simplex_pivots().is_empty());
+        builder.set_trace(true);
+        let solution = solve(&builder);
+        assert_eq!((solution.max_flow(), solution.total_cost()), (4., Some(13.)));
+        assert_eq!(solution.total_cost(), untraced.total_cost());
+        let pivots = solution.simplex_pivots();
+        assert_eq!(solution.stats().pivots(), Some(pivots.len() as u32));
+        assert_eq!(pivots.last().unwrap().total_cost(), 13.);
+        assert!(pivots.iter().all(|pivot| pivot.entering().is_some()));
+        assert!(pivots.iter().any(|pivot| pivot.leaving().is_none()));
+    }
+
+    #[test]
+    fn solver() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let mut solver = builder.solver(
+            "s".to_owned(), "t".to_owned(), Default::default(), MinCostFlowAlgorithm::SuccessiveShortestPaths,
+            Default::default(),
+        ).unwrap();
+        let state = solver.current_state();
+        assert_eq!((state.phase(), state.steps(), state.flow()), ("max_flow", 0, 0.));
+        solver.step().unwrap();
+        let state = solver.current_state();
+        assert_eq!((state.flow(), state.edge_flows()), (1., &[0., 0., 1.][..]));
+        assert_eq!(state.last_path().unwrap().nodes(), ["s", "t"]);
+        solver.step().unwrap();
+        assert_eq!(solver.current_state().edge_flows(), [2., 2., 1.]);
+        while !solver.is_done() {
+            solver.step().unwrap();
+        }
+        let state = solver.current_state();
+        assert_eq!((state.phase(), state.flow()), ("done", 3.));
+        assert_eq!(solver.solution().unwrap().total_cost(), Some(9.));
+        solver.step().unwrap();
+        assert_eq!(solver.current_state().steps(), state.steps());
+    }
+
+    #[test]
+    fn secondary_objective() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("r".to_owned(), "s".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 2.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 2.).unwrap();
+        for objective in [SecondaryObjective::ShortestPaths, SecondaryObjective::FewestEdges] {
+            builder.set_secondary_objective(objective);
+            let solution = builder.solve_mcmf(
+                "r".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+            ).unwrap();
+            assert_eq!((solution.max_flow(), solution.total_cost()), (2., Some(4.)));
+            let flows: Vec<_> = solution.edge_flows().iter().map(|edge| edge.flow()).collect();
+            assert_eq!(flows, vec![2., 0., 0., 0., 1., 1.], "{:?}", objective);
+        }
+        builder.add_edge("r".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let solution = builder.solve_mcmf(
+            "r".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()
+        ).unwrap();
+        let flows: Vec<_> = solution.edge_flows().iter().map(|edge| edge.flow()).collect();
+        assert_eq!(flows, vec![2., 0., 0., 0., 1., 1., 1.]);
+        let solution = builder.solve_min_cost_flow(
+            "r".to_owned(), "t".to_owned(), 1., Default::default(), Default::default()
+        ).unwrap();
+        assert_eq!(solution.total_cost(), Some(2.));
+        assert_eq!(solution.edge_flows().iter().filter(|edge| edge.flow() > 0.).count(), 2);
+        // Max flows ignore the costs: the direct edge is used although it is the most expensive.
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("b".to_owned(), "t".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 9.).unwrap();
+        builder.set_secondary_objective(SecondaryObjective::FewestEdges);
+        let solution = builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default()).unwrap();
+        let flows: Vec<_> = solution.edge_flows().iter().map(|edge| edge.flow()).collect();
+        assert_eq!((flows, solution.total_cost(), solution.paths()[0].cost()), (vec![2., 0., 0., 2.], None, 18.));
+    }
+
+    #[test]
+    fn max_flow_profile() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 0.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 5., 0.).unwrap();
+        let edge = builder.add_edge("a".to_owned(), "t".to_owned(), 3., 0.).unwrap();
+        let profile = |capacities: &[f64]| builder.max_flow_profile(
+            "s".to_owned(), "t".to_owned(), edge, capacities, Default::default()
+        );
+        assert_eq!(profile(&[4., 0., 10., 3., 4.]).unwrap(), vec![6., 2., 7., 5., 6.]);
+        assert_eq!(profile(&[]).unwrap(), Vec::<f64>::new());
+        assert_eq!(profile(&[-1.]).unwrap_err(), McmfError::NegativeCapacity(-1.));
+        builder.add_edge_with_bounds("s".to_owned(), "t".to_owned(), 1., 2., 0.).unwrap();
+        let error = builder.max_flow_profile("s".to_owned(), "t".to_owned(), edge, &[4.], Default::default());
+        assert_eq!(error.unwrap_err(), "max flow profiles don't support lower bounds");
+    }
+
+    #[test]
+    fn max_profit_flow() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 3., 2.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 5., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 5., 4.).unwrap();
+        let solve = |revenue| builder.solve_max_profit_flow(
+            "s".to_owned(), "t".to_owned(), revenue, Default::default(), Default::default()
+        ).unwrap();
+        // The path through a costs 5, more than it earns.
+        let solution = solve(4.);
+        assert_eq!((solution.max_flow(), solution.total_cost()), (3., Some(6.)));
+        assert_eq!(solve(6.).max_flow(), 8.);
+        assert_eq!(solve(1.).max_flow(), 0.);
+    }
+
+    #[test]
+    fn piecewise_costs() {
+        let mut builder = GraphBuilder::new();
+        // 10 units at 1, then 10 at 3 and 5 at 6.
+        builder.add_edge_piecewise("s".to_owned(), "t".to_owned(), &[10., 10., 5.], &[1., 3., 6.]).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 20., 2.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 8., 2.).unwrap();
+        let solve = |flow_value| builder.solve_min_cost_flow(
+            "s".to_owned(), "t".to_owned(), flow_value, Default::default(), Default::default()
+        );
+        let solution = solve(25.).unwrap();
+        // The path through a costs 4, less than the last piece.
+        assert_eq!(solution.edge_flows()[0].flow(), 20.);
+        assert_eq!(solution.total_cost(), Some(10. + 30. + 20.));
+        let paths: Vec<_> = solution.paths().iter().map(|path| (path.flow(), path.edges().to_vec())).collect();
+        assert_eq!(paths, [(20., vec![0]), (5., vec![1, 2])]);
+        let edge = &builder.edges()[0];
+        assert_eq!((edge.capacity(), edge.cost()), (25., 1.));
+        let verification = builder.verify_flow("s".to_owned(), "t".to_owned(), &[17., 8., 8.]).unwrap();
+        assert_eq!(verification.total_cost(), 63.);
+        let solution = builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost()), (33., Some(10. + 30. + 30. + 32.)));
+        assert_eq!(builder.update_cost(0, 2.).unwrap_err(), "edge 0 has a piecewise cost");
+        assert_eq!(
+            builder.add_edge_piecewise("s".to_owned(), "b".to_owned(), &[1., 1.], &[2., 1.]).unwrap_err(),
+            "piece costs must be non-decreasing"
+        );
+        assert!(!builder.has_node("b"));
+        let json = builder.to_json();
+        let pieces = r#""pieces":[{"capacity":10,"cost":1},{"capacity":10,"cost":3},{"capacity":5,"cost":6}]"#;
+        assert!(json.contains(pieces));
+        assert_eq!(GraphBuilder::from_json(&json).unwrap().to_json(), json);
+        assert!(builder.to_dimacs_min().unwrap().contains("a 1 2 0 10 1\na 1 2 0 10 3\na 1 2 0 5 6\n"));
+    }
+
+    #[test]
+    fn flow_over_time() {
+        let mut builder = TimeExpandedBuilder::new(4);
+        builder.add_edge("s".to_owned(), "t".to_owned(), 2., 5., 1).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 3., 1., 1).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 3., 1., 2).unwrap();
+        // The direct edge delivers 2 units at each of steps 1 to 4, the one through a 3 units at steps 3 and 4.
+        let flow = builder.solve_max_flow_over_time("s".to_owned(), "t".to_owned()).unwrap();
+        assert_eq!((flow.value(), flow.total_cost(), flow.horizon()), (14., 52., 4));
+        let departures: Vec<_> = flow.departures().iter().map(|d| (d.edge(), d.time(), d.flow())).collect();
+        assert_eq!(departures, [
+            (0, 0, 2.), (1, 0, 3.), (0, 1, 2.), (1, 1, 3.), (2, 1, 3.), (0, 2, 2.), (2, 2, 3.), (0, 3, 2.),
+        ]);
+        let flow = builder.solve_quickest_flow("s".to_owned(), "t".to_owned(), 5.).unwrap();
+        assert_eq!((flow.value(), flow.horizon()), (5., 3));
+        assert_eq!(
+            builder.solve_quickest_flow("s".to_owned(), "t".to_owned(), 15.).unwrap_err(),
+            "cannot send 15 units by time 4"
+        );
+    }
+
+    #[test]
+    fn multi_commodity_flow() {
+        let mut builder = MultiCommodityBuilder::new();
+        // Both commodities need the shared edge a -> b, and the first one can detour via c.
+        builder.add_edge("a".to_owned(), "b".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "c".to_owned(), 4., 1.).unwrap();
+        builder.add_edge("c".to_owned(), "b".to_owned(), 4., 1.).unwrap();
+        builder.add_commodity("a".to_owned(), "b".to_owned(), 8.).unwrap();
+        builder.add_commodity("a".to_owned(), "b".to_owned(), 12.).unwrap();
+        builder.set_epsilon(0.05).unwrap();
+        let flow = builder.solve_max_concurrent_flow().unwrap();
+        // 14 units fit, so 70% of each demand.
+        assert!(flow.fraction() > 0.63 && flow.fraction() <= 0.7, "{}", flow.fraction());
+        assert_eq!(flow.edge_flows().len(), 3);
+        assert!(flow.edge_flows().iter().zip([10., 4., 4.]).all(|(&flow, capacity)| flow <= capacity + 1e-9));
+        let commodity = &flow.commodities()[1];
+        assert_eq!((commodity.source(), commodity.sink(), commodity.demand()), ("a", "b", 12.));
+        let routed: f64 = commodity.paths().iter().map(|path| path.flow()).sum();
+        assert!((routed - 12. * flow.fraction()).abs() < 1e-9);
+
+        builder.add_commodity("b".to_owned(), "c".to_owned(), 1.).unwrap();
+        assert_eq!(builder.solve_max_concurrent_flow().unwrap_err(), "no path from 'b' to 'c'");
+        assert_eq!(builder.add_commodity("a".to_owned(), "b".to_owned(), 0.).unwrap_err(), "demand must be positive");
+
+        // Without scaling the demands, this would take millions of phases.
+        let mut builder = MultiCommodityBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1e7, 0.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1e7, 0.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1e7, 0.).unwrap();
+        builder.add_commodity("s".to_owned(), "t".to_owned(), 1.).unwrap();
+        builder.add_commodity("a".to_owned(), "t".to_owned(), 3e7).unwrap();
+        let flow = builder.solve_max_concurrent_flow().unwrap();
+        // The second commodity fits a third of its demand.
+        assert!(flow.fraction() > 0.3 && flow.fraction() <= 1. / 3. + 1e-9, "{}", flow.fraction());
+
+        let mut builder = MultiCommodityBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 5., 2.).unwrap();
+        builder.add_edge("u".to_owned(), "v".to_owned(), 5., 1.).unwrap();
+        builder.add_commodity("s".to_owned(), "t".to_owned(), 3.).unwrap();
+        builder.add_commodity("u".to_owned(), "v".to_owned(), 4.).unwrap();
+        let flow = builder.solve_max_concurrent_flow().unwrap();
+        assert_eq!((flow.fraction(), flow.total_cost()), (1., 10.));
+        assert_eq!(flow.commodities()[0].paths()[0].nodes(), ["s", "t"]);
+    }
+
+    #[test]
+    fn stats() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 5.).unwrap();
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), Default::default(), Default::default(),
+        ).unwrap();
+        let stats = solution.stats();
+        assert_eq!((stats.num_nodes(), stats.num_edges()), (3, 3));
+        // One phase for the direct edge, one for the path through `a`.
+        assert_eq!(stats.augmentations(), Some(2));
+        assert!(stats.pivots().is_some());
+        let timings = [stats.network_ms(), stats.max_flow_ms(), stats.min_cost_flow_ms(), stats.paths_ms()];
+        assert!(timings.iter().all(|&ms| ms >= 0.));
+
+        let solution = builder.solve_max_flow(
+            "s".to_owned(), "t".to_owned(), Default::default(), MaxFlowAlgorithm::PushRelabel,
+        ).unwrap();
+        assert_eq!((solution.stats().augmentations(), solution.stats().pivots()), (None, None));
+        assert_eq!(solution.stats().min_cost_flow_ms(), 0.);
+    }
+
+    #[test]
+    fn limits() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 3.).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 2., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        builder.set_iteration_limit(Some(1));
+        let solve_max_flow = |builder: &GraphBuilder| {
+            builder.solve_max_flow("s".to_owned(), "t".to_owned(), Default::default(), Default::default())
+        };
+        assert_eq!(solve_max_flow(&builder).unwrap_err(), "iteration limit of 1 reached");
+        let solve = |builder: &GraphBuilder, algorithm| {
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), algorithm, Default::default())
+        };
+        builder.set_partial_results(true);
+        let solution = solve_max_flow(&builder).unwrap();
+        assert_eq!((solution.max_flow(), solution.limit_reached()), (1.0, true));
+        assert!(solution.min_cut().is_none());
+        let solution = solve(&builder, MinCostFlowAlgorithm::NetworkSimplex).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost(), solution.limit_reached()), (1.0, Some(3.0), true));
+
+        // Max flow and the feasible flow that the min-cost phase starts from both take the
+        // expensive edge to `t`, one iteration each, so no cycle gets canceled within the limit.
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "m".to_owned(), 1., 0.).unwrap();
+        builder.add_edge("m".to_owned(), "t".to_owned(), 1., 3.).unwrap();
+        builder.add_edge("m".to_owned(), "a".to_owned(), 1., 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 1., 1.).unwrap();
+        builder.set_iteration_limit(Some(2));
+        builder.set_partial_results(true);
+        let solution = solve(&builder, MinCostFlowAlgorithm::CycleCanceling).unwrap();
+        assert_eq!((solution.max_flow(), solution.total_cost(), solution.limit_reached()), (1.0, Some(3.0), true));
+        // Successive shortest paths has no feasible flow until its only path is done.
+        builder.set_iteration_limit(Some(1));
+        assert_eq!(
+            solve(&builder, MinCostFlowAlgorithm::SuccessiveShortestPaths).unwrap_err(),
+            "iteration limit of 1 reached"
+        );
+        builder.set_iteration_limit(None);
+        let solution = solve(&builder, MinCostFlowAlgorithm::CycleCanceling).unwrap();
+        assert_eq!((solution.total_cost(), solution.limit_reached()), (Some(2.0), false));
+
+        builder.graph.set_time_limit_ms(Some(1e9)).unwrap();
+        assert!(!solve(&builder, MinCostFlowAlgorithm::CostScaling).unwrap().limit_reached());
+        assert_eq!(builder.graph.set_time_limit_ms(Some(-1.)).unwrap_err(), "time limit must be non-negative");
+    }
+
+    #[test]
+    fn async_solve() {
+        let mut builder = GraphBuilder::new();
+        for (from, to, capacity, cost) in [("s", "a", 2., 1.), ("a", "t", 2., 1.), ("s", "t", 1., 3.), ("a", "b", 1., 0.)] {
+            builder.add_edge(from.to_owned(), to.to_owned(), capacity, cost).unwrap();
+        }
+        let start = |builder: &GraphBuilder, algorithm, slice_ms| {
+            builder.solve_mcmf_task("s".to_owned(), "t".to_owned(), Default::default(), algorithm, Default::default(), slice_ms)
+        };
+        // With empty slices a solve yields at every iteration. Later changes to the builder don't
+        // affect a running solve.
+        let mut task = start(&builder, MinCostFlowAlgorithm::CycleCanceling, 0.).unwrap();
+        builder.add_edge("s".to_owned(), "b".to_owned(), 5., 0.).unwrap();
+        let mut steps = 1;
+        let solution = loop {
+            match task.step().unwrap() {
+                Some(solution) => break solution,
+                None => steps += 1,
+            }
+        };
+        assert!(steps > 2);
+        assert_eq!((solution.max_flow(), solution.total_cost()), (3.0, Some(7.0)));
+
+        builder.set_iteration_limit(Some(1));
+        let mut task = start(&builder, MinCostFlowAlgorithm::SuccessiveShortestPaths, 0.).unwrap();
+        let error = std::iter::repeat_with(|| task.step()).find_map(Result::err).unwrap();
+        assert_eq!(error, "iteration limit of 1 reached");
+        let error = start(&builder, MinCostFlowAlgorithm::CycleCanceling, -1.).err().unwrap();
+        assert_eq!(error, "slice length must be non-negative");
+    }
+
+    #[test]
+    fn progress() {
+        let mut builder = GraphBuilder::new();
+        builder.graph.set_capacity_denominator(2).unwrap();
+        builder.add_edge("s".to_owned(), "a".to_owned(), 1.5, 1.).unwrap();
+        builder.add_edge("a".to_owned(), "t".to_owned(), 2., 1.).unwrap();
+        let reports = Rc::new(std::cell::RefCell::new(vec![]));
+        let sink = reports.clone();
+        builder.graph.progress = Some(Rc::new(move |phase: &str, percent, flow| {
+            sink.borrow_mut().push((phase.to_owned(), percent, flow));
+        }));
+        let solution = builder.solve_mcmf(
+            "s".to_owned(), "t".to_owned(), Default::default(), MinCostFlowAlgorithm::SuccessiveShortestPaths,
+            Default::default(),
+        ).unwrap();
+        assert_eq!(solution.max_flow(), 1.5);
+        // Reports in between are throttled, so a small solve only has the ones at the ends.
+        let phases = |phase: &str| [(phase.to_owned(), 0., 0.), (phase.to_owned(), 100., 1.5)];
+        assert_eq!(*reports.borrow(), [phases("max_flow"), phases("min_cost_flow")].concat());
+    }
+
+    #[test]
+    fn cancellation() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("s".to_owned(), "t".to_owned(), 1., 3.).unwrap();
+        let token = CancellationToken::new();
+        builder.set_cancellation_token(&token);
+        builder.set_partial_results(true);
+        let cancel = token.clone();
+        builder.graph.progress = Some(Rc::new(move |phase: &str, _, _| {
+            if phase == "min_cost_flow" {
+                cancel.cancel();
+            }
+        }));
+        let solve = |builder: &GraphBuilder, algorithm| {
+            builder.solve_mcmf("s".to_owned(), "t".to_owned(), Default::default(), algorithm, Default::default())
+        };
+        for algorithm in [MinCostFlowAlgorithm::CycleCanceling, MinCostFlowAlgorithm::NetworkSimplex] {
+            assert_eq!(solve(&builder, algorithm).unwrap_err(), "solve cancelled");
+        }
+        assert!(token.is_cancelled());
+        builder.graph.progress = None;
+        builder.set_cancellation_token(&CancellationToken::new());
+        assert_eq!(solve(&builder, MinCostFlowAlgorithm::CycleCanceling).unwrap().total_cost(), Some(3.0));
+    }
+
+    #[test]
+    fn repeated_solve() {
+        let mut builder = GraphBuilder::new();
+        builder.add_edge("a".to_owned(), "b".to_owned(), 10., 1.).unwrap();
+        builder.add_edge("b".to_owned(), "c".to_owned(), 5., 1.).unwrap();
+        let solution = builder.solve_mcmf("a".to_owned(), "c".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 5.0);
+        let solution = builder.solve_mcmf("a".to_owned(), "b".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 10.0);
+        builder.add_edge("a".to_owned(), "c".to_owned(), 1., 3.).unwrap();
+        let solution = builder.solve_mcmf("a".to_owned(), "c".to_owned(), Default::default(), Default::default(), Default::default()).unwrap();
+        assert_eq!(solution.max_flow(), 6.0);
+        assert_eq!(solution.total_cost(), Some(13.0));
+    }
+
+    #[test]
+    fn invalid_input() {
+        let mut builder = GraphBuilder::new();
+        assert_eq!(
+            builder.add_edge("a".to_owned(), "b".to_owned(), -1., 1.).unwrap_err(),
+            McmfError::NegativeCapacity(-1.)
+        );
+        builder.add_edge("a".to_owned(), "b".to_owned(), 1., 1.).unwrap();
+        assert_eq!(
+            builder.solve_mcmf("a".to_owned(), "x".to_owned(), Default::default(), Default::default(), Default::default()).unwrap_err(),
+            "node 'x' not found"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// Log messages from building and solving, e.g. to see which phase a slow solve is in. They go to
+// the browser console on wasm and to stderr elsewhere. Only warnings and errors are logged by
+// default, see `set_log_level`.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off,
+    // Failed solves.
+    Error,
+    // Results that may not be what was asked for, e.g. partial results or fallbacks.
+    #[default]
+    Warn,
+    // One message per solve or load with its outcome.
+    Info,
+    // Phases of a solve with their timings.
+    Trace,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Warn as u8);
+
+// Applies to all builders.
+pub fn set_log_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn log_level() -> LogLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Off,
+        1 => LogLevel::Error,
+        2 => LogLevel::Warn,
+        3 => LogLevel::Info,
+        _ => LogLevel::Trace,
+    }
+}
+
+pub(crate) fn enabled(level: LogLevel) -> bool {
+    level != LogLevel::Off && level <= log_level()
+}
+
+// Formats lazily, so that disabled messages cost nothing but the level check.
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::$level) {
+            $crate::logging::write($crate::logging::LogLevel::$level, &format!($($arg)*));
+        }
+    };
+}
+pub(crate) use log;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn write(level: LogLevel, message: &str) {
+    match level {
+        LogLevel::Off => {}
+        LogLevel::Error => console::error(message),
+        LogLevel::Warn => console::warn(message),
+        LogLevel::Info => console::info(message),
+        LogLevel::Trace => console::debug(message),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn write(level: LogLevel, message: &str) {
+    eprintln!("[mcmf {:?}] {}", level, message);
+}
+
+#[cfg(target_arch = "wasm32")]
+mod console {
+    #[wasm_bindgen::prelude::wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console)]
+        pub fn error(message: &str);
+        #[wasm_bindgen(js_namespace = console)]
+        pub fn warn(message: &str);
+        #[wasm_bindgen(js_namespace = console)]
+        pub fn info(message: &str);
+        #[wasm_bindgen(js_namespace = console)]
+        pub fn debug(message: &str);
+    }
+}
@@ -0,0 +1,205 @@
+// Export of the min-cost flow problem with supplies, as solved by `GraphBuilder::solve_b_flow`, as
+// a linear program in the CPLEX LP and MPS formats, e.g. to cross-check a solution with CBC or
+// Gurobi. The model has a variable per arc of the solver network:
+//
+//   x<e>      the flow on edge `e`, between its lower bound and capacity
+//   x<e>_<k>  the flow on piece `k` of an edge with a piecewise-linear cost
+//   x<e>_r    the flow against undirected edge `e`
+//   y<v>      the flow through node `v` with a capacity
+//
+// and a flow conservation constraint `n<v>` per node: outflow minus inflow equals the supply. A
+// node with a capacity gets a second one, `n<v>_out`, for the flow leaving it through `y<v>`.
+// Comments name the ends of each edge and the node of each constraint. Infinite capacities are
+// left unbounded.
+
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{network::INFINITE_CAPACITY, GraphBuilder, McmfError};
+
+struct Variable {
+    name: String,
+    comment: String,
+    lower: f64,
+    upper: f64,
+    cost: f64,
+}
+
+struct Row {
+    name: String,
+    comment: String,
+    terms: Vec<(usize, f64)>,
+    rhs: f64,
+}
+
+struct Model {
+    variables: Vec<Variable>,
+    rows: Vec<Row>,
+}
+
+fn model(builder: &GraphBuilder) -> Result<Model, McmfError> {
+    let graph = &builder.graph;
+    if graph.edges.is_empty() {
+        return Err("the graph has no edges".into());
+    }
+    let upper = |capacity| if capacity == INFINITE_CAPACITY { f64::INFINITY } else { graph.unscale_amount(capacity) };
+    let mut variables = vec![];
+    // The rows of the nodes that each variable leaves and enters. A split node is entered by its
+    // edges and left through `y<v>`, see `Network`.
+    let mut ends = vec![];
+    let entry = |v: usize| (v, "");
+    let exit = |v: usize| (v, if graph.nodes[v].capacity.is_some() { "_out" } else { "" });
+    for (e, edge) in graph.edges.iter().enumerate() {
+        let comment = format!("{} -> {}", graph.node_name(edge.from), graph.node_name(edge.to));
+        if edge.pieces.is_empty() {
+            variables.push(Variable {
+                name: format!("x{}", e),
+                comment: comment.clone(),
+                lower: graph.unscale_amount(edge.lower),
+                upper: upper(edge.capacity),
+                cost: edge.cost,
+            });
+            ends.push((exit(edge.from), entry(edge.to)));
+        }
+        for (k, &(capacity, cost)) in edge.pieces.iter().enumerate() {
+            variables.push(Variable {
+                name: format!("x{}_{}", e, k),
+                comment: format!("{}, piece {}", comment, k),
+                lower: 0.,
+                upper: graph.unscale_amount(capacity),
+                cost,
+            });
+            ends.push((exit(edge.from), entry(edge.to)));
+        }
+        if edge.undirected {
+            variables.push(Variable {
+                name: format!("x{}_r", e),
+                comment: format!("{} -> {}", graph.node_name(edge.to), graph.node_name(edge.from)),
+                lower: 0.,
+                upper: upper(edge.capacity),
+                cost: edge.cost,
+            });
+            ends.push((exit(edge.to), entry(edge.from)));
+        }
+    }
+    for (v, node) in graph.nodes.iter().enumerate() {
+        if let Some(capacity) = node.capacity {
+            variables.push(Variable {
+                name: format!("y{}", v),
+                comment: format!("through {}", graph.node_name(v)),
+                lower: 0.,
+                upper: graph.unscale_amount(capacity),
+                cost: 0.,
+            });
+            ends.push((entry(v), (v, "_out")));
+        }
+    }
+    let mut rows = vec![];
+    let mut row_indices = HashMap::new();
+    for (v, node) in graph.nodes.iter().enumerate() {
+        let suffixes: &[&str] = if node.capacity.is_some() { &["", "_out"] } else { &[""] };
+        for &suffix in suffixes {
+            row_indices.insert((v, suffix), rows.len());
+            rows.push(Row {
+                name: format!("n{}{}", v, suffix),
+                comment: graph.node_name(v),
+                terms: vec![],
+                rhs: if suffix.is_empty() { graph.unscale_amount(node.supply) } else { 0. },
+            });
+        }
+    }
+    // Self-loops cancel out.
+    for (i, (from, to)) in ends.into_iter().enumerate().filter(|(_, (from, to))| from != to) {
+        rows[row_indices[&from]].terms.push((i, 1.));
+        rows[row_indices[&to]].terms.push((i, -1.));
+    }
+    Ok(Model { variables, rows })
+}
+
+pub(crate) fn builder_to_lp(builder: &GraphBuilder) -> Result<String, McmfError> {
+    let Model { variables, rows } = model(builder)?;
+    let mut out = String::new();
+    for variable in &variables {
+        writeln!(out, "\\ {}: {}", variable.name, variable.comment).unwrap();
+    }
+    for row in &rows {
+        writeln!(out, "\\ {}: {}", row.name, row.comment).unwrap();
+    }
+    let terms = |terms: &mut dyn Iterator<Item = (usize, f64)>| {
+        let mut out = String::new();
+        for (i, (variable, coefficient)) in terms.enumerate() {
+            let sign = match (i, coefficient < 0.) {
+                (0, negative) => if negative { "-" } else { "" },
+                (_, negative) => if negative { "- " } else { "+ " },
+            };
+            write!(out, " {}{} {}", sign, coefficient.abs(), variables[variable].name).unwrap();
+        }
+        out
+    };
+    writeln!(out, "Minimize").unwrap();
+    writeln!(out, " obj:{}", terms(&mut variables.iter().map(|v| v.cost).enumerate())).unwrap();
+    writeln!(out, "Subject To").unwrap();
+    for row in &rows {
+        // A node without edges has no terms, but a zero term keeps its constraint, which fails if
+        // it has a supply.
+        let lhs = match row.terms.is_empty() {
+            true => format!(" 0 {}", variables[0].name),
+            false => terms(&mut row.terms.iter().copied()),
+        };
+        writeln!(out, " {}:{} = {}", row.name, lhs, row.rhs).unwrap();
+    }
+    writeln!(out, "Bounds").unwrap();
+    for variable in &variables {
+        match variable.upper {
+            f64::INFINITY => writeln!(out, " {} >= {}", variable.name, variable.lower).unwrap(),
+            upper => writeln!(out, " {} <= {} <= {}", variable.lower, variable.name, upper).unwrap(),
+        }
+    }
+    writeln!(out, "End").unwrap();
+    Ok(out)
+}
+
+pub(crate) fn builder_to_mps(builder: &GraphBuilder) -> Result<String, McmfError> {
+    let Model { variables, rows } = model(builder)?;
+    let mut out = String::new();
+    for variable in &variables {
+        writeln!(out, "* {}: {}", variable.name, variable.comment).unwrap();
+    }
+    for row in &rows {
+        writeln!(out, "* {}: {}", row.name, row.comment).unwrap();
+    }
+    writeln!(out, "NAME mcmf").unwrap();
+    writeln!(out, "ROWS").unwrap();
+    writeln!(out, " N obj").unwrap();
+    for row in &rows {
+        writeln!(out, " E {}", row.name).unwrap();
+    }
+    // Entries are listed by column, and every column has an objective entry so that it appears.
+    let mut columns = vec![vec![]; variables.len()];
+    for row in &rows {
+        for &(variable, coefficient) in &row.terms {
+            columns[variable].push((row.name.as_str(), coefficient));
+        }
+    }
+    writeln!(out, "COLUMNS").unwrap();
+    for (variable, entries) in variables.iter().zip(columns) {
+        writeln!(out, "    {} obj {}", variable.name, variable.cost).unwrap();
+        for (row, coefficient) in entries {
+            writeln!(out, "    {} {} {}", variable.name, row, coefficient).unwrap();
+        }
+    }
+    writeln!(out, "RHS").unwrap();
+    for row in rows.iter().filter(|row| row.rhs != 0.) {
+        writeln!(out, "    rhs {} {}", row.name, row.rhs).unwrap();
+    }
+    writeln!(out, "BOUNDS").unwrap();
+    for variable in &variables {
+        if variable.lower != 0. {
+            writeln!(out, " LO bnd {} {}", variable.name, variable.lower).unwrap();
+        }
+        if variable.upper != f64::INFINITY {
+            writeln!(out, " UP bnd {} {}", variable.name, variable.upper).unwrap();
+        }
+    }
+    writeln!(out, "ENDATA").unwrap();
+    Ok(out)
+}
@@ -0,0 +1,165 @@
+// Max flow algorithms implemented here rather than taken from rs-graph, so that they can report
+// statistics.
+
+use std::collections::VecDeque;
+
+use crate::{mcf::Arc, utils::{Budget, TracedPhase, MAX_FLOW_PHASE}};
+
+// Residual graph. Arc `i` is represented by residual arcs `2 * i` (forward) and `2 * i + 1`
+// (backward), so `a ^ 1` is the reverse of residual arc `a`. The arcs leaving each node are
+// stored in compressed sparse row form: one array for all nodes rather than a vector per node,
+// which saves memory on graphs with millions of arcs and keeps the scans sequential.
+pub(crate) struct Residual {
+    // The arcs leaving node `v` are `adjacent[offsets[v]..offsets[v + 1]]`, in the order of `arcs`.
+    offsets: Vec<usize>,
+    adjacent: Vec<usize>,
+    pub to: Vec<usize>,
+    pub capacity: Vec<i64>,
+    pub cost: Vec<i64>,
+}
+
+impl Residual {
+    pub fn new(num_nodes: usize, arcs: &[Arc]) -> Residual {
+        let mut offsets = vec![0; num_nodes + 1];
+        for &(from, to, _, _) in arcs {
+            offsets[from + 1] += 1;
+            offsets[to + 1] += 1;
+        }
+        for v in 0..num_nodes {
+            offsets[v + 1] += offsets[v];
+        }
+        let mut next = offsets.clone();
+        let mut adjacent = vec![0; 2 * arcs.len()];
+        for (i, &(from, to, _, _)) in arcs.iter().enumerate() {
+            for (v, a) in [(from, 2 * i), (to, 2 * i + 1)] {
+                adjacent[next[v]] = a;
+                next[v] += 1;
+            }
+        }
+        Residual {
+            offsets,
+            adjacent,
+            to: arcs.iter().flat_map(|&(from, to, _, _)| [to, from]).collect(),
+            capacity: arcs.iter().flat_map(|&(_, _, capacity, _)| [capacity, 0]).collect(),
+            cost: arcs.iter().flat_map(|&(_, _, _, cost)| [cost, -cost]).collect(),
+        }
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn arcs_from(&self, v: usize) -> &[usize] {
+        &self.adjacent[self.offsets[v]..self.offsets[v + 1]]
+    }
+
+    pub fn from(&self, a: usize) -> usize {
+        self.to[a ^ 1]
+    }
+
+    pub fn push(&mut self, a: usize, amount: i64, excess: &mut [i64]) {
+        self.capacity[a] -= amount;
+        self.capacity[a ^ 1] += amount;
+        excess[self.from(a)] -= amount;
+        excess[self.to[a]] += amount;
+    }
+
+    pub fn flows(&self) -> Vec<i64> {
+        (0..self.to.len() / 2).map(|i| self.capacity[2 * i + 1]).collect()
+    }
+}
+
+// Dinic: repeatedly augments along shortest residual paths until the sink is unreachable, finding
+// a blocking flow in the level graph in each phase. Returns the flow value, the flow on each arc
+// and the number of augmenting paths. Each path counts as an iteration; if `budget` runs out, the
+// flow found so far is returned. With `report`, the flow is reported as max flow progress, as a
+// share of the capacity leaving the source or entering the sink, whichever is smaller, and the
+// phases and augmenting paths are recorded if the budget has a trace.
+pub(crate) async fn dinic(
+    num_nodes: usize, arcs: &[(usize, usize, i64)], source: usize, sink: usize, budget: &Budget, report: bool
+) -> (i64, Vec<i64>, u32) {
+    // The solves reject this, but a path from the source to itself would have no arcs to take
+    // the bottleneck of.
+    if source == sink {
+        return (0, vec![0; arcs.len()], 0);
+    }
+    let capacity = |v: usize, end: fn(&(usize, usize, i64)) -> usize| {
+        arcs.iter().filter(|arc| end(arc) == v).fold(0i64, |total, arc| total.saturating_add(arc.2))
+    };
+    let bound = capacity(source, |arc| arc.0).min(capacity(sink, |arc| arc.1)).max(1);
+    let arcs: Vec<_> = arcs.iter().map(|&(from, to, capacity)| (from, to, capacity, 0)).collect();
+    let mut residual = Residual::new(num_nodes, &arcs);
+    let mut excess = vec![0; num_nodes];
+    let mut augmentations = 0;
+    'phases: while let Some(mut levels) = levels(&residual, source, sink) {
+        if let (true, Some(trace)) = (report, &budget.trace) {
+            let mut reached: Vec<_> = (0..num_nodes).filter(|&v| levels[v] != usize::MAX).collect();
+            reached.sort_by_key(|&v| levels[v]);
+            let arcs = reached.iter().flat_map(|&u| residual.arcs_from(u))
+                .filter(|&&a| residual.capacity[a] > 0 && levels[residual.to[a]] == levels[residual.from(a)] + 1)
+                .map(|&a| (a, residual.capacity[a]))
+                .collect();
+            let mut trace = trace.borrow_mut();
+            let first_augmentation = trace.augmentations.len();
+            let levels = reached.into_iter().map(|v| (v, levels[v])).collect();
+            trace.phases.push(TracedPhase { levels, arcs, first_augmentation });
+        }
+        let mut current_arcs = vec![0; num_nodes];
+        let mut path = vec![];
+        let mut u = source;
+        loop {
+            if u == sink {
+                if !budget.checkpoint().await {
+                    break 'phases;
+                }
+                let amount = path.iter().map(|&a| residual.capacity[a]).min().unwrap();
+                for &a in &path {
+                    residual.push(a, amount, &mut excess);
+                }
+                augmentations += 1;
+                if report {
+                    budget.report(MAX_FLOW_PHASE, 100. * excess[sink] as f64 / bound as f64, excess[sink]);
+                    if let Some(trace) = &budget.trace {
+                        let arcs = path.iter().map(|&a| (a, residual.capacity[a])).collect();
+                        trace.borrow_mut().augmentations.push((arcs, amount));
+                    }
+                }
+                path.clear();
+                u = source;
+            }
+            let admissible = |a: usize| residual.capacity[a] > 0 && levels[residual.to[a]] == levels[u] + 1;
+            let arcs = residual.arcs_from(u);
+            while current_arcs[u] < arcs.len() && !admissible(arcs[current_arcs[u]]) {
+                current_arcs[u] += 1;
+            }
+            if let Some(&a) = arcs.get(current_arcs[u]) {
+                path.push(a);
+                u = residual.to[a];
+            } else {
+                // Dead end: retreat and never come back in this phase.
+                levels[u] = usize::MAX;
+                let Some(a) = path.pop() else { break };
+                u = residual.from(a);
+                current_arcs[u] += 1;
+            }
+        }
+    }
+    (excess[sink], residual.flows(), augmentations)
+}
+
+// BFS distances from `source` in the residual graph, or `None` if `sink` is unreachable.
+fn levels(residual: &Residual, source: usize, sink: usize) -> Option<Vec<usize>> {
+    let mut levels = vec![usize::MAX; residual.num_nodes()];
+    levels[source] = 0;
+    let mut queue = VecDeque::from([source]);
+    while let Some(u) = queue.pop_front() {
+        for &a in residual.arcs_from(u) {
+            let v = residual.to[a];
+            if residual.capacity[a] > 0 && levels[v] == usize::MAX {
+                levels[v] = levels[u] + 1;
+                queue.push_back(v);
+            }
+        }
+    }
+    (levels[sink] != usize::MAX).then_some(levels)
+}
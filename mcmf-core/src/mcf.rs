@@ -0,0 +1,433 @@
+// Min-cost flow algorithms other than the network simplex from rs-graph, plus a traceable copy of
+// it. They all solve the b-flow problem on a list of `(from, to, capacity, cost)` arcs with zero
+// lower bounds, where `balances` give the supply (positive) or demand (negative) of each node.
+// The result is the flow on each arc, or `None` if the balances cannot be satisfied. If `budget`
+// runs out, they return the best feasible flow found so far, or `None` if there is none yet.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::{
+    maxflow::Residual, network::max_flow_on_arcs, utils::{Budget, MIN_COST_FLOW_PHASE}, MaxFlowAlgorithm,
+};
+
+pub(crate) type Arc = (usize, usize, i64, i64);
+
+// Augments along shortest paths from nodes with excess to nodes with deficit. Arcs with negative
+// costs are saturated up front, so that all residual costs are non-negative and Dijkstra with
+// node potentials applies. Each path counts as an iteration; flows are only feasible at the end.
+// The supply routed so far is reported as progress.
+pub(crate) async fn successive_shortest_paths(
+    num_nodes: usize, arcs: &[Arc], balances: &[i64], budget: &Budget
+) -> Option<Vec<i64>> {
+    let mut residual = Residual::new(num_nodes, arcs);
+    let mut excess = balances.to_vec();
+    let total_supply: i64 = balances.iter().filter(|&&balance| balance > 0).sum();
+    for (i, &(_, _, capacity, cost)) in arcs.iter().enumerate() {
+        if cost < 0 {
+            residual.push(2 * i, capacity, &mut excess);
+        }
+    }
+    // Reduced costs `cost + potential[from] - potential[to]` stay non-negative throughout.
+    let mut potentials = vec![0i128; num_nodes];
+    loop {
+        let mut distances = vec![i128::MAX; num_nodes];
+        let mut predecessors = vec![None; num_nodes];
+        let mut heap = BinaryHeap::new();
+        for v in (0..num_nodes).filter(|&v| excess[v] > 0) {
+            distances[v] = 0;
+            heap.push(Reverse((0, v)));
+        }
+        if heap.is_empty() {
+            return Some(residual.flows());
+        }
+        let mut target = None;
+        while let Some(Reverse((distance, u))) = heap.pop() {
+            if distance > distances[u] {
+                continue;
+            }
+            if excess[u] < 0 {
+                target = Some(u);
+                break;
+            }
+            for &a in residual.arcs_from(u) {
+                let v = residual.to[a];
+                let reduced_cost = residual.cost[a] as i128 + potentials[u] - potentials[v];
+                if residual.capacity[a] > 0 && distance + reduced_cost < distances[v] {
+                    distances[v] = distance + reduced_cost;
+                    predecessors[v] = Some(a);
+                    heap.push(Reverse((distances[v], v)));
+                }
+            }
+        }
+        let target = target?;
+        if !budget.checkpoint().await {
+            return None;
+        }
+        // Nodes that were not reached before the target get its distance, which keeps the
+        // reduced costs of all residual arcs non-negative.
+        for (potential, &distance) in potentials.iter_mut().zip(&distances) {
+            *potential += distance.min(distances[target]);
+        }
+        let mut path = vec![];
+        let mut v = target;
+        while let Some(a) = predecessors[v] {
+            path.push(a);
+            v = residual.from(a);
+        }
+        let amount = path.iter().map(|&a| residual.capacity[a]).min().unwrap_or(i64::MAX)
+            .min(excess[v]).min(-excess[target]);
+        for &a in &path {
+            residual.push(a, amount, &mut excess);
+        }
+        let routed = (total_supply - excess.iter().filter(|&&excess| excess > 0).sum::<i64>()).max(0);
+        budget.report(MIN_COST_FLOW_PHASE, 100. * routed as f64 / total_supply.max(1) as f64, routed);
+    }
+}
+
+// Starts with any feasible flow and cancels negative cycles in the residual graph until there
+// are none left. Each cycle counts as an iteration.
+pub(crate) async fn cycle_canceling(
+    num_nodes: usize, arcs: &[Arc], balances: &[i64], budget: &Budget
+) -> Option<Vec<i64>> {
+    let mut residual = feasible_flow(num_nodes, arcs, balances, budget).await?;
+    cancel_cycles(&mut residual, budget).await;
+    Some(residual.flows())
+}
+
+async fn cancel_cycles(residual: &mut Residual, budget: &Budget) {
+    let mut excess = vec![0; residual.num_nodes()];
+    while let Some(cycle) = negative_residual_cycle(residual) {
+        if !budget.checkpoint().await {
+            break;
+        }
+        let amount = cycle.iter().map(|&a| residual.capacity[a]).min().unwrap();
+        for &a in &cycle {
+            residual.push(a, amount, &mut excess);
+        }
+    }
+}
+
+// Min-cost max flow from `source` to `sink`, starting from `flows`, e.g. the solution before
+// some capacities or costs changed. `flows` may exceed the capacities and need not be balanced:
+// they are clamped, and the imbalance is routed away in the residual graph of the flow extended
+// by a return arc from `sink` to `source`, which gives a circulation. The flow on the return arc
+// is the flow value, which is then maximized. Finally negative cycles are canceled, which takes
+// few iterations if the starting flow was nearly optimal. Returns the flow value and the flow on
+// each arc, or `None` if the imbalance cannot be routed. The augmenting paths and canceled cycles
+// count as iterations.
+pub(crate) async fn reoptimize(
+    num_nodes: usize, arcs: &[Arc], flows: &[i64], (source, sink): (usize, usize), budget: &Budget
+) -> Option<(i64, Vec<i64>)> {
+    let unbounded = arcs.iter().fold(1, |total: i64, arc| total.saturating_add(arc.2));
+    let mut with_return = arcs.to_vec();
+    with_return.push((sink, source, unbounded, 0));
+    let mut residual = Residual::new(num_nodes, &with_return);
+    let mut excess = vec![0; num_nodes];
+    for (i, (&flow, arc)) in flows.iter().zip(arcs).enumerate() {
+        residual.push(2 * i, flow.clamp(0, arc.2), &mut excess);
+    }
+    let return_arc = 2 * arcs.len();
+    residual.push(return_arc, excess[sink].max(0), &mut excess);
+
+    let imbalance: i64 = excess.iter().filter(|&&excess| excess > 0).sum();
+    let (super_source, super_sink) = (num_nodes, num_nodes + 1);
+    let mut terminal_arcs = vec![];
+    for (v, &excess) in excess.iter().enumerate() {
+        if excess > 0 {
+            terminal_arcs.push((super_source, v, excess));
+        } else if excess < 0 {
+            terminal_arcs.push((v, super_sink, -excess));
+        }
+    }
+    if augment(&mut residual, &terminal_arcs, (super_source, super_sink), budget).await != imbalance {
+        return None;
+    }
+    // The flow value stays fixed from here on.
+    let value = residual.capacity[return_arc ^ 1];
+    residual.capacity[return_arc] = 0;
+    residual.capacity[return_arc ^ 1] = 0;
+    let value = value + augment(&mut residual, &[], (source, sink), budget).await;
+    cancel_cycles(&mut residual, budget).await;
+    let mut flows = residual.flows();
+    flows.pop();
+    Some((value, flows))
+}
+
+// Augments `residual` by a max flow from `source` to `sink` over its residual arcs and the given
+// extra arcs, which may involve two more nodes. Returns the flow value.
+async fn augment(
+    residual: &mut Residual, extra_arcs: &[(usize, usize, i64)], (source, sink): (usize, usize), budget: &Budget
+) -> i64 {
+    let num_nodes = residual.num_nodes();
+    let residual_arcs = (0..residual.to.len()).map(|a| (residual.from(a), residual.to[a], residual.capacity[a]));
+    let flow_arcs: Vec<_> = residual_arcs.chain(extra_arcs.iter().copied()).collect();
+    let (value, flows, _) =
+        max_flow_on_arcs(num_nodes + 2, &flow_arcs, source, sink, MaxFlowAlgorithm::Dinic, budget, false).await;
+    let mut excess = vec![0; num_nodes + 2];
+    for (a, &flow) in flows.iter().take(residual.to.len()).enumerate() {
+        residual.push(a, flow, &mut excess);
+    }
+    value
+}
+
+// Primal network simplex, slower than the one from rs-graph but traceable: each pivot counts as an
+// iteration and is recorded to the budget's trace, if set, as the entering arc, the leaving arc
+// (`None` for an artificial one) and the total cost after the pivot plus `cost_offset`. The first
+// basis connects every node to an artificial root by an arc costing more than any path. The
+// entering arc is the first one with a negative reduced cost, and the tree is kept strongly
+// feasible, which rules out cycling. Returns the flows and the number of pivots.
+pub(crate) async fn traced_network_simplex(
+    num_nodes: usize, arcs: &[Arc], balances: &[i64], cost_offset: i64, budget: &Budget
+) -> Option<(Vec<i64>, u32)> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State { Tree, Lower, Upper }
+    let (m, root) = (arcs.len(), num_nodes);
+    let big_m = arcs.iter().map(|arc| (arc.3 as i128).abs()).sum::<i128>() + 1;
+    let (mut from, mut to): (Vec<_>, Vec<_>) = arcs.iter().map(|arc| (arc.0, arc.1)).unzip();
+    let mut capacity: Vec<_> = arcs.iter().map(|arc| arc.2).collect();
+    let mut cost: Vec<_> = arcs.iter().map(|arc| arc.3 as i128).collect();
+    let mut flow = vec![0; m];
+    let mut state = vec![State::Lower; m];
+    // Zero flows on artificial arcs point towards the root, as strong feasibility requires.
+    for (v, &balance) in balances.iter().enumerate() {
+        let (u, w) = if balance >= 0 { (v, root) } else { (root, v) };
+        from.push(u);
+        to.push(w);
+        capacity.push(i64::MAX / 4);
+        cost.push(big_m);
+        flow.push(balance.abs());
+        state.push(State::Tree);
+    }
+    let mut parent: Vec<_> = (0..=num_nodes).map(|v| if v == root { usize::MAX } else { root }).collect();
+    let mut parent_arc: Vec<_> = (0..=num_nodes).map(|v| m + v).collect();
+    let mut pivots = 0;
+    loop {
+        // Potentials make the reduced costs `cost + potential[from] - potential[to]` of tree arcs zero.
+        let mut children = vec![vec![]; num_nodes + 1];
+        for v in 0..num_nodes {
+            children[parent[v]].push(v);
+        }
+        let (mut depth, mut potentials) = (vec![0; num_nodes + 1], vec![0i128; num_nodes + 1]);
+        let mut queue = VecDeque::from([root]);
+        while let Some(u) = queue.pop_front() {
+            for &v in &children[u] {
+                let a = parent_arc[v];
+                depth[v] = depth[u] + 1;
+                potentials[v] = if from[a] == v { potentials[u] - cost[a] } else { potentials[u] + cost[a] };
+                queue.push_back(v);
+            }
+        }
+        let reduced_cost = |a: usize| cost[a] + potentials[from[a]] - potentials[to[a]];
+        let entering = (0..m).find(|&a| match state[a] {
+            State::Lower => capacity[a] > 0 && reduced_cost(a) < 0,
+            State::Upper => reduced_cost(a) > 0,
+            State::Tree => false,
+        });
+        let Some(entering) = entering else {
+            break;
+        };
+        if !budget.checkpoint().await {
+            break;
+        }
+        // The cycle runs from the apex, where the tree paths meet, down to `first`, over the
+        // entering arc to `second` and back up.
+        let (first, second) = match state[entering] {
+            State::Lower => (from[entering], to[entering]),
+            _ => (to[entering], from[entering]),
+        };
+        let (mut first_side, mut second_side) = (vec![], vec![]);
+        let (mut x, mut y) = (first, second);
+        while x != y {
+            if depth[x] >= depth[y] {
+                first_side.push(x);
+                x = parent[x];
+            } else {
+                second_side.push(y);
+                y = parent[y];
+            }
+        }
+        let mut cycle: Vec<_> =
+            first_side.iter().rev().map(|&v| (parent_arc[v], from[parent_arc[v]] == parent[v])).collect();
+        cycle.push((entering, state[entering] == State::Lower));
+        cycle.extend(second_side.iter().map(|&v| (parent_arc[v], from[parent_arc[v]] == v)));
+        let residual = |&(a, forward): &(usize, bool)| if forward { capacity[a] - flow[a] } else { flow[a] };
+        let amount = cycle.iter().map(residual).min().unwrap();
+        // The last blocking arc leaves, which keeps the tree strongly feasible.
+        let position = cycle.iter().rposition(|arc| residual(arc) == amount).unwrap();
+        for &(a, forward) in &cycle {
+            flow[a] += if forward { amount } else { -amount };
+        }
+        let leaving = cycle[position].0;
+        if leaving == entering {
+            state[entering] = if state[entering] == State::Lower { State::Upper } else { State::Lower };
+        } else {
+            state[entering] = State::Tree;
+            state[leaving] = if flow[leaving] == 0 { State::Lower } else { State::Upper };
+            // The subtree cut off by the leaving arc hangs from the entering arc instead, so the
+            // tree path from the entering arc to the leaving one is reversed.
+            let (mut v, mut new_parent, last) = if position < first_side.len() {
+                (first, second, first_side[first_side.len() - 1 - position])
+            } else {
+                (second, first, second_side[position - first_side.len() - 1])
+            };
+            let mut new_arc = entering;
+            loop {
+                let (old_parent, old_arc) = (parent[v], parent_arc[v]);
+                (parent[v], parent_arc[v]) = (new_parent, new_arc);
+                if v == last {
+                    break;
+                }
+                (v, new_parent, new_arc) = (old_parent, v, old_arc);
+            }
+        }
+        pivots += 1;
+        if let Some(trace) = &budget.trace {
+            let total_cost = (0..m).map(|a| flow[a] as i128 * cost[a]).sum::<i128>() as i64 + cost_offset;
+            trace.borrow_mut().pivots.push((entering, (leaving < m).then_some(leaving), total_cost));
+        }
+    }
+    // Flow left on artificial arcs means that the balances cannot be satisfied, or that the
+    // budget ran out before they were.
+    if flow[m..].iter().any(|&flow| flow > 0) {
+        return None;
+    }
+    flow.truncate(m);
+    Some((flow, pivots))
+}
+
+// Goldberg-Tarjan cost scaling: maintains an `epsilon`-optimal flow for decreasing `epsilon`,
+// restoring it with push-relabel after each step. Costs are multiplied by `num_nodes + 1`, so
+// that a 1-optimal flow is optimal. Each relabel counts as an iteration.
+pub(crate) async fn cost_scaling(
+    num_nodes: usize, arcs: &[Arc], balances: &[i64], budget: &Budget
+) -> Option<Vec<i64>> {
+    // Push-relabel only terminates if there is a feasible flow, so check that first.
+    let mut residual = feasible_flow(num_nodes, arcs, balances, budget).await?;
+    let scale = num_nodes as i128 + 1;
+    let costs: Vec<_> = residual.cost.iter().map(|&cost| cost as i128 * scale).collect();
+    let mut prices = vec![0i128; num_nodes];
+    let mut epsilon = costs.iter().map(|cost| cost.abs()).max().unwrap_or(0).max(1);
+    loop {
+        epsilon = (epsilon / 4).max(1);
+        let capacities = residual.capacity.clone();
+        if !refine(&mut residual, &costs, &mut prices, epsilon, budget).await {
+            // The flow is infeasible midway, so go back to the one from the previous step.
+            residual.capacity = capacities;
+            return Some(residual.flows());
+        }
+        if epsilon == 1 {
+            return Some(residual.flows());
+        }
+    }
+}
+
+// Makes a flow `epsilon`-optimal: saturates the arcs with negative reduced cost, then pushes the
+// resulting excesses along admissible arcs (those with negative reduced cost), relabeling nodes
+// that have none. Returns false if `budget` runs out before that.
+async fn refine(
+    residual: &mut Residual, costs: &[i128], prices: &mut [i128], epsilon: i128, budget: &Budget
+) -> bool {
+    let num_nodes = residual.num_nodes();
+    let reduced_cost = |a: usize, prices: &[i128], residual: &Residual| {
+        costs[a] + prices[residual.from(a)] - prices[residual.to[a]]
+    };
+    let mut excess = vec![0; num_nodes];
+    for a in 0..residual.to.len() {
+        if residual.capacity[a] > 0 && reduced_cost(a, prices, residual) < 0 {
+            let capacity = residual.capacity[a];
+            residual.push(a, capacity, &mut excess);
+        }
+    }
+    let mut active: VecDeque<_> = (0..num_nodes).filter(|&v| excess[v] > 0).collect();
+    let mut current_arcs = vec![0; num_nodes];
+    while let Some(u) = active.pop_front() {
+        while excess[u] > 0 {
+            let Some(&a) = residual.arcs_from(u).get(current_arcs[u]) else {
+                // Relabel: make the cheapest residual arc admissible.
+                if !budget.checkpoint().await {
+                    return false;
+                }
+                prices[u] = residual.arcs_from(u).iter()
+                    .filter(|&&a| residual.capacity[a] > 0)
+                    .map(|&a| prices[residual.to[a]] - costs[a])
+                    .max()
+                    .expect("node with excess has no residual arcs")
+                    - epsilon;
+                current_arcs[u] = 0;
+                continue;
+            };
+            if residual.capacity[a] > 0 && reduced_cost(a, prices, residual) < 0 {
+                let v = residual.to[a];
+                let was_active = excess[v] > 0;
+                let amount = excess[u].min(residual.capacity[a]);
+                residual.push(a, amount, &mut excess);
+                if !was_active && excess[v] > 0 {
+                    active.push_back(v);
+                }
+            } else {
+                current_arcs[u] += 1;
+            }
+        }
+    }
+    true
+}
+
+// A flow satisfying the balances, found as a max flow from a super source to a super sink.
+async fn feasible_flow(num_nodes: usize, arcs: &[Arc], balances: &[i64], budget: &Budget) -> Option<Residual> {
+    let (super_source, super_sink) = (num_nodes, num_nodes + 1);
+    let mut flow_arcs: Vec<_> = arcs.iter().map(|&(from, to, capacity, _)| (from, to, capacity)).collect();
+    for (v, &balance) in balances.iter().enumerate() {
+        if balance > 0 {
+            flow_arcs.push((super_source, v, balance));
+        } else if balance < 0 {
+            flow_arcs.push((v, super_sink, -balance));
+        }
+    }
+    let (value, flows, _) =
+        max_flow_on_arcs(num_nodes + 2, &flow_arcs, super_source, super_sink, MaxFlowAlgorithm::Dinic, budget, false).await;
+    if budget.reached() || value != balances.iter().filter(|&&balance| balance > 0).sum::<i64>() {
+        return None;
+    }
+    let mut residual = Residual::new(num_nodes, arcs);
+    let mut excess = vec![0; num_nodes];
+    for (i, &flow) in flows.iter().take(arcs.len()).enumerate() {
+        residual.push(2 * i, flow, &mut excess);
+    }
+    Some(residual)
+}
+
+// Bellman-Ford with all nodes as sources. Returns the residual arcs of a negative cycle.
+fn negative_residual_cycle(residual: &Residual) -> Option<Vec<usize>> {
+    let n = residual.num_nodes();
+    let mut distances = vec![0i128; n];
+    let mut predecessors = vec![None; n];
+    let mut last_relaxed = None;
+    for _ in 0..n {
+        last_relaxed = None;
+        for a in (0..residual.to.len()).filter(|&a| residual.capacity[a] > 0) {
+            let (from, to) = (residual.from(a), residual.to[a]);
+            if distances[from] + (residual.cost[a] as i128) < distances[to] {
+                distances[to] = distances[from] + residual.cost[a] as i128;
+                predecessors[to] = Some(a);
+                last_relaxed = Some(to);
+            }
+        }
+        last_relaxed?;
+    }
+    // As in `Network::negative_cycle`, going back n steps ends up on the cycle.
+    let mut v = last_relaxed?;
+    for _ in 0..n {
+        v = residual.from(predecessors[v].unwrap());
+    }
+    let mut cycle = vec![];
+    let mut u = v;
+    loop {
+        let a = predecessors[u].unwrap();
+        cycle.push(a);
+        u = residual.from(a);
+        if u == v {
+            return Some(cycle);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+// Multi-commodity flow: several commodities, each with its own source, sink and demand, share the
+// edge capacities. Solved approximately for the max concurrent flow, i.e. the largest fraction of
+// every demand that can be routed at the same time, with the Garg-Könemann scheme as improved by
+// Fleischer: edges get lengths that grow exponentially with their load, and each commodity
+// repeatedly routes along its shortest path under those lengths. Costs don't affect the routing
+// and are only reported.
+
+use std::{cmp::Ordering, collections::{BinaryHeap, HashMap}};
+
+use crate::{McmfError, Path, ID};
+
+const DEFAULT_EPSILON: f64 = 0.1;
+
+#[derive(Clone, Debug)]
+struct CommodityEdge {
+    from: usize,
+    to: usize,
+    capacity: f64,
+    cost: f64,
+}
+
+#[derive(Clone, Debug)]
+struct Commodity {
+    source: usize,
+    sink: usize,
+    demand: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiCommodityBuilder {
+    node_names: Vec<String>,
+    node_indices: HashMap<String, usize>,
+    edges: Vec<CommodityEdge>,
+    commodities: Vec<Commodity>,
+    epsilon: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct CommodityFlow {
+    source: String,
+    sink: String,
+    demand: f64,
+    flow: f64,
+    paths: Vec<Path>,
+}
+
+impl CommodityFlow {
+    pub fn source(&self) -> &str { &self.source }
+    pub fn sink(&self) -> &str { &self.sink }
+    pub fn demand(&self) -> f64 { self.demand }
+    // The routed amount, `MultiCommodityFlow::fraction` of the demand.
+    pub fn flow(&self) -> f64 { self.flow }
+    pub fn paths(&self) -> &[Path] { &self.paths }
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiCommodityFlow {
+    fraction: f64,
+    total_cost: f64,
+    edge_flows: Vec<f64>,
+    commodities: Vec<CommodityFlow>,
+}
+
+impl MultiCommodityFlow {
+    // The fraction of each demand that is routed, at most 1. It is within a factor of about
+    // `1 - 3 * epsilon` of the largest possible one, see `MultiCommodityBuilder::set_epsilon`.
+    pub fn fraction(&self) -> f64 { self.fraction }
+    pub fn total_cost(&self) -> f64 { self.total_cost }
+    // Total flow of all commodities on each edge, by edge ID.
+    pub fn edge_flows(&self) -> &[f64] { &self.edge_flows }
+    // In the order the commodities were added.
+    pub fn commodities(&self) -> &[CommodityFlow] { &self.commodities }
+}
+
+impl Default for MultiCommodityBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiCommodityBuilder {
+    pub fn new() -> Self {
+        MultiCommodityBuilder {
+            node_names: vec![],
+            node_indices: HashMap::new(),
+            edges: vec![],
+            commodities: vec![],
+            epsilon: DEFAULT_EPSILON,
+        }
+    }
+
+    pub fn add_edge(&mut self, from: String, to: String, capacity: f64, cost: f64) -> Result<ID, McmfError> {
+        if !(capacity > 0. && capacity.is_finite()) {
+            return Err("capacity must be positive".into());
+        }
+        let (from, to) = (self.get_or_insert_vertex(from), self.get_or_insert_vertex(to));
+        self.edges.push(CommodityEdge { from, to, capacity, cost });
+        Ok((self.edges.len() - 1) as ID)
+    }
+
+    // Adds a commodity that needs `demand` units routed from `source` to `sink`. Returns its
+    // index in `MultiCommodityFlow::commodities`.
+    pub fn add_commodity(&mut self, source: String, sink: String, demand: f64) -> Result<ID, McmfError> {
+        if !(demand > 0. && demand.is_finite()) {
+            return Err("demand must be positive".into());
+        }
+        if source == sink {
+            return Err("commodity source and sink must differ".into());
+        }
+        let (source, sink) = (self.get_or_insert_vertex(source), self.get_or_insert_vertex(sink));
+        self.commodities.push(Commodity { source, sink, demand });
+        Ok((self.commodities.len() - 1) as ID)
+    }
+
+    // Trades accuracy for speed: the number of shortest path computations grows with
+    // `1 / epsilon^2`. The default is 0.1.
+    pub fn set_epsilon(&mut self, epsilon: f64) -> Result<(), McmfError> {
+        if !(epsilon > 0. && epsilon < 1.) {
+            return Err("epsilon must be between 0 and 1".into());
+        }
+        self.epsilon = epsilon;
+        Ok(())
+    }
+
+    pub fn solve_max_concurrent_flow(&self) -> Result<MultiCommodityFlow, McmfError> {
+        if self.commodities.is_empty() {
+            return Err("at least one commodity is required".into());
+        }
+        let outgoing = self.outgoing();
+        for commodity in &self.commodities {
+            if shortest_path(&outgoing, &self.edges, &vec![1.; self.edges.len()], commodity).is_none() {
+                return Err(format!(
+                    "no path from '{}' to '{}'", self.node_names[commodity.source], self.node_names[commodity.sink]
+                ).into());
+            }
+        }
+        let epsilon = self.epsilon;
+        let m = self.edges.len() as f64;
+        let delta = (m / (1. - epsilon)).powf(-1. / epsilon);
+        let mut lengths: Vec<_> = self.edges.iter().map(|edge| delta / edge.capacity).collect();
+        let volume = |lengths: &[f64]| self.edges.iter().zip(lengths).map(|(edge, l)| edge.capacity * l).sum::<f64>();
+        // The number of phases grows with the best fraction, so demands are scaled to make it at
+        // least 1 at first, see `demand_scale`. A fraction of at most 2 takes `doubling_phases`,
+        // so whenever that many phases pass, the fraction must be larger and the demands double.
+        let mut scale = self.demand_scale(&outgoing);
+        let doubling_phases = (2. * (1. / delta).ln() / (1. + epsilon).ln()).ceil().max(1.) as usize;
+        let mut phases = 0;
+        // Flow of each commodity along each path, keyed by edge IDs.
+        let mut path_flows: Vec<HashMap<Vec<usize>, f64>> = vec![HashMap::new(); self.commodities.len()];
+        'phases: while volume(&lengths) < 1. {
+            if phases > 0 && phases % doubling_phases == 0 {
+                scale *= 2.;
+            }
+            phases += 1;
+            for (i, commodity) in self.commodities.iter().enumerate() {
+                let mut remaining = commodity.demand * scale;
+                while remaining > 0. {
+                    if volume(&lengths) >= 1. {
+                        break 'phases;
+                    }
+                    let path = shortest_path(&outgoing, &self.edges, &lengths, commodity).unwrap();
+                    let amount = path.iter().map(|&e| self.edges[e].capacity).fold(remaining, f64::min);
+                    for &e in &path {
+                        lengths[e] *= 1. + epsilon * amount / self.edges[e].capacity;
+                    }
+                    *path_flows[i].entry(path).or_default() += amount;
+                    remaining -= amount;
+                }
+            }
+        }
+        // The flow overloads edges by about `log_{1 + epsilon}(1 / delta)`, but scaling by the
+        // actual congestion is what makes it feasible for sure.
+        let mut loads = vec![0.; self.edges.len()];
+        for (path, &flow) in path_flows.iter().flatten() {
+            for &e in path {
+                loads[e] += flow;
+            }
+        }
+        let congestion = loads.iter().zip(&self.edges).map(|(load, edge)| load / edge.capacity).fold(0., f64::max);
+        let routed: Vec<f64> = path_flows.iter().map(|paths| paths.values().sum::<f64>() / congestion).collect();
+        let fraction = self.commodities.iter().zip(&routed)
+            .map(|(commodity, routed)| routed / commodity.demand)
+            .fold(1., f64::min);
+        Ok(self.flow(path_flows, fraction))
+    }
+
+    // A factor for the demands that makes the best fraction between 1 and `m * k` for `m` edges
+    // and `k` commodities. Alone, a commodity can send at least the bottleneck `b` of its widest
+    // path and at most `m * b`, since a max flow splits into at most `m` paths. All commodities
+    // can send `1 / k` of that together.
+    fn demand_scale(&self, outgoing: &[Vec<usize>]) -> f64 {
+        let k = self.commodities.len() as f64;
+        self.commodities.iter()
+            .map(|commodity| widest_path_bottleneck(outgoing, &self.edges, commodity) / (k * commodity.demand))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    // Scales every commodity to `fraction` of its demand, which only lowers the flow.
+    fn flow(&self, path_flows: Vec<HashMap<Vec<usize>, f64>>, fraction: f64) -> MultiCommodityFlow {
+        let mut edge_flows = vec![0.; self.edges.len()];
+        let mut commodities = vec![];
+        for (commodity, paths) in self.commodities.iter().zip(path_flows) {
+            let scale = fraction * commodity.demand / paths.values().sum::<f64>();
+            let mut paths: Vec<_> = paths.into_iter().map(|(edges, flow)| (flow * scale, edges)).collect();
+            paths.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+            let paths = paths.into_iter().map(|(flow, edges)| {
+                for &e in &edges {
+                    edge_flows[e] += flow;
+                }
+                let node_indices: Vec<_> = std::iter::once(commodity.source)
+                    .chain(edges.iter().map(|&e| self.edges[e].to))
+                    .collect();
+                Path {
+                    flow,
+                    cost: flow * edges.iter().map(|&e| self.edges[e].cost).sum::<f64>(),
+                    nodes: node_indices.iter().map(|&v| self.node_names[v].clone()).collect(),
+                    node_indices: node_indices.into_iter().map(|v| v as ID).collect(),
+                    edges: edges.into_iter().map(|e| e as ID).collect(),
+                }
+            }).collect();
+            commodities.push(CommodityFlow {
+                source: self.node_names[commodity.source].clone(),
+                sink: self.node_names[commodity.sink].clone(),
+                demand: commodity.demand,
+                flow: fraction * commodity.demand,
+                paths,
+            });
+        }
+        let total_cost = self.edges.iter().zip(&edge_flows).map(|(edge, flow)| edge.cost * flow).sum();
+        MultiCommodityFlow { fraction, total_cost, edge_flows, commodities }
+    }
+
+    fn outgoing(&self) -> Vec<Vec<usize>> {
+        let mut outgoing = vec![vec![]; self.node_names.len()];
+        for (i, edge) in self.edges.iter().enumerate() {
+            outgoing[edge.from].push(i);
+        }
+        outgoing
+    }
+
+    fn get_or_insert_vertex(&mut self, v: String) -> usize {
+        *self.node_indices.entry(v.clone()).or_insert_with(|| {
+            self.node_names.push(v);
+            self.node_names.len() - 1
+        })
+    }
+}
+
+// Dijkstra from the commodity source. Returns the edges of a shortest path to its sink under
+// `lengths`, or `None` if there is none.
+fn shortest_path(
+    outgoing: &[Vec<usize>], edges: &[CommodityEdge], lengths: &[f64], commodity: &Commodity
+) -> Option<Vec<usize>> {
+    let mut distances = vec![f64::INFINITY; outgoing.len()];
+    let mut parents = vec![None; outgoing.len()];
+    let mut queue = BinaryHeap::from([Entry(0., commodity.source)]);
+    distances[commodity.source] = 0.;
+    while let Some(Entry(distance, u)) = queue.pop() {
+        if u == commodity.sink {
+            break;
+        }
+        if distance > distances[u] {
+            continue;
+        }
+        for &e in &outgoing[u] {
+            let v = edges[e].to;
+            if distance + lengths[e] < distances[v] {
+                distances[v] = distance + lengths[e];
+                parents[v] = Some(e);
+                queue.push(Entry(distances[v], v));
+            }
+        }
+    }
+    let mut path = vec![];
+    let mut v = commodity.sink;
+    while v != commodity.source {
+        let e = parents[v]?;
+        path.push(e);
+        v = edges[e].from;
+    }
+    path.reverse();
+    Some(path)
+}
+
+// The largest capacity `c` such that the commodity sink can be reached along edges of at least
+// that capacity, found with a modified Dijkstra, or 0 if it can't be reached.
+fn widest_path_bottleneck(outgoing: &[Vec<usize>], edges: &[CommodityEdge], commodity: &Commodity) -> f64 {
+    let mut widths = vec![0.; outgoing.len()];
+    // Negated, so that the widest node comes first.
+    let mut queue = BinaryHeap::from([Entry(-f64::INFINITY, commodity.source)]);
+    widths[commodity.source] = f64::INFINITY;
+    while let Some(Entry(width, u)) = queue.pop() {
+        let width = -width;
+        if u == commodity.sink {
+            return width;
+        }
+        if width < widths[u] {
+            continue;
+        }
+        for &e in &outgoing[u] {
+            let v = edges[e].to;
+            let width = width.min(edges[e].capacity);
+            if width > widths[v] {
+                widths[v] = width;
+                queue.push(Entry(-width, v));
+            }
+        }
+    }
+    widths[commodity.sink]
+}
+
+// A queue entry ordered so that the closest node comes first.
+struct Entry(f64, usize);
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+impl Eq for Entry {}
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering { other.0.total_cmp(&self.0).then_with(|| other.1.cmp(&self.1)) }
+}
@@ -0,0 +1,739 @@
+use std::collections::HashSet;
+
+use rs_graph::{
+    Buildable, Builder, EdgeVec,
+    traits::{GraphSize, IndexGraph},
+    maxflow::pushrelabel,
+    mcf::{NetworkSimplex, MinCostFlow, SolutionState},
+};
+
+use crate::{
+    maxflow, mcf, utils::Budget, EdgeHandle, Graph, GraphEdge, GraphFlows, GraphNode, MaxFlowAlgorithm, McmfError,
+    MinCostFlowAlgorithm,
+};
+
+// The capacity of builder edges added with an infinite capacity. The network replaces it with a
+// finite bound, see `Network::infinite_capacity`.
+pub(crate) const INFINITE_CAPACITY: i64 = i64::MAX;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NodeData {
+    pub supply: i64,
+    pub capacity: Option<i64>,
+    // Not used by the solvers, see `GraphBuilder::set_node_group` and `set_node_metadata`.
+    pub group: Option<String>,
+    pub metadata: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EdgeData {
+    pub from: usize,
+    pub to: usize,
+    pub lower: i64,
+    pub capacity: i64,
+    pub cost: f64,
+    pub undirected: bool,
+    // `(capacity, cost)` pieces of a piecewise-linear cost, cheapest first, or empty for a single
+    // cost. `capacity` is then their total and `cost` that of the first piece.
+    pub pieces: Vec<(i64, f64)>,
+    // Not used by the solvers, only passed through to reports, see `GraphBuilder::set_edge_metadata`.
+    pub metadata: Option<String>,
+    pub handle: EdgeHandle,
+}
+
+impl EdgeData {
+    // All the per-unit costs of the edge.
+    pub fn costs(&self) -> impl Iterator<Item = f64> + '_ {
+        std::iter::once(self.cost).chain(self.pieces.iter().skip(1).map(|&(_, cost)| cost))
+    }
+}
+
+// The graph that is actually passed to the solvers. Its first nodes and edges correspond to
+// builder nodes and edges with the same indices. The rest are synthetic and must be mapped back
+// (or hidden) when reporting results:
+//   - A node with a capacity is split into an entry node (the original one, receiving all
+//     incoming edges) and an exit node (sending all outgoing edges), connected by an edge
+//     limited by the node capacity.
+//   - An undirected edge gets a synthetic reverse arc. Any flow going both ways is cancelled
+//     out after solving, so together they never carry more than the edge capacity.
+//   - An edge with a piecewise-linear cost keeps its first piece and gets a parallel arc for
+//     each further piece. Costs are convex, so cheaper pieces fill up first.
+//   - With `split_antiparallel`, a directed edge from a node to one with a smaller index is
+//     routed through a middle node, without an origin, if there is a directed edge the other
+//     way. It keeps its arc to the middle node, which passes the flow on over an uncapacitated
+//     arc, so that no two arcs connect the same nodes in opposite directions.
+//   - Multiple sources and sinks are connected to a super source and a super sink, which have
+//     no origin.
+// The solvers work with integer costs, so costs are multiplied by `cost_multiplier` and rounded.
+// All solves on the network share `budget`, which is unlimited by default.
+pub(crate) struct Network {
+    pub graph: Graph,
+    pub arcs: Vec<EdgeData>,
+    pub cost_multiplier: f64,
+    pub budget: Budget,
+    pub node_origins: Vec<Option<usize>>,
+    // What infinite capacities were replaced with, if there are any: one more than all finite
+    // capacities and supplies together, which a bounded flow never reaches on any cut.
+    pub infinite_capacity: Option<i64>,
+    exits: Vec<usize>,
+    num_edges: usize,
+    reverse_arcs: Vec<(usize, usize)>,
+    piece_arcs: Vec<(usize, usize)>,
+    middle_arcs: Vec<(usize, usize)>,
+    super_terminals: Option<(usize, usize)>,
+}
+
+impl Network {
+    pub fn new(nodes: &[NodeData], edges: &[EdgeData], cost_multiplier: f64, split_antiparallel: bool) -> Network {
+        Self::with_super_terminals(nodes, edges, cost_multiplier, &[], &[], split_antiparallel)
+    }
+
+    // Adds a super source feeding every node in `sources` and a super sink fed by every node in
+    // `sinks`, unless both are empty. A terminal without a capacity is effectively unbounded.
+    pub fn with_super_terminals(
+        nodes: &[NodeData], edges: &[EdgeData], cost_multiplier: f64,
+        sources: &[(usize, Option<i64>)], sinks: &[(usize, Option<i64>)], split_antiparallel: bool,
+    ) -> Network {
+        let mut node_origins: Vec<_> = (0..nodes.len()).map(Some).collect();
+        let mut exits: Vec<_> = (0..nodes.len()).collect();
+        let mut synthetic_arcs = vec![];
+        for (v, node) in nodes.iter().enumerate() {
+            if let Some(capacity) = node.capacity {
+                let exit = node_origins.len();
+                node_origins.push(Some(v));
+                exits[v] = exit;
+                synthetic_arcs.push(EdgeData { from: v, to: exit, capacity, ..Default::default() });
+            }
+        }
+        let mut middles = vec![None; edges.len()];
+        if split_antiparallel {
+            let directed = |edge: &EdgeData| !edge.undirected && edge.from != edge.to;
+            let ends: HashSet<_> =
+                edges.iter().filter(|edge| directed(edge)).map(|edge| (edge.from, edge.to)).collect();
+            for (middle, edge) in middles.iter_mut().zip(edges) {
+                if directed(edge) && edge.from > edge.to && ends.contains(&(edge.to, edge.from)) {
+                    *middle = Some(node_origins.len());
+                    node_origins.push(None);
+                }
+            }
+        }
+        let target = |i: usize| middles[i].unwrap_or(edges[i].to);
+        let mut reverse_arcs = vec![];
+        for (i, edge) in edges.iter().enumerate() {
+            if edge.undirected {
+                reverse_arcs.push((i, edges.len() + synthetic_arcs.len()));
+                synthetic_arcs.push(EdgeData { from: exits[edge.to], to: edge.from, ..edge.clone() });
+            }
+        }
+        let mut piece_arcs = vec![];
+        for (i, edge) in edges.iter().enumerate() {
+            for &(capacity, cost) in edge.pieces.iter().skip(1) {
+                piece_arcs.push((i, edges.len() + synthetic_arcs.len()));
+                let from = exits[edge.from];
+                synthetic_arcs.push(EdgeData { from, to: target(i), capacity, cost, ..Default::default() });
+            }
+        }
+        let mut middle_arcs = vec![];
+        for (i, edge) in edges.iter().enumerate() {
+            if let Some(middle) = middles[i] {
+                middle_arcs.push((i, edges.len() + synthetic_arcs.len()));
+                let arc = EdgeData { from: middle, to: edge.to, capacity: INFINITE_CAPACITY, ..Default::default() };
+                synthetic_arcs.push(arc);
+            }
+        }
+        let mut super_terminals = None;
+        if !sources.is_empty() || !sinks.is_empty() {
+            // Saturating, since the totals are only checked for overflow after construction.
+            let unbounded = edges.iter().fold(1, |total: i64, edge| total.saturating_add(edge.capacity));
+            let (super_source, super_sink) = (node_origins.len(), node_origins.len() + 1);
+            node_origins.extend([None, None]);
+            for &(v, capacity) in sources {
+                let capacity = capacity.unwrap_or(unbounded);
+                synthetic_arcs.push(EdgeData { from: super_source, to: v, capacity, ..Default::default() });
+            }
+            for &(v, capacity) in sinks {
+                let capacity = capacity.unwrap_or(unbounded);
+                synthetic_arcs.push(EdgeData { from: exits[v], to: super_sink, capacity, ..Default::default() });
+            }
+            super_terminals = Some((super_source, super_sink));
+        }
+        let mut arcs: Vec<_> = edges.iter().enumerate()
+            .map(|(i, edge)| match edge.pieces.first() {
+                Some(&(capacity, cost)) => EdgeData {
+                    from: exits[edge.from], to: target(i), capacity, cost, pieces: vec![], metadata: None, ..*edge
+                },
+                None => EdgeData { from: exits[edge.from], to: target(i), ..edge.clone() },
+            })
+            .chain(synthetic_arcs)
+            .collect();
+        let mut infinite_capacity = None;
+        if arcs.iter().any(|arc| arc.capacity == INFINITE_CAPACITY) {
+            let bound = arcs.iter()
+                .filter(|arc| arc.capacity != INFINITE_CAPACITY)
+                .map(|arc| arc.capacity)
+                .chain(nodes.iter().map(|node| node.supply.abs()))
+                .fold(1, |total: i64, amount| total.saturating_add(amount));
+            for arc in arcs.iter_mut().filter(|arc| arc.capacity == INFINITE_CAPACITY) {
+                arc.capacity = bound;
+            }
+            infinite_capacity = Some(bound);
+        }
+        let graph = build_graph(node_origins.len(), arcs.iter().map(|arc| (arc.from, arc.to)));
+        Network {
+            graph, arcs, cost_multiplier, budget: Budget::default(), node_origins, infinite_capacity, exits,
+            num_edges: edges.len(), reverse_arcs, piece_arcs, middle_arcs, super_terminals,
+        }
+    }
+
+    // The node where flow originating at builder node `v` starts.
+    pub fn entry(&self, v: usize) -> GraphNode {
+        self.graph.id2node(v)
+    }
+
+    // The node where flow destined for builder node `v` ends.
+    pub fn exit(&self, v: usize) -> GraphNode {
+        self.graph.id2node(self.exits[v])
+    }
+
+    pub fn super_source(&self) -> GraphNode {
+        self.graph.id2node(self.super_terminals.unwrap().0)
+    }
+
+    pub fn super_sink(&self) -> GraphNode {
+        self.graph.id2node(self.super_terminals.unwrap().1)
+    }
+
+    // The builder node that a graph node was created for, if any.
+    pub fn origin(&self, node: GraphNode) -> Option<usize> {
+        self.node_origins[self.graph.node_id(node)]
+    }
+
+    // The builder edge that goes through a middle node, see `split_antiparallel`.
+    pub fn middle_edge(&self, node: GraphNode) -> Option<usize> {
+        let node = self.graph.node_id(node);
+        self.middle_arcs.iter().find(|&&(_, arc)| self.arcs[arc].from == node).map(|&(edge, _)| edge)
+    }
+
+    // Maps per-builder-node balances to the graph so that supplies and demands pass through
+    // node capacities.
+    pub fn balances(&self, supplies: &[i64]) -> Vec<i64> {
+        let mut balances = vec![0; self.graph.num_nodes()];
+        for (v, &supply) in supplies.iter().enumerate() {
+            let node = if supply > 0 { self.entry(v) } else { self.exit(v) };
+            balances[self.graph.node_id(node)] += supply;
+        }
+        balances
+    }
+
+    // Returns the max flow value, edge flows and the number of augmenting paths if `algorithm`
+    // has them. Without lower bounds this is a single run of `algorithm`. Otherwise a feasible
+    // flow is found first as a max flow from a super source to a super sink, with an
+    // uncapacitated return edge from `sink` to `source`. It is then augmented in the residual
+    // graph. If the budget runs out, the flow found so far is returned, unless there is none
+    // because lower bounds are not satisfied yet.
+    pub async fn max_flow(
+        &self, source: GraphNode, sink: GraphNode, algorithm: MaxFlowAlgorithm
+    ) -> Result<(i64, GraphFlows<'_>, Option<u32>), McmfError> {
+        let graph = &self.graph;
+        if self.arcs.iter().all(|arc| arc.lower == 0) {
+            let arcs: Vec<_> = self.arcs.iter().map(|arc| (arc.from, arc.to, arc.capacity)).collect();
+            let (source, sink) = (graph.node_id(source), graph.node_id(sink));
+            let (max_flow, flows, augmentations) =
+                max_flow_on_arcs(graph.num_nodes(), &arcs, source, sink, algorithm, &self.budget, true).await;
+            let mut flows = EdgeVec::new_with(graph, |e| flows[graph.edge_id(e)]);
+            self.cancel_opposite_flows(&mut flows);
+            self.check_bounded(max_flow)?;
+            return Ok((max_flow, flows, augmentations));
+        }
+
+        let n = graph.num_nodes();
+        let (source, sink) = (graph.node_id(source), graph.node_id(sink));
+        let mut excesses = vec![0; n];
+        for arc in &self.arcs {
+            excesses[arc.from] -= arc.lower;
+            excesses[arc.to] += arc.lower;
+        }
+        let unbounded = self.arcs.iter().map(|arc| arc.capacity).sum::<i64>() + 1;
+        let (super_source, super_sink) = (n, n + 1);
+        let mut feasibility_arcs: Vec<_> = self.arcs.iter()
+            .map(|arc| (arc.from, arc.to, arc.capacity - arc.lower))
+            .collect();
+        feasibility_arcs.push((sink, source, unbounded));
+        for (v, &excess) in excesses.iter().enumerate() {
+            if excess > 0 {
+                feasibility_arcs.push((super_source, v, excess));
+            } else if excess < 0 {
+                feasibility_arcs.push((v, super_sink, -excess));
+            }
+        }
+        let (value, feasible_flows, feasibility_augmentations) =
+            max_flow_on_arcs(n + 2, &feasibility_arcs, super_source, super_sink, algorithm, &self.budget, false).await;
+        if let Some(error) = self.budget.error() {
+            return Err(error);
+        }
+        if value != excesses.iter().filter(|&&excess| excess > 0).sum::<i64>() {
+            return Err(McmfError::Infeasible("lower bounds cannot be satisfied".to_owned()));
+        }
+        let initial_flow = feasible_flows[self.arcs.len()];
+
+        let residual_arcs: Vec<_> = self.arcs.iter().zip(&feasible_flows).flat_map(|(arc, &flow)| [
+            (arc.from, arc.to, arc.capacity - arc.lower - flow),
+            (arc.to, arc.from, flow),
+        ]).collect();
+        let (augmentation, residual_flows, augmentations) =
+            max_flow_on_arcs(n, &residual_arcs, source, sink, algorithm, &self.budget, false).await;
+        let mut flows = EdgeVec::new_with(graph, |e| {
+            let i = graph.edge_id(e);
+            self.arcs[i].lower + feasible_flows[i] + residual_flows[2 * i] - residual_flows[2 * i + 1]
+        });
+        self.cancel_opposite_flows(&mut flows);
+        let augmentations = feasibility_augmentations.zip(augmentations).map(|(a, b)| a + b);
+        self.check_bounded(initial_flow + augmentation)?;
+        Ok((initial_flow + augmentation, flows, augmentations))
+    }
+
+    // Hoffman's reduction for a circulation that meets the lower bounds: after sending `lower`
+    // units up front, see `min_cost_flow`, a max flow from a super source feeding each excess to a
+    // super sink fed by each deficit moves the rest where it is needed. Returns its flows and
+    // whether each graph node is reachable from the super source in the residual graph. Unless
+    // none is, the flows are not feasible, and the reachable nodes must take in more lower bound
+    // flow than they can send on.
+    pub async fn feasible_circulation(
+        &self, algorithm: MaxFlowAlgorithm
+    ) -> Result<(GraphFlows<'_>, Vec<bool>), McmfError> {
+        let n = self.graph.num_nodes();
+        let (arcs, excesses, _) = self.without_lower_bounds(vec![0; n]);
+        let (super_source, super_sink) = (n, n + 1);
+        let mut arcs: Vec<_> = arcs.into_iter().map(|(from, to, capacity, _)| (from, to, capacity)).collect();
+        for (v, &excess) in excesses.iter().enumerate() {
+            if excess > 0 {
+                arcs.push((super_source, v, excess));
+            } else if excess < 0 {
+                arcs.push((v, super_sink, -excess));
+            }
+        }
+        let (_, flows, _) =
+            max_flow_on_arcs(n + 2, &arcs, super_source, super_sink, algorithm, &self.budget, false).await;
+        if let Some(error) = self.budget.error() {
+            return Err(error);
+        }
+        let mut residual_arcs = vec![vec![]; n + 2];
+        for (&(from, to, capacity), &flow) in arcs.iter().zip(&flows) {
+            if flow < capacity {
+                residual_arcs[from].push(to);
+            }
+            if flow > 0 {
+                residual_arcs[to].push(from);
+            }
+        }
+        let mut reachable = vec![false; n + 2];
+        let mut stack = vec![super_source];
+        reachable[super_source] = true;
+        while let Some(v) = stack.pop() {
+            for &u in &residual_arcs[v] {
+                if !reachable[u] {
+                    reachable[u] = true;
+                    stack.push(u);
+                }
+            }
+        }
+        reachable.truncate(n);
+        Ok((self.lower_bounds_added(&flows), reachable))
+    }
+
+    // A flow as large as `infinite_capacity` crosses every cut on an arc of infinite capacity.
+    pub fn check_bounded(&self, flow_value: i64) -> Result<(), McmfError> {
+        match self.infinite_capacity {
+            Some(bound) if flow_value >= bound => {
+                Err("max flow is unbounded: a path of infinite capacity edges connects the source to the sink".into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Max flow values as the capacity of builder edge `edge` takes each of `capacities`, which
+    // must be in increasing order. Raising a capacity keeps the previous flow feasible, so each
+    // value is found by augmenting that flow in the residual graph rather than from scratch.
+    // Lower bounds and piecewise costs on `edge` are not supported.
+    pub async fn max_flow_profile(
+        &mut self, source: GraphNode, sink: GraphNode, edge: usize, capacities: &[i64], algorithm: MaxFlowAlgorithm
+    ) -> Vec<i64> {
+        let graph = &self.graph;
+        let (n, source, sink) = (graph.num_nodes(), graph.node_id(source), graph.node_id(sink));
+        let reverse = self.reverse_arcs.iter().find(|&&(forward, _)| forward == edge).map(|&(_, reverse)| reverse);
+        let mut flows = vec![0; self.arcs.len()];
+        let mut value = 0;
+        let mut values = vec![];
+        for &capacity in capacities {
+            for arc in std::iter::once(edge).chain(reverse) {
+                self.arcs[arc].capacity = capacity;
+            }
+            let residual_arcs: Vec<_> = self.arcs.iter().zip(&flows).flat_map(|(arc, &flow)| [
+                (arc.from, arc.to, arc.capacity - flow),
+                (arc.to, arc.from, flow),
+            ]).collect();
+            let (augmentation, residual_flows, _) =
+                max_flow_on_arcs(n, &residual_arcs, source, sink, algorithm, &self.budget, false).await;
+            for (i, flow) in flows.iter_mut().enumerate() {
+                *flow += residual_flows[2 * i] - residual_flows[2 * i + 1];
+            }
+            value += augmentation;
+            values.push(value);
+        }
+        values
+    }
+
+    // Solves min-cost b-flow with the given algorithm, returning the scaled cost, edge flows and
+    // the number of simplex pivots if it applies, or `None` if the balances cannot be satisfied.
+    // Lower bounds are eliminated by sending `lower` units up front, i.e. moving them from the
+    // balance of the edge source to the balance of the edge target. See `mcf` for what happens
+    // when the budget runs out; the network simplex from rs-graph cannot be interrupted and
+    // ignores it. If the budget has a trace, network simplex runs on a copy that records its
+    // pivots, see `mcf::traced_network_simplex`.
+    pub async fn min_cost_flow(
+        &self, balances: Vec<i64>, algorithm: MinCostFlowAlgorithm
+    ) -> Option<(i64, GraphFlows<'_>, Option<u32>)> {
+        let (arcs, balances, lower_bounds_cost) = self.without_lower_bounds(balances);
+        if matches!(algorithm, MinCostFlowAlgorithm::NetworkSimplex) && self.budget.trace.is_none() {
+            return self.network_simplex(&balances, lower_bounds_cost);
+        }
+        let n = self.graph.num_nodes();
+        let (flows, pivots) =
+            min_cost_flow_on_arcs(n, &arcs, &balances, lower_bounds_cost, algorithm, &self.budget).await?;
+        let cost: i64 = arcs.iter().zip(&flows).map(|(arc, flow)| arc.3 * flow).sum();
+        Some((cost + lower_bounds_cost, self.lower_bounds_added(&flows), pivots))
+    }
+
+    // Min-cost max flow from `source` to `sink` in one min-cost flow solve rather than a max flow
+    // followed by a min-cost flow of its value. A return arc from the sink to the source with the
+    // capacity and (negative) cost of `return_arc` makes every unit that reaches the sink pay
+    // off, so a min-cost circulation sends as much as it can and at the least cost. The cost must
+    // be below minus the cost of any path, e.g. one more than the sum of absolute arc costs.
+    // Returns the flow value and then the same as `min_cost_flow`, with the cost of the builder
+    // arcs only. As there, `None` means that the lower bounds cannot be satisfied or the budget
+    // ran out. A traced network simplex records totals that include the return arc.
+    pub async fn min_cost_max_flow(
+        &self, source: GraphNode, sink: GraphNode, return_arc: (i64, i64), algorithm: MinCostFlowAlgorithm
+    ) -> Option<(i64, i64, GraphFlows<'_>, Option<u32>)> {
+        let graph = &self.graph;
+        let (mut arcs, balances, lower_bounds_cost) = self.without_lower_bounds(vec![0; graph.num_nodes()]);
+        arcs.push((graph.node_id(sink), graph.node_id(source), return_arc.0, return_arc.1));
+        let n = graph.num_nodes();
+        let (flows, pivots) =
+            min_cost_flow_on_arcs(n, &arcs, &balances, lower_bounds_cost, algorithm, &self.budget).await?;
+        let cost: i64 = arcs.iter().zip(&flows).take(self.arcs.len()).map(|(arc, flow)| arc.3 * flow).sum();
+        Some((flows[self.arcs.len()], cost + lower_bounds_cost, self.lower_bounds_added(&flows), pivots))
+    }
+
+    // Lower bounds are eliminated by sending `lower` units up front, see `min_cost_flow`. Returns
+    // the remaining arcs as `(from, to, capacity, cost)`, the adjusted balances and the scaled
+    // cost of the lower bounds.
+    fn without_lower_bounds(&self, mut balances: Vec<i64>) -> (Vec<mcf::Arc>, Vec<i64>, i64) {
+        let mut lower_bounds_cost = 0;
+        for arc in &self.arcs {
+            balances[arc.from] -= arc.lower;
+            balances[arc.to] += arc.lower;
+            lower_bounds_cost += arc.lower * self.scaled_cost(arc.cost);
+        }
+        let arcs = self.arcs.iter()
+            .map(|arc| (arc.from, arc.to, arc.capacity - arc.lower, self.scaled_cost(arc.cost)))
+            .collect();
+        (arcs, balances, lower_bounds_cost)
+    }
+
+    // Edge flows from the flows of `without_lower_bounds` arcs. Extra arcs at the end are dropped.
+    fn lower_bounds_added(&self, arc_flows: &[i64]) -> GraphFlows<'_> {
+        let graph = &self.graph;
+        let mut flows = EdgeVec::new_with(graph, |e| arc_flows[graph.edge_id(e)] + self.arcs[graph.edge_id(e)].lower);
+        self.cancel_opposite_flows(&mut flows);
+        flows
+    }
+
+    // Min-cost max flow starting from the given net flows on builder edges, see
+    // `mcf::reoptimize`. Edges without a flow start empty. Lower bounds are not supported.
+    pub async fn reoptimize(
+        &self, source: GraphNode, sink: GraphNode, edge_flows: &[i64]
+    ) -> Option<(i64, GraphFlows<'_>)> {
+        let graph = &self.graph;
+        let flows = self.arc_flows(edge_flows);
+        let arcs: Vec<_> = self.arcs.iter()
+            .map(|arc| (arc.from, arc.to, arc.capacity, self.scaled_cost(arc.cost)))
+            .collect();
+        let terminals = (graph.node_id(source), graph.node_id(sink));
+        let (value, flows) = mcf::reoptimize(graph.num_nodes(), &arcs, &flows, terminals, &self.budget).await?;
+        let mut flows = EdgeVec::new_with(graph, |e| flows[graph.edge_id(e)]);
+        self.cancel_opposite_flows(&mut flows);
+        Some((value, flows))
+    }
+
+    // Flows on all arcs for the given net flows on builder edges. Edges without a flow are empty.
+    pub fn arc_flows(&self, edge_flows: &[i64]) -> Vec<i64> {
+        let mut flows = vec![0; self.arcs.len()];
+        for (i, &flow) in edge_flows.iter().enumerate().take(self.num_edges) {
+            flows[i] = flow.max(0);
+        }
+        for &(forward, reverse) in &self.reverse_arcs {
+            flows[reverse] = edge_flows.get(forward).map_or(0, |&flow| (-flow).max(0));
+        }
+        // Whatever doesn't fit into the pieces so far moves on to the next one.
+        for &(edge, piece) in &self.piece_arcs {
+            let moved = (flows[edge] - self.arcs[edge].capacity).clamp(0, self.arcs[piece].capacity);
+            flows[edge] -= moved;
+            flows[piece] = moved;
+        }
+        for &(edge, arc) in &self.middle_arcs {
+            flows[arc] = edge_flows.get(edge).map_or(0, |&flow| flow.max(0));
+        }
+        // Split nodes pass on whatever enters their entry or leaves their exit, whichever is more,
+        // so that the flow is conserved unless the node is a terminal.
+        let is_split = |i: usize, arc: &EdgeData| {
+            i >= self.num_edges && self.exits.get(arc.from) == Some(&arc.to) && arc.from != arc.to
+        };
+        let (mut inflows, mut outflows) = (vec![0; self.graph.num_nodes()], vec![0; self.graph.num_nodes()]);
+        for (i, arc) in self.arcs.iter().enumerate() {
+            outflows[arc.from] += flows[i];
+            inflows[arc.to] += flows[i];
+        }
+        for (i, arc) in self.arcs.iter().enumerate() {
+            if is_split(i, arc) {
+                flows[i] = inflows[arc.from].max(outflows[arc.to]);
+            }
+        }
+        flows
+    }
+
+    fn network_simplex(&self, balances: &[i64], lower_bounds_cost: i64) -> Option<(i64, GraphFlows<'_>, Option<u32>)> {
+        let graph = &self.graph;
+        let arc = |e| &self.arcs[graph.edge_id(e)];
+        let mut spx = NetworkSimplex::new(graph);
+        // The default artificial cost only accounts for positive costs, so with negative ones
+        // feasible problems could be reported as infeasible.
+        spx.artificial_cost = Some(graph.edges().map(|e| self.scaled_cost(arc(e).cost).abs()).sum::<i64>() + 1);
+        spx.set_uppers(|e| arc(e).capacity - arc(e).lower);
+        spx.set_costs(|e| self.scaled_cost(arc(e).cost));
+        spx.set_balances(|u| balances[graph.node_id(u)]);
+        match spx.solve() {
+            SolutionState::Optimal => {},
+            SolutionState::Infeasible => return None,
+            state => {
+                self.budget.fail(format!("network simplex ended in state {:?}", state));
+                return None;
+            }
+        }
+        let mut flows = EdgeVec::new_with(graph, |e| spx.flow(e) + arc(e).lower);
+        self.cancel_opposite_flows(&mut flows);
+        Some((spx.value() + lower_bounds_cost, flows, Some(spx.num_iterations() as u32)))
+    }
+
+    // Finds a cycle of negative total cost among the arcs that can carry flow (beyond their lower
+    // bounds) using Bellman-Ford with all nodes as sources. Returns the cycle nodes in order.
+    pub fn negative_cycle(&self) -> Option<Vec<GraphNode>> {
+        self.negative_cycle_among(|arc| arc.capacity > arc.lower)
+    }
+
+    // A negative cycle of arcs with infinite capacity, around which the cost of a flow with
+    // supplies decreases without bound.
+    pub fn unbounded_cycle(&self) -> Option<Vec<GraphNode>> {
+        let bound = self.infinite_capacity?;
+        self.negative_cycle_among(|arc| arc.capacity == bound)
+    }
+
+    fn negative_cycle_among(&self, usable: impl Fn(&EdgeData) -> bool) -> Option<Vec<GraphNode>> {
+        let n = self.graph.num_nodes();
+        let arcs: Vec<_> = self.arcs.iter()
+            .filter(|arc| usable(arc))
+            .map(|arc| (arc.from, arc.to, self.scaled_cost(arc.cost) as i128))
+            .collect();
+        // Distances are sums of up to `n` costs, which may not fit into `i64`.
+        let mut distances = vec![0; n];
+        let mut predecessors = vec![None; n];
+        let mut last_relaxed = None;
+        for _ in 0..n {
+            last_relaxed = None;
+            for &(from, to, cost) in &arcs {
+                if distances[from] + cost < distances[to] {
+                    distances[to] = distances[from] + cost;
+                    predecessors[to] = Some(from);
+                    last_relaxed = Some(to);
+                }
+            }
+            last_relaxed?;
+        }
+        // A relaxation in the n-th round means a negative cycle. Going back n steps from the
+        // relaxed node is guaranteed to end up on the cycle.
+        let mut v = last_relaxed?;
+        for _ in 0..n {
+            v = predecessors[v].unwrap();
+        }
+        let mut cycle = vec![v];
+        let mut u = predecessors[v].unwrap();
+        while u != v {
+            cycle.push(u);
+            u = predecessors[u].unwrap();
+        }
+        cycle.reverse();
+        Some(cycle.into_iter().map(|v| self.graph.id2node(v)).collect())
+    }
+
+    // Nodes reachable from `source` in the residual graph of `flows`, indexed by node ID. If the
+    // flow is maximum, these form the source side of a minimum cut.
+    pub fn residual_reachable(&self, flows: &GraphFlows, source: GraphNode) -> Vec<bool> {
+        let graph = &self.graph;
+        let mut residual_arcs = vec![vec![]; graph.num_nodes()];
+        for (i, arc) in self.arcs.iter().enumerate() {
+            let flow = flows[graph.id2edge(i)];
+            if flow < arc.capacity {
+                residual_arcs[arc.from].push(arc.to);
+            }
+            if flow > arc.lower {
+                residual_arcs[arc.to].push(arc.from);
+            }
+        }
+        let mut reachable = vec![false; graph.num_nodes()];
+        let mut stack = vec![graph.node_id(source)];
+        reachable[graph.node_id(source)] = true;
+        while let Some(v) = stack.pop() {
+            for &u in &residual_arcs[v] {
+                if !reachable[u] {
+                    reachable[u] = true;
+                    stack.push(u);
+                }
+            }
+        }
+        reachable
+    }
+
+    // Leaves flow on at most one of the two arcs of each undirected edge. The arcs run from the
+    // exit of each end to the entry of the other, so an end with a node capacity gets a cycle
+    // through its capacity arc, and the flow taken off the arcs is taken off that arc as well.
+    // This keeps flow conservation intact and doesn't increase the cost, since undirected edge
+    // costs are non-negative.
+    fn cancel_opposite_flows(&self, flows: &mut GraphFlows) {
+        for &(forward, reverse) in &self.reverse_arcs {
+            let ends = [self.arcs[forward].to, self.arcs[reverse].to];
+            let (forward, reverse) = (self.graph.id2edge(forward), self.graph.id2edge(reverse));
+            let common = flows[forward].min(flows[reverse]);
+            flows[forward] -= common;
+            flows[reverse] -= common;
+            for v in ends.into_iter().filter(|&v| self.exits[v] != v) {
+                flows[self.graph.id2edge(self.capacity_arc(v))] -= common;
+            }
+        }
+    }
+
+    // The arc from the entry to the exit of builder node `v`, which must have a node capacity.
+    // These come first after the builder edges, in the order of the exits.
+    fn capacity_arc(&self, v: usize) -> usize {
+        self.num_edges + self.exits[v] - self.exits.len()
+    }
+
+    // The builder edge that a graph edge was created for, if any. Arcs out of middle nodes have
+    // none, so that paths list each edge once.
+    pub fn arc_origin(&self, e: GraphEdge) -> Option<usize> {
+        let arc = self.graph.edge_id(e);
+        if arc < self.num_edges {
+            Some(arc)
+        } else {
+            self.reverse_arcs.iter().chain(&self.piece_arcs).find(|&&(_, other)| other == arc).map(|&(edge, _)| edge)
+        }
+    }
+
+    pub fn scaled_cost(&self, cost: f64) -> i64 {
+        (cost * self.cost_multiplier).round() as i64
+    }
+
+    pub fn total_scaled_cost(&self, flows: &GraphFlows) -> i64 {
+        self.graph.edges().map(|e| flows[e] * self.scaled_cost(self.arcs[self.graph.edge_id(e)].cost)).sum()
+    }
+
+    // Net flow along a builder edge: negative if an undirected edge is used backwards.
+    pub fn edge_flow(&self, flows: &GraphFlows, edge: usize) -> i64 {
+        let reverse = self.reverse_arcs.iter().find(|&&(forward, _)| forward == edge);
+        let reverse_flow = reverse.map_or(0, |&(_, reverse)| flows[self.graph.id2edge(reverse)]);
+        let pieces_flow: i64 = self.piece_arcs.iter()
+            .filter(|&&(origin, _)| origin == edge)
+            .map(|&(_, piece)| flows[self.graph.id2edge(piece)])
+            .sum();
+        flows[self.graph.id2edge(edge)] + pieces_flow - reverse_flow
+    }
+}
+
+// Runs `algorithm` on a graph given as a list of `(from, to, capacity, cost)` arcs, see
+// `Network::min_cost_flow`. Returns the flow on each arc and the number of simplex pivots if it
+// applies, or `None` if the balances cannot be satisfied.
+async fn min_cost_flow_on_arcs(
+    num_nodes: usize, arcs: &[mcf::Arc], balances: &[i64], cost_offset: i64, algorithm: MinCostFlowAlgorithm,
+    budget: &Budget,
+) -> Option<(Vec<i64>, Option<u32>)> {
+    match algorithm {
+        MinCostFlowAlgorithm::NetworkSimplex if budget.trace.is_none() => {
+            let graph = build_graph(num_nodes, arcs.iter().map(|arc| (arc.0, arc.1)));
+            let mut spx = NetworkSimplex::new(&graph);
+            // The default artificial cost only accounts for positive costs, which isn't enough for
+            // a return arc, see `Network::min_cost_max_flow`.
+            spx.artificial_cost = Some(arcs.iter().map(|arc| arc.3.abs()).sum::<i64>() + 1);
+            spx.set_uppers(|e| arcs[graph.edge_id(e)].2);
+            spx.set_costs(|e| arcs[graph.edge_id(e)].3);
+            spx.set_balances(|u| balances[graph.node_id(u)]);
+            match spx.solve() {
+                SolutionState::Optimal => {},
+                SolutionState::Infeasible => return None,
+                state => {
+                    budget.fail(format!("network simplex ended in state {:?}", state));
+                    return None;
+                }
+            }
+            Some((graph.edges().map(|e| spx.flow(e)).collect(), Some(spx.num_iterations() as u32)))
+        }
+        MinCostFlowAlgorithm::NetworkSimplex => {
+            let traced = mcf::traced_network_simplex(num_nodes, arcs, balances, cost_offset, budget).await;
+            traced.map(|(flows, pivots)| (flows, Some(pivots)))
+        }
+        MinCostFlowAlgorithm::SuccessiveShortestPaths => {
+            Some((mcf::successive_shortest_paths(num_nodes, arcs, balances, budget).await?, None))
+        }
+        MinCostFlowAlgorithm::CostScaling => Some((mcf::cost_scaling(num_nodes, arcs, balances, budget).await?, None)),
+        MinCostFlowAlgorithm::CycleCanceling => {
+            Some((mcf::cycle_canceling(num_nodes, arcs, balances, budget).await?, None))
+        }
+    }
+}
+
+fn build_graph(num_nodes: usize, arcs: impl Iterator<Item = (usize, usize)>) -> Graph {
+    Graph::new_with(|b| {
+        let nodes = b.add_nodes(num_nodes);
+        for (from, to) in arcs {
+            b.add_edge(nodes[from], nodes[to]);
+        }
+    })
+}
+
+// Runs `algorithm` on a graph given as a list of `(from, to, capacity)` arcs. Returns the flow
+// value, the flow on each arc and the number of augmenting paths if `algorithm` has them.
+// Push-relabel from rs-graph cannot be interrupted, so it ignores `budget`. See `maxflow::dinic`
+// for `report`.
+pub(crate) async fn max_flow_on_arcs(
+    num_nodes: usize, arcs: &[(usize, usize, i64)], source: usize, sink: usize, algorithm: MaxFlowAlgorithm,
+    budget: &Budget, report: bool,
+) -> (i64, Vec<i64>, Option<u32>) {
+    match algorithm {
+        MaxFlowAlgorithm::Dinic => {
+            let (value, flows, augmentations) = maxflow::dinic(num_nodes, arcs, source, sink, budget, report).await;
+            (value, flows, Some(augmentations))
+        }
+        MaxFlowAlgorithm::PushRelabel => {
+            let graph = build_graph(num_nodes, arcs.iter().map(|&(from, to, _)| (from, to)));
+            let capacities = |e| arcs[graph.edge_id(e)].2;
+            let (value, preflow, _) = pushrelabel(&graph, graph.id2node(source), graph.id2node(sink), capacities);
+            // It can stop with excess left at nodes that don't reach the sink. Everything that
+            // reaches the sink came from the source along arcs with preflow, so a max flow within
+            // those arcs has the same value and is conserved.
+            let preflow_arcs: Vec<_> = graph.edges().map(|e| {
+                let (from, to, _) = arcs[graph.edge_id(e)];
+                (from, to, preflow[e])
+            }).collect();
+            let (_, flows, _) = maxflow::dinic(num_nodes, &preflow_arcs, source, sink, budget, false).await;
+            (value, flows, None)
+        }
+    }
+}
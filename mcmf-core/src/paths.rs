@@ -0,0 +1,291 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, VecDeque}};
+
+use rs_graph::traits::{Directed, GraphIterator, GraphSize, IndexGraph};
+
+use crate::{network::Network, random::SplitMix64, GraphEdge, GraphFlows, GraphNode, PathDecomposition};
+
+// A path in the network graph together with the flow sent along it.
+pub(crate) struct FlowPath {
+    pub flow: i64,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+// Splits `flows` into paths from `source` to `sink` and cycles, returned in that order. Flow is
+// left on cycles e.g. when forced by lower bounds, or along zero cost cycles. The paths carry the
+// flow value, even where a cycle runs through `source` or `sink`. The result only depends on the
+// arguments; `seed` shuffles the depth-first one, see `GraphBuilder::set_path_seed`.
+pub(crate) fn decompose(
+    network: &Network,
+    flows: &GraphFlows,
+    node_names: &[String],
+    source: GraphNode,
+    sink: GraphNode,
+    strategy: PathDecomposition,
+    seed: Option<u32>,
+) -> (Vec<FlowPath>, Vec<FlowPath>) {
+    let graph = &network.graph;
+    let mut remaining_flows: Vec<_> = graph.edges().map(|e| flows[e]).collect();
+    let value = graph.out_iter(source).iter(graph).map(|(e, _)| flows[e]).sum::<i64>()
+        - graph.in_iter(source).iter(graph).map(|(e, _)| flows[e]).sum::<i64>();
+    let mut cycles = vec![];
+    let paths = match strategy {
+        PathDecomposition::DepthFirst => {
+            depth_first_paths(network, &mut remaining_flows, &mut cycles, (source, sink, value), seed)
+        }
+        // Finding the minimum number of paths is NP-hard, so this simply keeps the best result
+        // among the other strategies.
+        PathDecomposition::FewestPaths => {
+            return [
+                PathDecomposition::DepthFirst,
+                PathDecomposition::WidestFirst,
+                PathDecomposition::ShortestFirst,
+                PathDecomposition::Lexicographic,
+            ].into_iter()
+                .map(|strategy| decompose(network, flows, node_names, source, sink, strategy, seed))
+                .min_by_key(|(paths, _)| paths.len())
+                .unwrap();
+        }
+        PathDecomposition::WidestFirst => extract_paths(network, &mut remaining_flows, (source, value), |remaining| {
+            widest_path(network, remaining, source, sink)
+        }),
+        PathDecomposition::ShortestFirst => extract_paths(network, &mut remaining_flows, (source, value), |remaining| {
+            shortest_path(network, remaining, source, sink)
+        }),
+        PathDecomposition::Lexicographic => extract_paths(network, &mut remaining_flows, (source, value), |remaining| {
+            lexicographic_path(network, node_names, remaining, source, sink)
+        }),
+    };
+    cycles.extend(leftover_cycles(network, &mut remaining_flows));
+    assert!(remaining_flows.iter().all(|&flow| flow == 0));
+    (paths, cycles)
+}
+
+// Walks from `source` along edges with remaining flow until `sink` is reached and takes that path,
+// or as much of it as is left of the flow value `value`. If the walk runs into itself, the cycle
+// is moved to `cycles` and the walk continues from there. Each path or cycle uses up at least one
+// edge, and every node remembers the first of its edges that may still have flow, so this takes
+// O(V·E) overall. With a seed, the edges of each node are tried in a random order instead of the
+// order they were added.
+fn depth_first_paths(
+    network: &Network, remaining_flows: &mut [i64], cycles: &mut Vec<FlowPath>,
+    (source, sink, mut value): (GraphNode, GraphNode, i64), seed: Option<u32>,
+) -> Vec<FlowPath> {
+    let graph = &network.graph;
+    let mut out_edges: Vec<Vec<_>> = graph.nodes().map(|u| graph.out_iter(u).iter(graph).collect()).collect();
+    if let Some(seed) = seed {
+        let mut random = SplitMix64::new(seed);
+        for edges in &mut out_edges {
+            random.shuffle(edges);
+        }
+    }
+    let mut next_edge = vec![0; graph.num_nodes()];
+    let mut positions = vec![None; graph.num_nodes()];
+    let mut paths = vec![];
+    let mut path_nodes = vec![source];
+    let mut path_edges: Vec<GraphEdge> = vec![];
+    positions[graph.node_id(source)] = Some(0);
+    while value > 0 {
+        let u = *path_nodes.last().unwrap();
+        if u == sink {
+            let flow = path_edges.iter().map(|&e| remaining_flows[graph.edge_id(e)]).min().unwrap().min(value);
+            value -= flow;
+            for &e in &path_edges {
+                remaining_flows[graph.edge_id(e)] -= flow;
+            }
+            for &v in &path_nodes[1..] {
+                positions[graph.node_id(v)] = None;
+            }
+            paths.push(FlowPath { flow, nodes: path_nodes.split_off(0), edges: path_edges.split_off(0) });
+            path_nodes.push(source);
+            continue;
+        }
+        let u = graph.node_id(u);
+        let edges = &out_edges[u];
+        while next_edge[u] < edges.len() && remaining_flows[graph.edge_id(edges[next_edge[u]].0)] == 0 {
+            next_edge[u] += 1;
+        }
+        // Flow is conserved, so only the source can run out of outgoing flow.
+        let Some(&(e, v)) = edges.get(next_edge[u]) else { break };
+        if let Some(start) = positions[graph.node_id(v)] {
+            path_edges.push(e);
+            let cycle = &path_edges[start..];
+            let flow = cycle.iter().map(|&e| remaining_flows[graph.edge_id(e)]).min().unwrap();
+            for &e in cycle {
+                remaining_flows[graph.edge_id(e)] -= flow;
+            }
+            for &v in &path_nodes[start + 1..] {
+                positions[graph.node_id(v)] = None;
+            }
+            let mut nodes = path_nodes.split_off(start);
+            nodes.push(v);
+            path_nodes.push(v);
+            cycles.push(FlowPath { flow, nodes, edges: path_edges.split_off(start) });
+        } else {
+            positions[graph.node_id(v)] = Some(path_nodes.len());
+            path_nodes.push(v);
+            path_edges.push(e);
+        }
+    }
+    paths
+}
+
+// Splits the flow left once there are no more paths, which is conserved at every node, into
+// cycles. Each cycle uses up at least one edge.
+fn leftover_cycles(network: &Network, remaining_flows: &mut [i64]) -> Vec<FlowPath> {
+    let graph = &network.graph;
+    let mut positions = vec![None; graph.num_nodes()];
+    let mut cycles = vec![];
+    for first in graph.edges() {
+        while remaining_flows[graph.edge_id(first)] > 0 {
+            let (mut nodes, mut edges) = (vec![graph.src(first)], vec![]);
+            positions[graph.node_id(graph.src(first))] = Some(0);
+            let mut e = first;
+            // Flow is conserved, so the walk goes on until it runs into itself.
+            let start = loop {
+                edges.push(e);
+                let v = graph.snk(e);
+                if let Some(start) = positions[graph.node_id(v)] {
+                    break start;
+                }
+                positions[graph.node_id(v)] = Some(nodes.len());
+                nodes.push(v);
+                e = graph.out_iter(v).iter(graph).map(|(e, _)| e)
+                    .find(|&e| remaining_flows[graph.edge_id(e)] > 0)
+                    .unwrap();
+            };
+            for &v in &nodes {
+                positions[graph.node_id(v)] = None;
+            }
+            let mut nodes = nodes.split_off(start);
+            nodes.push(nodes[0]);
+            let edges = edges.split_off(start);
+            let flow = edges.iter().map(|&e| remaining_flows[graph.edge_id(e)]).min().unwrap();
+            for &e in &edges {
+                remaining_flows[graph.edge_id(e)] -= flow;
+            }
+            cycles.push(FlowPath { flow, nodes, edges });
+        }
+    }
+    cycles
+}
+
+// Repeatedly takes the path chosen by `next_path` and sends as much flow along it as possible, up to
+// the flow value `value` that is left.
+fn extract_paths(
+    network: &Network,
+    remaining_flows: &mut [i64],
+    (source, mut value): (GraphNode, i64),
+    next_path: impl Fn(&[i64]) -> Option<Vec<GraphEdge>>,
+) -> Vec<FlowPath> {
+    let graph = &network.graph;
+    let mut paths = vec![];
+    while let Some(edges) = (value > 0).then(|| next_path(remaining_flows)).flatten() {
+        let flow = edges.iter().map(|&e| remaining_flows[graph.edge_id(e)]).min().unwrap().min(value);
+        value -= flow;
+        for &e in &edges {
+            remaining_flows[graph.edge_id(e)] -= flow;
+        }
+        let nodes = std::iter::once(source).chain(edges.iter().map(|&e| graph.snk(e))).collect();
+        paths.push(FlowPath { flow, nodes, edges });
+    }
+    paths
+}
+
+// Follows `predecessors` back from `sink` to collect the path edges.
+fn trace_back(network: &Network, predecessors: &[Option<GraphEdge>], sink: GraphNode) -> Vec<GraphEdge> {
+    let graph = &network.graph;
+    let mut edges = vec![];
+    let mut v = sink;
+    while let Some(e) = predecessors[graph.node_id(v)] {
+        edges.push(e);
+        v = graph.src(e);
+    }
+    edges.reverse();
+    edges
+}
+
+// The path with the largest bottleneck, found with a modified Dijkstra.
+fn widest_path(network: &Network, remaining_flows: &[i64], source: GraphNode, sink: GraphNode) -> Option<Vec<GraphEdge>> {
+    let graph = &network.graph;
+    let mut widths = vec![0; graph.num_nodes()];
+    let mut predecessors = vec![None; graph.num_nodes()];
+    let mut done = vec![false; graph.num_nodes()];
+    let mut queue = BinaryHeap::from([(i64::MAX, Reverse(graph.node_id(source)))]);
+    widths[graph.node_id(source)] = i64::MAX;
+    while let Some((width, Reverse(u))) = queue.pop() {
+        if done[u] {
+            continue;
+        }
+        done[u] = true;
+        for (e, v) in graph.out_iter(graph.id2node(u)).iter(graph) {
+            let v = graph.node_id(v);
+            let width = width.min(remaining_flows[graph.edge_id(e)]);
+            if width > widths[v] {
+                widths[v] = width;
+                predecessors[v] = Some(e);
+                queue.push((width, Reverse(v)));
+            }
+        }
+    }
+    (widths[graph.node_id(sink)] > 0).then(|| trace_back(network, &predecessors, sink))
+}
+
+// The path with the fewest edges, found with BFS.
+fn shortest_path(network: &Network, remaining_flows: &[i64], source: GraphNode, sink: GraphNode) -> Option<Vec<GraphEdge>> {
+    let graph = &network.graph;
+    let mut predecessors = vec![None; graph.num_nodes()];
+    let mut visited = vec![false; graph.num_nodes()];
+    let mut queue = VecDeque::from([source]);
+    visited[graph.node_id(source)] = true;
+    while let Some(u) = queue.pop_front() {
+        for (e, v) in graph.out_iter(u).iter(graph) {
+            if remaining_flows[graph.edge_id(e)] > 0 && !visited[graph.node_id(v)] {
+                visited[graph.node_id(v)] = true;
+                predecessors[graph.node_id(v)] = Some(e);
+                queue.push_back(v);
+            }
+        }
+    }
+    visited[graph.node_id(sink)].then(|| trace_back(network, &predecessors, sink))
+}
+
+// The path whose sequence of node names is lexicographically smallest, found with DFS that tries
+// the successors in name order. A node that was left without reaching the sink cannot lead to a
+// smaller path later, so each node is visited at most once.
+fn lexicographic_path(
+    network: &Network, node_names: &[String],
+    remaining_flows: &[i64], source: GraphNode, sink: GraphNode,
+) -> Option<Vec<GraphEdge>> {
+    let graph = &network.graph;
+    // Unnamed nodes are ordered by index.
+    let name = |v| network.origin(v).map(|v| (node_names.get(v), v));
+    let successors = |u| {
+        let mut successors: Vec<_> = graph.out_iter(u).iter(graph)
+            .filter(|&(e, _)| remaining_flows[graph.edge_id(e)] > 0)
+            .collect();
+        // Sorting by edge ID as well keeps the choice between parallel edges stable.
+        successors.sort_by_key(|&(e, v)| (name(v), graph.edge_id(e)));
+        successors.reverse();
+        successors
+    };
+    let mut visited = vec![false; graph.num_nodes()];
+    let mut predecessors = vec![None; graph.num_nodes()];
+    visited[graph.node_id(source)] = true;
+    let mut stack = vec![successors(source)];
+    while let Some(candidates) = stack.last_mut() {
+        match candidates.pop() {
+            Some((e, v)) if !visited[graph.node_id(v)] => {
+                visited[graph.node_id(v)] = true;
+                predecessors[graph.node_id(v)] = Some(e);
+                if v == sink {
+                    return Some(trace_back(network, &predecessors, sink));
+                }
+                stack.push(successors(v));
+            }
+            Some(_) => {}
+            None => { stack.pop(); }
+        }
+    }
+    None
+}
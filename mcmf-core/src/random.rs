@@ -0,0 +1,82 @@
+// Random instances, e.g. for trying out the solvers or benchmarks. The same seed gives the same
+// graph on every platform, so an instance can be shared as its parameters.
+
+use std::collections::HashSet;
+
+use crate::{GraphBuilder, McmfError};
+
+// A graph with `nodes` nodes and `edges` edges, capacities from 1 to `max_capacity` and costs
+// from 0 to `max_cost`, all integers. The nodes are the source "s", the sink "t" and "v1", "v2",
+// ... in between, and the first edges chain them all in a random order from "s" to "t", so that
+// there is always a flow. The remaining edges connect random pairs of different nodes, without
+// parallel edges. The builder has the source and sink set.
+pub fn generate_random_graph(
+    nodes: u32, edges: u32, max_capacity: u32, max_cost: u32, seed: u32
+) -> Result<GraphBuilder, McmfError> {
+    let n = nodes as u64;
+    if n < 2 {
+        return Err("a random graph needs at least 2 nodes".into());
+    }
+    if !(n - 1..=n * (n - 1)).contains(&(edges as u64)) {
+        return Err(format!("a random graph with {} nodes needs {} to {} edges", n, n - 1, n * (n - 1)).into());
+    }
+    if max_capacity == 0 {
+        return Err("max capacity must be positive".into());
+    }
+    let mut random = SplitMix64::new(seed);
+    let mut names = vec!["s".to_owned()];
+    names.extend((1..nodes - 1).map(|i| format!("v{}", i)));
+    names.push("t".to_owned());
+    let mut chain: Vec<_> = (1..nodes as usize - 1).collect();
+    random.shuffle(&mut chain);
+    chain.insert(0, 0);
+    chain.push(nodes as usize - 1);
+    let mut pairs: Vec<_> = chain.windows(2).map(|pair| (pair[0], pair[1])).collect();
+    let mut used: HashSet<_> = pairs.iter().copied().collect();
+    while pairs.len() < edges as usize {
+        let (from, to) = (random.below(n) as usize, random.below(n) as usize);
+        if from != to && used.insert((from, to)) {
+            pairs.push((from, to));
+        }
+    }
+    let mut builder = GraphBuilder::new();
+    builder.add_nodes(names.clone())?;
+    for (from, to) in pairs {
+        let capacity = 1 + random.below(max_capacity as u64);
+        let cost = random.below(max_cost as u64 + 1);
+        builder.add_edge(names[from].clone(), names[to].clone(), capacity as f64, cost as f64)?;
+    }
+    builder.set_source(Some("s".to_owned()));
+    builder.set_sink(Some("t".to_owned()));
+    Ok(builder)
+}
+
+// The SplitMix64 generator, which is tiny and good enough for instances and tie-breaking.
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u32) -> SplitMix64 {
+        SplitMix64(seed as u64)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // A number below `bound`, which must be positive. The modulo bias is negligible for the sizes
+    // of graphs that can be solved.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    // Fisher-Yates.
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+// Sensitivity analysis of a solution. The solution keeps the residual graph of its flow, so that
+// queries can be answered after the network is gone. Costs and amounts are in solver units
+// internally and unscaled when reported.
+
+use std::{cmp::Reverse, collections::{BinaryHeap, VecDeque}};
+
+use rs_graph::traits::{GraphSize, IndexGraph};
+
+use crate::{network::Network, GraphFlows, GraphNode, McmfError};
+
+#[derive(Clone, Debug)]
+struct Arc {
+    from: usize,
+    to: usize,
+    flow: i64,
+    lower: i64,
+    capacity: i64,
+    cost: i64,
+    // The builder edge, see `Network::arc_origin`.
+    origin: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Sensitivity {
+    num_nodes: usize,
+    source: usize,
+    sink: usize,
+    arcs: Vec<Arc>,
+    cost_multiplier: f64,
+    capacity_denominator: f64,
+    // Residual distances from a virtual node connected to every node, see `find_potentials`.
+    potentials: Option<Vec<i128>>,
+}
+
+impl Sensitivity {
+    pub fn new(
+        network: &Network, flows: &GraphFlows, (source, sink): (GraphNode, GraphNode), capacity_denominator: u32,
+    ) -> Sensitivity {
+        let graph = &network.graph;
+        let arcs = graph.edges().map(|e| {
+            let arc = &network.arcs[graph.edge_id(e)];
+            Arc {
+                from: arc.from,
+                to: arc.to,
+                flow: flows[e],
+                lower: arc.lower,
+                capacity: arc.capacity,
+                cost: network.scaled_cost(arc.cost),
+                origin: network.arc_origin(e),
+            }
+        }).collect();
+        Sensitivity {
+            num_nodes: graph.num_nodes(),
+            source: graph.node_id(source),
+            sink: graph.node_id(sink),
+            arcs,
+            cost_multiplier: network.cost_multiplier,
+            capacity_denominator: capacity_denominator as f64,
+            potentials: None,
+        }
+    }
+
+    // Computes node potentials for which no residual arc has a negative reduced cost using
+    // Bellman-Ford. Fails if there is a negative residual cycle, i.e. the flow is not of minimum
+    // cost; the queries below that need costs are unavailable then.
+    pub fn find_potentials(&mut self) -> bool {
+        let residual_arcs: Vec<_> = self.residual_arcs(None).collect();
+        let mut distances = vec![0; self.num_nodes];
+        for _ in 0..=self.num_nodes {
+            let mut relaxed = false;
+            for &(from, to, cost) in &residual_arcs {
+                if distances[from] + cost < distances[to] {
+                    distances[to] = distances[from] + cost;
+                    relaxed = true;
+                }
+            }
+            if !relaxed {
+                self.potentials = Some(distances);
+                return true;
+            }
+        }
+        false
+    }
+
+    // The potential of network node `v` in cost units, relative to the source.
+    pub fn potential(&self, v: usize) -> Option<f64> {
+        let potentials = self.potentials.as_ref()?;
+        Some((potentials[v] - potentials[self.source]) as f64 / self.cost_multiplier)
+    }
+
+    // The range of costs for builder edge `edge` over which the flow stays optimal. Lowering the
+    // cost below the range makes sending more flow along the edge worthwhile, raising it above
+    // makes sending less worthwhile.
+    pub fn cost_range(&self, edge: usize) -> Result<Option<(f64, f64)>, McmfError> {
+        let mut arcs = self.edge_arcs(edge)?;
+        let arc = arcs.next().unwrap();
+        if arcs.next().is_some() {
+            return Err("cost ranges are not supported for undirected edges".into());
+        }
+        if self.potentials.is_none() {
+            return Ok(None);
+        }
+        // Any cost keeps the flow optimal unless the change closes a negative residual cycle
+        // through the edge.
+        let lower = match arc.flow < arc.capacity {
+            true => self.distance(arc.to, arc.from, Some(edge)).map_or(f64::NEG_INFINITY, |d| -d as f64),
+            false => f64::NEG_INFINITY,
+        };
+        let upper = match arc.flow > arc.lower {
+            true => self.distance(arc.from, arc.to, Some(edge)).map_or(f64::INFINITY, |d| d as f64),
+            false => f64::INFINITY,
+        };
+        Ok(Some((lower / self.cost_multiplier, upper / self.cost_multiplier)))
+    }
+
+    // The range of capacities for builder edge `edge` over which the flow stays feasible and,
+    // for the same flow value, of minimum cost. Raising the capacity of a saturated edge makes
+    // the flow suboptimal if there is a cheaper way to route part of it through the edge.
+    pub fn capacity_range(&self, edge: usize) -> Result<Option<(f64, f64)>, McmfError> {
+        let arc = self.edge_arcs(edge)?.max_by_key(|arc| arc.flow).unwrap();
+        if self.potentials.is_none() {
+            return Ok(None);
+        }
+        let gets_cheaper = arc.flow == arc.capacity
+            && self.distance(arc.to, arc.from, Some(edge)).is_some_and(|d| arc.cost as i128 + d < 0);
+        let upper = if gets_cheaper { arc.capacity as f64 } else { f64::INFINITY };
+        Ok(Some((arc.flow as f64 / self.capacity_denominator, upper / self.capacity_denominator)))
+    }
+
+    // The cost per unit of the cheapest residual path from the source to the sink, i.e. of sending
+    // a little more flow, or infinity if the flow is maximum. `None` without potentials.
+    pub fn marginal_cost(&self) -> Option<f64> {
+        self.potentials.as_ref()?;
+        let distance = self.distance(self.source, self.sink, None);
+        Some(distance.map_or(f64::INFINITY, |d| d as f64 / self.cost_multiplier))
+    }
+
+    // Saturated builder edges that would let more flow through if they had more capacity: the
+    // source reaches their start and their end reaches the sink in the residual graph. This is
+    // stricter than being in a min cut, since an edge may only help together with others on the
+    // same path. `None` if the flow is not maximum.
+    pub fn bottleneck_edges(&self) -> Option<Vec<usize>> {
+        let from_source = self.reachable(self.source, false);
+        if from_source[self.sink] {
+            return None;
+        }
+        let to_sink = self.reachable(self.sink, true);
+        let mut edges: Vec<_> = self.arcs.iter()
+            .filter(|arc| arc.flow == arc.capacity && from_source[arc.from] && to_sink[arc.to])
+            .filter_map(|arc| arc.origin)
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        Some(edges)
+    }
+
+    // Net flows on the `num_edges` builder edges of a different flow with the same value and cost,
+    // or `None` if this one is unique or there are no potentials. The flow is changed along a
+    // residual cycle of zero cost, i.e. of arcs with zero reduced cost, as far as it goes.
+    pub fn alternative_edge_flows(&self, num_edges: usize) -> Option<Vec<f64>> {
+        let potentials = self.potentials.as_ref()?;
+        // Residual arcs as `(from, to, arc, forward)`.
+        let tight_arcs: Vec<_> = self.arcs.iter().enumerate().flat_map(|(i, arc)| {
+            let reduced_cost = arc.cost as i128 + potentials[arc.from] - potentials[arc.to];
+            let forward = (arc.flow < arc.capacity).then_some((arc.from, arc.to, i, true));
+            let backward = (arc.flow > arc.lower).then_some((arc.to, arc.from, i, false));
+            forward.into_iter().chain(backward).filter(move |_| reduced_cost == 0)
+        }).collect();
+        let mut outgoing = vec![vec![]; self.num_nodes];
+        for (k, &(from, ..)) in tight_arcs.iter().enumerate() {
+            outgoing[from].push(k);
+        }
+        let cycle = (0..tight_arcs.len()).find_map(|first| self.tight_cycle(&tight_arcs, &outgoing, first))?;
+        let residual_capacity = |&(_, _, i, forward): &(usize, usize, usize, bool)| {
+            let arc = &self.arcs[i];
+            if forward { arc.capacity - arc.flow } else { arc.flow - arc.lower }
+        };
+        let amount = cycle.iter().map(|&k| residual_capacity(&tight_arcs[k])).min().unwrap();
+        let mut flows: Vec<_> = self.arcs.iter().map(|arc| arc.flow).collect();
+        for &k in &cycle {
+            let (_, _, i, forward) = tight_arcs[k];
+            flows[i] += if forward { amount } else { -amount };
+        }
+        let mut edge_flows = vec![0.; num_edges];
+        for (i, (arc, flow)) in self.arcs.iter().zip(flows).enumerate() {
+            if let Some(edge) = arc.origin {
+                // The arc with the edge's own index is the forward one.
+                let sign = if i == edge { 1. } else { -1. };
+                edge_flows[edge] += sign * flow as f64 / self.capacity_denominator;
+            }
+        }
+        Some(edge_flows)
+    }
+
+    // Bounds `(lower, capacity)` for each network arc within which every flow of the same value
+    // is of minimum cost, or `None` without potentials. Arcs with a positive reduced cost must
+    // stay at their lower bound, those with a negative one must be saturated.
+    pub fn optimal_bounds(&self) -> Option<Vec<(i64, i64)>> {
+        let potentials = self.potentials.as_ref()?;
+        Some(self.arcs.iter().map(|arc| {
+            match (arc.cost as i128 + potentials[arc.from] - potentials[arc.to]).signum() {
+                1 => (arc.lower, arc.lower),
+                -1 => (arc.capacity, arc.capacity),
+                _ => (arc.lower, arc.capacity),
+            }
+        }).collect())
+    }
+
+    // A cycle of `tight_arcs` through `tight_arcs[first]`, as indices into `tight_arcs`. Going
+    // back and forth along one edge doesn't count, so the rest of the cycle avoids it.
+    fn tight_cycle(
+        &self, tight_arcs: &[(usize, usize, usize, bool)], outgoing: &[Vec<usize>], first: usize,
+    ) -> Option<Vec<usize>> {
+        let (start, end, first_arc, _) = tight_arcs[first];
+        let same_edge = |i: usize| i == first_arc || self.arcs[i].origin.is_some_and(|edge| {
+            self.arcs[first_arc].origin == Some(edge)
+        });
+        let mut parents = vec![None; self.num_nodes];
+        let mut visited = vec![false; self.num_nodes];
+        visited[end] = true;
+        let mut queue = VecDeque::from([end]);
+        while let Some(u) = queue.pop_front() {
+            if u == start {
+                let mut cycle = vec![first];
+                let mut v = start;
+                while let Some(k) = parents[v] {
+                    cycle.push(k);
+                    v = tight_arcs[k].0;
+                }
+                return Some(cycle);
+            }
+            for &k in &outgoing[u] {
+                let (_, to, i, _) = tight_arcs[k];
+                if !visited[to] && !same_edge(i) {
+                    visited[to] = true;
+                    parents[to] = Some(k);
+                    queue.push_back(to);
+                }
+            }
+        }
+        None
+    }
+
+    // Whether no path from the source to the sink can carry more flow.
+    pub fn is_maximum(&self) -> bool {
+        !self.reachable(self.source, false)[self.sink]
+    }
+
+    // Nodes reachable from `start` in the residual graph, or that reach it if `reverse` is set.
+    fn reachable(&self, start: usize, reverse: bool) -> Vec<bool> {
+        let mut adjacent = vec![vec![]; self.num_nodes];
+        for (u, v, _) in self.residual_arcs(None) {
+            if reverse { adjacent[v].push(u) } else { adjacent[u].push(v) }
+        }
+        let mut reachable = vec![false; self.num_nodes];
+        reachable[start] = true;
+        let mut stack = vec![start];
+        while let Some(u) = stack.pop() {
+            for &v in &adjacent[u] {
+                if !reachable[v] {
+                    reachable[v] = true;
+                    stack.push(v);
+                }
+            }
+        }
+        reachable
+    }
+
+    // The arc created for the edge, followed by the reverse arc if the edge is undirected.
+    fn edge_arcs(&self, edge: usize) -> Result<impl Iterator<Item = &Arc>, McmfError> {
+        if !self.arcs.iter().any(|arc| arc.origin == Some(edge)) {
+            return Err(McmfError::EdgeOutOfRange(edge));
+        }
+        Ok(self.arcs.iter().filter(move |arc| arc.origin == Some(edge)))
+    }
+
+    // Residual arcs as `(from, to, cost)`, leaving out those of builder edge `excluded`.
+    fn residual_arcs(&self, excluded: Option<usize>) -> impl Iterator<Item = (usize, usize, i128)> + '_ {
+        self.arcs.iter().filter(move |arc| excluded.is_none() || arc.origin != excluded).flat_map(|arc| {
+            let forward = (arc.flow < arc.capacity).then_some((arc.from, arc.to, arc.cost as i128));
+            let backward = (arc.flow > arc.lower).then_some((arc.to, arc.from, -arc.cost as i128));
+            forward.into_iter().chain(backward)
+        })
+    }
+
+    // The cost of the cheapest residual path from `from` to `to` that doesn't use builder edge
+    // `excluded`. Dijkstra on reduced costs, which are non-negative thanks to the potentials.
+    fn distance(&self, from: usize, to: usize, excluded: Option<usize>) -> Option<i128> {
+        let potentials = self.potentials.as_ref().unwrap();
+        let mut adjacent = vec![vec![]; self.num_nodes];
+        for (u, v, cost) in self.residual_arcs(excluded) {
+            adjacent[u].push((v, cost + potentials[u] - potentials[v]));
+        }
+        let mut distances = vec![None; self.num_nodes];
+        let mut queue = BinaryHeap::from([Reverse((0, from))]);
+        while let Some(Reverse((distance, u))) = queue.pop() {
+            if distances[u].is_some() {
+                continue;
+            }
+            distances[u] = Some(distance);
+            for &(v, reduced_cost) in &adjacent[u] {
+                if distances[v].is_none() {
+                    queue.push(Reverse((distance + reduced_cost, v)));
+                }
+            }
+        }
+        distances[to].map(|distance| distance - potentials[from] + potentials[to])
+    }
+}
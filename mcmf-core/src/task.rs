@@ -0,0 +1,106 @@
+// Solves that run in slices, so that the caller can do other work in between, e.g. keep a
+// browser's main thread responsive. The solvers are suspended at the points where they check the
+// budget, see `Budget::checkpoint`. Network simplex and push-relabel from rs-graph can't be
+// suspended, so they still run in one go.
+
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc, task::Poll};
+
+use crate::{utils::{poll_once, Trace}, AugmentingPath, IndexedGraphBuilder, McmfError, McmfSolution};
+
+// A solve in progress, see `GraphBuilder::solve_mcmf_task`. The caller decides when to run the
+// next slice, e.g. from an event loop.
+pub struct SolveTask {
+    future: Pin<Box<dyn Future<Output = Result<McmfSolution, McmfError>>>>,
+}
+
+impl SolveTask {
+    pub(crate) fn new(solve: impl Future<Output = Result<McmfSolution, McmfError>> + 'static) -> SolveTask {
+        SolveTask { future: Box::pin(solve) }
+    }
+
+    // Runs the solve until the end of the current slice. Returns the solution once it's done.
+    pub fn step(&mut self) -> Result<Option<McmfSolution>, McmfError> {
+        match poll_once(self.future.as_mut()) {
+            Poll::Ready(result) => result.map(Some),
+            Poll::Pending => Ok(None),
+        }
+    }
+}
+
+// A solve driven one step at a time, see `GraphBuilder::solver`. A step is one augmenting path in
+// the max flow phase and one iteration of the min-cost flow algorithm after that, e.g. a path for
+// successive shortest paths or a pivot for network simplex. Push-relabel takes a single step.
+pub struct Solver {
+    task: SolveTask,
+    // The solver's copy of the builder, for reporting the state.
+    graph: IndexedGraphBuilder,
+    source: usize,
+    trace: Rc<RefCell<Trace>>,
+    steps: u32,
+    result: Option<Result<McmfSolution, McmfError>>,
+}
+
+impl Solver {
+    // Runs the solve up to its first step, so that each `step` makes one.
+    pub(crate) fn new(
+        task: SolveTask, graph: IndexedGraphBuilder, source: usize, trace: Rc<RefCell<Trace>>
+    ) -> Result<Solver, McmfError> {
+        let mut solver = Solver { task, graph, source, trace, steps: 0, result: None };
+        solver.advance();
+        match solver.result {
+            Some(Err(error)) => Err(error),
+            _ => Ok(solver),
+        }
+    }
+
+    // Takes the next step. Fails if the solve does, and keeps failing after that.
+    pub fn step(&mut self) -> Result<(), McmfError> {
+        if self.result.is_none() {
+            self.steps += 1;
+            self.advance();
+        }
+        match &self.result {
+            Some(Err(error)) => Err(error.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    fn advance(&mut self) {
+        self.result = self.task.step().transpose();
+    }
+
+    pub fn is_done(&self) -> bool { self.result.is_some() }
+
+    // The solution once the solve is done.
+    pub fn solution(&self) -> Option<&McmfSolution> {
+        self.result.as_ref().and_then(|result| result.as_ref().ok())
+    }
+
+    pub fn current_state(&self) -> SolverState {
+        self.graph.solver_state(self.source, &self.trace.borrow(), self.steps, self.solution())
+    }
+}
+
+// A snapshot of a `Solver` after some steps.
+#[derive(Clone, Debug)]
+pub struct SolverState {
+    pub(crate) phase: String,
+    pub(crate) steps: u32,
+    pub(crate) flow: f64,
+    pub(crate) edge_flows: Vec<f64>,
+    pub(crate) last_path: Option<AugmentingPath>,
+}
+
+impl SolverState {
+    // "max_flow", "min_cost_flow" or "done".
+    pub fn phase(&self) -> &str { &self.phase }
+    pub fn steps(&self) -> u32 { self.steps }
+    // The flow value so far. It only changes in the max flow phase.
+    pub fn flow(&self) -> f64 { self.flow }
+    // Net flow on each edge by ID, as in `McmfSolution::edge_flows` but signed: negative flow goes
+    // against the edge, which can only happen for undirected ones. The min-cost phase only shows
+    // its flow once it is done. Graphs with lower bounds show no flow until then.
+    pub fn edge_flows(&self) -> &[f64] { &self.edge_flows }
+    // The augmenting path of the last step in the max flow phase, if any.
+    pub fn last_path(&self) -> Option<&AugmentingPath> { self.last_path.as_ref() }
+}
@@ -0,0 +1,187 @@
+// Flows over time: edges take a number of time steps to traverse and carry at most their capacity
+// per step. The problem is solved on the time-expanded network, which has a copy of every node
+// for each step `0..=horizon`, an arc `(u, t) -> (v, t + travel time)` for each edge and step,
+// and uncapacitated holdover arcs `(v, t) -> (v, t + 1)` for waiting. Flow leaves the source at
+// step 0 or later and counts once it reaches the sink by the horizon.
+
+use std::collections::HashMap;
+
+use crate::{IndexedGraphBuilder, McmfError, McmfSolution, ID};
+
+#[derive(Clone, Debug)]
+struct TimedEdge {
+    from: usize,
+    to: usize,
+    capacity: f64,
+    cost: f64,
+    travel_time: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TimeExpandedBuilder {
+    node_names: Vec<String>,
+    node_indices: HashMap<String, usize>,
+    edges: Vec<TimedEdge>,
+    horizon: u32,
+}
+
+// Flow sent along an edge at a time step, see `FlowOverTime::departures`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Departure {
+    edge: ID,
+    from: String,
+    to: String,
+    time: u32,
+    flow: f64,
+}
+
+impl Departure {
+    pub fn edge(&self) -> ID { self.edge }
+    pub fn from(&self) -> &str { &self.from }
+    pub fn to(&self) -> &str { &self.to }
+    pub fn time(&self) -> u32 { self.time }
+    pub fn flow(&self) -> f64 { self.flow }
+}
+
+#[derive(Clone, Debug)]
+pub struct FlowOverTime {
+    value: f64,
+    total_cost: f64,
+    horizon: u32,
+    departures: Vec<Departure>,
+}
+
+impl FlowOverTime {
+    // The amount that reaches the sink by `horizon`.
+    pub fn value(&self) -> f64 { self.value }
+    pub fn total_cost(&self) -> f64 { self.total_cost }
+    // The step by which all flow has arrived: the builder's horizon, or the earliest possible
+    // one for `solve_quickest_flow`.
+    pub fn horizon(&self) -> u32 { self.horizon }
+    // Flow entering each edge, ordered by time step and then by edge.
+    pub fn departures(&self) -> &[Departure] { &self.departures }
+}
+
+impl TimeExpandedBuilder {
+    pub fn new(horizon: u32) -> Self {
+        TimeExpandedBuilder { horizon, ..Default::default() }
+    }
+
+    pub fn horizon(&self) -> u32 { self.horizon }
+    pub fn set_horizon(&mut self, horizon: u32) {
+        self.horizon = horizon;
+    }
+
+    // Adds an edge that takes `travel_time` steps and accepts up to `capacity` units per step,
+    // at `cost` per unit.
+    pub fn add_edge(
+        &mut self, from: String, to: String, capacity: f64, cost: f64, travel_time: u32
+    ) -> Result<ID, McmfError> {
+        if !(capacity > 0. && capacity.is_finite()) {
+            return Err("capacity must be positive".into());
+        }
+        let (from, to) = (self.get_or_insert_vertex(from), self.get_or_insert_vertex(to));
+        self.edges.push(TimedEdge { from, to, capacity, cost, travel_time });
+        Ok((self.edges.len() - 1) as ID)
+    }
+
+    // The most flow that can reach `sink` from `source` by the horizon, at the lowest cost.
+    pub fn solve_max_flow_over_time(&self, source: String, sink: String) -> Result<FlowOverTime, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        let (expanded, arcs) = self.expanded(self.horizon)?;
+        let (source, sink) = (copy(source, 0, self.horizon), copy(sink, self.horizon, self.horizon));
+        let solution = expanded.solve_mcmf(source, sink, Default::default(), Default::default(), Default::default())?;
+        Ok(self.flow_over_time(&solution, &arcs, self.horizon))
+    }
+
+    // Sends `amount` units from `source` to `sink` so that the last of them arrives as early as
+    // possible, and at the lowest cost for that arrival time. Fails if that's impossible within
+    // the horizon.
+    pub fn solve_quickest_flow(&self, source: String, sink: String, amount: f64) -> Result<FlowOverTime, McmfError> {
+        let (source, sink) = (self.get_vertex(source)?, self.get_vertex(sink)?);
+        let max_flow_by = |horizon| -> Result<f64, McmfError> {
+            let (expanded, _) = self.expanded(horizon)?;
+            let (source, sink) = (copy(source, 0, horizon), copy(sink, horizon, horizon));
+            Ok(expanded.solve_max_flow(source, sink, Default::default(), Default::default())?.max_flow())
+        };
+        if max_flow_by(self.horizon)? < amount {
+            return Err(McmfError::Infeasible(format!("cannot send {} units by time {}", amount, self.horizon)));
+        }
+        // The max flow only grows with the horizon, so the earliest one that fits is bisected.
+        let (mut earliest, mut latest) = (0, self.horizon);
+        while earliest < latest {
+            let horizon = earliest + (latest - earliest) / 2;
+            if max_flow_by(horizon)? >= amount {
+                latest = horizon;
+            } else {
+                earliest = horizon + 1;
+            }
+        }
+        let (expanded, arcs) = self.expanded(earliest)?;
+        let (source, sink) = (copy(source, 0, earliest), copy(sink, earliest, earliest));
+        let solution = expanded.solve_min_cost_flow(source, sink, amount, Default::default(), Default::default())?;
+        Ok(self.flow_over_time(&solution, &arcs, earliest))
+    }
+
+    // The time-expanded network up to `horizon` and the `(edge, time)` that each of its first
+    // arcs stands for. Holdover arcs come after those.
+    fn expanded(&self, horizon: u32) -> Result<(IndexedGraphBuilder, Vec<(usize, u32)>), McmfError> {
+        let mut expanded = IndexedGraphBuilder::new();
+        expanded.set_cost_precision(None);
+        let steps = horizon as usize + 1;
+        expanded.add_nodes((self.node_names.len() * steps) as u32);
+        let names = (0..self.node_names.len() * steps).map(|i| format!("{}@{}", self.node_names[i / steps], i % steps));
+        expanded.set_node_names(names.collect())?;
+        let mut arcs = vec![];
+        for (i, edge) in self.edges.iter().enumerate() {
+            for time in (0..=horizon).take_while(|&time| time + edge.travel_time <= horizon) {
+                let (from, to) = (copy(edge.from, time, horizon), copy(edge.to, time + edge.travel_time, horizon));
+                expanded.add_edge(from, to, edge.capacity, edge.cost)?;
+                arcs.push((i, time));
+            }
+        }
+        // Waiting never needs more than everything that can move in the network.
+        let unbounded = arcs.iter().map(|&(i, _)| self.edges[i].capacity).sum::<f64>().max(1.);
+        for v in 0..self.node_names.len() {
+            for time in 0..horizon {
+                expanded.add_edge(copy(v, time, horizon), copy(v, time + 1, horizon), unbounded, 0.)?;
+            }
+        }
+        Ok((expanded, arcs))
+    }
+
+    fn flow_over_time(&self, solution: &McmfSolution, arcs: &[(usize, u32)], horizon: u32) -> FlowOverTime {
+        let mut departures: Vec<_> = arcs.iter().zip(solution.edge_flows())
+            .filter(|(_, edge_flow)| edge_flow.flow() > 0.)
+            .map(|(&(i, time), edge_flow)| Departure {
+                edge: i as ID,
+                from: self.node_names[self.edges[i].from].clone(),
+                to: self.node_names[self.edges[i].to].clone(),
+                time,
+                flow: edge_flow.flow(),
+            })
+            .collect();
+        departures.sort_by_key(|departure| (departure.time, departure.edge));
+        FlowOverTime {
+            value: solution.max_flow(),
+            total_cost: solution.total_cost().unwrap_or_default(),
+            horizon,
+            departures,
+        }
+    }
+
+    fn get_vertex(&self, v: String) -> Result<usize, McmfError> {
+        self.node_indices.get(&v).copied().ok_or(McmfError::UnknownNode(v))
+    }
+    fn get_or_insert_vertex(&mut self, v: String) -> usize {
+        *self.node_indices.entry(v.clone()).or_insert_with(|| {
+            self.node_names.push(v);
+            self.node_names.len() - 1
+        })
+    }
+}
+
+// The index of node `v` at step `time` in the time-expanded network up to `horizon`.
+fn copy(v: usize, time: u32, horizon: u32) -> usize {
+    v * (horizon as usize + 1) + time as usize
+}
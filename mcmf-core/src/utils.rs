@@ -0,0 +1,210 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+
+use crate::McmfError;
+
+// Measures phases of a solve. `Instant` panics on wasm32-unknown-unknown, so the browser clock is
+// used there.
+pub struct Stopwatch {
+    start: f64,
+}
+
+impl Stopwatch {
+    pub fn start() -> Stopwatch {
+        Stopwatch { start: now_ms() }
+    }
+
+    // Milliseconds since the start or the previous lap.
+    pub fn lap(&mut self) -> f64 {
+        let now = now_ms();
+        let elapsed = now - self.start;
+        self.start = now;
+        elapsed
+    }
+}
+
+// Time and iteration limits of a solve, see `GraphBuilder::set_time_limit_ms`. Once a limit is
+// reached, `tick` keeps failing, so that all later phases stop right away. With a yield interval,
+// `checkpoint` also suspends the solve every so often, see `SolveTask`. Progress of the solve is
+// reported to `progress`, if set, see `GraphBuilder::set_progress_callback`, and steps are
+// recorded to `trace`, if set.
+#[derive(Default)]
+pub struct Budget {
+    pub progress: Option<Progress<i64>>,
+    pub cancelled: Option<Rc<Cell<bool>>>,
+    pub trace: Option<Rc<RefCell<Trace>>>,
+    time_limit_ms: Option<f64>,
+    iteration_limit: Option<u32>,
+    yield_interval_ms: Option<f64>,
+    deadline: Option<f64>,
+    slice_start: Cell<f64>,
+    iterations: Cell<u32>,
+    reached: Cell<bool>,
+    failure: RefCell<Option<String>>,
+    last_report: Cell<f64>,
+}
+
+impl Budget {
+    pub fn new(time_limit_ms: Option<f64>, iteration_limit: Option<u32>, yield_interval_ms: Option<f64>) -> Budget {
+        let now = now_ms();
+        let deadline = time_limit_ms.map(|limit| now + limit);
+        Budget {
+            time_limit_ms, iteration_limit, yield_interval_ms, deadline, slice_start: Cell::new(now),
+            ..Default::default()
+        }
+    }
+
+    // Counts an iteration that is about to be made. Returns false if it exceeds a limit.
+    pub fn tick(&self) -> bool {
+        if !self.reached.get() {
+            let iterations = self.iterations.get().saturating_add(1);
+            self.iterations.set(iterations);
+            let out_of_time = self.deadline.is_some_and(|deadline| now_ms() > deadline);
+            let out_of_iterations = self.iteration_limit.is_some_and(|limit| iterations > limit);
+            self.reached.set(out_of_time || out_of_iterations || self.cancelled());
+        }
+        !self.reached.get()
+    }
+
+    // Like `tick`, but first yields to whoever polls the solve if the current time slice is over.
+    pub async fn checkpoint(&self) -> bool {
+        if self.yield_interval_ms.is_some_and(|interval| now_ms() >= self.slice_start.get() + interval) {
+            YieldNow(false).await;
+            self.slice_start.set(now_ms());
+        }
+        self.tick()
+    }
+
+    pub fn reached(&self) -> bool {
+        self.reached.get()
+    }
+
+    // Records that a solver failed, and stops the later phases like a limit.
+    pub fn fail(&self, message: String) {
+        self.reached.set(true);
+        self.failure.borrow_mut().get_or_insert(message);
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancelled.as_ref().is_some_and(|cancelled| cancelled.get())
+    }
+
+    // Reports that `phase` is `percent` done with `flow` units found so far, unless there was
+    // another report less than `PROGRESS_INTERVAL_MS` ago.
+    pub fn report(&self, phase: &str, percent: f64, flow: i64) {
+        if self.progress.is_some() && now_ms() >= self.last_report.get() + PROGRESS_INTERVAL_MS {
+            self.report_now(phase, percent, flow);
+        }
+    }
+
+    pub fn report_now(&self, phase: &str, percent: f64, flow: i64) {
+        if let Some(trace) = &self.trace {
+            trace.borrow_mut().phase = phase.to_owned();
+        }
+        if let Some(progress) = &self.progress {
+            self.last_report.set(now_ms());
+            progress(phase, percent.clamp(0., 100.), flow);
+        }
+    }
+
+    // Describes the limit that was reached or the failure, if any.
+    pub fn error(&self) -> Option<McmfError> {
+        if self.cancelled() {
+            return Some(McmfError::Cancelled);
+        }
+        if let Some(message) = &*self.failure.borrow() {
+            return Some(McmfError::Internal(message.clone()));
+        }
+        if !self.reached.get() {
+            return None;
+        }
+        Some(McmfError::LimitReached(match self.iteration_limit {
+            Some(limit) if self.iterations.get() > limit => format!("iteration limit of {} reached", limit),
+            _ => format!("time limit of {} ms reached", self.time_limit_ms.unwrap_or_default()),
+        }))
+    }
+}
+
+// Steps of a solve in solver units, see `GraphBuilder::set_trace`.
+#[derive(Default)]
+pub struct Trace {
+    // The phase of the last progress report.
+    pub phase: String,
+    // Augmenting paths of the max flow phase, as residual arcs (see `maxflow::Residual`) with
+    // their capacity after the augmentation, and the amount sent.
+    pub augmentations: Vec<(Vec<(usize, i64)>, i64)>,
+    // Dinic phases of the max flow phase.
+    pub phases: Vec<TracedPhase>,
+    // Network simplex pivots of the min-cost phase as the entering arc, the leaving arc unless it
+    // is artificial, and the total cost after the pivot, see `mcf::traced_network_simplex`.
+    pub pivots: Vec<(usize, Option<usize>, i64)>,
+}
+
+// A level graph of Dinic, see `maxflow::dinic`.
+pub struct TracedPhase {
+    // The BFS level of each node reached from the source, in level order.
+    pub levels: Vec<(usize, usize)>,
+    // Residual arcs of the level graph with their capacity.
+    pub arcs: Vec<(usize, i64)>,
+    // The number of augmentations before the phase.
+    pub first_augmentation: usize,
+}
+
+// Called with the phase, percent done and flow found so far.
+pub type Progress<Flow> = Rc<dyn Fn(&str, f64, Flow)>;
+
+pub const MAX_FLOW_PHASE: &str = "max_flow";
+pub const MIN_COST_FLOW_PHASE: &str = "min_cost_flow";
+const PROGRESS_INTERVAL_MS: f64 = 100.;
+
+// Returns pending once, so that the solve is suspended until it is polled again.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+// Runs a solve to completion. Solves only wait for `YieldNow`, so it's fine to poll right away.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = poll_once(future.as_mut()) {
+            return output;
+        }
+    }
+}
+
+pub fn poll_once<F: Future + ?Sized>(future: Pin<&mut F>) -> Poll<F::Output> {
+    future.poll(&mut Context::from_waker(Waker::noop()))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn now_ms() -> f64;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0., |time| time.as_secs_f64() * 1000.)
+}
+
+// Approximate size of the table of a hash map: an entry and a control byte per bucket.
+pub(crate) fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
@@ -0,0 +1,106 @@
+use mcmf_core::McmfError;
+use wasm_bindgen::prelude::*;
+
+use crate::{js_array, js_strings, StringArray, ID};
+
+// Nested arrays don't cross the boundary, so matrices are passed flat in row-major order.
+fn matrix(values: &[f64], columns: u32) -> Result<Vec<Vec<f64>>, McmfError> {
+    if values.is_empty() {
+        return Ok(vec![]);
+    }
+    if columns == 0 || !values.len().is_multiple_of(columns as usize) {
+        return Err(McmfError::Invalid(format!("{} values don't make rows of {} columns", values.len(), columns)));
+    }
+    Ok(values.chunks(columns as usize).map(<[f64]>::to_vec).collect())
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Assignment(pub(crate) mcmf_core::Assignment);
+
+#[wasm_bindgen]
+impl Assignment {
+    // Assigned rows and their columns, as two arrays of the same length.
+    pub fn rows(&self) -> Vec<ID> { self.0.pairs().iter().map(|&(row, _)| row).collect() }
+    pub fn columns(&self) -> Vec<ID> { self.0.pairs().iter().map(|&(_, column)| column).collect() }
+    pub fn column(&self, row: ID) -> Option<ID> { self.0.column(row) }
+    pub fn total_cost(&self) -> f64 { self.0.total_cost() }
+}
+
+#[wasm_bindgen]
+pub fn solve_assignment(costs: &[f64], columns: u32) -> Result<Assignment, JsError> {
+    let costs = matrix(costs, columns).map_err(JsError::from)?;
+    mcmf_core::solve_assignment(&costs).map(Assignment).map_err(JsError::from)
+}
+
+// `Infinity` in `costs` forbids the pair.
+#[wasm_bindgen]
+pub fn solve_min_cost_matching(costs: &[f64], columns: u32, allow_partial: bool) -> Result<Assignment, JsError> {
+    let costs = matrix(costs, columns).map_err(JsError::from)?;
+    mcmf_core::solve_min_cost_matching(&costs, allow_partial).map(Assignment).map_err(JsError::from)
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Transportation(pub(crate) mcmf_core::Transportation);
+
+#[wasm_bindgen]
+impl Transportation {
+    // Shipments in row-major order, one row per supplier.
+    pub fn shipments(&self) -> Vec<f64> { self.0.shipments().concat() }
+    pub fn total_cost(&self) -> f64 { self.0.total_cost() }
+}
+
+// `costs` has a row per supplier and a column per consumer, see `solve_assignment`.
+#[wasm_bindgen]
+pub fn solve_transportation(supplies: &[f64], demands: &[f64], costs: &[f64]) -> Result<Transportation, JsError> {
+    let costs = matrix(costs, demands.len() as u32).map_err(JsError::from)?;
+    mcmf_core::solve_transportation(supplies, demands, &costs).map(Transportation).map_err(JsError::from)
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ProjectSelection(pub(crate) mcmf_core::ProjectSelection);
+
+#[wasm_bindgen]
+impl ProjectSelection {
+    pub fn projects(&self) -> Vec<ID> { self.0.projects().to_vec() }
+    pub fn profit(&self) -> f64 { self.0.profit() }
+}
+
+// Dependencies are given as two arrays of the same length: `projects[i]` requires `required[i]`.
+#[wasm_bindgen]
+pub fn solve_project_selection(profits: &[f64], projects: &[ID], required: &[ID]) -> Result<ProjectSelection, JsError> {
+    if required.len() != projects.len() {
+        return Err(JsError::new(&format!("expected {} required projects, got {}", projects.len(), required.len())));
+    }
+    let dependencies: Vec<_> = projects.iter().copied().zip(required.iter().copied()).collect();
+    mcmf_core::solve_project_selection(profits, &dependencies).map(ProjectSelection).map_err(JsError::from)
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Matching(pub(crate) mcmf_core::Matching);
+
+#[wasm_bindgen]
+impl Matching {
+    // Matched left nodes and their right nodes, as two arrays of the same length.
+    pub fn left(&self) -> StringArray { js_array(self.0.pairs().iter().map(|(left, _)| left.as_str())) }
+    pub fn right(&self) -> StringArray { js_array(self.0.pairs().iter().map(|(_, right)| right.as_str())) }
+    pub fn size(&self) -> usize { self.0.size() }
+}
+
+// Edges are given as two arrays of the same length, like in `GraphBuilder::add_edges_bulk`.
+#[wasm_bindgen]
+pub fn solve_bipartite_matching(
+    left: Vec<JsValue>, right: Vec<JsValue>, edge_lefts: Vec<JsValue>, edge_rights: Vec<JsValue>
+) -> Result<Matching, JsError> {
+    let strings = |values: Vec<JsValue>| js_strings(values).map_err(JsError::from);
+    let (left, right) = (strings(left)?, strings(right)?);
+    let (edge_lefts, edge_rights) = (strings(edge_lefts)?, strings(edge_rights)?);
+    if edge_rights.len() != edge_lefts.len() {
+        return Err(JsError::new(&format!("expected {} edge right ends, got {}", edge_lefts.len(), edge_rights.len())));
+    }
+    let edges: Vec<_> = edge_lefts.into_iter().zip(edge_rights).collect();
+    mcmf_core::solve_bipartite_matching(&left, &right, &edges).map(Matching).map_err(JsError::from)
+}
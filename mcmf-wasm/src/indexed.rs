@@ -0,0 +1,337 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    js_strings, memory, or_default, progress, task, BalanceDiagnosis, Benchmark, CancellationToken, DisjointPaths,
+    DuplicateEdges, EdgeHandle, FlowDiagnosis, FlowSolution, FlowVerification, GomoryHuTree, MaxFlowAlgorithm,
+    McmfSolution, MemoryStats, MinCostFlowAlgorithm, Path, PathArray, PathDecomposition, ProgressCallback,
+    SecondaryObjective, SelfLoops, SolutionPromise, SolveTask, Solver, StringArray, ID,
+    js_array,
+};
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct IndexedGraphBuilder(mcmf_core::IndexedGraphBuilder);
+
+#[wasm_bindgen]
+impl IndexedGraphBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // See `GraphBuilder::clone_builder`.
+    pub fn clone_builder(&self) -> IndexedGraphBuilder { self.clone() }
+    // See `GraphBuilder::restore`.
+    pub fn restore(&mut self, snapshot: &IndexedGraphBuilder) { self.clone_from(snapshot); }
+    pub fn clear(&mut self) { self.0.clear(); }
+    pub fn memory_stats(&self) -> MemoryStats { MemoryStats::new(self.0.storage_stats()) }
+
+    pub fn add_nodes(&mut self, count: u32) -> ID { self.0.add_nodes(count) }
+    pub fn num_nodes(&self) -> u32 { self.0.num_nodes() }
+
+    pub fn set_node_names(&mut self, names: Vec<JsValue>) -> Result<(), JsError> {
+        let names = js_strings(names).map_err(JsError::from)?;
+        self.0.set_node_names(names).map_err(JsError::from)
+    }
+
+    pub fn add_edge(&mut self, from: ID, to: ID, capacity: f64, cost: f64) -> Result<ID, JsError> {
+        self.0.add_edge(from as usize, to as usize, capacity, cost).map_err(JsError::from)
+    }
+
+    // Takes `BigInt`s, see `add_edge_exact` in the core crate.
+    pub fn add_edge_exact(&mut self, from: ID, to: ID, capacity: i64, cost: i64) -> Result<ID, JsError> {
+        self.0.add_edge_exact(from as usize, to as usize, capacity, cost).map_err(JsError::from)
+    }
+
+    pub fn add_edge_with_bounds(
+        &mut self, from: ID, to: ID, lower: f64, upper: f64, cost: f64
+    ) -> Result<ID, JsError> {
+        self.0.add_edge_with_bounds(from as usize, to as usize, lower, upper, cost).map_err(JsError::from)
+    }
+
+    pub fn add_edge_piecewise(&mut self, from: ID, to: ID, capacities: &[f64], costs: &[f64]) -> Result<ID, JsError> {
+        self.0.add_edge_piecewise(from as usize, to as usize, capacities, costs).map_err(JsError::from)
+    }
+
+    pub fn add_edges_bulk(
+        &mut self, froms: &[u32], tos: &[u32], capacities: &[f64], costs: &[f64]
+    ) -> Result<ID, JsError> {
+        self.0.add_edges_bulk(froms, tos, capacities, costs).map_err(JsError::from)
+    }
+
+    pub fn add_undirected_edge(&mut self, a: ID, b: ID, capacity: f64, cost: f64) -> Result<ID, JsError> {
+        self.0.add_undirected_edge(a as usize, b as usize, capacity, cost).map_err(JsError::from)
+    }
+
+    pub fn update_capacity(&mut self, edge: ID, capacity: f64) -> Result<(), JsError> {
+        self.0.update_capacity(edge as usize, capacity).map_err(JsError::from)
+    }
+
+    pub fn update_cost(&mut self, edge: ID, cost: f64) -> Result<(), JsError> {
+        self.0.update_cost(edge as usize, cost).map_err(JsError::from)
+    }
+
+    // See `GraphBuilder::set_edge_metadata`.
+    pub fn set_edge_metadata(&mut self, edge: ID, metadata: Option<String>) -> Result<(), JsError> {
+        self.0.set_edge_metadata(edge as usize, metadata).map_err(JsError::from)
+    }
+
+    // See `GraphBuilder::remove_edge`.
+    pub fn remove_edge(&mut self, edge: ID) -> Result<(), JsError> {
+        self.0.remove_edge(edge as usize).map_err(JsError::from)
+    }
+
+    pub fn edge_handle(&self, edge: ID) -> Result<EdgeHandle, JsError> {
+        self.0.edge_handle(edge as usize).map(EdgeHandle).map_err(JsError::from)
+    }
+    pub fn edge_id(&self, handle: &EdgeHandle) -> Result<ID, JsError> {
+        self.0.edge_id(handle.0).map_err(JsError::from)
+    }
+
+    pub fn set_supply(&mut self, node: ID, amount: f64) -> Result<(), JsError> {
+        self.0.set_supply(node as usize, amount).map_err(JsError::from)
+    }
+
+    pub fn set_demand(&mut self, node: ID, amount: f64) -> Result<(), JsError> {
+        self.0.set_demand(node as usize, amount).map_err(JsError::from)
+    }
+
+    pub fn set_node_capacity(&mut self, node: ID, capacity: f64) -> Result<(), JsError> {
+        self.0.set_node_capacity(node as usize, capacity).map_err(JsError::from)
+    }
+
+    // See `GraphBuilder::set_node_group`.
+    pub fn set_node_group(&mut self, node: ID, group: Option<String>) -> Result<(), JsError> {
+        self.0.set_node_group(node as usize, group).map_err(JsError::from)
+    }
+
+    // See `GraphBuilder::set_node_metadata`.
+    pub fn set_node_metadata(&mut self, node: ID, metadata: Option<String>) -> Result<(), JsError> {
+        self.0.set_node_metadata(node as usize, metadata).map_err(JsError::from)
+    }
+
+    pub fn set_cost_precision(&mut self, decimals: Option<u32>) {
+        self.0.set_cost_precision(decimals);
+    }
+
+    pub fn set_duplicate_edges(&mut self, policy: DuplicateEdges) {
+        self.0.set_duplicate_edges(policy.into());
+    }
+
+    pub fn set_self_loops(&mut self, policy: SelfLoops) {
+        self.0.set_self_loops(policy.into());
+    }
+
+    pub fn set_capacity_denominator(&mut self, denominator: u32) -> Result<(), JsError> {
+        self.0.set_capacity_denominator(denominator).map_err(JsError::from)
+    }
+
+    pub fn set_cost_denominator(&mut self, denominator: Option<u32>) -> Result<(), JsError> {
+        self.0.set_cost_denominator(denominator).map_err(JsError::from)
+    }
+
+    pub fn set_time_limit_ms(&mut self, limit: Option<f64>) -> Result<(), JsError> {
+        self.0.set_time_limit_ms(limit).map_err(JsError::from)
+    }
+
+    pub fn set_iteration_limit(&mut self, limit: Option<u32>) {
+        self.0.set_iteration_limit(limit);
+    }
+
+    pub fn set_partial_results(&mut self, enabled: bool) {
+        self.0.set_partial_results(enabled);
+    }
+
+    pub fn set_pruning(&mut self, enabled: bool) {
+        self.0.set_pruning(enabled);
+    }
+
+    pub fn set_chain_contraction(&mut self, enabled: bool) {
+        self.0.set_chain_contraction(enabled);
+    }
+
+    pub fn set_single_pass(&mut self, enabled: bool) {
+        self.0.set_single_pass(enabled);
+    }
+
+    pub fn set_max_paths(&mut self, max_paths: Option<u32>) {
+        self.0.set_max_paths(max_paths);
+    }
+
+    pub fn set_secondary_objective(&mut self, objective: SecondaryObjective) {
+        self.0.set_secondary_objective(objective.into());
+    }
+
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.0.set_trace(enabled);
+    }
+
+    pub fn set_path_node_names(&mut self, enabled: bool) {
+        self.0.set_path_node_names(enabled);
+    }
+
+    pub fn set_split_antiparallel_edges(&mut self, enabled: bool) {
+        self.0.set_split_antiparallel_edges(enabled);
+    }
+
+    pub fn set_path_seed(&mut self, seed: Option<u32>) {
+        self.0.set_path_seed(seed);
+    }
+
+    pub fn set_cross_validation(&mut self, enabled: bool) {
+        self.0.set_cross_validation(enabled);
+    }
+
+    pub fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.0.set_cancellation_token(&token.0);
+    }
+
+    // See `GraphBuilder::set_progress_callback`.
+    pub fn set_progress_callback(&mut self, callback: Option<ProgressCallback>) {
+        self.0.set_progress_callback(progress(callback));
+    }
+
+    pub fn resolve(&self) -> Result<McmfSolution, JsError> {
+        memory::measure(|| self.0.resolve()).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn find_negative_cycle(&self) -> Option<Vec<ID>> {
+        self.0.find_negative_cycle().map(|cycle| cycle.into_iter().map(|v| v as ID).collect())
+    }
+
+    pub fn solve_mcmf(
+        &self, source: ID, sink: ID, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>, max_flow_algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        memory::measure(|| self.0.solve_mcmf(
+            source as usize, sink as usize, or_default(decomposition), or_default(algorithm),
+            or_default(max_flow_algorithm),
+        )).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn benchmark(
+        &self, source: ID, sink: ID, algorithm: Option<MinCostFlowAlgorithm>,
+        max_flow_algorithm: Option<MaxFlowAlgorithm>, repetitions: u32,
+    ) -> Result<Benchmark, JsError> {
+        self.0.benchmark(
+            source as usize, sink as usize, or_default(algorithm), or_default(max_flow_algorithm), repetitions,
+        ).map(Benchmark).map_err(JsError::from)
+    }
+
+    pub fn solver(
+        &self, source: ID, sink: ID, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>, max_flow_algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<Solver, JsError> {
+        self.0.solver(
+            source as usize, sink as usize, or_default(decomposition), or_default(algorithm),
+            or_default(max_flow_algorithm),
+        ).map(Solver).map_err(JsError::from)
+    }
+
+    // See `GraphBuilder::solve_mcmf_async`.
+    pub fn solve_mcmf_async(
+        &self, source: ID, sink: ID, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>, max_flow_algorithm: Option<MaxFlowAlgorithm>, slice_ms: Option<f64>,
+    ) -> Result<SolutionPromise, JsError> {
+        self.0.solve_mcmf_task(
+            source as usize, sink as usize, or_default(decomposition), or_default(algorithm),
+            or_default(max_flow_algorithm), slice_ms.unwrap_or(task::DEFAULT_SLICE_MS),
+        ).map(SolveTask::run).map_err(JsError::from)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn solve_mcmf_multi(
+        &self, sources: &[u32], sinks: &[u32],
+        source_capacities: Option<Vec<f64>>, sink_capacities: Option<Vec<f64>>,
+        decomposition: Option<PathDecomposition>, algorithm: Option<MinCostFlowAlgorithm>,
+        max_flow_algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        let sources = sources.iter().map(|&v| v as usize).collect();
+        let sinks = sinks.iter().map(|&v| v as usize).collect();
+        memory::measure(|| self.0.solve_mcmf_multi(
+            sources, sinks, source_capacities, sink_capacities,
+            or_default(decomposition), or_default(algorithm), or_default(max_flow_algorithm),
+        )).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_max_flow(
+        &self, source: ID, sink: ID, decomposition: Option<PathDecomposition>, algorithm: Option<MaxFlowAlgorithm>
+    ) -> Result<McmfSolution, JsError> {
+        let (decomposition, algorithm) = (or_default(decomposition), or_default(algorithm));
+        memory::measure(|| self.0.solve_max_flow(source as usize, sink as usize, decomposition, algorithm))
+            .map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_min_cost_flow(
+        &self, source: ID, sink: ID, flow_value: f64, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        memory::measure(|| self.0.solve_min_cost_flow(
+            source as usize, sink as usize, flow_value, or_default(decomposition), or_default(algorithm),
+        )).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_max_flow_with_budget(
+        &self, source: ID, sink: ID, budget: f64, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        self.0.solve_max_flow_with_budget(
+            source as usize, sink as usize, budget, or_default(decomposition), or_default(algorithm),
+        ).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn max_flow_profile(
+        &self, source: ID, sink: ID, edge: ID, capacities: &[f64], algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<Vec<f64>, JsError> {
+        self.0.max_flow_profile(source as usize, sink as usize, edge as usize, capacities, or_default(algorithm))
+            .map_err(JsError::from)
+    }
+
+    pub fn solve_max_profit_flow(
+        &self, source: ID, sink: ID, revenue: f64, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        self.0.solve_max_profit_flow(
+            source as usize, sink as usize, revenue, or_default(decomposition), or_default(algorithm),
+        ).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_min_cost_circulation(&self, algorithm: Option<MinCostFlowAlgorithm>) -> Result<FlowSolution, JsError> {
+        self.0.solve_min_cost_circulation(or_default(algorithm)).map(FlowSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_b_flow(&self, algorithm: Option<MinCostFlowAlgorithm>) -> Result<FlowSolution, JsError> {
+        self.0.solve_b_flow(or_default(algorithm)).map(FlowSolution).map_err(JsError::from)
+    }
+
+    pub fn diagnose_b_flow(&self) -> Result<BalanceDiagnosis, JsError> {
+        self.0.diagnose_b_flow().map(BalanceDiagnosis).map_err(JsError::from)
+    }
+
+    pub fn edge_disjoint_paths(&self, source: ID, sink: ID, k: Option<u32>) -> Result<PathArray, JsError> {
+        self.0.edge_disjoint_paths(source as usize, sink as usize, k).map(|paths| js_array(paths.into_iter().map(Path)))
+            .map_err(JsError::from)
+    }
+
+    pub fn vertex_disjoint_paths(&self, source: ID, sink: ID) -> Result<DisjointPaths, JsError> {
+        self.0.vertex_disjoint_paths(source as usize, sink as usize).map(DisjointPaths).map_err(JsError::from)
+    }
+
+    // Unnamed nodes are passed to `GomoryHuTree::min_cut_between` by index, e.g. "3".
+    pub fn gomory_hu_tree(&self) -> Result<GomoryHuTree, JsError> {
+        self.0.gomory_hu_tree().map(GomoryHuTree).map_err(JsError::from)
+    }
+
+    pub fn validate(&self, source: Option<ID>, sink: Option<ID>) -> Result<StringArray, JsError> {
+        let terminals = source.zip(sink).map(|(source, sink)| (source as usize, sink as usize));
+        self.0.validate(terminals).map(js_array).map_err(JsError::from)
+    }
+
+    pub fn diagnose_flow(&self, source: ID, sink: ID, flow_value: f64) -> Result<FlowDiagnosis, JsError> {
+        self.0.diagnose_flow(source as usize, sink as usize, flow_value).map(FlowDiagnosis).map_err(JsError::from)
+    }
+
+    pub fn verify_flow(&self, source: ID, sink: ID, flows: Vec<f64>) -> Result<FlowVerification, JsError> {
+        self.0.verify_flow(source as usize, sink as usize, &flows).map(FlowVerification).map_err(JsError::from)
+    }
+}
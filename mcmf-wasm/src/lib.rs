@@ -1,210 +1,1439 @@
-mod utils;
+// JS bindings for `mcmf-core`. Each type wraps the core type of the same name and converts
+// arguments and results to what JS can handle; see the core crate for what the methods do.
 
-use std::{cmp, collections::HashMap};
+mod applications;
+mod indexed;
+mod memory;
+mod multicommodity;
+mod task;
+mod time_expanded;
+mod utils;
 
-use bimap::BiMap;
-use rs_graph::{
-    Builder, VecGraph, EdgeVec,
-    traits::{Directed, GraphIterator},
-    vecgraph::{self, VecGraphBuilder},
-    maxflow::dinic,
-    mcf::{NetworkSimplex, MinCostFlow, SolutionState},
+pub use applications::{
+    solve_assignment, solve_bipartite_matching, solve_min_cost_matching, solve_project_selection, solve_transportation,
+    Assignment, Matching, ProjectSelection, Transportation,
 };
+pub use indexed::IndexedGraphBuilder;
+pub use memory::{MemoryStats, StorageStats};
+pub use multicommodity::{CommodityFlow, MultiCommodityBuilder, MultiCommodityFlow};
+pub use task::{SolutionPromise, SolveTask, Solver, SolverState};
+pub use time_expanded::{Departure, FlowOverTime, TimeExpandedBuilder};
+use mcmf_core::{McmfError, ID, Progress};
+use std::rc::Rc;
 use utils::set_panic_hook;
-use wasm_bindgen::prelude::*;
+use wasm_bindgen::{prelude::*, JsCast};
 
-// When the `wee_alloc` feature is enabled, use `wee_alloc` as the global allocator.
+// When the `wee_alloc` feature is enabled, use `wee_alloc` as the global allocator. Either way,
+// allocations are counted for `memory_stats`.
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+static ALLOC: memory::CountingAllocator<wee_alloc::WeeAlloc> = memory::CountingAllocator(wee_alloc::WeeAlloc::INIT);
+#[cfg(not(feature = "wee_alloc"))]
+#[global_allocator]
+static ALLOC: memory::CountingAllocator<std::alloc::System> = memory::CountingAllocator(std::alloc::System);
+
+
+// Shapes of the plain objects returned by `to_object`, see `json.rs` in the core crate.
+#[wasm_bindgen(typescript_custom_section)]
+const SOLUTION_OBJECTS: &'static str = r#"
+export interface PathObject {
+  flow: number;
+  cost: number;
+  nodes: string[];
+  nodeIndices: number[];
+  edges: number[];
+}
+
+export interface EdgeFlowObject {
+  edge: number;
+  from: string;
+  to: string;
+  flow: number;
+  capacity: number;
+  cost: number;
+  metadata?: string;
+}
 
+export interface NodeFlowObject {
+  node: string;
+  group?: string;
+  metadata?: string;
+  inflow: number;
+  outflow: number;
+}
+
+export interface MinCutObject {
+  edges: number[];
+  sourceSide: string[];
+  sinkSide: string[];
+}
 
-type ID = u32;
-type Graph = VecGraph<ID>;
-type GraphNode = vecgraph::Node<ID>;
-type GraphEdge = vecgraph::Edge<ID>;
+export interface McmfSolutionObject {
+  maxFlow: number;
+  totalCost: number | null;
+  exactTotalCost: string | null;
+  costsRounded: boolean;
+  limitReached: boolean;
+  paths: PathObject[];
+  edgeFlows: EdgeFlowObject[];
+  nodeFlows: NodeFlowObject[];
+  minCut: MinCutObject | null;
+  potentials: Record<string, number> | null;
+}
 
-const COST_MULTIPLIER: f64 = 1000.0;
+export interface FlowSolutionObject {
+  totalCost: number;
+  exactTotalCost: string;
+  costsRounded: boolean;
+  limitReached: boolean;
+  edgeFlows: EdgeFlowObject[];
+}
+"#;
+
+// Plain JS values with the TypeScript types that wasm-bindgen can't infer for `Vec<JsValue>`.
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "string[]")]
+    pub type StringArray;
+    #[wasm_bindgen(typescript_type = "string[][]")]
+    pub type StringArrayArray;
+    #[wasm_bindgen(typescript_type = "Path[]")]
+    pub type PathArray;
+    #[wasm_bindgen(typescript_type = "EdgeFlow[]")]
+    pub type EdgeFlowArray;
+    #[wasm_bindgen(typescript_type = "Edge[]")]
+    pub type EdgeArray;
+    #[wasm_bindgen(typescript_type = "NodeFlow[]")]
+    pub type NodeFlowArray;
+    #[wasm_bindgen(typescript_type = "EdgeFlowChange[]")]
+    pub type EdgeFlowChangeArray;
+    #[wasm_bindgen(typescript_type = "EdgeUtilization[]")]
+    pub type EdgeUtilizationArray;
+    #[wasm_bindgen(typescript_type = "PathAnimation[]")]
+    pub type PathAnimationArray;
+    #[wasm_bindgen(typescript_type = "GroupFlow[]")]
+    pub type GroupFlowArray;
+    #[wasm_bindgen(typescript_type = "ResidualEdge[]")]
+    pub type ResidualEdgeArray;
+    #[wasm_bindgen(typescript_type = "AugmentingPath[]")]
+    pub type AugmentingPathArray;
+    #[wasm_bindgen(typescript_type = "DinicPhase[]")]
+    pub type DinicPhaseArray;
+    #[wasm_bindgen(typescript_type = "Pivot[]")]
+    pub type PivotArray;
+    #[wasm_bindgen(typescript_type = "NodeImbalance[]")]
+    pub type NodeImbalanceArray;
+    #[wasm_bindgen(typescript_type = "McmfSolutionObject")]
+    pub type McmfSolutionObject;
+    #[wasm_bindgen(typescript_type = "FlowSolutionObject")]
+    pub type FlowSolutionObject;
+    #[wasm_bindgen(typescript_type = "Record<string, number>")]
+    pub type NodePotentials;
+    #[wasm_bindgen(typescript_type = "Record<string, number>")]
+    pub type NodeLevels;
+    #[wasm_bindgen(typescript_type = "(phase: string, percent: number, currentFlow: number) => void")]
+    pub type ProgressCallback;
+    #[wasm_bindgen(typescript_type = "(path: Path, index: number) => boolean | void")]
+    pub type PathCallback;
+
+    #[wasm_bindgen(method, catch, js_name = call)]
+    fn call(
+        this: &ProgressCallback, context: &JsValue, phase: &str, percent: f64, current_flow: f64
+    ) -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(method, catch, js_name = call)]
+    fn call_with_path(this: &PathCallback, context: &JsValue, path: Path, index: u32) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_namespace = JSON, js_name = parse)]
+    fn parse_json(text: &str) -> JsValue;
+    #[wasm_bindgen(js_namespace = Array, js_name = from)]
+    fn array_from(values: Vec<JsValue>) -> JsValue;
+    #[wasm_bindgen(js_namespace = Object, js_name = fromEntries)]
+    fn object_from_entries(entries: JsValue) -> JsValue;
+}
+
+fn js_array<T: JsCast, V: Into<JsValue>>(values: impl IntoIterator<Item = V>) -> T {
+    array_from(values.into_iter().map(Into::into).collect()).unchecked_into()
+}
+
+fn js_strings(values: Vec<JsValue>) -> Result<Vec<String>, McmfError> {
+    values.into_iter().map(|v| v.as_string().ok_or_else(|| "expected an array of strings".into())).collect()
+}
+
+// An exception would unwind through the solver and leave the builder borrowed, so it's ignored.
+fn progress(callback: Option<ProgressCallback>) -> Option<Progress<f64>> {
+    callback.map(|callback| -> Progress<f64> {
+        Rc::new(move |phase, percent, flow| {
+            let _ = callback.call(&JsValue::NULL, phase, percent, flow);
+        })
+    })
+}
 
 #[wasm_bindgen]
 pub fn init() {
     set_panic_hook();
 }
 
+// Solves log to the browser console; see `logging.rs` in the core crate for what each level adds.
 #[wasm_bindgen]
-#[derive(Clone, Debug)]
-pub struct Path {
-    flow: f64,
-    nodes: Vec<String>,
+pub fn set_log_level(level: LogLevel) {
+    mcmf_core::set_log_level(level.into());
 }
 
 #[wasm_bindgen]
-impl Path {
-    pub fn flow(&self) -> f64 { self.flow }
-    pub fn nodes(&mut self) -> Vec<JsValue> { self.nodes.iter().map(|v| v.clone().into()).collect() }
+pub fn generate_random_graph(
+    nodes: u32, edges: u32, max_capacity: u32, max_cost: u32, seed: u32
+) -> Result<GraphBuilder, JsError> {
+    mcmf_core::generate_random_graph(nodes, edges, max_capacity, max_cost, seed)
+        .map(GraphBuilder)
+        .map_err(JsError::from)
 }
 
 #[wasm_bindgen]
-#[derive(Clone, Debug)]
-pub struct McmfSolution {
-    max_flow: f64,
-    total_cost: f64,
-    paths: Vec<Path>,
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    #[default]
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl From<Difficulty> for mcmf_core::Difficulty {
+    fn from(difficulty: Difficulty) -> Self {
+        match difficulty {
+            Difficulty::Easy => Self::Easy,
+            Difficulty::Medium => Self::Medium,
+            Difficulty::Hard => Self::Hard,
+        }
+    }
 }
 
 #[wasm_bindgen]
-impl McmfSolution {
-    pub fn max_flow(&self) -> f64 { self.max_flow }
-    pub fn total_cost(&self) -> f64 { self.total_cost }
-    pub fn paths(&mut self) -> Vec<JsValue> { self.paths.iter().map(|v| v.clone().into()).collect() }
+pub fn generate_exercise(difficulty: Option<Difficulty>, seed: u32) -> Result<Exercise, JsError> {
+    mcmf_core::generate_exercise(or_default(difficulty), seed).map(Exercise).map_err(JsError::from)
 }
 
 #[wasm_bindgen]
-pub struct GraphBuilder {
-    node_names: BiMap<String, GraphNode>,
-    graph_builder: VecGraphBuilder<ID>,
-    capacities: HashMap<GraphEdge, i64>,
-    costs: HashMap<GraphEdge, f64>,
+#[derive(Clone)]
+pub struct Exercise(mcmf_core::Exercise);
+
+#[wasm_bindgen]
+impl Exercise {
+    pub fn builder(&self) -> GraphBuilder { GraphBuilder(self.0.builder().clone()) }
+    pub fn max_flow(&self) -> f64 { self.0.max_flow() }
+    pub fn min_cost(&self) -> f64 { self.0.min_cost() }
+    pub fn min_cut(&self) -> MinCut { MinCut(self.0.min_cut().clone()) }
 }
 
 #[wasm_bindgen]
-impl GraphBuilder {
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> Self {
-        GraphBuilder {
-            node_names: BiMap::new(),
-            graph_builder: VecGraphBuilder::new(),
-            capacities: HashMap::new(),
-            costs: HashMap::new(),
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecondaryObjective {
+    #[default]
+    None,
+    ShortestPaths,
+    FewestEdges,
+}
+
+impl From<SecondaryObjective> for mcmf_core::SecondaryObjective {
+    fn from(objective: SecondaryObjective) -> Self {
+        match objective {
+            SecondaryObjective::None => Self::None,
+            SecondaryObjective::ShortestPaths => Self::ShortestPaths,
+            SecondaryObjective::FewestEdges => Self::FewestEdges,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateEdges {
+    #[default]
+    Keep,
+    Reject,
+    MergeMinCost,
+    MergeAverageCost,
+}
+
+impl From<DuplicateEdges> for mcmf_core::DuplicateEdges {
+    fn from(policy: DuplicateEdges) -> Self {
+        match policy {
+            DuplicateEdges::Keep => Self::Keep,
+            DuplicateEdges::Reject => Self::Reject,
+            DuplicateEdges::MergeMinCost => Self::MergeMinCost,
+            DuplicateEdges::MergeAverageCost => Self::MergeAverageCost,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelfLoops {
+    #[default]
+    Keep,
+    Reject,
+    Ignore,
+}
+
+impl From<SelfLoops> for mcmf_core::SelfLoops {
+    fn from(policy: SelfLoops) -> Self {
+        match policy {
+            SelfLoops::Keep => Self::Keep,
+            SelfLoops::Reject => Self::Reject,
+            SelfLoops::Ignore => Self::Ignore,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathOrder {
+    #[default]
+    Flow,
+    Cost,
+}
+
+impl From<PathOrder> for mcmf_core::PathOrder {
+    fn from(order: PathOrder) -> Self {
+        match order {
+            PathOrder::Flow => Self::Flow,
+            PathOrder::Cost => Self::Cost,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathDecomposition {
+    #[default]
+    DepthFirst,
+    FewestPaths,
+    WidestFirst,
+    ShortestFirst,
+    Lexicographic,
+}
+
+impl From<PathDecomposition> for mcmf_core::PathDecomposition {
+    fn from(decomposition: PathDecomposition) -> Self {
+        match decomposition {
+            PathDecomposition::DepthFirst => Self::DepthFirst,
+            PathDecomposition::FewestPaths => Self::FewestPaths,
+            PathDecomposition::WidestFirst => Self::WidestFirst,
+            PathDecomposition::ShortestFirst => Self::ShortestFirst,
+            PathDecomposition::Lexicographic => Self::Lexicographic,
         }
     }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MinCostFlowAlgorithm {
+    #[default]
+    NetworkSimplex,
+    SuccessiveShortestPaths,
+    CostScaling,
+    CycleCanceling,
+}
 
-    pub fn add_edge(&mut self, from: String, to: String, capacity: f64, cost: f64) {
-        let capacity = capacity as i64;
-        assert!(capacity > 0);
-        let from = self.get_or_insert_vertex(from);
-        let to = self.get_or_insert_vertex(to);
-        let edge = self.graph_builder.add_edge(from, to);
-        self.capacities.insert(edge, capacity);
-        self.costs.insert(edge, cost);
-    }
-
-    pub fn solve_mcmf(self, source: String, sink: String) -> JsValue {
-        return self.solve_mcmf_impl(source, sink).into()
-    }
-
-    fn solve_mcmf_impl(self, source: String, sink: String) -> McmfSolution {
-        let source = self.get_vertex(source);
-        let sink = self.get_vertex(sink);
-        let graph = self.graph_builder.into_graph();
-        let capacities = |v| self.capacities[&v];
-        let costs = |v| (self.costs[&v] * COST_MULTIPLIER) as i64;
-        let max_flow = dinic(&graph, source, sink, capacities).0 as i64;
-
-        let mut spx = NetworkSimplex::new(&graph);
-        spx.set_uppers(capacities);
-        spx.set_costs(costs);
-        spx.set_balance(source, max_flow);
-        spx.set_balance(sink, -max_flow);
-        assert_eq!(spx.solve(), SolutionState::Optimal);
-        let paths = reconstruct_paths(&spx, &self.node_names, source, sink);
-        McmfSolution {
-            max_flow: max_flow as f64,
-            total_cost: (spx.value() as f64) / COST_MULTIPLIER,
-            paths,
+impl From<MinCostFlowAlgorithm> for mcmf_core::MinCostFlowAlgorithm {
+    fn from(algorithm: MinCostFlowAlgorithm) -> Self {
+        match algorithm {
+            MinCostFlowAlgorithm::NetworkSimplex => Self::NetworkSimplex,
+            MinCostFlowAlgorithm::SuccessiveShortestPaths => Self::SuccessiveShortestPaths,
+            MinCostFlowAlgorithm::CostScaling => Self::CostScaling,
+            MinCostFlowAlgorithm::CycleCanceling => Self::CycleCanceling,
         }
     }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaxFlowAlgorithm {
+    #[default]
+    Dinic,
+    PushRelabel,
+}
 
-    fn get_vertex(&self, v: String) -> GraphNode {
-        *self.node_names.get_by_left(&v).unwrap()
+impl From<MaxFlowAlgorithm> for mcmf_core::MaxFlowAlgorithm {
+    fn from(algorithm: MaxFlowAlgorithm) -> Self {
+        match algorithm {
+            MaxFlowAlgorithm::Dinic => Self::Dinic,
+            MaxFlowAlgorithm::PushRelabel => Self::PushRelabel,
+        }
     }
-    fn get_or_insert_vertex(&mut self, v: String) -> GraphNode {
-        if let Some(&id) = self.node_names.get_by_left(&v) {
-            id
-        } else {
-            let id = self.graph_builder.add_node();
-            self.node_names.insert_no_overwrite(v, id).unwrap();
-            id
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogLevel {
+    Off,
+    Error,
+    #[default]
+    Warn,
+    Info,
+    Trace,
+}
+
+impl From<LogLevel> for mcmf_core::LogLevel {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => Self::Off,
+            LogLevel::Error => Self::Error,
+            LogLevel::Warn => Self::Warn,
+            LogLevel::Info => Self::Info,
+            LogLevel::Trace => Self::Trace,
         }
     }
 }
 
-fn fill_paths<'g>(
-    graph: &Graph,
-    node_names: &BiMap<String, GraphNode>,
-    to: GraphNode,
-    path_flow: i64,
-    path_prefix: &mut Vec<GraphNode>,
-    path_prefix_edges: &mut Vec<GraphEdge>,
-    remaining_flows: &mut EdgeVec<'g, &'g Graph, i64>,
-    paths: &mut Vec<Path>
-) {
-    let from = *path_prefix.last().unwrap();
-    for (e, v) in graph.out_iter(from).iter(&graph) {
-        if remaining_flows[e] > 0 {
-            let path_flow = cmp::min(path_flow, remaining_flows[e]);
-            path_prefix.push(v);
-            path_prefix_edges.push(e);
-            if v == to {
-                let path_nodes = path_prefix.iter().map(
-                    |n| node_names.get_by_right(n).unwrap().clone()
-                ).collect();
-                paths.push(Path {
-                    flow: path_flow as f64,
-                    nodes: path_nodes,
-                });
-                for &e in path_prefix_edges.iter() {
-                    remaining_flows[e] -= path_flow;
-                }
-            } else {
-                fill_paths(
-                    graph, node_names, to, path_flow,
-                    path_prefix, path_prefix_edges, remaining_flows, paths
-                );
+// Omitted optional arguments get the core defaults.
+fn or_default<T: Default + Into<U>, U>(value: Option<T>) -> U {
+    value.unwrap_or_default().into()
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct CsvOptions {
+    // E.g. '\t' for text copied from a spreadsheet. ',' by default.
+    pub delimiter: char,
+    // Whether the first row names the columns. True by default.
+    pub has_header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl CsvOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let options = mcmf_core::CsvOptions::default();
+        CsvOptions { delimiter: options.delimiter, has_header: options.has_header }
+    }
+}
+
+impl From<CsvOptions> for mcmf_core::CsvOptions {
+    fn from(options: CsvOptions) -> Self {
+        mcmf_core::CsvOptions { delimiter: options.delimiter, has_header: options.has_header }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Path(pub(crate) mcmf_core::Path);
+
+#[wasm_bindgen]
+impl Path {
+    pub fn flow(&self) -> f64 { self.0.flow() }
+    pub fn cost(&self) -> f64 { self.0.cost() }
+    pub fn length(&self) -> u32 { self.0.length() }
+    pub fn nodes(&self) -> StringArray { js_array(self.0.nodes()) }
+    pub fn node_indices(&self) -> Vec<ID> { self.0.node_indices().to_vec() }
+    pub fn edges(&self) -> Vec<ID> { self.0.edges().to_vec() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SortedPaths(mcmf_core::SortedPaths);
+
+#[wasm_bindgen]
+impl SortedPaths {
+    pub fn paths(&self) -> PathArray { js_array(self.0.paths().iter().cloned().map(Path)) }
+    pub fn other_paths(&self) -> u32 { self.0.other_paths() }
+    pub fn other_flow(&self) -> f64 { self.0.other_flow() }
+    pub fn other_cost(&self) -> f64 { self.0.other_cost() }
+}
+
+// Each getter returns a typed array copy, e.g. `Uint32Array` for the indices.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathArrays(mcmf_core::PathArrays);
+
+#[wasm_bindgen]
+impl PathArrays {
+    pub fn flows(&self) -> Vec<f64> { self.0.flows().to_vec() }
+    pub fn node_offsets(&self) -> Vec<u32> { self.0.node_offsets().to_vec() }
+    pub fn node_indices(&self) -> Vec<ID> { self.0.node_indices().to_vec() }
+    pub fn edge_offsets(&self) -> Vec<u32> { self.0.edge_offsets().to_vec() }
+    pub fn edges(&self) -> Vec<ID> { self.0.edges().to_vec() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct MinCut(pub(crate) mcmf_core::MinCut);
+
+#[wasm_bindgen]
+impl MinCut {
+    pub fn edges(&self) -> Vec<ID> { self.0.edges().to_vec() }
+    pub fn source_side(&self) -> StringArray { js_array(self.0.source_side()) }
+    pub fn sink_side(&self) -> StringArray { js_array(self.0.sink_side()) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct McmfSolution(pub(crate) mcmf_core::McmfSolution);
+
+#[wasm_bindgen]
+impl McmfSolution {
+    pub fn max_flow(&self) -> f64 { self.0.max_flow() }
+    // A `BigInt` and a `BigInt64Array`, see `exact_max_flow` in the core crate.
+    pub fn exact_max_flow(&self) -> Option<i64> { self.0.exact_max_flow() }
+    pub fn exact_edge_flows(&self) -> Vec<i64> { self.0.exact_edge_flows().to_vec() }
+    pub fn total_cost(&self) -> Option<f64> { self.0.total_cost() }
+    pub fn exact_total_cost(&self) -> Option<String> { self.0.exact_total_cost().map(str::to_owned) }
+    pub fn costs_rounded(&self) -> bool { self.0.costs_rounded() }
+    pub fn limit_reached(&self) -> bool { self.0.limit_reached() }
+    pub fn paths(&self) -> PathArray { js_array(self.0.paths().iter().cloned().map(Path)) }
+    pub fn path_arrays(&self) -> PathArrays { PathArrays(self.0.path_arrays()) }
+    pub fn cycles(&self) -> PathArray { js_array(self.0.cycles().iter().cloned().map(Path)) }
+    pub fn paths_sorted(&self, by: Option<PathOrder>, limit: Option<u32>) -> SortedPaths {
+        SortedPaths(self.0.paths_sorted(or_default(by), limit))
+    }
+    // Paths one at a time, for solutions with too many to convert into one array.
+    pub fn path_count(&self) -> u32 { self.0.paths().len() as u32 }
+    pub fn path(&self, index: u32) -> Option<Path> { self.0.paths().get(index as usize).cloned().map(Path) }
+    // Calls `callback(path, index)` for each path in order until it returns `false`. Exceptions
+    // thrown by the callback stop the iteration and are rethrown.
+    pub fn for_each_path(&self, callback: &PathCallback) -> Result<(), JsValue> {
+        for (i, path) in self.0.paths().iter().enumerate() {
+            if callback.call_with_path(&JsValue::NULL, Path(path.clone()), i as u32)?.as_bool() == Some(false) {
+                break;
             }
-            path_prefix.pop();
-            path_prefix_edges.pop();
         }
+        Ok(())
+    }
+    pub fn edge_flows(&self) -> EdgeFlowArray { js_array(self.0.edge_flows().iter().cloned().map(EdgeFlow)) }
+    pub fn flow_on(&self, handle: &EdgeHandle) -> Result<f64, JsError> {
+        self.0.flow_on(handle.0).map_err(JsError::from)
+    }
+    pub fn node_flows(&self) -> NodeFlowArray { js_array(self.0.node_flows().iter().cloned().map(NodeFlow)) }
+    pub fn group_flows(&self) -> GroupFlowArray { js_array(self.0.group_flows().into_iter().map(GroupFlow)) }
+    // A `Float64Array` by node index.
+    pub fn node_throughput(&self) -> Vec<f64> { self.0.node_throughput() }
+    pub fn utilization_report(&self) -> EdgeUtilizationArray {
+        js_array(self.0.utilization_report().into_iter().map(EdgeUtilization))
+    }
+    pub fn animation_plan(&self, duration_ms: f64) -> Result<PathAnimationArray, JsError> {
+        Ok(js_array(self.0.animation_plan(duration_ms)?.into_iter().map(PathAnimation)))
+    }
+    pub fn residual_edges(&self) -> ResidualEdgeArray {
+        js_array(self.0.residual_edges().iter().cloned().map(ResidualEdge))
+    }
+    pub fn augmenting_paths(&self) -> AugmentingPathArray {
+        js_array(self.0.augmenting_paths().iter().cloned().map(AugmentingPath))
+    }
+    pub fn dinic_phases(&self) -> DinicPhaseArray {
+        js_array(self.0.dinic_phases().iter().cloned().map(DinicPhase))
+    }
+    pub fn simplex_pivots(&self) -> PivotArray {
+        js_array(self.0.simplex_pivots().iter().cloned().map(Pivot))
+    }
+    pub fn min_cut(&self) -> Option<MinCut> { self.0.min_cut().cloned().map(MinCut) }
+    pub fn potentials(&self) -> Option<NodePotentials> {
+        let entries = self.0.potentials()?.iter().map(|(node, potential)| {
+            js_array::<JsValue, _>([JsValue::from(node), JsValue::from(*potential)])
+        });
+        Some(object_from_entries(js_array(entries)).unchecked_into())
+    }
+    pub fn edge_metadata(&self, edge: ID) -> Result<Option<String>, JsError> {
+        self.0.edge_metadata(edge).map(|metadata| metadata.map(str::to_owned)).map_err(JsError::from)
+    }
+    pub fn reduced_cost(&self, edge: ID) -> Result<Option<f64>, JsError> {
+        self.0.reduced_cost(edge).map_err(JsError::from)
+    }
+    pub fn cost_range(&self, edge: ID) -> Result<Option<SensitivityRange>, JsError> {
+        self.0.cost_range(edge).map(|range| range.map(SensitivityRange)).map_err(JsError::from)
+    }
+    pub fn capacity_range(&self, edge: ID) -> Result<Option<SensitivityRange>, JsError> {
+        self.0.capacity_range(edge).map(|range| range.map(SensitivityRange)).map_err(JsError::from)
+    }
+    pub fn marginal_cost(&self) -> Option<f64> { self.0.marginal_cost() }
+    pub fn bottleneck_edges(&self) -> Option<Vec<ID>> { self.0.bottleneck_edges() }
+    pub fn is_unique(&self) -> Option<bool> { self.0.is_unique() }
+    pub fn alternative_solution(&self) -> Option<FlowSolution> { self.0.alternative_solution().map(FlowSolution) }
+    pub fn stats(&self) -> SolverStats { SolverStats(self.0.stats()) }
+    pub fn to_dimacs_flow(&self) -> String { self.0.to_dimacs_flow() }
+    pub fn to_dot(&self) -> String { self.0.to_dot() }
+    // The whole solution as a plain JS object, e.g. `{maxFlow, totalCost, paths: [{flow, nodes}]}`,
+    // which needs neither getters nor `free`.
+    pub fn to_object(&self) -> McmfSolutionObject { parse_json(&self.0.to_json()).unchecked_into() }
+    pub fn to_json(&self) -> String { self.0.to_json() }
+    pub fn from_json(json: &str) -> Result<McmfSolution, JsError> {
+        mcmf_core::McmfSolution::from_json(json).map(McmfSolution).map_err(JsError::from)
+    }
+    pub fn diff(&self, other: &McmfSolution) -> SolutionDiff { SolutionDiff(self.0.diff(&other.0)) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SolutionDiff(mcmf_core::SolutionDiff);
+
+#[wasm_bindgen]
+impl SolutionDiff {
+    pub fn edge_changes(&self) -> EdgeFlowChangeArray {
+        js_array(self.0.edge_changes().iter().cloned().map(EdgeFlowChange))
+    }
+    pub fn added_paths(&self) -> PathArray { js_array(self.0.added_paths().iter().cloned().map(Path)) }
+    pub fn removed_paths(&self) -> PathArray { js_array(self.0.removed_paths().iter().cloned().map(Path)) }
+    pub fn flow_delta(&self) -> f64 { self.0.flow_delta() }
+    pub fn cost_delta(&self) -> Option<f64> { self.0.cost_delta() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeFlowChange(mcmf_core::EdgeFlowChange);
+
+#[wasm_bindgen]
+impl EdgeFlowChange {
+    pub fn edge(&self) -> ID { self.0.edge() }
+    pub fn from(&self) -> String { self.0.from().to_owned() }
+    pub fn to(&self) -> String { self.0.to().to_owned() }
+    pub fn old_flow(&self) -> f64 { self.0.old_flow() }
+    pub fn new_flow(&self) -> f64 { self.0.new_flow() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SensitivityRange(pub(crate) mcmf_core::SensitivityRange);
+
+#[wasm_bindgen]
+impl SensitivityRange {
+    pub fn lower(&self) -> f64 { self.0.lower() }
+    pub fn upper(&self) -> f64 { self.0.upper() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolverStats(pub(crate) mcmf_core::SolverStats);
+
+#[wasm_bindgen]
+impl SolverStats {
+    pub fn num_nodes(&self) -> u32 { self.0.num_nodes() }
+    pub fn num_edges(&self) -> u32 { self.0.num_edges() }
+    pub fn augmentations(&self) -> Option<u32> { self.0.augmentations() }
+    pub fn pivots(&self) -> Option<u32> { self.0.pivots() }
+    pub fn network_ms(&self) -> f64 { self.0.network_ms() }
+    pub fn max_flow_ms(&self) -> f64 { self.0.max_flow_ms() }
+    pub fn min_cost_flow_ms(&self) -> f64 { self.0.min_cost_flow_ms() }
+    pub fn paths_ms(&self) -> f64 { self.0.paths_ms() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Benchmark(pub(crate) mcmf_core::Benchmark);
+
+#[wasm_bindgen]
+impl Benchmark {
+    pub fn repetitions(&self) -> u32 { self.0.repetitions() }
+    pub fn network(&self) -> Timing { Timing(self.0.network()) }
+    pub fn max_flow(&self) -> Timing { Timing(self.0.max_flow()) }
+    pub fn min_cost_flow(&self) -> Timing { Timing(self.0.min_cost_flow()) }
+    pub fn paths(&self) -> Timing { Timing(self.0.paths()) }
+    pub fn total(&self) -> Timing { Timing(self.0.total()) }
+}
+
+// Opaque, see `GraphBuilder::edge_handle`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeHandle(pub(crate) mcmf_core::EdgeHandle);
+
+// Each getter returns a `Float64Array` with a coordinate per node.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout(mcmf_core::Layout);
+
+#[wasm_bindgen]
+impl Layout {
+    pub fn x(&self) -> Vec<f64> { self.0.x().to_vec() }
+    pub fn y(&self) -> Vec<f64> { self.0.y().to_vec() }
+}
+
+// Each getter returns a `Float64Array` with a value per scenario.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioResults(mcmf_core::ScenarioResults);
+
+#[wasm_bindgen]
+impl ScenarioResults {
+    pub fn max_flows(&self) -> Vec<f64> { self.0.max_flows().to_vec() }
+    pub fn total_costs(&self) -> Vec<f64> { self.0.total_costs().to_vec() }
+    pub fn limit_reached(&self) -> bool { self.0.limit_reached() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing(mcmf_core::Timing);
+
+#[wasm_bindgen]
+impl Timing {
+    pub fn min_ms(&self) -> f64 { self.0.min_ms() }
+    pub fn median_ms(&self) -> f64 { self.0.median_ms() }
+    pub fn max_ms(&self) -> f64 { self.0.max_ms() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edge(pub(crate) mcmf_core::Edge);
+
+#[wasm_bindgen]
+impl Edge {
+    pub fn id(&self) -> ID { self.0.id() }
+    pub fn from(&self) -> String { self.0.from().to_owned() }
+    pub fn to(&self) -> String { self.0.to().to_owned() }
+    pub fn lower(&self) -> f64 { self.0.lower() }
+    pub fn capacity(&self) -> f64 { self.0.capacity() }
+    pub fn cost(&self) -> f64 { self.0.cost() }
+    pub fn undirected(&self) -> bool { self.0.undirected() }
+    pub fn metadata(&self) -> Option<String> { self.0.metadata().map(str::to_owned) }
+}
+
+fn js_edges(edges: Vec<mcmf_core::Edge>) -> EdgeArray {
+    js_array(edges.into_iter().map(Edge))
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct EdgeFlow(pub(crate) mcmf_core::EdgeFlow);
+
+#[wasm_bindgen]
+impl EdgeFlow {
+    pub fn edge(&self) -> ID { self.0.edge() }
+    pub fn from(&self) -> String { self.0.from().to_owned() }
+    pub fn to(&self) -> String { self.0.to().to_owned() }
+    pub fn flow(&self) -> f64 { self.0.flow() }
+    pub fn capacity(&self) -> f64 { self.0.capacity() }
+    pub fn cost(&self) -> f64 { self.0.cost() }
+    pub fn metadata(&self) -> Option<String> { self.0.metadata().map(str::to_owned) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct NodeFlow(mcmf_core::NodeFlow);
+
+#[wasm_bindgen]
+impl NodeFlow {
+    pub fn node(&self) -> String { self.0.node().to_owned() }
+    pub fn group(&self) -> Option<String> { self.0.group().map(str::to_owned) }
+    pub fn metadata(&self) -> Option<String> { self.0.metadata().map(str::to_owned) }
+    pub fn inflow(&self) -> f64 { self.0.inflow() }
+    pub fn outflow(&self) -> f64 { self.0.outflow() }
+    pub fn net_outflow(&self) -> f64 { self.0.net_outflow() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct GroupFlow(mcmf_core::GroupFlow);
+
+#[wasm_bindgen]
+impl GroupFlow {
+    pub fn group(&self) -> String { self.0.group().to_owned() }
+    pub fn nodes(&self) -> u32 { self.0.nodes() }
+    pub fn inflow(&self) -> f64 { self.0.inflow() }
+    pub fn outflow(&self) -> f64 { self.0.outflow() }
+    pub fn internal_flow(&self) -> f64 { self.0.internal_flow() }
+    pub fn internal_cost(&self) -> f64 { self.0.internal_cost() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathAnimation(mcmf_core::PathAnimation);
+
+#[wasm_bindgen]
+impl PathAnimation {
+    pub fn path(&self) -> u32 { self.0.path() }
+    // A `Float64Array` with a time per node of the path.
+    pub fn node_times(&self) -> Vec<f64> { self.0.node_times().to_vec() }
+    pub fn start_ms(&self) -> f64 { self.0.start_ms() }
+    pub fn end_ms(&self) -> f64 { self.0.end_ms() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeUtilization(mcmf_core::EdgeUtilization);
+
+#[wasm_bindgen]
+impl EdgeUtilization {
+    pub fn edge(&self) -> ID { self.0.edge() }
+    pub fn from(&self) -> String { self.0.from().to_owned() }
+    pub fn to(&self) -> String { self.0.to().to_owned() }
+    pub fn flow(&self) -> f64 { self.0.flow() }
+    pub fn capacity(&self) -> f64 { self.0.capacity() }
+    pub fn utilization(&self) -> f64 { self.0.utilization() }
+    pub fn saturated(&self) -> bool { self.0.saturated() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ResidualEdge(mcmf_core::ResidualEdge);
+
+#[wasm_bindgen]
+impl ResidualEdge {
+    pub fn edge(&self) -> Option<ID> { self.0.edge() }
+    pub fn from(&self) -> String { self.0.from().to_owned() }
+    pub fn to(&self) -> String { self.0.to().to_owned() }
+    pub fn capacity(&self) -> f64 { self.0.capacity() }
+    pub fn cost(&self) -> f64 { self.0.cost() }
+    pub fn reverse(&self) -> bool { self.0.reverse() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct AugmentingPath(mcmf_core::AugmentingPath);
+
+#[wasm_bindgen]
+impl AugmentingPath {
+    pub fn flow(&self) -> f64 { self.0.flow() }
+    pub fn nodes(&self) -> StringArray { js_array(self.0.nodes()) }
+    pub fn arcs(&self) -> ResidualEdgeArray { js_array(self.0.arcs().iter().cloned().map(ResidualEdge)) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct DinicPhase(mcmf_core::DinicPhase);
+
+#[wasm_bindgen]
+impl DinicPhase {
+    pub fn levels(&self) -> NodeLevels {
+        let entries = self.0.levels().iter().map(|(node, level)| {
+            js_array::<JsValue, _>([JsValue::from(node), JsValue::from(*level)])
+        });
+        object_from_entries(js_array(entries)).unchecked_into()
     }
+    pub fn arcs(&self) -> ResidualEdgeArray { js_array(self.0.arcs().iter().cloned().map(ResidualEdge)) }
+    pub fn first_path(&self) -> u32 { self.0.first_path() as u32 }
+    pub fn path_count(&self) -> u32 { self.0.path_count() as u32 }
 }
 
-fn reconstruct_paths(
-    spx: &NetworkSimplex<Graph, i64>,
-    node_names: &BiMap<String, GraphNode>,
-    source: GraphNode,
-    sink: GraphNode
-) -> Vec<Path> {
-    let graph = spx.as_graph();
-    let mut path_prefix = vec![source];
-    let mut path_prefix_edges = vec![];
-    let mut paths = vec![];
-    let mut remaining_flows = spx.flow_vec();
-    let path_flow = i64::MAX;
-    fill_paths(
-        &graph, node_names, sink, path_flow,
-        &mut path_prefix, &mut path_prefix_edges, &mut remaining_flows, &mut paths
-    );
-    assert_eq!(path_prefix.len(), 1);
-    assert!(path_prefix_edges.is_empty());
-    assert!(remaining_flows.iter().all(|(_, &flow)| flow == 0));
-    paths
-}
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn simple_graph() {
-        let mut builder = GraphBuilder::new();
-        builder.add_edge("a".to_owned(), "b".to_owned(), 10., 200.);
-        builder.add_edge("b".to_owned(), "c".to_owned(), 20., 0.);
-        builder.add_edge("c".to_owned(), "e".to_owned(), 15., 0.);
-        builder.add_edge("a".to_owned(), "d".to_owned(), 2., 100.);
-        builder.add_edge("d".to_owned(), "e".to_owned(), 3., 0.);
-        let solution = builder.solve_mcmf_impl("a".to_owned(), "e".to_owned());
-        assert_eq!(solution.max_flow(), 12.0);
-        assert_eq!(solution.total_cost(), 2200.0);
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Pivot(mcmf_core::Pivot);
+
+#[wasm_bindgen]
+impl Pivot {
+    pub fn entering(&self) -> Option<ID> { self.0.entering() }
+    pub fn leaving(&self) -> Option<ID> { self.0.leaving() }
+    pub fn total_cost(&self) -> f64 { self.0.total_cost() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct FlowSolution(pub(crate) mcmf_core::FlowSolution);
+
+#[wasm_bindgen]
+impl FlowSolution {
+    pub fn total_cost(&self) -> f64 { self.0.total_cost() }
+    pub fn exact_total_cost(&self) -> String { self.0.exact_total_cost().to_owned() }
+    pub fn costs_rounded(&self) -> bool { self.0.costs_rounded() }
+    pub fn limit_reached(&self) -> bool { self.0.limit_reached() }
+    pub fn edge_flows(&self) -> EdgeFlowArray { js_array(self.0.edge_flows().iter().cloned().map(EdgeFlow)) }
+    pub fn to_dimacs_flow(&self) -> String { self.0.to_dimacs_flow() }
+    // `{totalCost, edgeFlows: [{edge, from, to, flow, capacity, cost}]}` as a plain JS object.
+    pub fn to_object(&self) -> FlowSolutionObject { parse_json(&self.0.to_json()).unchecked_into() }
+    pub fn to_json(&self) -> String { self.0.to_json() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct FlowDiagnosis(pub(crate) mcmf_core::FlowDiagnosis);
+
+#[wasm_bindgen]
+impl FlowDiagnosis {
+    pub fn feasible(&self) -> bool { self.0.feasible() }
+    pub fn max_flow(&self) -> f64 { self.0.max_flow() }
+    pub fn shortfall(&self) -> f64 { self.0.shortfall() }
+    pub fn unreachable(&self) -> StringArray { js_array(self.0.unreachable()) }
+    pub fn cut(&self) -> MinCut { MinCut(self.0.cut().clone()) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct BalanceDiagnosis(pub(crate) mcmf_core::BalanceDiagnosis);
+
+#[wasm_bindgen]
+impl BalanceDiagnosis {
+    pub fn feasible(&self) -> bool { self.0.feasible() }
+    pub fn total_supply(&self) -> f64 { self.0.total_supply() }
+    pub fn total_demand(&self) -> f64 { self.0.total_demand() }
+    pub fn imbalances(&self) -> NodeImbalanceArray {
+        js_array(self.0.imbalances().iter().cloned().map(NodeImbalance))
+    }
+    pub fn cut(&self) -> Option<MinCut> { self.0.cut().cloned().map(MinCut) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct NodeImbalance(mcmf_core::NodeImbalance);
+
+#[wasm_bindgen]
+impl NodeImbalance {
+    pub fn node(&self) -> String { self.0.node().to_owned() }
+    pub fn supply(&self) -> f64 { self.0.supply() }
+    pub fn unmet(&self) -> f64 { self.0.unmet() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Circulation(pub(crate) mcmf_core::Circulation);
+
+#[wasm_bindgen]
+impl Circulation {
+    pub fn feasible(&self) -> bool { self.0.feasible() }
+    pub fn edge_flows(&self) -> EdgeFlowArray { js_array(self.0.edge_flows().iter().cloned().map(EdgeFlow)) }
+    pub fn violated_cut(&self) -> Option<ViolatedCut> { self.0.violated_cut().cloned().map(ViolatedCut) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ViolatedCut(pub(crate) mcmf_core::ViolatedCut);
+
+#[wasm_bindgen]
+impl ViolatedCut {
+    pub fn nodes(&self) -> StringArray { js_array(self.0.nodes()) }
+    pub fn entering_edges(&self) -> Vec<ID> { self.0.entering_edges().to_vec() }
+    pub fn leaving_edges(&self) -> Vec<ID> { self.0.leaving_edges().to_vec() }
+    pub fn required_inflow(&self) -> f64 { self.0.required_inflow() }
+    pub fn outflow_capacity(&self) -> f64 { self.0.outflow_capacity() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct FlowVerification(pub(crate) mcmf_core::FlowVerification);
+
+#[wasm_bindgen]
+impl FlowVerification {
+    pub fn feasible(&self) -> bool { self.0.feasible() }
+    pub fn violations(&self) -> StringArray { js_array(self.0.violations()) }
+    pub fn flow_value(&self) -> f64 { self.0.flow_value() }
+    pub fn total_cost(&self) -> f64 { self.0.total_cost() }
+    pub fn is_maximum(&self) -> Option<bool> { self.0.is_maximum() }
+    pub fn is_optimal(&self) -> Option<bool> { self.0.is_optimal() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct DisjointPaths(pub(crate) mcmf_core::DisjointPaths);
+
+#[wasm_bindgen]
+impl DisjointPaths {
+    pub fn paths(&self) -> PathArray { js_array(self.0.paths().iter().cloned().map(Path)) }
+    pub fn connectivity(&self) -> u32 { self.0.connectivity() }
+}
+
+#[wasm_bindgen]
+pub struct ManualMaxFlow(mcmf_core::ManualMaxFlow);
+
+#[wasm_bindgen]
+impl ManualMaxFlow {
+    // Takes the path as an array of node names.
+    pub fn augment(&mut self, nodes: Vec<JsValue>) -> Result<AugmentingPath, JsError> {
+        let nodes = js_strings(nodes).map_err(JsError::from)?;
+        self.0.augment(&nodes).map(AugmentingPath).map_err(JsError::from)
+    }
+
+    pub fn flow(&self) -> f64 { self.0.flow() }
+    pub fn edge_flows(&self) -> Vec<f64> { self.0.edge_flows() }
+    pub fn residual_edges(&self) -> ResidualEdgeArray {
+        js_array(self.0.residual_edges().into_iter().map(ResidualEdge))
+    }
+    pub fn is_maximum(&self) -> bool { self.0.is_maximum() }
+}
+
+#[wasm_bindgen]
+pub struct SolvedNetwork(mcmf_core::SolvedNetwork);
+
+#[wasm_bindgen]
+impl SolvedNetwork {
+    pub fn max_flow(
+        &mut self, source: &str, sink: &str, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        self.0.max_flow(source, sink, or_default(decomposition), or_default(algorithm))
+            .map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn min_cost_max_flow(
+        &mut self, source: &str, sink: &str, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>, max_flow_algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        let (decomposition, algorithm, max_flow_algorithm) =
+            (or_default(decomposition), or_default(algorithm), or_default(max_flow_algorithm));
+        self.0.min_cost_max_flow(source, sink, decomposition, algorithm, max_flow_algorithm)
+            .map(McmfSolution).map_err(JsError::from)
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct GomoryHuTree(pub(crate) mcmf_core::GomoryHuTree);
+
+#[wasm_bindgen]
+impl GomoryHuTree {
+    // Tree edges as three arrays of the same length.
+    pub fn children(&self) -> StringArray { js_array(self.0.edges().into_iter().map(|(node, _, _)| node)) }
+    pub fn parents(&self) -> StringArray { js_array(self.0.edges().into_iter().map(|(_, parent, _)| parent)) }
+    pub fn cut_values(&self) -> Vec<f64> { self.0.edges().into_iter().map(|(_, _, value)| value).collect() }
+
+    pub fn min_cut_between(&self, a: &str, b: &str) -> Result<f64, JsError> {
+        self.0.min_cut_between(a, b).map_err(JsError::from)
+    }
+}
+
+// JS only gets to cancel while a solve is running from a progress callback or during
+// `solve_mcmf_async`, e.g. from an `AbortController` listener.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(pub(crate) mcmf_core::CancellationToken);
+
+#[wasm_bindgen]
+impl CancellationToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) { self.0.cancel(); }
+    pub fn is_cancelled(&self) -> bool { self.0.is_cancelled() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct GraphBuilder(pub(crate) mcmf_core::GraphBuilder);
+
+#[wasm_bindgen]
+impl GraphBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // An independent copy of the graph and settings, e.g. for undo or trying out changes. The
+    // cancellation token and the progress callback are shared with the original.
+    pub fn clone_builder(&self) -> GraphBuilder { self.clone() }
+    // Replaces the graph and settings with those of `snapshot`, usually made by `clone_builder`.
+    pub fn restore(&mut self, snapshot: &GraphBuilder) { self.clone_from(snapshot); }
+    pub fn clear(&mut self) { self.0.clear(); }
+    pub fn memory_stats(&self) -> MemoryStats { MemoryStats::new(self.0.storage_stats()) }
+
+    pub fn from_json(json: &str) -> Result<GraphBuilder, JsError> {
+        mcmf_core::GraphBuilder::from_json(json).map(GraphBuilder).map_err(JsError::from)
+    }
+
+    pub fn to_json(&self) -> String {
+        self.0.to_json()
+    }
+
+    // The attribute keys are "capacity" and "cost" by default.
+    pub fn from_graphml(
+        text: &str, capacity_key: Option<String>, cost_key: Option<String>
+    ) -> Result<GraphBuilder, JsError> {
+        let capacity_key = capacity_key.as_deref().unwrap_or("capacity");
+        let cost_key = cost_key.as_deref().unwrap_or("cost");
+        mcmf_core::GraphBuilder::from_graphml(text, capacity_key, cost_key)
+            .map(GraphBuilder).map_err(JsError::from)
+    }
+
+    // Comma-separated with a header row by default.
+    pub fn from_csv(text: &str, options: Option<CsvOptions>) -> Result<GraphBuilder, JsError> {
+        mcmf_core::GraphBuilder::from_csv(text, options.unwrap_or_default().into())
+            .map(GraphBuilder).map_err(JsError::from)
+    }
+
+    pub fn to_dot(&self) -> String {
+        self.0.to_dot()
+    }
+
+    pub fn from_dimacs(text: &str) -> Result<GraphBuilder, JsError> {
+        mcmf_core::GraphBuilder::from_dimacs(text).map(GraphBuilder).map_err(JsError::from)
+    }
+
+    pub fn to_dimacs_min(&self) -> Result<String, JsError> {
+        self.0.to_dimacs_min().map_err(JsError::from)
+    }
+
+    pub fn to_dimacs_max(&self) -> Result<String, JsError> {
+        self.0.to_dimacs_max().map_err(JsError::from)
+    }
+
+    pub fn layout(&self, iterations: u32, weight_by_cost: bool) -> Layout {
+        Layout(self.0.layout(iterations, weight_by_cost))
+    }
+
+    pub fn to_lp(&self) -> Result<String, JsError> {
+        self.0.to_lp().map_err(JsError::from)
+    }
+
+    pub fn to_mps(&self) -> Result<String, JsError> {
+        self.0.to_mps().map_err(JsError::from)
+    }
+
+    pub fn source(&self) -> Option<String> { self.0.source().map(str::to_owned) }
+    pub fn sink(&self) -> Option<String> { self.0.sink().map(str::to_owned) }
+    pub fn set_source(&mut self, source: Option<String>) { self.0.set_source(source); }
+    pub fn set_sink(&mut self, sink: Option<String>) { self.0.set_sink(sink); }
+
+    pub fn add_edge(&mut self, from: String, to: String, capacity: f64, cost: f64) -> Result<ID, JsError> {
+        self.0.add_edge(from, to, capacity, cost).map_err(JsError::from)
+    }
+
+    // Takes `BigInt`s, see `add_edge_exact` in the core crate.
+    pub fn add_edge_exact(&mut self, from: String, to: String, capacity: i64, cost: i64) -> Result<ID, JsError> {
+        self.0.add_edge_exact(from, to, capacity, cost).map_err(JsError::from)
+    }
+
+    pub fn add_edge_with_bounds(
+        &mut self, from: String, to: String, lower: f64, upper: f64, cost: f64
+    ) -> Result<ID, JsError> {
+        self.0.add_edge_with_bounds(from, to, lower, upper, cost).map_err(JsError::from)
+    }
+
+    pub fn add_edge_piecewise(
+        &mut self, from: String, to: String, capacities: &[f64], costs: &[f64]
+    ) -> Result<ID, JsError> {
+        self.0.add_edge_piecewise(from, to, capacities, costs).map_err(JsError::from)
+    }
+
+    pub fn add_nodes(&mut self, names: Vec<JsValue>) -> Result<ID, JsError> {
+        let names = js_strings(names).map_err(JsError::from)?;
+        self.0.add_nodes(names).map_err(JsError::from)
+    }
+
+    pub fn add_edges_bulk(
+        &mut self, froms: &[u32], tos: &[u32], capacities: &[f64], costs: &[f64]
+    ) -> Result<ID, JsError> {
+        self.0.add_edges_bulk(froms, tos, capacities, costs).map_err(JsError::from)
+    }
+
+    pub fn add_undirected_edge(&mut self, a: String, b: String, capacity: f64, cost: f64) -> Result<ID, JsError> {
+        self.0.add_undirected_edge(a, b, capacity, cost).map_err(JsError::from)
+    }
+
+    pub fn node_count(&self) -> u32 { self.0.node_count() }
+    pub fn edge_count(&self) -> u32 { self.0.edge_count() }
+    pub fn has_node(&self, name: String) -> bool { self.0.has_node(&name) }
+    pub fn nodes(&self) -> StringArray { js_array(self.0.nodes()) }
+    pub fn edges(&self) -> EdgeArray { js_edges(self.0.edges()) }
+
+    pub fn out_edges(&self, node: String) -> Result<EdgeArray, JsError> {
+        self.0.out_edges(node).map(js_edges).map_err(JsError::from)
+    }
+
+    pub fn update_capacity(&mut self, edge: ID, capacity: f64) -> Result<(), JsError> {
+        self.0.update_capacity(edge, capacity).map_err(JsError::from)
+    }
+
+    pub fn update_cost(&mut self, edge: ID, cost: f64) -> Result<(), JsError> {
+        self.0.update_cost(edge, cost).map_err(JsError::from)
+    }
+
+    // E.g. `JSON.stringify` of the caller's own edge data, which `EdgeFlow::metadata` returns.
+    pub fn set_edge_metadata(&mut self, edge: ID, metadata: Option<String>) -> Result<(), JsError> {
+        self.0.set_edge_metadata(edge, metadata).map_err(JsError::from)
+    }
+
+    // Like `Array.prototype.splice`, this shifts the IDs of all later edges down by one.
+    pub fn remove_edge(&mut self, edge: ID) -> Result<(), JsError> {
+        self.0.remove_edge(edge).map_err(JsError::from)
+    }
+
+    pub fn edge_handle(&self, edge: ID) -> Result<EdgeHandle, JsError> {
+        self.0.edge_handle(edge).map(EdgeHandle).map_err(JsError::from)
+    }
+    pub fn edge_id(&self, handle: &EdgeHandle) -> Result<ID, JsError> {
+        self.0.edge_id(handle.0).map_err(JsError::from)
+    }
+
+    pub fn set_supply(&mut self, node: String, amount: f64) -> Result<(), JsError> {
+        self.0.set_supply(node, amount).map_err(JsError::from)
+    }
+
+    pub fn set_demand(&mut self, node: String, amount: f64) -> Result<(), JsError> {
+        self.0.set_demand(node, amount).map_err(JsError::from)
+    }
+
+    pub fn set_node_capacity(&mut self, node: String, capacity: f64) -> Result<(), JsError> {
+        self.0.set_node_capacity(node, capacity).map_err(JsError::from)
+    }
+
+    pub fn set_node_group(&mut self, node: String, group: Option<String>) -> Result<(), JsError> {
+        self.0.set_node_group(node, group).map_err(JsError::from)
+    }
+
+    pub fn set_node_metadata(&mut self, node: String, metadata: Option<String>) -> Result<(), JsError> {
+        self.0.set_node_metadata(node, metadata).map_err(JsError::from)
+    }
+
+    pub fn set_duplicate_edges(&mut self, policy: DuplicateEdges) {
+        self.0.set_duplicate_edges(policy.into());
+    }
+
+    pub fn set_self_loops(&mut self, policy: SelfLoops) {
+        self.0.set_self_loops(policy.into());
+    }
+
+    pub fn set_capacity_denominator(&mut self, denominator: u32) -> Result<(), JsError> {
+        self.0.set_capacity_denominator(denominator).map_err(JsError::from)
+    }
+
+    pub fn set_cost_precision(&mut self, decimals: Option<u32>) {
+        self.0.set_cost_precision(decimals);
+    }
+
+    pub fn set_cost_denominator(&mut self, denominator: Option<u32>) -> Result<(), JsError> {
+        self.0.set_cost_denominator(denominator).map_err(JsError::from)
+    }
+
+    pub fn set_time_limit_ms(&mut self, limit: Option<f64>) -> Result<(), JsError> {
+        self.0.set_time_limit_ms(limit).map_err(JsError::from)
+    }
+
+    pub fn set_iteration_limit(&mut self, limit: Option<u32>) {
+        self.0.set_iteration_limit(limit);
+    }
+
+    pub fn set_partial_results(&mut self, enabled: bool) {
+        self.0.set_partial_results(enabled);
+    }
+
+    pub fn set_pruning(&mut self, enabled: bool) {
+        self.0.set_pruning(enabled);
+    }
+
+    pub fn set_chain_contraction(&mut self, enabled: bool) {
+        self.0.set_chain_contraction(enabled);
+    }
+
+    pub fn set_single_pass(&mut self, enabled: bool) {
+        self.0.set_single_pass(enabled);
+    }
+
+    pub fn set_max_paths(&mut self, max_paths: Option<u32>) {
+        self.0.set_max_paths(max_paths);
+    }
+
+    pub fn set_secondary_objective(&mut self, objective: SecondaryObjective) {
+        self.0.set_secondary_objective(objective.into());
+    }
+
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.0.set_trace(enabled);
+    }
+
+    pub fn set_path_node_names(&mut self, enabled: bool) {
+        self.0.set_path_node_names(enabled);
+    }
+
+    pub fn set_split_antiparallel_edges(&mut self, enabled: bool) {
+        self.0.set_split_antiparallel_edges(enabled);
+    }
+
+    pub fn set_path_seed(&mut self, seed: Option<u32>) {
+        self.0.set_path_seed(seed);
+    }
+
+    pub fn set_cross_validation(&mut self, enabled: bool) {
+        self.0.set_cross_validation(enabled);
+    }
+
+    pub fn set_cancellation_token(&mut self, token: &CancellationToken) {
+        self.0.set_cancellation_token(&token.0);
+    }
+
+    // Unregisters the callback if `callback` is undefined. Exceptions thrown by the callback are
+    // ignored.
+    pub fn set_progress_callback(&mut self, callback: Option<ProgressCallback>) {
+        self.0.set_progress_callback(progress(callback));
+    }
+
+    pub fn resolve(&self) -> Result<McmfSolution, JsError> {
+        memory::measure(|| self.0.resolve()).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn find_negative_cycle(&self) -> Option<StringArray> {
+        self.0.find_negative_cycle().map(js_array)
+    }
+
+    pub fn solve_mcmf(
+        &self, source: String, sink: String, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>, max_flow_algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        let (decomposition, algorithm, max_flow_algorithm) =
+            (or_default(decomposition), or_default(algorithm), or_default(max_flow_algorithm));
+        memory::measure(|| self.0.solve_mcmf(source, sink, decomposition, algorithm, max_flow_algorithm))
+            .map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn benchmark(
+        &self, source: String, sink: String, algorithm: Option<MinCostFlowAlgorithm>,
+        max_flow_algorithm: Option<MaxFlowAlgorithm>, repetitions: u32,
+    ) -> Result<Benchmark, JsError> {
+        self.0.benchmark(source, sink, or_default(algorithm), or_default(max_flow_algorithm), repetitions)
+            .map(Benchmark).map_err(JsError::from)
+    }
+
+    // The scenarios are packed into flat arrays in one call: scenario `i` overrides the edges
+    // `edges[scenario_offsets[i]..scenario_offsets[i + 1]]` with the capacities and costs at the
+    // same positions, where NaN keeps the value of the graph.
+    #[allow(clippy::too_many_arguments)]
+    pub fn evaluate_scenarios(
+        &self, source: String, sink: String, scenario_offsets: &[u32], edges: &[u32], capacities: &[f64],
+        costs: &[f64], algorithm: Option<MinCostFlowAlgorithm>,
+    ) -> Result<ScenarioResults, JsError> {
+        if edges.len() != capacities.len() || edges.len() != costs.len() {
+            return Err(JsError::new("edges, capacities and costs must have the same length"));
+        }
+        let value = |x: f64| (!x.is_nan()).then_some(x);
+        let scenarios = scenario_offsets.windows(2).map(|range| {
+            let range = range[0] as usize..range[1] as usize;
+            let changes = edges.get(range.clone()).ok_or_else(|| JsError::new("scenario offsets out of range"))?;
+            Ok(changes.iter().zip(&capacities[range.clone()]).zip(&costs[range]).map(|((&edge, &capacity), &cost)| {
+                mcmf_core::EdgeOverride { edge, capacity: value(capacity), cost: value(cost) }
+            }).collect())
+        }).collect::<Result<Vec<_>, JsError>>()?;
+        self.0.evaluate_scenarios(source, sink, &scenarios, or_default(algorithm))
+            .map(ScenarioResults).map_err(JsError::from)
+    }
+
+    pub fn solver(
+        &self, source: String, sink: String, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>, max_flow_algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<Solver, JsError> {
+        self.0.solver(source, sink, or_default(decomposition), or_default(algorithm), or_default(max_flow_algorithm))
+            .map(Solver).map_err(JsError::from)
+    }
+
+    pub fn manual_max_flow(&self, source: String, sink: String) -> Result<ManualMaxFlow, JsError> {
+        self.0.manual_max_flow(source, sink).map(ManualMaxFlow).map_err(JsError::from)
+    }
+
+    // Like `solve_mcmf`, but returns a promise and solves in slices of about `slice_ms`
+    // milliseconds (10 by default), yielding to the event loop in between to keep the page
+    // responsive.
+    pub fn solve_mcmf_async(
+        &self, source: String, sink: String, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>, max_flow_algorithm: Option<MaxFlowAlgorithm>, slice_ms: Option<f64>,
+    ) -> Result<SolutionPromise, JsError> {
+        self.0.solve_mcmf_task(
+            source, sink, or_default(decomposition), or_default(algorithm), or_default(max_flow_algorithm),
+            slice_ms.unwrap_or(task::DEFAULT_SLICE_MS),
+        ).map(SolveTask::run).map_err(JsError::from)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn solve_mcmf_multi(
+        &self, sources: Vec<JsValue>, sinks: Vec<JsValue>,
+        source_capacities: Option<Vec<f64>>, sink_capacities: Option<Vec<f64>>,
+        decomposition: Option<PathDecomposition>, algorithm: Option<MinCostFlowAlgorithm>,
+        max_flow_algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        let sources = js_strings(sources).map_err(JsError::from)?;
+        let sinks = js_strings(sinks).map_err(JsError::from)?;
+        memory::measure(|| self.0.solve_mcmf_multi(
+            sources, sinks, source_capacities, sink_capacities,
+            or_default(decomposition), or_default(algorithm), or_default(max_flow_algorithm),
+        )).map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_max_flow(
+        &self, source: String, sink: String, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        memory::measure(|| self.0.solve_max_flow(source, sink, or_default(decomposition), or_default(algorithm)))
+            .map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_min_cost_flow(
+        &self, source: String, sink: String, flow_value: f64, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        let (decomposition, algorithm) = (or_default(decomposition), or_default(algorithm));
+        memory::measure(|| self.0.solve_min_cost_flow(source, sink, flow_value, decomposition, algorithm))
+            .map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_max_flow_with_budget(
+        &self, source: String, sink: String, budget: f64, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        self.0.solve_max_flow_with_budget(source, sink, budget, or_default(decomposition), or_default(algorithm))
+            .map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn max_flow_profile(
+        &self, source: String, sink: String, edge: ID, capacities: &[f64], algorithm: Option<MaxFlowAlgorithm>,
+    ) -> Result<Vec<f64>, JsError> {
+        self.0.max_flow_profile(source, sink, edge, capacities, or_default(algorithm)).map_err(JsError::from)
+    }
+
+    pub fn solve_max_profit_flow(
+        &self, source: String, sink: String, revenue: f64, decomposition: Option<PathDecomposition>,
+        algorithm: Option<MinCostFlowAlgorithm>,
+    ) -> Result<McmfSolution, JsError> {
+        self.0.solve_max_profit_flow(source, sink, revenue, or_default(decomposition), or_default(algorithm))
+            .map(McmfSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_min_cost_circulation(&self, algorithm: Option<MinCostFlowAlgorithm>) -> Result<FlowSolution, JsError> {
+        self.0.solve_min_cost_circulation(or_default(algorithm)).map(FlowSolution).map_err(JsError::from)
+    }
+
+    pub fn solve_feasible_circulation(&self, algorithm: Option<MaxFlowAlgorithm>) -> Result<Circulation, JsError> {
+        self.0.solve_feasible_circulation(or_default(algorithm)).map(Circulation).map_err(JsError::from)
+    }
+
+    pub fn solve_b_flow(&self, algorithm: Option<MinCostFlowAlgorithm>) -> Result<FlowSolution, JsError> {
+        self.0.solve_b_flow(or_default(algorithm)).map(FlowSolution).map_err(JsError::from)
+    }
+
+    pub fn diagnose_b_flow(&self) -> Result<BalanceDiagnosis, JsError> {
+        self.0.diagnose_b_flow().map(BalanceDiagnosis).map_err(JsError::from)
+    }
+
+    pub fn edge_disjoint_paths(&self, source: String, sink: String, k: Option<u32>) -> Result<PathArray, JsError> {
+        self.0.edge_disjoint_paths(source, sink, k).map(|paths| js_array(paths.into_iter().map(Path)))
+            .map_err(JsError::from)
+    }
+
+    pub fn vertex_disjoint_paths(&self, source: String, sink: String) -> Result<DisjointPaths, JsError> {
+        self.0.vertex_disjoint_paths(source, sink).map(DisjointPaths).map_err(JsError::from)
+    }
+
+    pub fn gomory_hu_tree(&self) -> Result<GomoryHuTree, JsError> {
+        self.0.gomory_hu_tree().map(GomoryHuTree).map_err(JsError::from)
+    }
+
+    pub fn reversed(&self) -> GraphBuilder { GraphBuilder(self.0.reversed()) }
+
+    // A `Float64Array` in row-major order.
+    pub fn all_pairs_max_flow(&self) -> Result<Vec<f64>, JsError> {
+        self.0.all_pairs_max_flow().map_err(JsError::from)
+    }
+
+    pub fn reachable_from(&self, node: String) -> Result<StringArray, JsError> {
+        self.0.reachable_from(node).map(js_array).map_err(JsError::from)
+    }
+
+    pub fn can_reach(&self, node: String) -> Result<StringArray, JsError> {
+        self.0.can_reach(node).map(js_array).map_err(JsError::from)
+    }
+
+    pub fn strongly_connected_components(&self) -> StringArrayArray {
+        js_array(self.0.strongly_connected_components().into_iter().map(js_array::<StringArray, _>))
+    }
+
+    pub fn solved_network(&self) -> Result<SolvedNetwork, JsError> {
+        self.0.solved_network().map(SolvedNetwork).map_err(JsError::from)
+    }
+
+    pub fn validate(&self) -> Result<StringArray, JsError> {
+        self.0.validate().map(js_array).map_err(JsError::from)
+    }
+
+    pub fn diagnose_flow(&self, source: String, sink: String, flow_value: f64) -> Result<FlowDiagnosis, JsError> {
+        self.0.diagnose_flow(source, sink, flow_value).map(FlowDiagnosis).map_err(JsError::from)
+    }
+
+    pub fn verify_flow(&self, source: String, sink: String, flows: Vec<f64>) -> Result<FlowVerification, JsError> {
+        self.0.verify_flow(source, sink, &flows).map(FlowVerification).map_err(JsError::from)
     }
 }
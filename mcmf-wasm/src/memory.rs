@@ -0,0 +1,103 @@
+// Memory usage for `GraphBuilder::memory_stats`, e.g. so that the page can warn before a graph
+// gets too large for the tab. The global allocator is wrapped to count the bytes it hands out.
+
+use std::{alloc::{GlobalAlloc, Layout}, sync::atomic::{AtomicUsize, Ordering}};
+
+use wasm_bindgen::prelude::*;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) struct CountingAllocator<A>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.0.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            allocated(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn allocated(size: usize) {
+    let total = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(total, Ordering::Relaxed);
+}
+
+// Runs `solve` as the solve that `MemoryStats::peak_solve_bytes` reports on.
+pub(crate) fn measure<T>(solve: impl FnOnce() -> T) -> T {
+    PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+    solve()
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryStats {
+    allocated_bytes: usize,
+    peak_solve_bytes: usize,
+    storage: mcmf_core::StorageStats,
+}
+
+impl MemoryStats {
+    pub(crate) fn new(storage: mcmf_core::StorageStats) -> MemoryStats {
+        MemoryStats {
+            allocated_bytes: ALLOCATED.load(Ordering::Relaxed),
+            peak_solve_bytes: PEAK.load(Ordering::Relaxed),
+            storage,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl MemoryStats {
+    // Allocated by the whole module right now, not just by this builder.
+    pub fn allocated_bytes(&self) -> usize { self.allocated_bytes }
+    // The most allocated at once during the last `solve_mcmf`, `solve_mcmf_multi`,
+    // `solve_max_flow`, `solve_min_cost_flow` or `resolve` of any builder, including what was
+    // allocated before it started. Async solves are not measured.
+    pub fn peak_solve_bytes(&self) -> usize { self.peak_solve_bytes }
+    // The size of the wasm memory, which never shrinks. Browsers limit it to 4 GiB at most.
+    pub fn memory_bytes(&self) -> usize {
+        #[cfg(target_arch = "wasm32")]
+        return core::arch::wasm32::memory_size(0) * 65536;
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.allocated_bytes;
+    }
+    // The part of `allocated_bytes` held by the graph of this builder.
+    pub fn storage(&self) -> StorageStats { StorageStats(self.storage) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct StorageStats(mcmf_core::StorageStats);
+
+#[wasm_bindgen]
+impl StorageStats {
+    pub fn node_bytes(&self) -> usize { self.0.node_bytes() }
+    pub fn edge_bytes(&self) -> usize { self.0.edge_bytes() }
+    pub fn name_bytes(&self) -> usize { self.0.name_bytes() }
+    pub fn total_bytes(&self) -> usize { self.0.total_bytes() }
+}
@@ -0,0 +1,64 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{js_array, Path, PathArray, ID};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "CommodityFlow[]")]
+    pub type CommodityFlowArray;
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct MultiCommodityBuilder(mcmf_core::MultiCommodityBuilder);
+
+#[wasm_bindgen]
+impl MultiCommodityBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        MultiCommodityBuilder(mcmf_core::MultiCommodityBuilder::new())
+    }
+
+    pub fn add_edge(&mut self, from: String, to: String, capacity: f64, cost: f64) -> Result<ID, JsError> {
+        self.0.add_edge(from, to, capacity, cost).map_err(JsError::from)
+    }
+
+    pub fn add_commodity(&mut self, source: String, sink: String, demand: f64) -> Result<ID, JsError> {
+        self.0.add_commodity(source, sink, demand).map_err(JsError::from)
+    }
+
+    pub fn set_epsilon(&mut self, epsilon: f64) -> Result<(), JsError> {
+        self.0.set_epsilon(epsilon).map_err(JsError::from)
+    }
+
+    pub fn solve_max_concurrent_flow(&self) -> Result<MultiCommodityFlow, JsError> {
+        self.0.solve_max_concurrent_flow().map(MultiCommodityFlow).map_err(JsError::from)
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct CommodityFlow(mcmf_core::CommodityFlow);
+
+#[wasm_bindgen]
+impl CommodityFlow {
+    pub fn source(&self) -> String { self.0.source().to_owned() }
+    pub fn sink(&self) -> String { self.0.sink().to_owned() }
+    pub fn demand(&self) -> f64 { self.0.demand() }
+    pub fn flow(&self) -> f64 { self.0.flow() }
+    pub fn paths(&self) -> PathArray { js_array(self.0.paths().iter().cloned().map(Path)) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct MultiCommodityFlow(mcmf_core::MultiCommodityFlow);
+
+#[wasm_bindgen]
+impl MultiCommodityFlow {
+    pub fn fraction(&self) -> f64 { self.0.fraction() }
+    pub fn total_cost(&self) -> f64 { self.0.total_cost() }
+    pub fn edge_flows(&self) -> Vec<f64> { self.0.edge_flows().to_vec() }
+    pub fn commodities(&self) -> CommodityFlowArray {
+        js_array(self.0.commodities().iter().cloned().map(CommodityFlow))
+    }
+}
@@ -0,0 +1,84 @@
+// Runs `mcmf_core::SolveTask` from the browser's event loop, one slice per task, so that a solve
+// started on the main thread doesn't block it for seconds.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{AugmentingPath, McmfSolution};
+
+pub(crate) const DEFAULT_SLICE_MS: f64 = 10.;
+
+#[wasm_bindgen(inline_js = r#"
+export function run_task(task) {
+    return new Promise((resolve, reject) => {
+        const step = () => {
+            let solution;
+            try {
+                solution = task.step();
+            } catch (e) {
+                task.free();
+                reject(e);
+                return;
+            }
+            if (solution === undefined) {
+                setTimeout(step, 0);
+            } else {
+                task.free();
+                resolve(solution);
+            }
+        };
+        step();
+    });
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Promise<McmfSolution>")]
+    pub type SolutionPromise;
+
+    fn run_task(task: SolveTask) -> SolutionPromise;
+}
+
+// A solve in progress. JS code gets a promise instead, which `run_task` resolves by calling `step`
+// from the event loop until the solution is ready.
+#[wasm_bindgen]
+pub struct SolveTask(mcmf_core::SolveTask);
+
+#[wasm_bindgen]
+impl SolveTask {
+    // Runs the solve until the end of the current slice. Returns the solution once it's done.
+    pub fn step(&mut self) -> Result<Option<McmfSolution>, JsError> {
+        self.0.step().map(|solution| solution.map(McmfSolution)).map_err(JsError::from)
+    }
+}
+
+impl SolveTask {
+    pub(crate) fn run(task: mcmf_core::SolveTask) -> SolutionPromise {
+        run_task(SolveTask(task))
+    }
+}
+
+// A solve driven one step at a time from JS, e.g. by a "next step" button.
+#[wasm_bindgen]
+pub struct Solver(pub(crate) mcmf_core::Solver);
+
+#[wasm_bindgen]
+impl Solver {
+    pub fn step(&mut self) -> Result<(), JsError> {
+        self.0.step().map_err(JsError::from)
+    }
+    pub fn is_done(&self) -> bool { self.0.is_done() }
+    pub fn solution(&self) -> Option<McmfSolution> { self.0.solution().cloned().map(McmfSolution) }
+    pub fn current_state(&self) -> SolverState { SolverState(self.0.current_state()) }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SolverState(mcmf_core::SolverState);
+
+#[wasm_bindgen]
+impl SolverState {
+    pub fn phase(&self) -> String { self.0.phase().to_owned() }
+    pub fn steps(&self) -> u32 { self.0.steps() }
+    pub fn flow(&self) -> f64 { self.0.flow() }
+    pub fn edge_flows(&self) -> Vec<f64> { self.0.edge_flows().to_vec() }
+    pub fn last_path(&self) -> Option<AugmentingPath> { self.0.last_path().cloned().map(AugmentingPath) }
+}
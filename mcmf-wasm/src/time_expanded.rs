@@ -0,0 +1,63 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{js_array, ID};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Departure[]")]
+    pub type DepartureArray;
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct TimeExpandedBuilder(mcmf_core::TimeExpandedBuilder);
+
+#[wasm_bindgen]
+impl TimeExpandedBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new(horizon: u32) -> Self {
+        TimeExpandedBuilder(mcmf_core::TimeExpandedBuilder::new(horizon))
+    }
+
+    pub fn horizon(&self) -> u32 { self.0.horizon() }
+    pub fn set_horizon(&mut self, horizon: u32) { self.0.set_horizon(horizon); }
+
+    pub fn add_edge(
+        &mut self, from: String, to: String, capacity: f64, cost: f64, travel_time: u32
+    ) -> Result<ID, JsError> {
+        self.0.add_edge(from, to, capacity, cost, travel_time).map_err(JsError::from)
+    }
+
+    pub fn solve_max_flow_over_time(&self, source: String, sink: String) -> Result<FlowOverTime, JsError> {
+        self.0.solve_max_flow_over_time(source, sink).map(FlowOverTime).map_err(JsError::from)
+    }
+
+    pub fn solve_quickest_flow(&self, source: String, sink: String, amount: f64) -> Result<FlowOverTime, JsError> {
+        self.0.solve_quickest_flow(source, sink, amount).map(FlowOverTime).map_err(JsError::from)
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Departure(mcmf_core::Departure);
+
+#[wasm_bindgen]
+impl Departure {
+    pub fn edge(&self) -> ID { self.0.edge() }
+    pub fn from(&self) -> String { self.0.from().to_owned() }
+    pub fn to(&self) -> String { self.0.to().to_owned() }
+    pub fn time(&self) -> u32 { self.0.time() }
+    pub fn flow(&self) -> f64 { self.0.flow() }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct FlowOverTime(mcmf_core::FlowOverTime);
+
+#[wasm_bindgen]
+impl FlowOverTime {
+    pub fn value(&self) -> f64 { self.0.value() }
+    pub fn total_cost(&self) -> f64 { self.0.total_cost() }
+    pub fn horizon(&self) -> u32 { self.0.horizon() }
+    pub fn departures(&self) -> DepartureArray { js_array(self.0.departures().iter().cloned().map(Departure)) }
+}
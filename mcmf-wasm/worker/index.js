@@ -0,0 +1,67 @@
+// Runs the solver in a dedicated Web Worker, so that solving large graphs doesn't block the page:
+//
+//   import { SolverWorker } from "./mcmf-wasm/worker/index.js";
+//
+//   const solver = new SolverWorker();
+//   solver.builder.add_edge("a", "b", 10, 1);
+//   const solution = await solver.builder.solve_mcmf("a", "b");
+//   console.log(solution.maxFlow, solution.paths);
+//
+// `builder` forwards any `GraphBuilder` method to the worker and returns a promise for its
+// result. Solutions arrive as plain objects, see `McmfSolution.to_object`. Requests are handled
+// in order, so there is no need to wait for one before sending the next. See `worker.js` for the
+// message protocol.
+//
+// The worker script is found relative to this module, which bundlers like webpack 5 understand.
+// Otherwise pass a `Worker` running `worker.js` to the constructor.
+
+export class SolverWorker {
+    constructor(worker) {
+        this.worker = worker || new Worker(new URL("./worker.js", import.meta.url), { type: "module" });
+        this.nextId = 0;
+        this.pending = new Map();
+        this.worker.onmessage = (event) => {
+            const { id, result, error } = event.data;
+            const { resolve, reject } = this.pending.get(id);
+            this.pending.delete(id);
+            if (error === undefined) {
+                resolve(result);
+            } else {
+                reject(new Error(error));
+            }
+        };
+        // Not thenable, so that it can be returned from async functions.
+        this.builder = new Proxy({}, {
+            get: (_, method) => method === "then"
+                ? undefined
+                : (...args) => this.request({ type: "call", method, args }),
+        });
+    }
+
+    // Replaces the graph with one parsed from `text`; see `worker.js` for the formats.
+    load(format, text, options) {
+        return this.request({ type: "load", format, text, options });
+    }
+
+    // Replaces the graph with an empty one.
+    reset() {
+        return this.request({ type: "reset" });
+    }
+
+    // Stops the worker. Requests still in flight are rejected.
+    terminate() {
+        this.worker.terminate();
+        for (const { reject } of this.pending.values()) {
+            reject(new Error("worker terminated"));
+        }
+        this.pending.clear();
+    }
+
+    request(message) {
+        const id = this.nextId++;
+        return new Promise((resolve, reject) => {
+            this.pending.set(id, { resolve, reject });
+            this.worker.postMessage({ id, ...message });
+        });
+    }
+}
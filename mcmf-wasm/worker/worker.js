@@ -0,0 +1,68 @@
+// Worker side of `SolverWorker`, see `index.js`. Owns a single `GraphBuilder` and answers
+// requests of the form
+//
+//   { id, type: "call", method, args }   calls a `GraphBuilder` method
+//   { id, type: "load", format, text, options }   replaces the builder with one parsed from text
+//       in the "json", "csv", "dimacs" or "graphml" format; options are `CsvOptions` fields for
+//       CSV and `{ capacity_key, cost_key }` for GraphML
+//   { id, type: "reset" }   replaces the builder with an empty one
+//
+// with `{ id, result }` or `{ id, error }`. Wasm objects cannot be posted, so results that have
+// `to_object` (solutions) are converted with it.
+
+import * as wasm from "mcmf-wasm";
+
+wasm.init();
+let builder = new wasm.GraphBuilder();
+
+function load(format, text, options) {
+    switch (format) {
+        case "json": return wasm.GraphBuilder.from_json(text);
+        case "csv": return wasm.GraphBuilder.from_csv(text, options && Object.assign(new wasm.CsvOptions(), options));
+        case "dimacs": return wasm.GraphBuilder.from_dimacs(text);
+        case "graphml": {
+            const { capacity_key, cost_key } = options || {};
+            return wasm.GraphBuilder.from_graphml(text, capacity_key, cost_key);
+        }
+        default: throw new Error(`unknown format '${format}'`);
+    }
+}
+
+function replace(newBuilder) {
+    builder.free();
+    builder = newBuilder;
+}
+
+function handle(request) {
+    switch (request.type) {
+        case "call": {
+            if (typeof builder[request.method] !== "function") {
+                throw new Error(`unknown method '${request.method}'`);
+            }
+            const result = builder[request.method](...(request.args || []));
+            if (!result || typeof result.to_object !== "function") {
+                return result;
+            }
+            const object = result.to_object();
+            result.free();
+            return object;
+        }
+        case "load":
+            replace(load(request.format, request.text, request.options));
+            return undefined;
+        case "reset":
+            replace(new wasm.GraphBuilder());
+            return undefined;
+        default:
+            throw new Error(`unknown request type '${request.type}'`);
+    }
+}
+
+self.onmessage = (event) => {
+    const { id } = event.data;
+    try {
+        self.postMessage({ id, result: handle(event.data) });
+    } catch (e) {
+        self.postMessage({ id, error: e instanceof Error ? e.message : String(e) });
+    }
+};